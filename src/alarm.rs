@@ -0,0 +1,89 @@
+//! Repeats the end of a phase for as long as it waits to be acknowledged, so that an alert
+//! missed the first time comes back instead of the schedule silently slipping.
+
+use std::time::Duration;
+
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::time::{self, Instant};
+
+use crate::event::Event;
+use crate::pomodoro::Phase;
+
+/// Forwards `events` to the receiver it returns, sending the `PhaseEnded` and `AwaitingAck`
+/// of a phase again every `every` until it is acknowledged, skipped or aborted.
+///
+/// Notifications and sounds which follow the returned receiver instead of `events` show and
+/// play again as long as the user has not come back.
+pub fn repeat(
+    every: Duration,
+    mut events: broadcast::Receiver<Event>,
+) -> broadcast::Receiver<Event> {
+    let (sender, receiver) = broadcast::channel(16);
+    tokio::spawn(async move {
+        let mut waiting: Option<Phase> = None;
+        let next = time::sleep(every);
+        tokio::pin!(next);
+        loop {
+            let event = tokio::select! {
+                event = events.recv() => event,
+                _ = &mut next, if waiting.is_some() => {
+                    if let Some(phase) = waiting {
+                        let _ = sender.send(Event::PhaseEnded(phase));
+                        let _ = sender.send(Event::AwaitingAck(phase));
+                    }
+                    next.as_mut().reset(Instant::now() + every);
+                    continue;
+                }
+            };
+            match &event {
+                Ok(Event::AwaitingAck(phase)) => {
+                    waiting = Some(*phase);
+                    next.as_mut().reset(Instant::now() + every);
+                }
+                Ok(Event::Acknowledged(_))
+                | Ok(Event::PhaseStarted(_))
+                | Ok(Event::PhaseSkipped(_))
+                | Ok(Event::PhaseAborted(_))
+                | Ok(Event::PhaseVoided(..))
+                | Ok(Event::Completed) => waiting = None,
+                Ok(_) | Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => break,
+            }
+            if let Ok(event) = event {
+                let _ = sender.send(event);
+            }
+        }
+    });
+    receiver
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn repeat_until_acknowledged() {
+    let (sender, receiver) = broadcast::channel(8);
+    let mut repeated = repeat(Duration::from_secs(60), receiver);
+    let start = Instant::now();
+    sender.send(Event::PhaseEnded(Phase::ShortBreak)).unwrap();
+    sender.send(Event::AwaitingAck(Phase::ShortBreak)).unwrap();
+    for _ in 0..3 {
+        assert_eq!(
+            repeated.recv().await.unwrap(),
+            Event::PhaseEnded(Phase::ShortBreak)
+        );
+        assert_eq!(
+            repeated.recv().await.unwrap(),
+            Event::AwaitingAck(Phase::ShortBreak)
+        );
+    }
+    assert_eq!(start.elapsed(), Duration::from_secs(120));
+
+    sender
+        .send(Event::Acknowledged(Duration::from_secs(120)))
+        .unwrap();
+    assert_eq!(
+        repeated.recv().await.unwrap(),
+        Event::Acknowledged(Duration::from_secs(120))
+    );
+    drop(sender);
+    assert_eq!(repeated.recv().await, Err(RecvError::Closed));
+    assert_eq!(start.elapsed(), Duration::from_secs(120));
+}
@@ -0,0 +1,261 @@
+//! A blocking counterpart of the [`Client`](crate::Client), for applications which do not
+//! run tokio themselves, such as GUI toolkits or simple scripts.
+//!
+//! The [`Client`] drives the pomodoro on a small runtime of its own, on one background
+//! thread, and waits for the answer to every call. It must not be used from async code,
+//! which should use [`start`](crate::start) instead.
+
+use std::io;
+use std::time::Duration;
+
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::config::Config;
+use crate::error::PomoError;
+use crate::event::{Event, InterruptionKind, Until};
+use crate::pomodoro::{Durations, Phase, Pomodoro, PomodoroSnapshot, Status};
+use crate::recovery::Checkpoint;
+use crate::runtime;
+
+/// Controls a pomodoro running on a runtime it owns, which stops with it.
+pub struct Client {
+    client: runtime::Client,
+    engine: Option<runtime::Runtime>,
+    runtime: tokio::runtime::Runtime,
+}
+
+impl Client {
+    /// Starts `pomodoro` on a runtime of its own, returning the [`Client`] controlling it and
+    /// the [`Events`] it emits.
+    pub fn start(pomodoro: Pomodoro) -> io::Result<(Self, Events)> {
+        Self::spawn(pomodoro, false)
+    }
+
+    /// Like [`start`](Self::start), but leaves the pomodoro paused before its first phase
+    /// until it is resumed.
+    pub fn start_idle(pomodoro: Pomodoro) -> io::Result<(Self, Events)> {
+        Self::spawn(pomodoro, true)
+    }
+
+    fn spawn(pomodoro: Pomodoro, idle: bool) -> io::Result<(Self, Events)> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("pomo")
+            .enable_all()
+            .build()?;
+        let (engine, client, events) = runtime.block_on(async {
+            if idle {
+                runtime::start_idle(pomodoro).await
+            } else {
+                runtime::start(pomodoro).await
+            }
+        });
+        let client = Self {
+            client,
+            engine: Some(engine),
+            runtime,
+        };
+        Ok((client, Events(events)))
+    }
+
+    /// Subscribes to the [`Event`]s emitted by the pomodoro from now on.
+    pub fn subscribe(&self) -> Events {
+        Events(self.client.subscribe())
+    }
+
+    /// Fetches a snapshot of the pomodoro.
+    pub fn status(&self) -> Result<Status, PomoError> {
+        self.runtime.block_on(self.client.status())
+    }
+
+    /// Waits for the pomodoro to reach `until`, or fails once it stops before.
+    pub fn wait(&self, until: Until) -> Result<(), PomoError> {
+        self.runtime.block_on(self.client.wait(until))
+    }
+
+    /// Fetches a snapshot of the engine, as a checkpoint would save it.
+    pub fn snapshot(&self) -> Result<PomodoroSnapshot, PomoError> {
+        self.runtime.block_on(self.client.snapshot())
+    }
+
+    /// Fetches what it takes to carry on with the pomodoro after a restart.
+    pub fn checkpoint(&self) -> Result<Checkpoint, PomoError> {
+        self.runtime.block_on(self.client.checkpoint())
+    }
+
+    /// Pauses the pomodoro, unless it is strict and in a working phase.
+    pub fn pause(&self) -> Result<(), PomoError> {
+        self.runtime.block_on(self.client.pause())
+    }
+
+    /// Pauses a running working phase the user has been away from for `idle`.
+    pub fn pause_idle(&self, idle: Duration) -> Result<(), PomoError> {
+        self.runtime.block_on(self.client.pause_idle(idle))
+    }
+
+    /// Resumes a paused pomodoro.
+    pub fn resume(&self) -> Result<(), PomoError> {
+        self.runtime.block_on(self.client.resume())
+    }
+
+    /// Pauses the running pomodoro or resumes the paused one, and tells how it is left.
+    pub fn toggle(&self) -> Result<Status, PomoError> {
+        self.runtime.block_on(self.client.toggle())
+    }
+
+    /// Resumes the pomodoro, starting a new session from the first phase when the last one
+    /// is over.
+    pub fn start_session(&self) -> Result<(), PomoError> {
+        self.runtime.block_on(self.client.start_session())
+    }
+
+    /// Ends the current phase early and moves on to the next one.
+    pub fn skip(&self) -> Result<(), PomoError> {
+        self.runtime.block_on(self.client.skip())
+    }
+
+    /// Starts the current phase over from zero without changing the totals.
+    pub fn restart_phase(&self) -> Result<(), PomoError> {
+        self.runtime.block_on(self.client.restart_phase())
+    }
+
+    /// Starts the counters over without touching the current phase or the history.
+    pub fn reset_counters(&self) -> Result<(), PomoError> {
+        self.runtime.block_on(self.client.reset_counters())
+    }
+
+    /// Abandons the current phase and moves on to `phase` right away.
+    pub fn goto(&self, phase: Phase) -> Result<(), PomoError> {
+        self.runtime.block_on(self.client.goto(phase))
+    }
+
+    /// Moves on from a phase which ended waiting to be acknowledged.
+    pub fn ack(&self) -> Result<(), PomoError> {
+        self.runtime.block_on(self.client.ack())
+    }
+
+    /// Abandons the running working phase for `reason` and starts it over.
+    pub fn void(&self, reason: impl Into<String>) -> Result<(), PomoError> {
+        self.runtime.block_on(self.client.void(reason))
+    }
+
+    /// Tags the pomodoros from now on with `task`. An empty task clears it.
+    pub fn set_task(&self, task: impl Into<String>) -> Result<(), PomoError> {
+        self.runtime.block_on(self.client.set_task(task))
+    }
+
+    /// Logs an interruption of the running working phase without stopping it.
+    pub fn interrupt(
+        &self,
+        kind: InterruptionKind,
+        note: impl Into<String>,
+    ) -> Result<(), PomoError> {
+        self.runtime.block_on(self.client.interrupt(kind, note))
+    }
+
+    /// Lengthens the current phase by `by`.
+    pub fn extend(&self, by: Duration) -> Result<(), PomoError> {
+        self.runtime.block_on(self.client.extend(by))
+    }
+
+    /// Changes the phase lengths and the long break interval, starting with the next phase.
+    pub fn set_durations(&self, durations: Durations) -> Result<(), PomoError> {
+        self.runtime.block_on(self.client.set_durations(durations))
+    }
+
+    /// Takes on the phase lengths and long break interval of a changed `config`.
+    pub fn reload(&self, config: &Config) -> Result<(), PomoError> {
+        self.runtime.block_on(self.client.reload(config))
+    }
+
+    /// Stops the pomodoro, resetting it to the beginning of the first working phase.
+    pub fn abort(&self) -> Result<(), PomoError> {
+        self.runtime.block_on(self.client.abort())
+    }
+
+    /// Waits until the pomodoro is aborted.
+    pub fn join(mut self) -> Result<(), PomoError> {
+        match self.engine.take() {
+            Some(engine) => self.runtime.block_on(engine.join()),
+            None => Ok(()),
+        }
+    }
+
+    /// Stops the pomodoro and waits for it to finish, as dropping the client does.
+    pub fn shutdown(mut self) -> Result<(), PomoError> {
+        self.stop()
+    }
+
+    fn stop(&mut self) -> Result<(), PomoError> {
+        match self.engine.take() {
+            Some(engine) => self.runtime.block_on(engine.shutdown()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+
+/// The [`Event`]s of a pomodoro, waiting for each to come. Events missed by a reader too
+/// slow to keep up are skipped, and the iteration ends once the pomodoro has stopped.
+pub struct Events(broadcast::Receiver<Event>);
+
+impl Events {
+    /// The next event if one came already, without waiting.
+    pub fn try_next(&mut self) -> Option<Event> {
+        loop {
+            match self.0.try_recv() {
+                Ok(event) => return Some(event),
+                Err(broadcast::error::TryRecvError::Lagged(_)) => continue,
+                Err(_) => return None,
+            }
+        }
+    }
+}
+
+impl Iterator for Events {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        loop {
+            match self.0.blocking_recv() {
+                Ok(event) => return Some(event),
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+#[test]
+fn control_without_async() {
+    use crate::pomodoro::{Clock, Phase};
+
+    let clock = |secs| Clock::new(Duration::from_secs(secs), Duration::from_millis(10));
+    let pomodoro = Pomodoro::new(clock(60), clock(1), clock(1), 4, true, None);
+    let (client, mut events) = Client::start(pomodoro).unwrap();
+    client.set_task("write report").unwrap();
+    client.pause().unwrap();
+    let status = client.status().unwrap();
+    assert_eq!(status.phase, Phase::Working);
+    assert!(status.paused);
+    assert_eq!(status.task.as_deref(), Some("write report"));
+    let seen: Vec<_> = std::iter::from_fn(|| events.try_next())
+        .filter(|event| !matches!(event, Event::Tick { .. }))
+        .collect();
+    assert!(seen.contains(&Event::PhaseStarted(Phase::Working)));
+    assert!(seen.contains(&Event::Paused));
+    client.resume().unwrap();
+    client.skip().unwrap();
+    assert_eq!(
+        events.find(|event| matches!(event, Event::PhaseStarted(_))),
+        Some(Event::PhaseStarted(Phase::ShortBreak))
+    );
+    client.shutdown().unwrap();
+    // Stopping is no completion, and ends the events.
+    assert!(events.all(|event| event != Event::Completed));
+}
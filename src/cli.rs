@@ -0,0 +1,831 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::{Local, NaiveDate};
+use clap::builder::{PossibleValuesParser, TypedValueParser};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use pomo::config::Config;
+use pomo::import;
+use pomo::stats::Period;
+use pomo::{InterruptionKind, Phase, Until};
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "pomo",
+    version,
+    about = "A pomodoro timer",
+    after_help = "Exit codes: 1 when a command fails, 2 on wrong usage, 3 when no daemon is \
+                  running, 4 when the config is invalid and 5 when the pomodoro refuses what \
+                  was asked, e.g. a pause in strict mode. Commands given `--format json` print \
+                  their errors as JSON too.",
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    /// Socket the daemon listens on, by default the one of the daemon running
+    #[arg(long, global = true)]
+    pub socket: Option<PathBuf>,
+
+    /// The daemon's timer to address instead of the default one. With `start`, this starts
+    /// the timer in the daemon
+    #[arg(long, global = true)]
+    pub timer: Option<String>,
+
+    /// The least severe log messages to print, e.g. `info` or `pomo=debug`; `RUST_LOG`
+    /// takes precedence when set
+    #[arg(long, global = true, default_value = "warn")]
+    pub log_level: String,
+
+    /// How to print the log messages
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub session: SessionArgs,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Run the pomodoro in the foreground, as running without a command does
+    Start(SessionArgs),
+    /// Run the pomodoro in the foreground on a single line with a progress bar
+    Run(SessionArgs),
+    /// Run the pomodoro in the background, controlled through the socket
+    Daemon {
+        /// Ask the daemon already running to quit and carry on with its session
+        #[arg(long)]
+        takeover: bool,
+        #[command(flatten)]
+        session: SessionArgs,
+    },
+    /// Run the pomodoro in an interactive terminal interface
+    #[cfg(feature = "tui")]
+    Tui {
+        /// How often to redraw the progress, e.g. `100ms` for a smooth gauge
+        #[arg(long, value_parser = parse_duration)]
+        tick_rate: Option<Duration>,
+        #[command(flatten)]
+        session: SessionArgs,
+    },
+    /// Continue the session which was interrupted by a crash or a reboot
+    ResumeSession {
+        /// Continue without asking first
+        #[arg(long, short)]
+        yes: bool,
+        #[command(flatten)]
+        session: SessionArgs,
+    },
+    /// Pause the running daemon
+    Pause,
+    /// Resume the paused daemon
+    Resume,
+    /// Pause the running daemon or resume the paused one, for a single hotkey, and show how
+    /// it is left
+    Toggle {
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+    },
+    /// Show the state of the daemon
+    Status {
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+    },
+    /// Print the state of the daemon's engine as JSON
+    Snapshot,
+    /// Change the daemon's phase lengths from its next phase on
+    Reconfigure {
+        /// Start from the durations of this profile
+        #[arg(long)]
+        profile: Option<String>,
+        /// Length of a working phase
+        #[arg(long, value_parser = parse_duration)]
+        work: Option<Duration>,
+        /// Length of a short break
+        #[arg(long, value_parser = parse_duration)]
+        short: Option<Duration>,
+        /// Length of a long break
+        #[arg(long, value_parser = parse_duration)]
+        long: Option<Duration>,
+        /// Number of working phases before a long break
+        #[arg(long)]
+        interval: Option<u8>,
+    },
+    /// Show the state of every timer of the daemon
+    Timers {
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+    },
+    /// Stop the daemon's timer named with --timer
+    Stop,
+    /// End the daemon's current phase early
+    Skip,
+    /// Start the daemon's phase over from zero, keeping the pomodoros counted so far
+    Restart,
+    /// Start the daemon's counters over, leaving the current phase and the history as they are
+    ResetCounters,
+    /// Abandon the daemon's phase and move on to another one right away
+    Goto {
+        /// `working`, `short_break` or `long_break`
+        #[arg(value_parser = one_of::<Phase>(&["working", "short_break", "long_break"]))]
+        phase: Phase,
+    },
+    /// Start the daemon's next phase once the last one waits to be acknowledged
+    Ack,
+    /// Tag the daemon's pomodoros from now on with a task, or clear it
+    Task { task: Option<String> },
+    /// Log an interruption of the daemon's working phase without stopping it
+    Interrupt {
+        /// `internal` or `external`
+        #[arg(value_parser = one_of::<InterruptionKind>(&["internal", "external"]))]
+        kind: InterruptionKind,
+        /// What the interruption was
+        note: Option<String>,
+    },
+    /// Abandon the daemon's working phase and start it over, recording it as voided
+    Void {
+        /// Why the pomodoro was abandoned
+        reason: Option<String>,
+    },
+    /// Wait for the daemon's phase to end, or for another point of the session, e.g. to run
+    /// `pomo wait --for work-end && make tea`
+    Wait {
+        /// What to wait for
+        #[arg(long = "for", default_value_t = Until::PhaseEnd, value_parser = until())]
+        until: Until,
+    },
+    /// Print the daemon's state for a status bar such as waybar or polybar
+    Statusbar {
+        #[arg(long, value_enum, default_value_t = BarStyle::Polybar)]
+        style: BarStyle,
+        /// What to show instead of the icon, countdown and phase, e.g. `{phase} {remaining}`
+        #[arg(long)]
+        template: Option<String>,
+        /// Keep printing a line every second instead of exiting
+        #[arg(long)]
+        watch: bool,
+    },
+    /// Print the daemon's countdown in tmux's colours, for `#(pomo tmux)` in the status
+    /// line, which the daemon refreshes as it goes
+    Tmux {
+        /// What to show instead of the icon and countdown, e.g. `{phase} {remaining}`
+        #[arg(long)]
+        template: Option<String>,
+    },
+    /// Print a short segment for shell prompts from the status file the daemon keeps, without
+    /// waiting on the daemon
+    Prompt {
+        /// What to show instead of the icon and countdown, e.g. `{phase} {remaining}`
+        #[arg(long)]
+        template: Option<String>,
+    },
+    /// Print when each phase would start and end if a session started now, without
+    /// starting it
+    Plan {
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+        #[command(flatten)]
+        session: SessionArgs,
+    },
+    /// Dump the recorded history for spreadsheets and scripts, in plain text even when the
+    /// history is encrypted, e.g. `pomo export | age -r age1... > history.csv.age`
+    Export {
+        #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+        /// Leave out the phases started before this day, e.g. `2024-01-01` or `yesterday`
+        #[arg(long, value_parser = parse_date)]
+        from: Option<NaiveDate>,
+        /// Leave out the phases started after this day, e.g. `today`
+        #[arg(long, value_parser = parse_date)]
+        to: Option<NaiveDate>,
+    },
+    /// Add the phases of a CSV or JSON file to the history, as `pomo export` writes them or
+    /// as another timer does, leaving out those already recorded
+    Import {
+        path: PathBuf,
+        /// `csv` or `json`, by default told by the extension of the file
+        #[arg(long, value_parser = one_of::<import::Format>(&["csv", "json"]))]
+        format: Option<import::Format>,
+        /// A TOML file naming the fields of another timer's records and its phases
+        #[arg(long)]
+        mapping: Option<PathBuf>,
+    },
+    /// Write a summary of the day or the week to read through
+    Report {
+        /// `day` or `week`, which starts on Monday
+        #[arg(long, default_value = "week", value_parser = one_of::<Period>(&["day", "week"]))]
+        period: Period,
+        #[arg(long, value_enum, default_value_t = ReportFormat::Text)]
+        format: ReportFormat,
+        /// Write the report to this file instead of the standard output
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Draw the pomodoros completed lately in the terminal
+    Graph {
+        #[arg(long, value_enum, default_value_t = GraphKind::Day)]
+        by: GraphKind,
+        /// How many days back to look, today included
+        #[arg(long, default_value_t = 30)]
+        days: usize,
+    },
+    /// Summarize the recorded history
+    Stats {
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+        /// Sum up the pomodoros of each task instead
+        #[arg(long)]
+        by_task: bool,
+    },
+    /// Print the completion script of a shell, e.g. `pomo completions bash >
+    /// ~/.local/share/bash-completion/completions/pomo`
+    Completions {
+        #[arg(value_enum, required_unless_present = "list")]
+        shell: Option<Shell>,
+        /// Print the names the scripts complete instead
+        #[arg(long, value_enum, hide = true, conflicts_with = "shell")]
+        list: Option<Names>,
+    },
+}
+
+impl Command {
+    /// The format of the command's output, which its errors are printed in too.
+    pub fn format(&self) -> Format {
+        match self {
+            Command::Status { format }
+            | Command::Toggle { format }
+            | Command::Timers { format }
+            | Command::Plan { format, .. }
+            | Command::Stats { format, .. } => *format,
+            Command::Export {
+                format: ExportFormat::Json,
+                ..
+            } => Format::Json,
+            _ => Format::Text,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BarStyle {
+    /// A JSON object for a waybar custom module
+    Waybar,
+    /// A single line of text
+    Polybar,
+    /// The full text, short text and colour lines of an i3blocks block
+    I3blocks,
+    /// The icon and countdown with tmux's colour codes
+    Tmux,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
+/// Names the completion scripts look up as they complete.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Names {
+    /// The profiles of the config file and the built-in ones
+    Profiles,
+    /// The timers of the running daemon
+    Timers,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    #[value(alias = "table")]
+    Text,
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogFormat {
+    /// A line of text for every message
+    Text,
+    /// A JSON object for every message, for a log collector
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// A header line and a line for every phase
+    Csv,
+    /// An array of an object for every phase
+    Json,
+    /// An iCalendar event for every completed working phase, to import as a time log
+    Ics,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GraphKind {
+    /// A sparkline of the pomodoros of each day
+    Day,
+    /// A heatmap of the pomodoros of each hour of every weekday
+    Hour,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReportFormat {
+    Text,
+    Markdown,
+}
+
+#[derive(Args, Debug)]
+pub struct SessionArgs {
+    /// Read settings from this file instead of the default config.toml
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Take the durations of this profile from the config, or of the built-in `classic`,
+    /// `52-17` or `90-20`
+    #[arg(long)]
+    pub profile: Option<String>,
+
+    /// Length of a working phase, e.g. `25m` or `1h30m`
+    #[arg(long, value_parser = parse_duration)]
+    pub work: Option<Duration>,
+
+    /// Length of a short break
+    #[arg(long, value_parser = parse_duration)]
+    pub short: Option<Duration>,
+
+    /// Length of a long break
+    #[arg(long, value_parser = parse_duration)]
+    pub long: Option<Duration>,
+
+    /// Number of working phases before a long break, or 0 for no long breaks
+    #[arg(long)]
+    pub interval: Option<u8>,
+
+    /// Go from one working phase to the next without short breaks
+    #[arg(long)]
+    pub no_short_breaks: bool,
+
+    /// Stop after this many working phases
+    #[arg(long)]
+    pub until: Option<u8>,
+
+    /// Stop after this much focused time, e.g. `4h`
+    #[arg(long, value_parser = parse_duration)]
+    pub until_duration: Option<Duration>,
+
+    /// Move on to the next phase without waiting to be resumed
+    #[arg(long, overrides_with = "no_continuous")]
+    pub continuous: bool,
+
+    /// Pause after every phase
+    #[arg(long)]
+    pub no_continuous: bool,
+
+    /// Start the breaks without waiting to be resumed, even when the session is not continuous
+    #[arg(long, overrides_with = "no_auto_start_breaks")]
+    pub auto_start_breaks: bool,
+
+    /// Wait to be resumed before every break, even when the session is continuous
+    #[arg(long)]
+    pub no_auto_start_breaks: bool,
+
+    /// Start the working phases without waiting to be resumed, even when the session is not
+    /// continuous
+    #[arg(long, overrides_with = "no_auto_start_work")]
+    pub auto_start_work: bool,
+
+    /// Wait to be resumed before every working phase, even when the session is continuous
+    #[arg(long)]
+    pub no_auto_start_work: bool,
+
+    /// Refuse to pause working phases, which can only be skipped
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Wait at the end of every phase until it is acknowledged
+    #[arg(long)]
+    pub ack: bool,
+
+    /// Alert again this often while the end of a phase waits to be acknowledged, e.g. `1m`
+    #[arg(long, value_parser = parse_duration)]
+    pub repeat_alarm: Option<Duration>,
+
+    /// Sleep until the end of each phase instead of waking up every second
+    #[arg(long)]
+    pub power_saving: bool,
+
+    /// Keep the machine from going to sleep during working phases
+    #[arg(long)]
+    pub inhibit_sleep: bool,
+
+    /// Show the remaining time in the system tray
+    #[arg(long)]
+    pub tray: bool,
+
+    /// Cover the screen with the countdown during breaks
+    #[arg(long)]
+    pub hard_breaks: bool,
+
+    /// Tag the pomodoros with the task they are spent on
+    #[arg(long)]
+    pub task: Option<String>,
+
+    /// Work on the Taskwarrior task with this id, starting it during working phases and
+    /// annotating it with the pomodoros completed
+    #[cfg(feature = "taskwarrior")]
+    #[arg(long, value_name = "ID", conflicts_with = "task")]
+    pub taskwarrior: Option<String>,
+
+    /// Pick the task from this todo.txt file or markdown checklist, which counts the
+    /// pomodoros spent on it
+    #[arg(long)]
+    pub tasks: Option<PathBuf>,
+
+    /// Do not show desktop notifications on phase transitions
+    #[arg(long)]
+    pub no_notify: bool,
+
+    /// Do not record finished phases in the history database
+    #[arg(long)]
+    pub no_history: bool,
+
+    /// Append every event as a JSON line to this file or FIFO
+    #[arg(long)]
+    pub event_file: Option<PathBuf>,
+
+    /// Serve a web dashboard and a WebSocket of the events on this address, e.g. 127.0.0.1:7878
+    #[arg(long)]
+    pub web_address: Option<SocketAddr>,
+
+    /// Volume of the phase end sound in percent
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub volume: Option<u8>,
+
+    /// Do not play a sound when a phase ends
+    #[arg(long)]
+    pub mute: bool,
+}
+
+impl SessionArgs {
+    /// Overrides the settings in `config` with the ones given on the command line.
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(work) = self.work {
+            config.work = work;
+        }
+        if let Some(short) = self.short {
+            config.short_break = short;
+        }
+        if let Some(long) = self.long {
+            config.long_break = long;
+        }
+        if let Some(interval) = self.interval {
+            config.long_break_interval = interval;
+        }
+        if self.no_short_breaks {
+            config.short_breaks = false;
+        }
+        if self.until.is_some() {
+            config.until = self.until;
+        }
+        if self.until_duration.is_some() {
+            config.until_duration = self.until_duration;
+        }
+        if self.continuous {
+            config.continuous = true;
+        }
+        if self.no_continuous {
+            config.continuous = false;
+        }
+        if self.auto_start_breaks {
+            config.auto_start_breaks = Some(true);
+        }
+        if self.no_auto_start_breaks {
+            config.auto_start_breaks = Some(false);
+        }
+        if self.auto_start_work {
+            config.auto_start_work = Some(true);
+        }
+        if self.no_auto_start_work {
+            config.auto_start_work = Some(false);
+        }
+        if self.strict {
+            config.strict = true;
+        }
+        if self.ack {
+            config.ack = true;
+        }
+        if self.repeat_alarm.is_some() {
+            config.repeat_alarm = self.repeat_alarm;
+        }
+        if self.power_saving {
+            config.power_saving = true;
+        }
+        if self.inhibit_sleep {
+            config.inhibit_sleep = true;
+        }
+        if self.tray {
+            config.tray = true;
+        }
+        if self.hard_breaks {
+            config.hard_breaks = true;
+        }
+        if self.event_file.is_some() {
+            config.event_file = self.event_file.clone();
+        }
+        if self.web_address.is_some() {
+            config.web_address = self.web_address;
+        }
+        if self.tasks.is_some() {
+            config.task_file = self.tasks.clone();
+        }
+        if let Some(volume) = self.volume {
+            config.sound.volume = volume;
+        }
+        if self.mute {
+            config.sound.mute = true;
+        }
+    }
+}
+
+fn parse_duration(s: &str) -> Result<Duration, humantime::DurationError> {
+    humantime::parse_duration(s)
+}
+
+fn until() -> impl TypedValueParser<Value = Until> {
+    PossibleValuesParser::new(Until::ALL.iter().map(Until::name))
+        .map(|name| name.parse::<Until>().unwrap())
+}
+
+/// Takes only `names`, so that they show in the help and the completion scripts.
+fn one_of<T>(names: &'static [&'static str]) -> impl TypedValueParser<Value = T>
+where
+    T: FromStr + Clone + Send + Sync + 'static,
+    T::Err: std::fmt::Debug,
+{
+    PossibleValuesParser::new(names).map(|name| name.parse::<T>().unwrap())
+}
+
+fn parse_date(s: &str) -> Result<NaiveDate, chrono::ParseError> {
+    let today = Local::now().date_naive();
+    match s {
+        "today" => Ok(today),
+        "yesterday" => Ok(today.pred_opt().unwrap_or(today)),
+        _ => NaiveDate::parse_from_str(s, "%Y-%m-%d"),
+    }
+}
+
+#[test]
+fn verify_cli() {
+    use clap::CommandFactory;
+    Cli::command().debug_assert();
+}
+
+#[test]
+fn human_durations() {
+    assert_eq!(parse_duration("25m").unwrap(), Duration::from_secs(25 * 60));
+    assert_eq!(
+        parse_duration("1h30m").unwrap(),
+        Duration::from_secs(90 * 60)
+    );
+    assert!(parse_duration("soon").is_err());
+}
+
+#[test]
+fn parse_arguments() {
+    let cli = Cli::try_parse_from([
+        "pomo",
+        "--work",
+        "50m",
+        "--short",
+        "10m",
+        "--long",
+        "1h",
+        "--interval",
+        "3",
+        "--until",
+        "8",
+        "--until-duration",
+        "4h",
+        "--tasks",
+        "todo.txt",
+    ])
+    .unwrap();
+    assert!(cli.command.is_none());
+    assert_eq!(cli.session.work, Some(Duration::from_secs(50 * 60)));
+    assert_eq!(cli.session.short, Some(Duration::from_secs(10 * 60)));
+    assert_eq!(cli.session.long, Some(Duration::from_secs(60 * 60)));
+    assert_eq!(cli.session.interval, Some(3));
+    assert_eq!(cli.session.until, Some(8));
+    assert_eq!(
+        cli.session.until_duration,
+        Some(Duration::from_secs(4 * 60 * 60))
+    );
+    assert_eq!(cli.session.tasks, Some(PathBuf::from("todo.txt")));
+
+    let cli = Cli::try_parse_from(["pomo"]).unwrap();
+    assert_eq!(cli.session.work, None);
+    assert_eq!(cli.session.until, None);
+}
+
+#[test]
+fn parse_subcommands() {
+    let cli = Cli::try_parse_from(["pomo", "daemon", "--work", "50m"]).unwrap();
+    match cli.command {
+        Some(Command::Daemon { takeover, session }) => {
+            assert!(!takeover);
+            assert_eq!(session.work, Some(Duration::from_secs(50 * 60)))
+        }
+        command => panic!("unexpected command {:?}", command),
+    }
+    let cli = Cli::try_parse_from(["pomo", "daemon", "--takeover"]).unwrap();
+    assert!(matches!(
+        cli.command,
+        Some(Command::Daemon { takeover: true, .. })
+    ));
+    #[cfg(feature = "tui")]
+    {
+        let cli = Cli::try_parse_from(["pomo", "tui", "--tick-rate", "100ms"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Tui {
+                tick_rate: Some(rate),
+                ..
+            }) if rate == Duration::from_millis(100)
+        ));
+    }
+    let cli = Cli::try_parse_from(["pomo", "pause", "--socket", "/tmp/pomo.sock"]).unwrap();
+    assert!(matches!(cli.command, Some(Command::Pause)));
+    assert_eq!(cli.socket, Some(PathBuf::from("/tmp/pomo.sock")));
+    let cli = Cli::try_parse_from(["pomo", "status", "--format", "json"]).unwrap();
+    assert!(matches!(
+        cli.command,
+        Some(Command::Status {
+            format: Format::Json
+        })
+    ));
+    let cli = Cli::try_parse_from(["pomo", "resume-session", "-y", "--mute"]).unwrap();
+    match cli.command {
+        Some(Command::ResumeSession { yes, session }) => assert!(yes && session.mute),
+        command => panic!("unexpected command {:?}", command),
+    }
+    let cli = Cli::try_parse_from(["pomo", "interrupt", "external", "door"]).unwrap();
+    assert!(matches!(
+        cli.command,
+        Some(Command::Interrupt {
+            kind: InterruptionKind::External,
+            ..
+        })
+    ));
+    assert!(Cli::try_parse_from(["pomo", "interrupt", "sideways"]).is_err());
+    let cli = Cli::try_parse_from(["pomo", "toggle", "--format", "json"]).unwrap();
+    assert_eq!(
+        cli.command.as_ref().map(Command::format),
+        Some(Format::Json)
+    );
+    assert!(matches!(
+        cli.command,
+        Some(Command::Toggle {
+            format: Format::Json
+        })
+    ));
+    let cli = Cli::try_parse_from(["pomo", "wait", "--for", "work-end"]).unwrap();
+    assert!(matches!(
+        cli.command,
+        Some(Command::Wait {
+            until: Until::WorkEnd
+        })
+    ));
+    let cli = Cli::try_parse_from(["pomo", "wait"]).unwrap();
+    assert!(matches!(
+        cli.command,
+        Some(Command::Wait {
+            until: Until::PhaseEnd
+        })
+    ));
+    assert!(Cli::try_parse_from(["pomo", "wait", "--for", "lunch"]).is_err());
+    let cli = Cli::try_parse_from(["pomo", "import", "sessions.txt", "--format", "csv"]).unwrap();
+    match cli.command {
+        Some(Command::Import {
+            path,
+            format,
+            mapping,
+        }) => {
+            assert_eq!(path, PathBuf::from("sessions.txt"));
+            assert_eq!(format, Some(import::Format::Csv));
+            assert_eq!(mapping, None);
+        }
+        command => panic!("unexpected command {:?}", command),
+    }
+    let cli = Cli::try_parse_from(["pomo", "completions", "powershell"]).unwrap();
+    assert!(matches!(
+        cli.command,
+        Some(Command::Completions {
+            shell: Some(Shell::Powershell),
+            list: None
+        })
+    ));
+    let cli = Cli::try_parse_from(["pomo", "completions", "--list", "timers"]).unwrap();
+    assert!(matches!(
+        cli.command,
+        Some(Command::Completions {
+            shell: None,
+            list: Some(Names::Timers)
+        })
+    ));
+    assert!(Cli::try_parse_from(["pomo", "completions"]).is_err());
+    let cli = Cli::try_parse_from(["pomo", "void", "phone call"]).unwrap();
+    match cli.command {
+        Some(Command::Void { reason }) => assert_eq!(reason.as_deref(), Some("phone call")),
+        command => panic!("unexpected command {:?}", command),
+    }
+    let cli = Cli::try_parse_from(["pomo", "start", "--task", "write report"]).unwrap();
+    match cli.command {
+        Some(Command::Start(session)) => assert_eq!(session.task.as_deref(), Some("write report")),
+        command => panic!("unexpected command {:?}", command),
+    }
+    let cli = Cli::try_parse_from(["pomo", "stats", "--format", "table", "--by-task"]).unwrap();
+    assert!(matches!(
+        cli.command,
+        Some(Command::Stats {
+            format: Format::Text,
+            by_task: true
+        })
+    ));
+    assert!(Cli::try_parse_from(["pomo", "--work", "50m", "status"]).is_err());
+    let cli = Cli::try_parse_from([
+        "pomo",
+        "daemon",
+        "--log-level",
+        "debug",
+        "--log-format",
+        "json",
+    ])
+    .unwrap();
+    assert_eq!(
+        (cli.log_level.as_str(), cli.log_format),
+        ("debug", LogFormat::Json)
+    );
+    assert_eq!(Cli::try_parse_from(["pomo"]).unwrap().log_level, "warn");
+}
+
+#[test]
+fn arguments_override_config() {
+    let mut config = Config {
+        long_break_interval: 2,
+        until: Some(4),
+        ..Config::default()
+    };
+    let cli = Cli::try_parse_from([
+        "pomo",
+        "--work",
+        "50m",
+        "--no-continuous",
+        "--auto-start-breaks",
+        "--repeat-alarm",
+        "30s",
+        "--mute",
+    ])
+    .unwrap();
+    cli.session.apply(&mut config);
+    assert!(config.sound.mute);
+    assert_eq!(config.work, Duration::from_secs(50 * 60));
+    assert_eq!(config.short_break, Config::default().short_break);
+    assert_eq!(config.long_break_interval, 2);
+    assert_eq!(config.until, Some(4));
+    assert!(!config.continuous);
+    assert_eq!(config.auto_start_breaks, Some(true));
+    assert_eq!(config.auto_start_work, None);
+    assert_eq!(config.repeat_alarm, Some(Duration::from_secs(30)));
+}
+
+#[test]
+fn plan_long_session() {
+    let cli = Cli::try_parse_from(["pomo", "plan", "--until-duration", "200h"]).unwrap();
+    let session = match cli.command {
+        Some(Command::Plan { session, .. }) => session,
+        command => panic!("unexpected command {:?}", command),
+    };
+    let mut config = Config::default();
+    session.apply(&mut config);
+    config.validate().unwrap();
+    // Past 255 working phases, more than the completed pomodoros are counted in.
+    let schedule = config.pomodoro().unwrap().schedule();
+    let working = schedule.iter().filter(|slot| slot.phase == Phase::Working);
+    assert_eq!(working.count(), 480);
+}
+
+#[test]
+fn export_dates() {
+    let today = Local::now().date_naive();
+    assert_eq!(parse_date("today").unwrap(), today);
+    assert_eq!(parse_date("yesterday").unwrap(), today.pred_opt().unwrap());
+    assert_eq!(
+        parse_date("2024-01-01").unwrap(),
+        NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()
+    );
+    assert!(parse_date("01/01/2024").is_err());
+}
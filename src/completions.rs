@@ -0,0 +1,568 @@
+//! Completion scripts for the shells, generated from the command line parser.
+//!
+//! The scripts complete the subcommands, their options, and the values of the options and
+//! arguments which only take some or take paths. They run `pomo completions --list` for
+//! the names of the profiles in the config file and of the timers of the running daemon, so
+//! those stay up to date.
+
+use std::fmt::Write;
+
+use clap::{Arg, CommandFactory, ValueHint};
+
+use crate::cli::{Cli, Shell};
+
+/// What the value of an option or an argument completes to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Values {
+    Words(Vec<String>),
+    Profiles,
+    Timers,
+    Paths,
+}
+
+/// An option and the values it completes to, if it takes any.
+struct Opt {
+    long: Option<String>,
+    short: Option<char>,
+    help: String,
+    takes_value: bool,
+    values: Option<Values>,
+    /// Whether it goes with every subcommand.
+    global: bool,
+}
+
+impl Opt {
+    fn flags(&self) -> Vec<String> {
+        let long = self.long.iter().map(|long| format!("--{}", long));
+        let short = self.short.iter().map(|short| format!("-{}", short));
+        long.chain(short).collect()
+    }
+}
+
+/// A subcommand, or the command itself under an empty name, with what completes after it.
+struct Subcommand {
+    name: String,
+    about: String,
+    options: Vec<Opt>,
+    /// What its arguments other than options complete to.
+    arguments: Option<Values>,
+}
+
+impl Subcommand {
+    /// The subcommands and options completing right after the subcommand, leaving out what
+    /// its arguments complete to.
+    fn words(&self, subcommands: &[Subcommand]) -> Vec<String> {
+        let mut words = Vec::new();
+        if self.name.is_empty() {
+            words.extend(subcommands[1..].iter().map(|sub| sub.name.clone()));
+        }
+        words.extend(self.options.iter().flat_map(Opt::flags));
+        words
+    }
+}
+
+fn first_line(help: Option<&clap::builder::StyledStr>) -> String {
+    help.map(|help| help.to_string())
+        .and_then(|help| help.lines().next().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn values_of(arg: &Arg) -> Option<Values> {
+    if !arg.get_action().takes_values() {
+        return None;
+    }
+    match arg.get_id().as_str() {
+        "profile" => return Some(Values::Profiles),
+        "timer" => return Some(Values::Timers),
+        _ => {}
+    }
+    let words: Vec<String> = arg
+        .get_possible_values()
+        .iter()
+        .filter(|value| !value.is_hide_set())
+        .map(|value| value.get_name().to_string())
+        .collect();
+    if !words.is_empty() {
+        return Some(Values::Words(words));
+    }
+    match arg.get_value_hint() {
+        ValueHint::AnyPath | ValueHint::FilePath | ValueHint::DirPath => Some(Values::Paths),
+        _ => None,
+    }
+}
+
+fn subcommand(name: &str, command: &clap::Command) -> Subcommand {
+    let arguments = command.get_arguments().filter(|arg| !arg.is_hide_set());
+    let (positionals, options): (Vec<&Arg>, Vec<&Arg>) =
+        arguments.partition(|arg| arg.is_positional());
+    Subcommand {
+        name: name.to_string(),
+        about: first_line(command.get_about()),
+        options: options
+            .into_iter()
+            .map(|arg| Opt {
+                long: arg.get_long().map(str::to_string),
+                short: arg.get_short(),
+                help: first_line(arg.get_help()),
+                takes_value: arg.get_action().takes_values(),
+                values: values_of(arg),
+                global: arg.is_global_set(),
+            })
+            .collect(),
+        arguments: positionals.into_iter().find_map(values_of),
+    }
+}
+
+/// The command itself and then each of its subcommands.
+fn subcommands() -> Vec<Subcommand> {
+    let mut command = Cli::command();
+    command.build();
+    let mut subcommands = vec![subcommand("", &command)];
+    subcommands.extend(
+        command
+            .get_subcommands()
+            .filter(|sub| !sub.is_hide_set())
+            .map(|sub| subcommand(sub.get_name(), sub)),
+    );
+    subcommands
+}
+
+/// The options taking values which complete to something, as the `subcommand:option`
+/// patterns they go with, `*:option` for the global ones.
+fn value_rules(subcommands: &[Subcommand]) -> Vec<(Vec<String>, Values)> {
+    let mut rules: Vec<(Vec<String>, Values)> = Vec::new();
+    for sub in subcommands {
+        for opt in &sub.options {
+            let values = match &opt.values {
+                Some(values) => values,
+                None => continue,
+            };
+            let scope = if opt.global { "*" } else { &sub.name };
+            let patterns = opt
+                .flags()
+                .into_iter()
+                .map(|flag| format!("{}:{}", scope, flag));
+            let rule = match rules.iter_mut().find(|(_, v)| v == values) {
+                Some(rule) => rule,
+                None => {
+                    rules.push((Vec::new(), values.clone()));
+                    rules.last_mut().unwrap()
+                }
+            };
+            for pattern in patterns {
+                if !rule.0.contains(&pattern) {
+                    rule.0.push(pattern);
+                }
+            }
+        }
+    }
+    rules
+}
+
+/// `text` in single quotes, with the quotes within escaped as `shell` takes them.
+fn quoted(text: &str, shell: Shell) -> String {
+    match shell {
+        Shell::Bash | Shell::Zsh | Shell::Fish => format!("'{}'", text.replace('\'', "'\\''")),
+        Shell::Powershell => format!("'{}'", text.replace('\'', "''")),
+    }
+}
+
+fn list(names: &str) -> String {
+    format!("pomo completions --list {}", names)
+}
+
+/// The words `values` complete to in bash, as they go in `COMPREPLY`.
+fn bash_reply(values: &Values) -> String {
+    match values {
+        Values::Words(words) => format!("$(compgen -W \"{}\" -- \"$cur\")", words.join(" ")),
+        Values::Profiles => format!(
+            "$(compgen -W \"$({} 2>/dev/null)\" -- \"$cur\")",
+            list("profiles")
+        ),
+        Values::Timers => format!(
+            "$(compgen -W \"$({} 2>/dev/null)\" -- \"$cur\")",
+            list("timers")
+        ),
+        Values::Paths => "$(compgen -f -- \"$cur\")".to_string(),
+    }
+}
+
+fn bash(subcommands: &[Subcommand]) -> String {
+    let names: Vec<&str> = subcommands[1..]
+        .iter()
+        .map(|sub| sub.name.as_str())
+        .collect();
+    let mut script = String::new();
+    script.push_str("_pomo() {\n");
+    script.push_str(
+        "    local cur=\"${COMP_WORDS[COMP_CWORD]}\" prev=\"${COMP_WORDS[COMP_CWORD-1]}\"\n",
+    );
+    script.push_str("    local subcommand=\"\" word\n");
+    script.push_str("    for word in \"${COMP_WORDS[@]:1:COMP_CWORD-1}\"; do\n");
+    script.push_str("        case \"$word\" in\n");
+    let _ = writeln!(
+        script,
+        "            {}) subcommand=\"$word\"; break ;;",
+        names.join("|")
+    );
+    script.push_str("        esac\n    done\n");
+    script.push_str("    case \"$subcommand:$prev\" in\n");
+    for (patterns, values) in value_rules(subcommands) {
+        let _ = writeln!(
+            script,
+            "        {}) COMPREPLY=({}); return ;;",
+            patterns.join("|"),
+            bash_reply(&values)
+        );
+    }
+    script.push_str("    esac\n");
+    script.push_str("    case \"$subcommand\" in\n");
+    for sub in subcommands {
+        let name = if sub.name.is_empty() {
+            "\"\""
+        } else {
+            &sub.name
+        };
+        let words = format!(
+            "COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))",
+            sub.words(subcommands).join(" ")
+        );
+        match &sub.arguments {
+            // The options complete once the word starts them, and the argument otherwise.
+            Some(values) => {
+                let _ = writeln!(
+                    script,
+                    "        {}) if [[ \"$cur\" == -* ]]; then {}; else COMPREPLY=({}); fi ;;",
+                    name,
+                    words,
+                    bash_reply(values)
+                );
+            }
+            None => {
+                let _ = writeln!(script, "        {}) {} ;;", name, words);
+            }
+        }
+    }
+    script.push_str("    esac\n}\n\ncomplete -F _pomo pomo\n");
+    script
+}
+
+/// The command completing `values` in zsh.
+fn zsh_complete(values: &Values) -> String {
+    match values {
+        Values::Words(words) => format!("compadd -- {}", words.join(" ")),
+        Values::Profiles => format!("compadd -- ${{(f)\"$({} 2>/dev/null)\"}}", list("profiles")),
+        Values::Timers => format!("compadd -- ${{(f)\"$({} 2>/dev/null)\"}}", list("timers")),
+        Values::Paths => "_files".to_string(),
+    }
+}
+
+fn zsh(subcommands: &[Subcommand]) -> String {
+    let names: Vec<&str> = subcommands[1..]
+        .iter()
+        .map(|sub| sub.name.as_str())
+        .collect();
+    let mut script = String::new();
+    script.push_str("#compdef pomo\n\n_pomo() {\n");
+    script.push_str("    local subcommand word prev=\"${words[CURRENT-1]}\"\n");
+    script.push_str("    for word in ${words[2,CURRENT-1]}; do\n");
+    script.push_str("        case \"$word\" in\n");
+    let _ = writeln!(
+        script,
+        "            ({}) subcommand=\"$word\"; break ;;",
+        names.join("|")
+    );
+    script.push_str("        esac\n    done\n");
+    script.push_str("    case \"$subcommand:$prev\" in\n");
+    for (patterns, values) in value_rules(subcommands) {
+        let _ = writeln!(
+            script,
+            "        ({}) {}; return ;;",
+            patterns.join("|"),
+            zsh_complete(&values)
+        );
+    }
+    script.push_str("    esac\n");
+    script.push_str("    case \"$subcommand\" in\n");
+    for sub in subcommands {
+        if sub.name.is_empty() {
+            script.push_str("        (\"\")\n            local -a subcommands=(\n");
+            for sub in &subcommands[1..] {
+                let described = format!("{}:{}", sub.name, sub.about);
+                let _ = writeln!(script, "                {}", quoted(&described, Shell::Zsh));
+            }
+            script.push_str("            )\n            _describe subcommand subcommands\n");
+            let flags: Vec<String> = sub.options.iter().flat_map(Opt::flags).collect();
+            let _ = writeln!(
+                script,
+                "            compadd -- {}\n            ;;",
+                flags.join(" ")
+            );
+        } else {
+            let mut complete = format!("compadd -- {}", sub.words(subcommands).join(" "));
+            if let Some(values) = &sub.arguments {
+                complete = format!("{}; {}", complete, zsh_complete(values));
+            }
+            let _ = writeln!(script, "        ({}) {} ;;", sub.name, complete);
+        }
+    }
+    script.push_str("    esac\n}\n\n");
+    script.push_str("if [ \"$funcstack[1]\" = \"_pomo\" ]; then\n    _pomo \"$@\"\n");
+    script.push_str("else\n    compdef _pomo pomo\nfi\n");
+    script
+}
+
+/// The flags of `complete` completing `values` in fish.
+fn fish_values(values: &Values) -> String {
+    match values {
+        Values::Words(words) => format!(" -x -a {}", quoted(&words.join(" "), Shell::Fish)),
+        Values::Profiles => format!(" -x -a '({} 2>/dev/null)'", list("profiles")),
+        Values::Timers => format!(" -x -a '({} 2>/dev/null)'", list("timers")),
+        Values::Paths => " -r -F".to_string(),
+    }
+}
+
+fn fish_option(opt: &Opt) -> String {
+    let mut line = String::new();
+    if let Some(long) = &opt.long {
+        let _ = write!(line, " -l {}", long);
+    }
+    if let Some(short) = opt.short {
+        let _ = write!(line, " -s {}", short);
+    }
+    let values = match &opt.values {
+        Some(values) => fish_values(values),
+        None if opt.takes_value => " -r".to_string(),
+        None => String::new(),
+    };
+    line.push_str(&values);
+    if !opt.help.is_empty() {
+        let _ = write!(line, " -d {}", quoted(&opt.help, Shell::Fish));
+    }
+    line
+}
+
+fn fish(subcommands: &[Subcommand]) -> String {
+    let names: Vec<&str> = subcommands[1..]
+        .iter()
+        .map(|sub| sub.name.as_str())
+        .collect();
+    let mut script = String::new();
+    script.push_str("function __pomo_subcommand\n");
+    script.push_str("    for word in (commandline -opc)[2..-1]\n");
+    script.push_str("        switch $word\n");
+    let _ = writeln!(script, "            case {}", names.join(" "));
+    script.push_str("                echo $word\n                return\n");
+    script.push_str("        end\n    end\nend\n\n");
+    script.push_str("function __pomo_using\n");
+    script.push_str("    set -l subcommand (__pomo_subcommand)\n");
+    script.push_str("    test \"$subcommand\" = \"$argv[1]\"\nend\n\n");
+    script.push_str("complete -c pomo -f\n");
+    for sub in &subcommands[1..] {
+        let _ = writeln!(
+            script,
+            "complete -c pomo -n \"__pomo_using ''\" -a {} -d {}",
+            sub.name,
+            quoted(&sub.about, Shell::Fish)
+        );
+    }
+    for opt in subcommands[0].options.iter().filter(|opt| opt.global) {
+        let _ = writeln!(script, "complete -c pomo{}", fish_option(opt));
+    }
+    for sub in subcommands {
+        let condition = if sub.name.is_empty() { "''" } else { &sub.name };
+        // Without an option before it, the argument takes what `-a` or `-F` offers.
+        let argument = match &sub.arguments {
+            Some(Values::Paths) => Some(" -F".to_string()),
+            Some(values) => Some(fish_values(values).replacen(" -x", "", 1)),
+            None => None,
+        };
+        if let Some(argument) = argument {
+            let _ = writeln!(
+                script,
+                "complete -c pomo -n \"__pomo_using {}\"{}",
+                condition, argument
+            );
+        }
+        for opt in sub.options.iter().filter(|opt| !opt.global) {
+            let _ = writeln!(
+                script,
+                "complete -c pomo -n \"__pomo_using {}\"{}",
+                condition,
+                fish_option(opt)
+            );
+        }
+    }
+    script
+}
+
+fn powershell_words(words: &[String]) -> String {
+    let quoted: Vec<String> = words
+        .iter()
+        .map(|word| quoted(word, Shell::Powershell))
+        .collect();
+    quoted.join(", ")
+}
+
+/// The statements of a `switch` branch completing `values` in PowerShell.
+fn powershell_candidates(values: &Values) -> String {
+    match values {
+        Values::Words(words) => format!("{}; break", powershell_words(words)),
+        Values::Profiles => format!("{} 2>$null; break", list("profiles")),
+        Values::Timers => format!("{} 2>$null; break", list("timers")),
+        // Completing nothing leaves it to PowerShell, which completes paths.
+        Values::Paths => "return".to_string(),
+    }
+}
+
+fn powershell(subcommands: &[Subcommand]) -> String {
+    let names: Vec<String> = subcommands[1..]
+        .iter()
+        .map(|sub| sub.name.clone())
+        .collect();
+    let mut script = String::new();
+    script.push_str("Register-ArgumentCompleter -Native -CommandName pomo -ScriptBlock {\n");
+    script.push_str("    param($wordToComplete, $commandAst, $cursorPosition)\n");
+    script.push_str("    $words = @($commandAst.CommandElements |\n");
+    script.push_str("        Where-Object { $_.Extent.EndOffset -lt $cursorPosition } |\n");
+    script.push_str("        ForEach-Object { $_.ToString() })\n");
+    let _ = writeln!(script, "    $subcommands = @({})", powershell_words(&names));
+    script.push_str("    $subcommand = ''\n");
+    script.push_str("    foreach ($word in $words | Select-Object -Skip 1) {\n");
+    script.push_str(
+        "        if ($subcommands -contains $word) { $subcommand = $word; break }\n    }\n",
+    );
+    script.push_str("    $previous = $words[-1]\n");
+    script.push_str("    $candidates = switch -Wildcard (\"${subcommand}:${previous}\") {\n");
+    for (patterns, values) in value_rules(subcommands) {
+        let candidates = powershell_candidates(&values);
+        for pattern in patterns {
+            let _ = writeln!(
+                script,
+                "        {} {{ {} }}",
+                quoted(&pattern, Shell::Powershell),
+                candidates
+            );
+        }
+    }
+    script.push_str("        default {\n            switch ($subcommand) {\n");
+    for sub in subcommands {
+        let words = sub.words(subcommands);
+        let mut candidates = powershell_words(&words);
+        match &sub.arguments {
+            // The options complete once the word starts them, and paths otherwise.
+            Some(Values::Paths) => {
+                candidates = format!(
+                    "if ($wordToComplete -notlike '-*') {{ return }}; {}",
+                    candidates
+                )
+            }
+            Some(values) => {
+                let arguments = powershell_candidates(values);
+                let arguments = arguments.trim_end_matches("; break");
+                candidates = format!("{}; {}", candidates, arguments)
+            }
+            None if words.is_empty() => continue,
+            None => {}
+        }
+        let _ = writeln!(
+            script,
+            "                {} {{ {} }}",
+            quoted(&sub.name, Shell::Powershell),
+            candidates
+        );
+    }
+    script.push_str("            }\n        }\n    }\n");
+    script.push_str(
+        "    $candidates | Where-Object { $_ -like \"$wordToComplete*\" } | ForEach-Object {\n",
+    );
+    script.push_str(
+        "        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)\n",
+    );
+    script.push_str("    }\n}\n");
+    script
+}
+
+/// The completion script of `shell`.
+pub fn script(shell: Shell) -> String {
+    let subcommands = subcommands();
+    match shell {
+        Shell::Bash => bash(&subcommands),
+        Shell::Zsh => zsh(&subcommands),
+        Shell::Fish => fish(&subcommands),
+        Shell::Powershell => powershell(&subcommands),
+    }
+}
+
+#[test]
+fn complete_subcommands_and_names() {
+    let subcommands = subcommands();
+    assert_eq!(subcommands[0].name, "");
+    let names: Vec<&str> = subcommands.iter().map(|sub| sub.name.as_str()).collect();
+    assert!(names.contains(&"pause") && names.contains(&"completions"));
+
+    let rules = value_rules(&subcommands);
+    let patterns = |values: Values| {
+        rules
+            .iter()
+            .find(|(_, v)| *v == values)
+            .map(|(patterns, _)| patterns.clone())
+            .unwrap_or_default()
+    };
+    assert_eq!(patterns(Values::Timers), ["*:--timer"]);
+    assert!(patterns(Values::Profiles).contains(&"daemon:--profile".to_string()));
+    assert!(patterns(Values::Paths).contains(&"*:--socket".to_string()));
+    let styles = ["waybar", "polybar", "i3blocks", "tmux"].map(String::from);
+    assert_eq!(
+        patterns(Values::Words(styles.to_vec())),
+        ["statusbar:--style"]
+    );
+
+    let bash = script(Shell::Bash);
+    assert!(bash.contains("*:--timer) COMPREPLY=($(compgen -W \"$(pomo completions --list timers"));
+    assert!(bash.ends_with("complete -F _pomo pomo\n"));
+    let fish = script(Shell::Fish);
+    assert!(fish.contains(
+        "complete -c pomo -n \"__pomo_using completions\" -a 'bash zsh fish powershell'"
+    ));
+    assert!(script(Shell::Zsh).starts_with("#compdef pomo\n"));
+    assert!(script(Shell::Powershell).contains("'export:--format' { 'csv', 'json', 'ics'; break }"));
+    assert!(script(Shell::Zsh).contains("(import) compadd -- --format"));
+    assert!(fish.contains("complete -c pomo -n \"__pomo_using import\" -F\n"));
+}
+
+#[cfg(unix)]
+#[test]
+fn complete_in_bash() {
+    use std::process::Command;
+
+    let dir = std::env::temp_dir().join(format!("pomo-completions-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("sessions.csv"), "").unwrap();
+    let script = script(Shell::Bash);
+    // Completes the last of `words` as bash would once the script is sourced.
+    let complete = |words: &str| -> Vec<String> {
+        let line = format!(
+            "{}\nCOMP_WORDS=({}); COMP_CWORD=$((${{#COMP_WORDS[@]}} - 1))\n\
+             _pomo; printf '%s\\n' \"${{COMPREPLY[@]}}\"",
+            script, words
+        );
+        let output = Command::new("bash")
+            .arg("-c")
+            .arg(line)
+            .current_dir(&dir)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let replies = String::from_utf8(output.stdout).unwrap();
+        replies.lines().map(str::to_string).collect()
+    };
+    assert_eq!(complete("pomo imp"), ["import"]);
+    assert_eq!(complete("pomo import ''"), ["sessions.csv"]);
+    assert_eq!(complete("pomo import --f"), ["--format"]);
+    assert_eq!(complete("pomo import --format ''"), ["csv", "json"]);
+    assert_eq!(complete("pomo goto s"), ["short_break"]);
+    assert_eq!(complete("pomo report --period w"), ["week"]);
+    assert_eq!(complete("pomo completions f"), ["fish"]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,749 @@
+//! Settings loaded from `config.toml` in the user's configuration directory.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::NaiveTime;
+use serde::Deserialize;
+
+use crate::error::PomoError;
+use crate::history::Encryption;
+use crate::hooks::Hooks;
+use crate::idle::Idle;
+use crate::mqtt::Mqtt;
+use crate::notify::Texts;
+use crate::org::Org;
+use crate::pomodoro::{Durations, Labels, Pomodoro, Step, SuspendPolicy};
+use crate::quiet::Quiet;
+use crate::schedule::Schedule;
+use crate::screen_lock::ScreenLock;
+use crate::signals::Signals;
+use crate::slack::Slack;
+use crate::sound::Sound;
+use crate::speech::Speech;
+use crate::status_file::Export;
+use crate::timetrack::TimeTracking;
+use crate::webhooks::Webhook;
+
+const TICK: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    ZeroDuration(&'static str),
+    ZeroUntil,
+    Volume(u8),
+    UnknownProfile(String),
+    Pomodoro(PomoError),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read(path, e) => write!(f, "cannot read {}: {}", path.display(), e),
+            ConfigError::Parse(path, e) => write!(f, "invalid config {}: {}", path.display(), e),
+            ConfigError::ZeroDuration(phase) => write!(f, "{} must last longer than zero", phase),
+            ConfigError::ZeroUntil => write!(f, "until must be at least 1"),
+            ConfigError::UnknownProfile(name) => write!(f, "there is no profile named {}", name),
+            ConfigError::Pomodoro(e) => write!(f, "{}", e),
+            ConfigError::Volume(volume) => {
+                write!(f, "volume must be between 0 and 100, got {}", volume)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Durations of a session which can be picked by name, from the `[profile.<name>]` tables
+/// of the config file or the [built-in ones](Profile::builtin). Missing settings are left
+/// as they are.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    #[serde(with = "humantime_serde")]
+    pub work: Option<Duration>,
+    #[serde(with = "humantime_serde")]
+    pub short_break: Option<Duration>,
+    #[serde(with = "humantime_serde")]
+    pub long_break: Option<Duration>,
+    pub long_break_interval: Option<u8>,
+}
+
+impl Profile {
+    fn minutes(work: u64, short_break: u64, long_break: u64, interval: u8) -> Self {
+        Self {
+            work: Some(Duration::from_secs(work * 60)),
+            short_break: Some(Duration::from_secs(short_break * 60)),
+            long_break: Some(Duration::from_secs(long_break * 60)),
+            long_break_interval: Some(interval),
+        }
+    }
+
+    /// The names of the [built-in](Self::builtin) profiles.
+    pub const BUILTIN: &'static [&'static str] = &["classic", "52-17", "90-20"];
+
+    /// The presets every config has: `classic` for 25/5/15, `52-17` and `90-20`.
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "classic" => Some(Self::minutes(25, 5, 15, 4)),
+            "52-17" => Some(Self::minutes(52, 17, 17, 4)),
+            "90-20" => Some(Self::minutes(90, 20, 30, 3)),
+            _ => None,
+        }
+    }
+}
+
+impl From<Profile> for Durations {
+    fn from(profile: Profile) -> Self {
+        Self {
+            work: profile.work,
+            short_break: profile.short_break,
+            long_break: profile.long_break,
+            long_break_interval: profile.long_break_interval,
+        }
+    }
+}
+
+/// Durations and cycle options of a pomodoro session.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    #[serde(with = "humantime_serde")]
+    pub work: Duration,
+    #[serde(with = "humantime_serde")]
+    pub short_break: Duration,
+    #[serde(with = "humantime_serde")]
+    pub long_break: Duration,
+    /// Working phases before each long break, or 0 for no long breaks.
+    pub long_break_interval: u8,
+    /// Follow the working phases with short breaks, or else only with the long ones.
+    pub short_breaks: bool,
+    pub continuous: bool,
+    /// Start the breaks by themselves, or wait for them to be started, whatever `continuous` is.
+    pub auto_start_breaks: Option<bool>,
+    /// Start the working phases by themselves, or wait for them to be started, whatever
+    /// `continuous` is.
+    pub auto_start_work: Option<bool>,
+    /// Refuse to pause working phases.
+    pub strict: bool,
+    /// Wait at the end of every phase until it is acknowledged.
+    pub ack: bool,
+    /// Show the notification and play the sound of a phase end again this often while it
+    /// waits to be acknowledged, e.g. `1m`.
+    #[serde(with = "humantime_serde")]
+    pub repeat_alarm: Option<Duration>,
+    /// Count the time waited for an acknowledgement as part of the phase which ended.
+    pub count_overtime: bool,
+    /// How often the progress is reported, e.g. `100ms` for a smooth gauge. The phases end
+    /// on time whatever it is.
+    #[serde(with = "humantime_serde")]
+    pub tick_rate: Duration,
+    /// Sleep until the end of each phase instead of waking up for every tick, which leaves
+    /// nothing to report the progress with in between.
+    pub power_saving: bool,
+    /// Keep the machine from going to sleep during working phases.
+    pub inhibit_sleep: bool,
+    /// Show the remaining time in the system tray, on Linux with the `tray` feature.
+    pub tray: bool,
+    /// Cover every monitor with the countdown during breaks, on Linux under X11 with the
+    /// `overlay` feature. Ctrl+Alt+Escape lifts it until the next break.
+    pub hard_breaks: bool,
+    /// Have tmux redraw `#(pomo tmux)` as the daemon's countdown goes, which the daemon
+    /// also does when it is started inside tmux.
+    pub tmux: bool,
+    /// How much the `e` key adds to the running phase.
+    #[serde(with = "humantime_serde")]
+    pub extend_step: Duration,
+    /// Pomodoros to complete every day.
+    pub daily_goal: Option<u32>,
+    /// The time of day at which the counters start over, e.g. `04:00`. They keep growing
+    /// when it is not set.
+    pub day_start: Option<NaiveTime>,
+    pub until: Option<u8>,
+    /// Stop once this much focused time has been spent, e.g. `4h`.
+    #[serde(with = "humantime_serde")]
+    pub until_duration: Option<Duration>,
+    /// Steps to cycle through instead of the classic rhythm, e.g. `[["work", "52m"], ["break", "17m"]]`.
+    pub plan: Vec<Step>,
+    /// Append an NDJSON line for every event to this file or FIFO.
+    pub event_file: Option<PathBuf>,
+    /// Serve a dashboard at `/` and the events as a WebSocket at `/events` on this address,
+    /// e.g. `127.0.0.1:7878`. The WebSocket takes the token the dashboard is served with.
+    pub web_address: Option<SocketAddr>,
+    /// A todo.txt file or markdown checklist to pick the session's task from.
+    pub task_file: Option<PathBuf>,
+    /// What to do after the machine wakes up: `pause`, `break`, `discard` or `ignore`.
+    pub on_suspend: SuspendPolicy,
+    pub hooks: Hooks,
+    /// URLs to POST to on events, from the `[[webhooks]]` tables.
+    pub webhooks: Vec<Webhook>,
+    /// The titles and bodies of the notifications, replacing the default ones.
+    pub notifications: Texts,
+    pub sound: Sound,
+    /// When notifications and sounds are held back.
+    pub quiet: Quiet,
+    pub screen_lock: ScreenLock,
+    /// When to pause working phases the user is away from.
+    pub idle: Idle,
+    pub signals: Signals,
+    /// The MQTT broker to publish the events to.
+    pub mqtt: Option<Mqtt>,
+    /// The Slack account whose status follows the working phases.
+    pub slack: Option<Slack>,
+    /// The spoken announcements of the phase changes, made when the table is there.
+    pub speech: Option<Speech>,
+    /// The time tracker to log the completed pomodoros on.
+    pub time_tracking: Option<TimeTracking>,
+    /// The org file to clock the completed pomodoros into.
+    pub org: Option<Org>,
+    /// A file the daemon keeps the status in for conky, xmobar and scripts.
+    pub status_file: Option<Export>,
+    /// Where the passphrase of the encrypted history comes from.
+    pub encryption: Option<Encryption>,
+    /// When the daemon starts sessions on its own.
+    pub schedule: Schedule,
+    pub labels: Labels,
+    /// Named durations to pick with `--profile`.
+    pub profile: BTreeMap<String, Profile>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            work: Duration::from_secs(25 * 60),
+            short_break: Duration::from_secs(5 * 60),
+            long_break: Duration::from_secs(15 * 60),
+            long_break_interval: 4,
+            short_breaks: true,
+            continuous: true,
+            auto_start_breaks: None,
+            auto_start_work: None,
+            strict: false,
+            ack: false,
+            repeat_alarm: None,
+            count_overtime: false,
+            tick_rate: TICK,
+            power_saving: false,
+            inhibit_sleep: false,
+            tray: false,
+            hard_breaks: false,
+            tmux: false,
+            extend_step: Duration::from_secs(5 * 60),
+            daily_goal: None,
+            day_start: None,
+            until: None,
+            until_duration: None,
+            plan: Vec::new(),
+            event_file: None,
+            web_address: None,
+            task_file: None,
+            on_suspend: SuspendPolicy::default(),
+            hooks: Hooks::default(),
+            webhooks: Vec::new(),
+            notifications: Texts::default(),
+            sound: Sound::default(),
+            quiet: Quiet::default(),
+            screen_lock: ScreenLock::default(),
+            idle: Idle::default(),
+            signals: Signals::default(),
+            mqtt: None,
+            slack: None,
+            speech: None,
+            time_tracking: None,
+            org: None,
+            status_file: None,
+            encryption: None,
+            schedule: Schedule::default(),
+            labels: Labels::default(),
+            profile: BTreeMap::new(),
+        }
+    }
+}
+
+impl Config {
+    /// The default location, `$XDG_CONFIG_HOME/pomo/config.toml` on Linux.
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("pomo").join("config.toml"))
+    }
+
+    /// The config of a daemon's timer named `name`, `$XDG_CONFIG_HOME/pomo/timers/<name>.toml`
+    /// on Linux.
+    pub fn timer_path(name: &str) -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| {
+            dir.join("pomo")
+                .join("timers")
+                .join(name)
+                .with_extension("toml")
+        })
+    }
+
+    /// Loads the config from the default location, falling back to the defaults when the
+    /// file does not exist.
+    pub fn load() -> Result<Self, ConfigError> {
+        match Self::path() {
+            Some(path) if path.exists() => Self::from_file(path),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let content =
+            fs::read_to_string(path).map_err(|e| ConfigError::Read(path.to_path_buf(), e))?;
+        toml::from_str(&content).map_err(|e| ConfigError::Parse(path.to_path_buf(), e))
+    }
+
+    /// The names of the config's own profiles, followed by those of the built-in ones it
+    /// does not override.
+    pub fn profile_names(&self) -> Vec<&str> {
+        let builtin = Profile::BUILTIN
+            .iter()
+            .copied()
+            .filter(|name| !self.profile.contains_key(*name));
+        self.profile
+            .keys()
+            .map(String::as_str)
+            .chain(builtin)
+            .collect()
+    }
+
+    /// The profile called `name`, preferring the config's own profiles to the built-in ones.
+    pub fn find_profile(&self, name: &str) -> Result<Profile, ConfigError> {
+        self.profile
+            .get(name)
+            .cloned()
+            .or_else(|| Profile::builtin(name))
+            .ok_or_else(|| ConfigError::UnknownProfile(name.to_string()))
+    }
+
+    /// Takes the durations of the profile called `name`, preferring the config's own
+    /// profiles to the built-in ones.
+    pub fn apply_profile(&mut self, name: &str) -> Result<(), ConfigError> {
+        let profile = self.find_profile(name)?;
+        if let Some(work) = profile.work {
+            self.work = work;
+        }
+        if let Some(short_break) = profile.short_break {
+            self.short_break = short_break;
+        }
+        if let Some(long_break) = profile.long_break {
+            self.long_break = long_break;
+        }
+        if let Some(interval) = profile.long_break_interval {
+            self.long_break_interval = interval;
+        }
+        Ok(())
+    }
+
+    /// The phase lengths and long break interval, to hand to a running pomodoro.
+    pub fn durations(&self) -> Durations {
+        Durations {
+            work: Some(self.work),
+            short_break: Some(self.short_break),
+            long_break: Some(self.long_break),
+            long_break_interval: Some(self.long_break_interval),
+        }
+    }
+
+    /// Rejects settings which would make the pomodoro meaningless.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let zero = Duration::from_secs(0);
+        if self.work == zero {
+            return Err(ConfigError::ZeroDuration("work"));
+        }
+        if self.short_break == zero {
+            return Err(ConfigError::ZeroDuration("short_break"));
+        }
+        if self.long_break == zero {
+            return Err(ConfigError::ZeroDuration("long_break"));
+        }
+        if self.tick_rate == zero {
+            return Err(ConfigError::ZeroDuration("tick_rate"));
+        }
+        if self.extend_step == zero {
+            return Err(ConfigError::ZeroDuration("extend_step"));
+        }
+        if self.until == Some(0) {
+            return Err(ConfigError::ZeroUntil);
+        }
+        if self.sound.volume > 100 {
+            return Err(ConfigError::Volume(self.sound.volume));
+        }
+        self.pomodoro().map_err(ConfigError::Pomodoro)?;
+        Ok(())
+    }
+
+    /// Builds the pomodoro these settings describe, following the `plan` if one is given.
+    pub fn pomodoro(&self) -> Result<Pomodoro, PomoError> {
+        let builder = Pomodoro::builder()
+            .work(self.work)
+            .short_break(self.short_break)
+            .long_break(self.long_break)
+            .tick(self.tick_rate)
+            .long_break_interval(self.long_break_interval)
+            .short_breaks(self.short_breaks)
+            .continuous(self.continuous)
+            .strict(self.strict)
+            .ack(self.ack)
+            .count_overtime(self.count_overtime)
+            .power_saving(self.power_saving)
+            .on_suspend(self.on_suspend)
+            .plan(self.plan.clone());
+        let builder = match self.auto_start_breaks {
+            Some(auto_start) => builder.auto_start_breaks(auto_start),
+            None => builder,
+        };
+        let builder = match self.auto_start_work {
+            Some(auto_start) => builder.auto_start_work(auto_start),
+            None => builder,
+        };
+        let builder = match self.daily_goal {
+            Some(target) => builder.daily_goal(target),
+            None => builder,
+        };
+        let builder = match self.day_start {
+            Some(day_start) => builder.day_start(day_start),
+            None => builder,
+        };
+        let builder = match self.until {
+            Some(until) => builder.until(until),
+            None => builder,
+        };
+        match self.until_duration {
+            Some(until_duration) => builder.until_duration(until_duration),
+            None => builder,
+        }
+        .build()
+    }
+}
+
+#[test]
+fn parse_config() {
+    use crate::notify::Text;
+    use crate::pomodoro::Phase;
+    use crate::schedule::QuietHours;
+    use crate::signals::SignalAction;
+    use crate::timetrack::Service;
+
+    let config: Config = toml::from_str(
+        r#"
+        work = "50m"
+        short_break = "10m"
+        long_break = "1h"
+        long_break_interval = 3
+        short_breaks = false
+        continuous = false
+        auto_start_breaks = true
+        strict = true
+        ack = true
+        repeat_alarm = "1m"
+        tick_rate = "250ms"
+        power_saving = true
+        inhibit_sleep = true
+        tray = true
+        hard_breaks = true
+        tmux = true
+        extend_step = "2m"
+        daily_goal = 8
+        day_start = "04:00"
+        until = 6
+        until_duration = "4h"
+        plan = [["work", "52m"], ["break", "17m", "walk"]]
+        event_file = "/tmp/pomo.fifo"
+        web_address = "127.0.0.1:7878"
+        task_file = "/tmp/todo.txt"
+        on_suspend = "break"
+
+        [hooks]
+        on_work_start = "dnd on"
+
+        [[webhooks]]
+        url = "https://ntfy.sh/pomo"
+        events = ["completed"]
+
+        [notifications]
+        work_end = { title = "Pomodoro {count} done" }
+
+        [sound]
+        file = "/tmp/bell.ogg"
+        volume = 40
+
+        [quiet]
+        hours = "21:00-08:00"
+        dnd = true
+
+        [screen_lock]
+        resume = false
+
+        [idle]
+        after = "5m"
+        command = ["xprintidle"]
+
+        [signals]
+        usr2 = "extend"
+
+        [mqtt]
+        host = "broker.local"
+
+        [slack]
+        token = "xoxp-1"
+        dnd = false
+
+        [speech]
+        command = ["espeak-ng"]
+        break_start = "Break until {ends_at}"
+
+        [time_tracking]
+        service = "clockify"
+        api_key = "secret"
+        workspace = "abc"
+
+        [org]
+        file = "/tmp/pomodoros.org"
+
+        [status_file]
+        path = "/tmp/pomo.txt"
+        format = "text"
+
+        [encryption]
+        passphrase_command = "pass show pomo"
+
+        [schedule]
+        quiet_hours = "22:00-07:00"
+
+        [labels]
+        working = "focus"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(
+        config,
+        Config {
+            work: Duration::from_secs(50 * 60),
+            short_break: Duration::from_secs(10 * 60),
+            long_break: Duration::from_secs(60 * 60),
+            long_break_interval: 3,
+            short_breaks: false,
+            continuous: false,
+            auto_start_breaks: Some(true),
+            auto_start_work: None,
+            strict: true,
+            ack: true,
+            repeat_alarm: Some(Duration::from_secs(60)),
+            count_overtime: false,
+            tick_rate: Duration::from_millis(250),
+            power_saving: true,
+            inhibit_sleep: true,
+            tray: true,
+            hard_breaks: true,
+            tmux: true,
+            extend_step: Duration::from_secs(2 * 60),
+            daily_goal: Some(8),
+            day_start: NaiveTime::from_hms_opt(4, 0, 0),
+            until: Some(6),
+            until_duration: Some(Duration::from_secs(4 * 60 * 60)),
+            plan: vec![
+                Step {
+                    phase: Phase::Working,
+                    length: Duration::from_secs(52 * 60),
+                    label: None,
+                },
+                Step {
+                    phase: Phase::ShortBreak,
+                    length: Duration::from_secs(17 * 60),
+                    label: Some("walk".to_string()),
+                },
+            ],
+            event_file: Some(PathBuf::from("/tmp/pomo.fifo")),
+            web_address: Some(SocketAddr::from(([127, 0, 0, 1], 7878))),
+            task_file: Some(PathBuf::from("/tmp/todo.txt")),
+            on_suspend: SuspendPolicy::Break,
+            hooks: Hooks {
+                on_work_start: Some("dnd on".to_string()),
+                ..Hooks::default()
+            },
+            webhooks: vec![Webhook {
+                url: "https://ntfy.sh/pomo".to_string(),
+                events: vec!["completed".to_string()],
+                body: None,
+                content_type: "application/json".to_string(),
+                headers: Default::default(),
+                retries: 3,
+            }],
+            notifications: Texts {
+                work_end: Text {
+                    title: Some("Pomodoro {count} done".to_string()),
+                    body: None,
+                },
+                ..Texts::default()
+            },
+            sound: Sound {
+                file: Some(PathBuf::from("/tmp/bell.ogg")),
+                volume: 40,
+                mute: false,
+            },
+            quiet: Quiet {
+                hours: Some(QuietHours {
+                    from: NaiveTime::from_hms_opt(21, 0, 0).unwrap(),
+                    to: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+                }),
+                dnd: true,
+            },
+            screen_lock: ScreenLock {
+                pause: true,
+                resume: false,
+            },
+            idle: Idle {
+                after: Some(Duration::from_secs(5 * 60)),
+                command: vec!["xprintidle".to_string()],
+                ..Idle::default()
+            },
+            signals: Signals {
+                usr1: SignalAction::TogglePause,
+                usr2: SignalAction::Extend,
+            },
+            mqtt: Some(Mqtt {
+                host: "broker.local".to_string(),
+                ..Mqtt::default()
+            }),
+            slack: Some(Slack {
+                token: "xoxp-1".to_string(),
+                emoji: ":tomato:".to_string(),
+                text: "Focusing until {ends_at}".to_string(),
+                dnd: false,
+            }),
+            speech: Some(Speech {
+                command: vec!["espeak-ng".to_string()],
+                break_start: "Break until {ends_at}".to_string(),
+                ..Speech::default()
+            }),
+            time_tracking: Some(TimeTracking {
+                service: Service::Clockify,
+                api_key: "secret".to_string(),
+                workspace: "abc".to_string(),
+                projects: BTreeMap::new(),
+                project: None,
+                retries: 3,
+            }),
+            org: Some(Org {
+                file: PathBuf::from("/tmp/pomodoros.org"),
+                heading: "Pomodoros".to_string(),
+            }),
+            status_file: Some(Export {
+                path: PathBuf::from("/tmp/pomo.txt"),
+                format: crate::status_file::Format::Text,
+                template: "{phase} {remaining}".to_string(),
+            }),
+            encryption: Some(Encryption {
+                passphrase_command: "pass show pomo".to_string(),
+            }),
+            schedule: Schedule {
+                start: Vec::new(),
+                quiet_hours: Some(QuietHours {
+                    from: NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+                    to: NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
+                }),
+            },
+            labels: Labels {
+                working: "focus".to_string(),
+                ..Labels::default()
+            },
+            profile: BTreeMap::new(),
+        }
+    );
+}
+
+#[test]
+fn missing_fields_fall_back_to_defaults() {
+    let config: Config = toml::from_str(r#"work = "45m""#).unwrap();
+    assert_eq!(config.work, Duration::from_secs(45 * 60));
+    assert_eq!(config.short_break, Config::default().short_break);
+    assert!(toml::from_str::<Config>("wrok = \"45m\"").is_err());
+}
+
+#[test]
+fn validate_config() {
+    assert!(Config::default().validate().is_ok());
+    let config = Config {
+        work: Duration::from_secs(0),
+        ..Config::default()
+    };
+    assert_eq!(
+        config.validate().unwrap_err().to_string(),
+        "work must last longer than zero"
+    );
+    let config = Config {
+        long_break_interval: 0,
+        short_breaks: false,
+        ..Config::default()
+    };
+    assert!(config.validate().is_ok());
+    let config = Config {
+        until: Some(0),
+        ..Config::default()
+    };
+    assert!(matches!(config.validate(), Err(ConfigError::ZeroUntil)));
+    let config = Config {
+        sound: Sound {
+            volume: 101,
+            ..Sound::default()
+        },
+        ..Config::default()
+    };
+    assert!(matches!(config.validate(), Err(ConfigError::Volume(101))));
+}
+
+#[test]
+fn pick_profiles() {
+    let mut config: Config = toml::from_str(
+        r#"
+        [profile.deepwork]
+        work = "75m"
+        long_break_interval = 2
+
+        [profile.classic]
+        work = "20m"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(
+        config.profile_names(),
+        ["classic", "deepwork", "52-17", "90-20"]
+    );
+    config.apply_profile("deepwork").unwrap();
+    assert_eq!(config.work, Duration::from_secs(75 * 60));
+    assert_eq!(config.short_break, Duration::from_secs(5 * 60));
+    assert_eq!(config.long_break_interval, 2);
+    config.apply_profile("classic").unwrap();
+    assert_eq!(config.work, Duration::from_secs(20 * 60));
+    config.apply_profile("52-17").unwrap();
+    assert_eq!(
+        (config.work, config.short_break),
+        (Duration::from_secs(52 * 60), Duration::from_secs(17 * 60))
+    );
+    assert!(matches!(
+        config.apply_profile("nap"),
+        Err(ConfigError::UnknownProfile(_))
+    ));
+}
+
+#[test]
+fn build_from_config() {
+    let pomodoro = Config::default().pomodoro().unwrap();
+    assert_eq!(pomodoro.remaining_in_phase(), Duration::from_secs(25 * 60));
+    let config: Config = toml::from_str(r#"plan = [["work", "52m"], ["break", "17m"]]"#).unwrap();
+    assert_eq!(
+        config.pomodoro().unwrap().remaining_in_phase(),
+        Duration::from_secs(52 * 60)
+    );
+    let config: Config = toml::from_str(r#"plan = [["break", "17m"]]"#).unwrap();
+    assert!(config.pomodoro().is_err());
+    assert!(toml::from_str::<Config>(r#"plan = [["nap", "17m"]]"#).is_err());
+}
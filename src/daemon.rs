@@ -0,0 +1,425 @@
+//! Control of background pomodoros over a Unix domain socket, or a named pipe on Windows.
+//!
+//! Once the [`protocol`](crate::protocol)'s handshake is done, each connection exchanges
+//! [`Request`]s, each answered by a [`Response`]. A daemon runs the [`DEFAULT_TIMER`] and any number of other timers
+//! started by name, each an independent pomodoro.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncWrite, BufReader};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::event::{InterruptionKind, Until};
+use crate::pomodoro::{Durations, Phase, PomodoroSnapshot, Status};
+use crate::protocol;
+use crate::runtime::Client;
+use crate::transport::{self, Listener};
+
+/// The timer requests go to unless they name another one.
+pub const DEFAULT_TIMER: &str = "default";
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Request {
+    Pause,
+    Resume,
+    /// Pause the running pomodoro or resume the paused one, answered with its status.
+    Toggle,
+    Skip,
+    /// Start the current phase over.
+    Restart,
+    /// Start the counters over.
+    ResetCounters,
+    /// Abandon the current phase for the given one.
+    Goto(Phase),
+    Ack,
+    SetTask(String),
+    Void(String),
+    Interrupt(InterruptionKind, String),
+    /// Change the phase lengths from the next phase on.
+    SetDurations(Durations),
+    Status,
+    /// The state of the engine, as it would be saved.
+    Snapshot,
+    /// Answer once the pomodoro reaches the given point.
+    Wait(Until),
+    /// The request for the timer of the given name instead of the default one.
+    Timer(String, Box<Request>),
+    /// Start a timer of the given name.
+    Start(String),
+    /// Stop the timer of the given name.
+    Stop(String),
+    /// The state of every timer.
+    Timers,
+    /// Stop the daemon, keeping its session for the daemon taking over.
+    Quit,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Response {
+    Done,
+    Status(Status),
+    Snapshot(PomodoroSnapshot),
+    Timers(Vec<(String, Status)>),
+    /// The pomodoro cannot do what was asked in its state, e.g. pause in strict mode.
+    Refused(String),
+    Failed(String),
+}
+
+/// A timer the owner of the daemon is asked to start or stop, or the daemon to quit.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Control {
+    Start(String),
+    Stop(String),
+    Quit,
+}
+
+/// A [`Control`] with the channel to answer it on, with the reason it failed if it did.
+pub type ControlRequest = (Control, oneshot::Sender<Result<(), String>>);
+
+/// The timers a daemon serves, by name. Starting and stopping them is left to whoever
+/// reads the [`ControlRequest`]s, which then updates the timers.
+#[derive(Clone)]
+pub struct Timers {
+    clients: Arc<Mutex<BTreeMap<String, Client>>>,
+    control: mpsc::Sender<ControlRequest>,
+}
+
+impl Timers {
+    pub fn new() -> (Self, mpsc::Receiver<ControlRequest>) {
+        let (control, requests) = mpsc::channel(8);
+        let timers = Self {
+            clients: Arc::default(),
+            control,
+        };
+        (timers, requests)
+    }
+
+    pub fn insert(&self, name: impl Into<String>, client: Client) {
+        self.lock().insert(name.into(), client);
+    }
+
+    pub fn remove(&self, name: &str) -> Option<Client> {
+        self.lock().remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<Client> {
+        self.lock().get(name).cloned()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, Client>> {
+        self.clients.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    async fn control(&self, control: Control) -> Result<(), String> {
+        let (reply, answer) = oneshot::channel();
+        let stopped = || "the daemon is shutting down".to_string();
+        self.control
+            .send((control, reply))
+            .await
+            .map_err(|_| stopped())?;
+        answer.await.map_err(|_| stopped())?
+    }
+}
+
+/// Whether `name` can name a timer, which also names its files.
+pub fn valid_timer_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// The default socket, `$XDG_RUNTIME_DIR/pomo.sock` falling back to the temporary directory,
+/// or the pipe `\\.\pipe\pomo-<user>` on Windows.
+pub fn socket_path() -> PathBuf {
+    transport::default_path()
+}
+
+/// Listens at `path`, replacing a stale socket file left by a dead daemon.
+pub async fn bind(path: impl AsRef<Path>) -> io::Result<Listener> {
+    Listener::bind(path.as_ref()).await
+}
+
+/// Answers requests from every connection accepted by `listener` through the `timers`.
+pub async fn serve(mut listener: Listener, timers: Timers) -> io::Result<()> {
+    loop {
+        let stream = listener.accept().await?;
+        let timers = timers.clone();
+        tokio::spawn(async move {
+            let _ = handle(stream, timers).await;
+        });
+    }
+}
+
+async fn handle<S>(stream: S, timers: Timers) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut reader = BufReader::new(reader);
+    if !protocol::welcome(&mut reader, &mut writer).await? {
+        return Ok(());
+    }
+    loop {
+        let response = match protocol::read(&mut reader).await {
+            Ok(Some(request)) => route(&timers, request).await,
+            Ok(None) => return Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                Response::Failed(format!("malformed request: {}", e))
+            }
+            Err(e) => return Err(e),
+        };
+        protocol::write(&mut writer, &response).await?;
+    }
+}
+
+async fn route(timers: &Timers, request: Request) -> Response {
+    let (name, request) = match request {
+        Request::Timer(name, request) => (name, *request),
+        Request::Start(name) if !valid_timer_name(&name) => {
+            return Response::Failed(format!("invalid timer name: {:?}", name))
+        }
+        Request::Start(name) => return done(timers.control(Control::Start(name)).await),
+        Request::Stop(name) => return done(timers.control(Control::Stop(name)).await),
+        Request::Quit => return done(timers.control(Control::Quit).await),
+        Request::Timers => {
+            let clients: Vec<_> = timers.lock().clone().into_iter().collect();
+            let mut statuses = Vec::new();
+            for (name, client) in clients {
+                if let Ok(status) = client.status().await {
+                    statuses.push((name, status));
+                }
+            }
+            return Response::Timers(statuses);
+        }
+        request => (DEFAULT_TIMER.to_string(), request),
+    };
+    match timers.get(&name) {
+        Some(client) => dispatch(&client, request).await,
+        None => Response::Failed(format!("there is no timer named {}", name)),
+    }
+}
+
+fn done(result: Result<(), String>) -> Response {
+    match result {
+        Ok(()) => Response::Done,
+        Err(reason) => Response::Failed(reason),
+    }
+}
+
+async fn dispatch(client: &Client, request: Request) -> Response {
+    let answered = match request {
+        Request::Pause => client.pause().await.map(|_| Response::Done),
+        Request::Resume => client.resume().await.map(|_| Response::Done),
+        Request::Toggle => client.toggle().await.map(Response::Status),
+        Request::Skip => client.skip().await.map(|_| Response::Done),
+        Request::Restart => client.restart_phase().await.map(|_| Response::Done),
+        Request::ResetCounters => client.reset_counters().await.map(|_| Response::Done),
+        Request::Goto(phase) => client.goto(phase).await.map(|_| Response::Done),
+        Request::Ack => client.ack().await.map(|_| Response::Done),
+        Request::SetTask(task) => client.set_task(task).await.map(|_| Response::Done),
+        Request::Void(reason) => client.void(reason).await.map(|_| Response::Done),
+        Request::Interrupt(kind, note) => {
+            client.interrupt(kind, note).await.map(|_| Response::Done)
+        }
+        Request::SetDurations(durations) => client
+            .set_durations(durations)
+            .await
+            .map(|_| Response::Done),
+        Request::Status => client.status().await.map(Response::Status),
+        Request::Snapshot => client.snapshot().await.map(Response::Snapshot),
+        Request::Wait(until) => client.wait(until).await.map(|_| Response::Done),
+        Request::Timer(..)
+        | Request::Start(_)
+        | Request::Stop(_)
+        | Request::Timers
+        | Request::Quit => {
+            return Response::Failed("a timer cannot be asked about timers".to_string())
+        }
+    };
+    answered.unwrap_or_else(|e| {
+        if e.is_refusal() {
+            Response::Refused(e.to_string())
+        } else {
+            Response::Failed(e.to_string())
+        }
+    })
+}
+
+/// Sends `request` to the daemon listening on `path` and waits for its response.
+pub async fn request(path: impl AsRef<Path>, request: &Request) -> io::Result<Response> {
+    let stream = transport::connect(path.as_ref()).await?;
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut reader = BufReader::new(reader);
+    protocol::greet(&mut reader, &mut writer).await?;
+    protocol::write(&mut writer, request).await?;
+    protocol::read(&mut reader)
+        .await?
+        .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "the daemon hung up"))
+}
+
+/// Removes what the daemon listening at `path` leaves behind once it stopped.
+pub fn clean_up(path: impl AsRef<Path>) {
+    transport::clean_up(path.as_ref())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn control_over_socket() {
+    use std::time::Duration;
+
+    use crate::pomodoro::{Clock, Phase, Pomodoro};
+    use crate::runtime::start;
+
+    let path = if cfg!(windows) {
+        PathBuf::from(format!(r"\\.\pipe\pomo-test-{}", std::process::id()))
+    } else {
+        std::env::temp_dir().join(format!("pomo-test-{}.sock", std::process::id()))
+    };
+    let clock = || Clock::new(Duration::from_secs(60), Duration::from_secs(1));
+    let pomodoro = Pomodoro::new(clock(), clock(), clock(), 4, true, None);
+    let (runtime, client, _events) = start(pomodoro).await;
+    let (timers, mut control) = Timers::new();
+    timers.insert(DEFAULT_TIMER, client);
+    let listener = bind(&path).await.unwrap();
+    let server = tokio::spawn(serve(listener, timers.clone()));
+    assert!(bind(&path).await.is_err());
+
+    assert_eq!(
+        request(&path, &Request::Pause).await.unwrap(),
+        Response::Done
+    );
+    match request(&path, &Request::Status).await.unwrap() {
+        Response::Status(status) => {
+            assert_eq!(status.phase, Phase::Working);
+            assert!(status.paused);
+        }
+        response => panic!("unexpected response {:?}", response),
+    }
+    match request(&path, &Request::Toggle).await.unwrap() {
+        Response::Status(status) => assert!(!status.paused),
+        response => panic!("unexpected response {:?}", response),
+    }
+    assert_eq!(
+        request(&path, &Request::Pause).await.unwrap(),
+        Response::Done
+    );
+    match request(&path, &Request::Snapshot).await.unwrap() {
+        Response::Snapshot(snapshot) => {
+            assert!(snapshot.paused);
+            assert_eq!(snapshot.config.work, Duration::from_secs(60));
+        }
+        response => panic!("unexpected response {:?}", response),
+    }
+    assert!(matches!(
+        request(&path, &Request::Ack).await.unwrap(),
+        Response::Refused(_)
+    ));
+    let longer = Request::SetDurations(Durations {
+        work: Some(Duration::from_secs(50 * 60)),
+        ..Durations::default()
+    });
+    assert_eq!(request(&path, &longer).await.unwrap(), Response::Done);
+    let zero = Request::SetDurations(Durations {
+        work: Some(Duration::ZERO),
+        ..Durations::default()
+    });
+    assert!(matches!(
+        request(&path, &zero).await.unwrap(),
+        Response::Failed(_)
+    ));
+
+    // Starting a timer is up to the owner of the daemon, here this test.
+    let (second, writing, _events) =
+        start(Pomodoro::new(clock(), clock(), clock(), 4, true, None)).await;
+    let owner = tokio::spawn(async move {
+        let (control, reply) = control.recv().await.unwrap();
+        assert_eq!(control, Control::Start("writing".to_string()));
+        timers.insert("writing", writing);
+        reply.send(Ok(())).unwrap();
+    });
+    let start_writing = Request::Start("writing".to_string());
+    assert_eq!(
+        request(&path, &start_writing).await.unwrap(),
+        Response::Done
+    );
+    owner.await.unwrap();
+    let skip_writing = Request::Timer("writing".to_string(), Box::new(Request::Skip));
+    assert_eq!(request(&path, &skip_writing).await.unwrap(), Response::Done);
+    match request(&path, &Request::Timers).await.unwrap() {
+        Response::Timers(timers) => {
+            let phases: Vec<_> = timers
+                .iter()
+                .map(|(name, s)| (name.as_str(), s.phase))
+                .collect();
+            assert_eq!(
+                phases,
+                vec![("default", Phase::Working), ("writing", Phase::ShortBreak)]
+            );
+        }
+        response => panic!("unexpected response {:?}", response),
+    }
+    let pause_study = Request::Timer("study".to_string(), Box::new(Request::Pause));
+    assert!(matches!(
+        request(&path, &pause_study).await.unwrap(),
+        Response::Failed(_)
+    ));
+    assert!(matches!(
+        request(&path, &Request::Start("../x".to_string()))
+            .await
+            .unwrap(),
+        Response::Failed(_)
+    ));
+
+    server.abort();
+    runtime.shutdown().await.unwrap();
+    second.shutdown().await.unwrap();
+    clean_up(&path);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn wait_over_socket() {
+    use std::time::{Duration, Instant};
+
+    use crate::runtime::start;
+
+    let path = if cfg!(windows) {
+        PathBuf::from(format!(r"\\.\pipe\pomo-wait-{}", std::process::id()))
+    } else {
+        std::env::temp_dir().join(format!("pomo-wait-{}.sock", std::process::id()))
+    };
+    let pomodoro = crate::Pomodoro::builder()
+        .work(Duration::from_millis(200))
+        .tick(Duration::from_millis(50))
+        .build()
+        .unwrap();
+    let (runtime, client, _events) = start(pomodoro).await;
+    let (timers, _control) = Timers::new();
+    timers.insert(DEFAULT_TIMER, client);
+    let server = tokio::spawn(serve(bind(&path).await.unwrap(), timers));
+
+    let started = Instant::now();
+    assert_eq!(
+        request(&path, &Request::Wait(Until::WorkEnd))
+            .await
+            .unwrap(),
+        Response::Done
+    );
+    assert!(started.elapsed() >= Duration::from_millis(150));
+
+    let waiting = tokio::spawn({
+        let path = path.clone();
+        async move { request(&path, &Request::Wait(Until::WorkStart)).await }
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    runtime.shutdown().await.unwrap();
+    assert!(matches!(
+        waiting.await.unwrap().unwrap(),
+        Response::Failed(_)
+    ));
+    server.abort();
+    clean_up(&path);
+}
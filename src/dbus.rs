@@ -0,0 +1,137 @@
+//! The daemon as the `dev.pomo.Timer` service on the D-Bus session bus, for desktop shell
+//! extensions and other tools which would rather speak D-Bus than the socket protocol.
+//!
+//! The object at `/dev/pomo/Timer` has the `Pause`, `Resume`, `Toggle`, `Skip` and `Status`
+//! methods and sends the `PhaseStarted` and `PhaseEnded` signals with the name of the phase.
+
+use serde::Serialize;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::Type;
+use zbus::{fdo, interface};
+
+use crate::error::PomoError;
+use crate::event::Event;
+use crate::pomodoro::Status;
+use crate::runtime::Client;
+
+/// The well-known name of the service, which is also the name of its interface.
+pub const NAME: &str = "dev.pomo.Timer";
+/// The path of the timer object.
+pub const PATH: &str = "/dev/pomo/Timer";
+
+/// What the `Status` method returns, as its `stbuu` out arguments.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Type)]
+pub struct TimerStatus {
+    pub phase: String,
+    pub remaining_secs: u64,
+    pub paused: bool,
+    pub pomodoros: u32,
+    pub pauses: u32,
+}
+
+impl From<Status> for TimerStatus {
+    fn from(status: Status) -> Self {
+        Self {
+            phase: status.phase.name().to_string(),
+            remaining_secs: status.remaining.as_secs()
+                + u64::from(status.remaining.subsec_nanos() > 0),
+            paused: status.paused,
+            pomodoros: u32::from(status.pomodoros),
+            pauses: status.pauses,
+        }
+    }
+}
+
+struct Timer {
+    client: Client,
+}
+
+fn failed(e: PomoError) -> fdo::Error {
+    fdo::Error::Failed(e.to_string())
+}
+
+#[interface(name = "dev.pomo.Timer")]
+impl Timer {
+    async fn pause(&self) -> fdo::Result<()> {
+        self.client.pause().await.map_err(failed)
+    }
+
+    async fn resume(&self) -> fdo::Result<()> {
+        self.client.resume().await.map_err(failed)
+    }
+
+    async fn skip(&self) -> fdo::Result<()> {
+        self.client.skip().await.map_err(failed)
+    }
+
+    /// Pauses the running timer or resumes the paused one, and returns how it is left.
+    async fn toggle(&self) -> fdo::Result<TimerStatus> {
+        let status = self.client.toggle().await.map_err(failed)?;
+        Ok(TimerStatus::from(status))
+    }
+
+    async fn status(&self) -> fdo::Result<TimerStatus> {
+        let status = self.client.status().await.map_err(failed)?;
+        Ok(TimerStatus::from(status))
+    }
+
+    #[zbus(signal)]
+    async fn phase_started(emitter: &SignalEmitter<'_>, phase: &str) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn phase_ended(emitter: &SignalEmitter<'_>, phase: &str) -> zbus::Result<()>;
+}
+
+/// Registers the service on the session bus and sends its signals until the pomodoro
+/// behind `client` stops.
+pub async fn serve(client: Client) -> zbus::Result<()> {
+    let mut events = client.subscribe();
+    let connection = zbus::connection::Builder::session()?
+        .name(NAME)?
+        .serve_at(PATH, Timer { client })?
+        .build()
+        .await?;
+    let emitter = SignalEmitter::new(&connection, PATH)?;
+    loop {
+        match events.recv().await {
+            Ok(Event::PhaseStarted(phase)) => Timer::phase_started(&emitter, phase.name()).await?,
+            Ok(Event::PhaseEnded(phase)) => Timer::phase_ended(&emitter, phase.name()).await?,
+            Ok(_) | Err(RecvError::Lagged(_)) => {}
+            Err(RecvError::Closed) => return Ok(()),
+        }
+    }
+}
+
+/// Spawns a task serving the pomodoro behind `client` on the session bus.
+pub fn spawn(client: Client) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        if let Err(e) = serve(client).await {
+            tracing::warn!(name = NAME, error = %e, "cannot serve on the session bus");
+        }
+    })
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn answer_methods() {
+    let pomodoro = crate::Pomodoro::builder().build().unwrap();
+    let (runtime, client, _events) = crate::start(pomodoro).await;
+    let timer = Timer { client };
+    timer.pause().await.unwrap();
+    let status = timer.status().await.unwrap();
+    assert_eq!(
+        status,
+        TimerStatus {
+            phase: "working".to_string(),
+            remaining_secs: 25 * 60,
+            paused: true,
+            pomodoros: 0,
+            pauses: 1,
+        }
+    );
+    assert!(!timer.toggle().await.unwrap().paused);
+    timer.skip().await.unwrap();
+    assert_eq!(timer.status().await.unwrap().phase, "short_break");
+    runtime.shutdown().await.unwrap();
+}
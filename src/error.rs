@@ -0,0 +1,47 @@
+//! Errors raised while driving a pomodoro.
+
+use thiserror::Error;
+use tokio::task::JoinError;
+
+/// A failure building or driving a pomodoro.
+#[derive(Debug, Error)]
+pub enum PomoError {
+    #[error("{0} must last longer than zero")]
+    ZeroDuration(&'static str),
+    #[error("until must be at least 1")]
+    ZeroUntil,
+    #[error("daily_goal must be at least 1")]
+    ZeroGoal,
+    #[error("a plan needs at least one work step")]
+    NoWorkInPlan,
+    /// A working phase was asked to pause while the pomodoro is strict.
+    #[error("a working phase cannot be paused in strict mode")]
+    Strict,
+    #[error("the pomodoro is not in a working phase")]
+    NotWorking,
+    #[error("the pomodoro is not waiting to be acknowledged")]
+    NotAwaitingAck,
+    /// A phase was asked for which the plan has no step of, by its name.
+    #[error("the plan has no {0} step")]
+    NotInPlan(&'static str),
+    /// The engine has stopped and no longer takes signals.
+    #[error("the pomodoro has stopped")]
+    Stopped,
+    /// The engine task panicked or was cancelled.
+    #[error("the pomodoro engine failed: {0}")]
+    Engine(#[from] JoinError),
+}
+
+impl PomoError {
+    /// Whether the pomodoro refused a request it cannot carry out in its state, such as a
+    /// pause in strict mode, rather than failing.
+    pub fn is_refusal(&self) -> bool {
+        matches!(
+            self,
+            PomoError::Strict
+                | PomoError::NotWorking
+                | PomoError::NotAwaitingAck
+                | PomoError::NotInPlan(_)
+        )
+    }
+}
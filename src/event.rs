@@ -0,0 +1,244 @@
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::pomodoro::Phase;
+
+pub(crate) const CAPACITY: usize = 64;
+
+/// The [`Event::name`] of every event.
+pub(crate) const NAMES: &[&str] = &[
+    "phase_started",
+    "phase_ended",
+    "phase_skipped",
+    "phase_aborted",
+    "phase_voided",
+    "phase_extended",
+    "tick",
+    "interrupted",
+    "task_set",
+    "paused",
+    "resumed",
+    "suspended",
+    "goal_reached",
+    "config_reloaded",
+    "awaiting_ack",
+    "acknowledged",
+    "completed",
+];
+
+/// Where an interruption of a working phase came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InterruptionKind {
+    /// The urge to do something else, such as checking mail.
+    Internal,
+    /// Someone or something else, such as a call.
+    External,
+}
+
+impl InterruptionKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            InterruptionKind::Internal => "internal",
+            InterruptionKind::External => "external",
+        }
+    }
+}
+
+impl Display for InterruptionKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for InterruptionKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "internal" => Ok(InterruptionKind::Internal),
+            "external" => Ok(InterruptionKind::External),
+            _ => Err(format!("unknown interruption kind: {}", s)),
+        }
+    }
+}
+
+/// A point of the pomodoro to wait for, until an [`Event`] reaches it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Until {
+    /// The end of whichever phase is running, skipped or abandoned ones included.
+    #[default]
+    PhaseEnd,
+    /// The start of the next phase.
+    PhaseStart,
+    WorkStart,
+    WorkEnd,
+    BreakStart,
+    BreakEnd,
+    /// The end of the session.
+    Completed,
+}
+
+impl Until {
+    pub const ALL: &'static [Until] = &[
+        Until::PhaseEnd,
+        Until::PhaseStart,
+        Until::WorkStart,
+        Until::WorkEnd,
+        Until::BreakStart,
+        Until::BreakEnd,
+        Until::Completed,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Until::PhaseEnd => "phase-end",
+            Until::PhaseStart => "phase-start",
+            Until::WorkStart => "work-start",
+            Until::WorkEnd => "work-end",
+            Until::BreakStart => "break-start",
+            Until::BreakEnd => "break-end",
+            Until::Completed => "completed",
+        }
+    }
+
+    /// Whether `event` is the point waited for.
+    pub fn is_reached_by(&self, event: &Event) -> bool {
+        let ended = match event {
+            Event::PhaseEnded(phase) | Event::PhaseSkipped(phase) | Event::PhaseAborted(phase) => {
+                Some(*phase)
+            }
+            _ => None,
+        };
+        let started = match event {
+            Event::PhaseStarted(phase) => Some(*phase),
+            _ => None,
+        };
+        match self {
+            Until::PhaseEnd => ended.is_some(),
+            Until::PhaseStart => started.is_some(),
+            Until::WorkStart => started == Some(Phase::Working),
+            Until::WorkEnd => ended == Some(Phase::Working),
+            Until::BreakStart => started.is_some_and(|phase| phase != Phase::Working),
+            Until::BreakEnd => ended.is_some_and(|phase| phase != Phase::Working),
+            Until::Completed => *event == Event::Completed,
+        }
+    }
+}
+
+impl Display for Until {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Until {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Until::ALL
+            .iter()
+            .copied()
+            .find(|until| until.name() == s)
+            .ok_or_else(|| format!("unknown point to wait for: {}", s))
+    }
+}
+
+/// Notifications broadcast by a running [`Pomodoro`](crate::Pomodoro).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    PhaseStarted(Phase),
+    PhaseEnded(Phase),
+    PhaseSkipped(Phase),
+    PhaseAborted(Phase),
+    /// The working phase was abandoned for the given reason and started over.
+    PhaseVoided(Phase, String),
+    PhaseExtended(Phase, Duration),
+    Tick {
+        phase: Phase,
+        elapsed: Duration,
+        remaining: Duration,
+    },
+    /// An interruption was logged without stopping the phase.
+    Interrupted {
+        phase: Phase,
+        kind: InterruptionKind,
+        note: String,
+    },
+    /// The pomodoros from now on are spent on this task, or on none when it is empty.
+    TaskSet(String),
+    Paused,
+    Resumed,
+    /// The machine was suspended for about this long while the pomodoro was running.
+    Suspended(Duration),
+    /// The user was away for this long, which was moved from the working phase into the
+    /// pause it was followed by.
+    Idle(Duration),
+    /// The pomodoros completed today reached the daily goal of this many.
+    GoalReached(u32),
+    /// The phase lengths were reloaded from the changed config file.
+    ConfigReloaded,
+    /// The counters started over, on request or at the start of a new day.
+    CountersReset,
+    /// The phase is over and the next one waits to be acknowledged.
+    AwaitingAck(Phase),
+    /// The end of the phase was acknowledged after waiting this long.
+    Acknowledged(Duration),
+    Completed,
+}
+
+impl Event {
+    /// The identifier used for the event in machine-readable output, e.g. `phase_started`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::PhaseStarted(_) => "phase_started",
+            Event::PhaseEnded(_) => "phase_ended",
+            Event::PhaseSkipped(_) => "phase_skipped",
+            Event::PhaseAborted(_) => "phase_aborted",
+            Event::PhaseVoided(..) => "phase_voided",
+            Event::PhaseExtended(..) => "phase_extended",
+            Event::Tick { .. } => "tick",
+            Event::Interrupted { .. } => "interrupted",
+            Event::TaskSet(_) => "task_set",
+            Event::Paused => "paused",
+            Event::Resumed => "resumed",
+            Event::Suspended(_) => "suspended",
+            Event::Idle(_) => "idle",
+            Event::GoalReached(_) => "goal_reached",
+            Event::ConfigReloaded => "config_reloaded",
+            Event::CountersReset => "counters_reset",
+            Event::AwaitingAck(_) => "awaiting_ack",
+            Event::Acknowledged(_) => "acknowledged",
+            Event::Completed => "completed",
+        }
+    }
+
+    /// The phase the event concerns, if any.
+    pub fn phase(&self) -> Option<Phase> {
+        match self {
+            Event::PhaseStarted(phase)
+            | Event::PhaseEnded(phase)
+            | Event::PhaseSkipped(phase)
+            | Event::PhaseAborted(phase)
+            | Event::PhaseVoided(phase, _)
+            | Event::PhaseExtended(phase, _)
+            | Event::AwaitingAck(phase)
+            | Event::Tick { phase, .. }
+            | Event::Interrupted { phase, .. } => Some(*phase),
+            Event::TaskSet(_)
+            | Event::Paused
+            | Event::Resumed
+            | Event::Suspended(_)
+            | Event::Idle(_)
+            | Event::GoalReached(_)
+            | Event::ConfigReloaded
+            | Event::CountersReset
+            | Event::Acknowledged(_)
+            | Event::Completed => None,
+        }
+    }
+}
@@ -0,0 +1,208 @@
+//! A newline-delimited JSON feed of events appended to a file or FIFO, for scripts which
+//! would rather `tail -f` than speak the socket protocol.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+
+use crate::event::{Event, InterruptionKind};
+use crate::pomodoro::Phase;
+
+#[derive(Serialize)]
+struct Line<'a> {
+    at: DateTime<Local>,
+    event: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    phase: Option<Phase>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    elapsed_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    remaining_secs: Option<u64>,
+    /// When the phase is over if it is not paused, as of the tick.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ends_at: Option<DateTime<Local>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extended_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    suspended_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    overtime_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    task: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kind: Option<InterruptionKind>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    note: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    goal: Option<u32>,
+}
+
+/// Encodes `event` as a single JSON line, without the trailing newline.
+pub fn encode(event: &Event, at: DateTime<Local>) -> String {
+    let (extended_secs, suspended_secs) = match event {
+        Event::PhaseExtended(_, by) => (Some(by.as_secs()), None),
+        Event::Suspended(gap) => (None, Some(gap.as_secs())),
+        _ => (None, None),
+    };
+    let (elapsed_secs, remaining_secs) = match event {
+        Event::Tick {
+            elapsed, remaining, ..
+        } => (
+            Some(elapsed.as_secs()),
+            // Rounded up, so a countdown only shows zero once it is over.
+            Some(remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0)),
+        ),
+        _ => (None, None),
+    };
+    let line = Line {
+        at,
+        event: event.name(),
+        phase: event.phase(),
+        elapsed_secs,
+        remaining_secs,
+        ends_at: match event {
+            Event::Tick { remaining, .. } => Some(at + *remaining),
+            _ => None,
+        },
+        extended_secs,
+        suspended_secs,
+        overtime_secs: match event {
+            Event::Acknowledged(overtime) => Some(overtime.as_secs()),
+            _ => None,
+        },
+        reason: match event {
+            Event::PhaseVoided(_, reason) => Some(reason),
+            _ => None,
+        },
+        task: match event {
+            Event::TaskSet(task) => Some(task),
+            _ => None,
+        },
+        kind: match event {
+            Event::Interrupted { kind, .. } => Some(*kind),
+            _ => None,
+        },
+        note: match event {
+            Event::Interrupted { note, .. } => Some(note),
+            _ => None,
+        },
+        goal: match event {
+            Event::GoalReached(target) => Some(*target),
+            _ => None,
+        },
+    };
+    serde_json::to_string(&line).unwrap_or_default()
+}
+
+fn append(file: &mut Option<File>, path: &Path, line: &str) -> io::Result<()> {
+    let file = match file {
+        Some(file) => file,
+        None => file.insert(OpenOptions::new().create(true).append(true).open(path)?),
+    };
+    writeln!(file, "{}", line)
+}
+
+/// Spawns a task appending every event but the ticks to `path`.
+///
+/// The file is reopened after a failed write, so a FIFO survives its reader going away.
+/// Opening a FIFO waits for a reader, so do not wait for this task to finish.
+pub fn spawn(path: PathBuf, mut events: broadcast::Receiver<Event>) -> JoinHandle<()> {
+    tokio::task::spawn_blocking(move || {
+        let mut file = None;
+        loop {
+            let event = match events.blocking_recv() {
+                Ok(Event::Tick { .. }) | Err(RecvError::Lagged(_)) => continue,
+                Ok(event) => event,
+                Err(RecvError::Closed) => break,
+            };
+            if let Err(e) = append(&mut file, &path, &encode(&event, Local::now())) {
+                tracing::warn!(path = %path.display(), error = %e, "cannot write the event");
+                file = None;
+            }
+        }
+    })
+}
+
+#[test]
+fn encode_events() {
+    use chrono::TimeZone;
+    use std::time::Duration;
+
+    let at = Local.with_ymd_and_hms(2024, 1, 2, 9, 0, 0).unwrap();
+    let decode = |event| serde_json::from_str::<serde_json::Value>(&encode(&event, at)).unwrap();
+    let line = decode(Event::PhaseStarted(Phase::Working));
+    assert_eq!(line["event"], "phase_started");
+    assert_eq!(line["phase"], "working");
+    let written: DateTime<Local> = serde_json::from_value(line["at"].clone()).unwrap();
+    assert_eq!(written, at);
+    assert!(line.get("extended_secs").is_none());
+    let line = decode(Event::PhaseExtended(
+        Phase::ShortBreak,
+        Duration::from_secs(120),
+    ));
+    assert_eq!(line["extended_secs"], 120);
+    let line = decode(Event::Suspended(Duration::from_secs(3600)));
+    assert_eq!(line["suspended_secs"], 3600);
+    let line = decode(Event::Acknowledged(Duration::from_secs(90)));
+    assert_eq!(line["overtime_secs"], 90);
+    let line = decode(Event::PhaseVoided(Phase::Working, "call".to_string()));
+    assert_eq!(line["reason"], "call");
+    let line = decode(Event::Interrupted {
+        phase: Phase::Working,
+        kind: InterruptionKind::Internal,
+        note: "mail".to_string(),
+    });
+    assert_eq!(
+        (&line["kind"], &line["note"]),
+        (&"internal".into(), &"mail".into())
+    );
+    let line = decode(Event::Tick {
+        phase: Phase::Working,
+        elapsed: Duration::from_millis(60_500),
+        remaining: Duration::from_millis(239_500),
+    });
+    assert_eq!(
+        (&line["elapsed_secs"], &line["remaining_secs"]),
+        (&60.into(), &240.into())
+    );
+    let ends_at: DateTime<Local> = serde_json::from_value(line["ends_at"].clone()).unwrap();
+    assert_eq!(ends_at, at + Duration::from_millis(239_500));
+    assert!(decode(Event::Paused).get("phase").is_none());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn append_events_to_file() {
+    use std::time::Duration;
+
+    let path = std::env::temp_dir().join(format!("pomo-feed-{}.ndjson", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let (sender, receiver) = broadcast::channel(8);
+    let writer = spawn(path.clone(), receiver);
+    sender.send(Event::Resumed).unwrap();
+    sender
+        .send(Event::Tick {
+            phase: Phase::Working,
+            elapsed: Duration::from_secs(1),
+            remaining: Duration::from_secs(1),
+        })
+        .unwrap();
+    sender.send(Event::Completed).unwrap();
+    drop(sender);
+    writer.await.unwrap();
+    let written = std::fs::read_to_string(&path).unwrap();
+    let events: Vec<serde_json::Value> = written
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0]["event"], "resumed");
+    assert_eq!(events[1]["event"], "completed");
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,801 @@
+//! Persistent record of every finished phase, stored in SQLite.
+//!
+//! With the `encryption` feature, SQLite is built as SQLCipher and the database may be
+//! encrypted with a passphrase, given in the `POMO_HISTORY_PASSPHRASE` environment variable
+//! or printed by the command of the `[encryption]` table. That command may decrypt the
+//! passphrase with an age key, e.g. `age -d -i ~/.config/age/key.txt passphrase.age`.
+//!
+//! Only the database is encrypted: `pomo export` writes the records in plain text, to be
+//! piped through `age -r` when they should stay encrypted. The daemon leaves the task out
+//! of the session checkpoint and of the status file while the history is encrypted, so a
+//! recovered session starts without one, but a `status_file` export with `{task}` in its
+//! template still writes it.
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use rusqlite::{ffi, params, Connection, OptionalExtension};
+use serde::Deserialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+
+use crate::event::{Event, InterruptionKind};
+use crate::hooks;
+use crate::pomodoro::Phase;
+
+/// How a phase came to an end.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Completed,
+    Skipped,
+    Aborted,
+    /// Abandoned and started over, as an interrupted pomodoro does not count.
+    Voided,
+}
+
+impl Outcome {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Outcome::Completed => "completed",
+            Outcome::Skipped => "skipped",
+            Outcome::Aborted => "aborted",
+            Outcome::Voided => "voided",
+        }
+    }
+}
+
+impl FromStr for Outcome {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "completed" => Ok(Outcome::Completed),
+            "skipped" => Ok(Outcome::Skipped),
+            "aborted" => Ok(Outcome::Aborted),
+            "voided" => Ok(Outcome::Voided),
+            _ => Err(format!("unknown outcome: {}", s)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Record {
+    pub phase: Phase,
+    pub outcome: Outcome,
+    pub started_at: DateTime<Local>,
+    pub ended_at: DateTime<Local>,
+    /// Time the phase spent paused.
+    pub paused: Duration,
+    pub pauses: u32,
+    /// Why the phase was voided.
+    pub reason: Option<String>,
+    /// The task the phase was spent on.
+    pub task: Option<String>,
+    pub interruptions: Vec<Interruption>,
+    /// How long the phase was over before the next one was started.
+    pub overtime: Duration,
+}
+
+/// An interruption logged while a working phase ran on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Interruption {
+    pub kind: InterruptionKind,
+    pub note: String,
+    pub at: DateTime<Local>,
+}
+
+/// The environment variable holding the passphrase of the database, which takes over from
+/// the [`Encryption`] of the config.
+pub const PASSPHRASE_VAR: &str = "POMO_HISTORY_PASSPHRASE";
+
+/// Takes the passphrase out of the environment, so that neither the hooks nor the other
+/// commands pomo runs inherit it. The variable is only removed safely before other threads
+/// start.
+pub fn take_passphrase() -> io::Result<Option<String>> {
+    let passphrase = std::env::var_os(PASSPHRASE_VAR);
+    if passphrase.is_some() {
+        std::env::remove_var(PASSPHRASE_VAR);
+    }
+    passphrase_from(passphrase)
+}
+
+/// The passphrase `value` of the environment variable holds, if it is set.
+fn passphrase_from(value: Option<OsString>) -> io::Result<Option<String>> {
+    let passphrase = match value {
+        Some(passphrase) => passphrase,
+        None => return Ok(None),
+    };
+    let passphrase = passphrase.into_string().map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} is not valid UTF-8", PASSPHRASE_VAR),
+        )
+    })?;
+    if passphrase.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} is empty", PASSPHRASE_VAR),
+        ));
+    }
+    Ok(Some(passphrase))
+}
+
+/// Where the passphrase of the database comes from, read from the `[encryption]` table of
+/// the config file.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Encryption {
+    /// A shell command printing the passphrase, such as `pass show pomo`.
+    pub passphrase_command: String,
+}
+
+impl Encryption {
+    /// Runs the command for the passphrase, leaving out the line break it ends with.
+    pub fn passphrase(&self) -> io::Result<String> {
+        let output = hooks::shell_command(&self.passphrase_command)
+            .stdin(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .output()?;
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "`{}` failed with {}",
+                self.passphrase_command, output.status
+            )));
+        }
+        let passphrase = String::from_utf8(output.stdout)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let passphrase = passphrase.trim_end_matches(['\r', '\n']);
+        if passphrase.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("`{}` printed no passphrase", self.passphrase_command),
+            ));
+        }
+        Ok(passphrase.to_string())
+    }
+}
+
+/// A database on disk, with the passphrase it is encrypted with when it is.
+#[derive(Clone)]
+pub struct Store {
+    pub path: PathBuf,
+    passphrase: Option<String>,
+}
+
+impl Store {
+    pub fn new(path: PathBuf, passphrase: Option<String>) -> Self {
+        Self { path, passphrase }
+    }
+
+    pub fn open(&self) -> rusqlite::Result<History> {
+        match &self.passphrase {
+            Some(passphrase) => History::open_encrypted(&self.path, passphrase),
+            None => History::open(&self.path),
+        }
+    }
+}
+
+pub struct History {
+    conn: Connection,
+}
+
+impl History {
+    /// The default database, `$XDG_DATA_HOME/pomo/history.sqlite3` on Linux.
+    pub fn path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("pomo").join("history.sqlite3"))
+    }
+
+    /// Opens the database at `path`, creating it and its parent directory when missing.
+    pub fn open(path: impl AsRef<Path>) -> rusqlite::Result<Self> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        Self::setup(Connection::open(path)?)
+    }
+
+    /// Opens the database at `path` encrypted with `passphrase`, creating it when missing.
+    /// A database left unencrypted by earlier runs is encrypted in place.
+    pub fn open_encrypted(path: impl AsRef<Path>, passphrase: &str) -> rusqlite::Result<Self> {
+        // Fails before the file is created when SQLite cannot encrypt it.
+        require_cipher(&Connection::open_in_memory()?)?;
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        let conn = Connection::open(path)?;
+        key(&conn, passphrase)?;
+        if readable(&conn).is_err() {
+            drop(conn);
+            encrypt(path, passphrase)?;
+            let conn = Connection::open(path)?;
+            key(&conn, passphrase)?;
+            return Self::setup(conn);
+        }
+        Self::setup(conn)
+    }
+
+    pub fn open_in_memory() -> rusqlite::Result<Self> {
+        Self::setup(Connection::open_in_memory()?)
+    }
+
+    fn setup(conn: Connection) -> rusqlite::Result<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS phases (
+                id INTEGER PRIMARY KEY,
+                phase TEXT NOT NULL,
+                outcome TEXT NOT NULL,
+                started_at TEXT NOT NULL,
+                ended_at TEXT NOT NULL,
+                paused_secs INTEGER NOT NULL DEFAULT 0,
+                pauses INTEGER NOT NULL DEFAULT 0,
+                reason TEXT,
+                task TEXT,
+                overtime_secs INTEGER NOT NULL DEFAULT 0
+            );
+            CREATE TABLE IF NOT EXISTS interruptions (
+                id INTEGER PRIMARY KEY,
+                phase_id INTEGER NOT NULL REFERENCES phases (id),
+                kind TEXT NOT NULL,
+                note TEXT NOT NULL,
+                at TEXT NOT NULL
+            );",
+        )?;
+        Self::migrate(&conn)?;
+        Ok(Self { conn })
+    }
+
+    /// Adds the columns missing from databases created by older versions.
+    fn migrate(conn: &Connection) -> rusqlite::Result<()> {
+        let mut statement = conn.prepare("SELECT name FROM pragma_table_info('phases')")?;
+        let columns = statement
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let added = [
+            ("paused_secs", "INTEGER NOT NULL DEFAULT 0"),
+            ("pauses", "INTEGER NOT NULL DEFAULT 0"),
+            ("reason", "TEXT"),
+            ("task", "TEXT"),
+            ("overtime_secs", "INTEGER NOT NULL DEFAULT 0"),
+        ];
+        for (column, definition) in added.iter() {
+            if !columns.iter().any(|name| name == column) {
+                conn.execute_batch(&format!(
+                    "ALTER TABLE phases ADD COLUMN {} {};",
+                    column, definition
+                ))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Stores `record`, returning the id to [amend](Self::record_overtime) it with.
+    pub fn record(&self, record: &Record) -> rusqlite::Result<i64> {
+        let transaction = self.conn.unchecked_transaction()?;
+        let phase_id = insert(&transaction, record)?;
+        transaction.commit()?;
+        Ok(phase_id)
+    }
+
+    /// Stores the `records` which are not stored yet, returning how many were. Records of
+    /// the same phase starting within the same second are taken for the same one.
+    pub fn merge(&self, records: &[Record]) -> rusqlite::Result<usize> {
+        let key = |record: &Record| (record.phase.name(), record.started_at.timestamp());
+        let mut known: HashSet<_> = self.records()?.iter().map(key).collect();
+        let transaction = self.conn.unchecked_transaction()?;
+        let mut merged = 0;
+        for record in records {
+            if known.insert(key(record)) {
+                insert(&transaction, record)?;
+                merged += 1;
+            }
+        }
+        transaction.commit()?;
+        Ok(merged)
+    }
+
+    /// Sets the overtime of the phase stored as `id`, which is only known once the next
+    /// phase starts.
+    pub fn record_overtime(&self, id: i64, overtime: Duration) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "UPDATE phases SET overtime_secs = ?1 WHERE id = ?2",
+            params![overtime.as_secs() as i64, id],
+        )?;
+        Ok(())
+    }
+
+    /// Every record, oldest first.
+    pub fn records(&self) -> rusqlite::Result<Vec<Record>> {
+        let mut interruptions = self.interruptions()?;
+        let mut statement = self.conn.prepare(
+            "SELECT phase, outcome, started_at, ended_at, paused_secs, pauses, reason, task, id,
+                    overtime_secs
+             FROM phases ORDER BY started_at, id",
+        )?;
+        let rows = statement.query_map([], |row| {
+            let phase: String = row.get(0)?;
+            let outcome: String = row.get(1)?;
+            Ok(Record {
+                phase: phase.parse().map_err(|e| invalid_column(0, e))?,
+                outcome: outcome.parse().map_err(|e| invalid_column(1, e))?,
+                started_at: row.get(2)?,
+                ended_at: row.get(3)?,
+                paused: Duration::from_secs(row.get::<_, i64>(4)? as u64),
+                pauses: row.get(5)?,
+                reason: row.get(6)?,
+                task: row.get(7)?,
+                interruptions: interruptions.remove(&row.get(8)?).unwrap_or_default(),
+                overtime: Duration::from_secs(row.get::<_, i64>(9)? as u64),
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Every interruption keyed by the id of its phase, oldest first.
+    fn interruptions(&self) -> rusqlite::Result<HashMap<i64, Vec<Interruption>>> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT phase_id, kind, note, at FROM interruptions ORDER BY id")?;
+        let rows = statement.query_map([], |row| {
+            let kind: String = row.get(1)?;
+            let interruption = Interruption {
+                kind: kind.parse().map_err(|e| invalid_column(1, e))?,
+                note: row.get(2)?,
+                at: row.get(3)?,
+            };
+            Ok((row.get::<_, i64>(0)?, interruption))
+        })?;
+        let mut interruptions: HashMap<i64, Vec<Interruption>> = HashMap::new();
+        for row in rows {
+            let (phase_id, interruption) = row?;
+            interruptions
+                .entry(phase_id)
+                .or_default()
+                .push(interruption);
+        }
+        Ok(interruptions)
+    }
+}
+
+fn insert(conn: &Connection, record: &Record) -> rusqlite::Result<i64> {
+    conn.execute(
+        "INSERT INTO phases
+         (phase, outcome, started_at, ended_at, paused_secs, pauses, reason, task,
+          overtime_secs)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            record.phase.name(),
+            record.outcome.name(),
+            record.started_at,
+            record.ended_at,
+            record.paused.as_secs() as i64,
+            record.pauses,
+            record.reason,
+            record.task,
+            record.overtime.as_secs() as i64
+        ],
+    )?;
+    let phase_id = conn.last_insert_rowid();
+    for interruption in &record.interruptions {
+        conn.execute(
+            "INSERT INTO interruptions (phase_id, kind, note, at) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                phase_id,
+                interruption.kind.name(),
+                interruption.note,
+                interruption.at
+            ],
+        )?;
+    }
+    Ok(phase_id)
+}
+
+/// Has SQLCipher read `conn` with `passphrase`, failing when SQLite was built without it.
+fn key(conn: &Connection, passphrase: &str) -> rusqlite::Result<()> {
+    // Keeps SQLCipher from logging to stderr as a wrong passphrase is tried.
+    conn.pragma_update(None, "cipher_log_level", "NONE")?;
+    conn.pragma_update(None, "key", passphrase)?;
+    require_cipher(conn)
+}
+
+/// Fails unless SQLite was built as SQLCipher, which `conn` tells by its cipher version.
+fn require_cipher(conn: &Connection) -> rusqlite::Result<()> {
+    let version = conn
+        .query_row("PRAGMA cipher_version", [], |row| row.get::<_, String>(0))
+        .optional()?;
+    if version.is_none() {
+        return Err(rusqlite::Error::SqliteFailure(
+            ffi::Error::new(ffi::SQLITE_MISUSE),
+            Some("this build cannot encrypt the history, it lacks the encryption feature".into()),
+        ));
+    }
+    Ok(())
+}
+
+/// Fails unless the schema of `conn` can be read, as it cannot with the wrong passphrase.
+fn readable(conn: &Connection) -> rusqlite::Result<()> {
+    conn.query_row("SELECT count(*) FROM sqlite_master", [], |_| Ok(()))
+}
+
+/// Replaces the unencrypted database at `path` with a copy encrypted with `passphrase`,
+/// failing when it is encrypted with another one.
+fn encrypt(path: &Path, passphrase: &str) -> rusqlite::Result<()> {
+    let conn = Connection::open(path)?;
+    readable(&conn).map_err(|_| {
+        rusqlite::Error::SqliteFailure(
+            ffi::Error::new(ffi::SQLITE_NOTADB),
+            Some("the passphrase does not open the history".into()),
+        )
+    })?;
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+    let _ = std::fs::remove_file(&partial);
+    conn.execute(
+        "ATTACH DATABASE ?1 AS encrypted KEY ?2",
+        params![partial.to_string_lossy(), passphrase],
+    )?;
+    conn.query_row("SELECT sqlcipher_export('encrypted')", [], |_| Ok(()))?;
+    conn.execute_batch("DETACH DATABASE encrypted")?;
+    drop(conn);
+    std::fs::rename(&partial, path).map_err(|e| {
+        rusqlite::Error::SqliteFailure(
+            ffi::Error::new(ffi::SQLITE_CANTOPEN),
+            Some(format!("cannot replace {}: {}", path.display(), e)),
+        )
+    })
+}
+
+fn invalid_column(index: usize, reason: String) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, reason.into())
+}
+
+/// Tracks when phases start and writes a [`Record`] whenever one ends.
+struct Recorder {
+    started_at: Option<(Phase, DateTime<Local>)>,
+    paused_at: Option<DateTime<Local>>,
+    paused: Duration,
+    pauses: u32,
+    interruptions: Vec<Interruption>,
+    task: Option<String>,
+}
+
+impl Recorder {
+    fn new() -> Self {
+        Self {
+            started_at: None,
+            paused_at: None,
+            paused: Duration::from_secs(0),
+            pauses: 0,
+            interruptions: Vec::new(),
+            task: None,
+        }
+    }
+
+    fn observe(&mut self, event: &Event, now: DateTime<Local>) -> Option<Record> {
+        let mut reason = None;
+        let (phase, outcome) = match event {
+            Event::PhaseStarted(phase) => {
+                self.started_at = Some((*phase, now));
+                self.paused = Duration::from_secs(0);
+                self.pauses = 0;
+                self.interruptions.clear();
+                return None;
+            }
+            Event::TaskSet(task) => {
+                self.task = Some(task.clone()).filter(|task| !task.is_empty());
+                return None;
+            }
+            Event::Interrupted { kind, note, .. } if self.started_at.is_some() => {
+                self.interruptions.push(Interruption {
+                    kind: *kind,
+                    note: note.clone(),
+                    at: now,
+                });
+                return None;
+            }
+            Event::Paused if self.started_at.is_some() => {
+                self.paused_at = Some(now);
+                self.pauses += 1;
+                return None;
+            }
+            Event::Resumed => {
+                self.close_pause(now);
+                return None;
+            }
+            Event::PhaseEnded(phase) => (*phase, Outcome::Completed),
+            Event::PhaseSkipped(phase) => (*phase, Outcome::Skipped),
+            Event::PhaseAborted(phase) => (*phase, Outcome::Aborted),
+            Event::PhaseVoided(phase, why) => {
+                reason = Some(why.clone()).filter(|why| !why.is_empty());
+                (*phase, Outcome::Voided)
+            }
+            _ => return None,
+        };
+        self.close_pause(now);
+        let interruptions = std::mem::take(&mut self.interruptions);
+        let (started_at, paused, pauses, interruptions) = match self.started_at.take() {
+            Some((started, at)) if started == phase => {
+                (at, self.paused, self.pauses, interruptions)
+            }
+            _ => (now, Duration::from_secs(0), 0, Vec::new()),
+        };
+        Some(Record {
+            phase,
+            outcome,
+            started_at,
+            ended_at: now,
+            paused,
+            pauses,
+            reason,
+            task: self.task.clone(),
+            interruptions,
+            overtime: Duration::from_secs(0),
+        })
+    }
+
+    fn close_pause(&mut self, now: DateTime<Local>) {
+        if let Some(at) = self.paused_at.take() {
+            self.paused += (now - at).to_std().unwrap_or_default();
+        }
+    }
+}
+
+/// Spawns a thread which writes a [`Record`] into `history` for every phase that ends,
+/// adding its overtime once it is acknowledged.
+pub fn spawn(history: History, mut events: broadcast::Receiver<Event>) -> JoinHandle<()> {
+    tokio::task::spawn_blocking(move || {
+        let mut recorder = Recorder::new();
+        let mut last = None;
+        loop {
+            let event = match events.blocking_recv() {
+                Ok(event) => event,
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            };
+            let stored = match (&event, last) {
+                (Event::Acknowledged(overtime), Some(id)) => {
+                    last = None;
+                    history.record_overtime(id, *overtime)
+                }
+                _ => match recorder.observe(&event, Local::now()) {
+                    Some(record) => history.record(&record).map(|id| last = Some(id)),
+                    None => Ok(()),
+                },
+            };
+            if let Err(e) = stored {
+                tracing::warn!(error = %e, "failed to record history");
+            }
+        }
+    })
+}
+
+#[test]
+fn store_records() {
+    use chrono::TimeZone;
+
+    let history = History::open_in_memory().unwrap();
+    let record = Record {
+        phase: Phase::ShortBreak,
+        outcome: Outcome::Skipped,
+        started_at: Local.with_ymd_and_hms(2024, 1, 2, 9, 0, 0).unwrap(),
+        ended_at: Local.with_ymd_and_hms(2024, 1, 2, 9, 3, 0).unwrap(),
+        paused: Duration::from_secs(42),
+        pauses: 2,
+        reason: Some("phone call".to_string()),
+        task: Some("report".to_string()),
+        interruptions: vec![Interruption {
+            kind: InterruptionKind::External,
+            note: "door".to_string(),
+            at: Local.with_ymd_and_hms(2024, 1, 2, 9, 1, 0).unwrap(),
+        }],
+        overtime: Duration::from_secs(0),
+    };
+    let other = Record {
+        interruptions: Vec::new(),
+        ..record.clone()
+    };
+    history.record(&record).unwrap();
+    let id = history.record(&other).unwrap();
+    history
+        .record_overtime(id, Duration::from_secs(90))
+        .unwrap();
+    let other = Record {
+        overtime: Duration::from_secs(90),
+        ..other
+    };
+    assert_eq!(history.records().unwrap(), vec![record, other]);
+}
+
+#[test]
+fn merge_records() {
+    use chrono::TimeZone;
+
+    let history = History::open_in_memory().unwrap();
+    let at = |minute| Local.with_ymd_and_hms(2024, 1, 2, 9, minute, 0).unwrap();
+    let record = |phase, minute| Record {
+        phase,
+        outcome: Outcome::Completed,
+        started_at: at(minute),
+        ended_at: at(minute + 5),
+        paused: Duration::from_secs(0),
+        pauses: 0,
+        reason: None,
+        task: None,
+        interruptions: Vec::new(),
+        overtime: Duration::from_secs(0),
+    };
+    history.record(&record(Phase::Working, 0)).unwrap();
+    let imported = [
+        record(Phase::Working, 0),
+        record(Phase::ShortBreak, 0),
+        record(Phase::Working, 10),
+        record(Phase::Working, 10),
+    ];
+    assert_eq!(history.merge(&imported).unwrap(), 2);
+    assert_eq!(history.merge(&imported).unwrap(), 0);
+    assert_eq!(history.records().unwrap().len(), 3);
+}
+
+#[test]
+fn record_phase_endings() {
+    use chrono::TimeZone;
+
+    let at = |minute| Local.with_ymd_and_hms(2024, 1, 2, 9, minute, 0).unwrap();
+    let mut recorder = Recorder::new();
+    assert_eq!(
+        recorder.observe(&Event::PhaseStarted(Phase::Working), at(0)),
+        None
+    );
+    assert_eq!(recorder.observe(&Event::Paused, at(10)), None);
+    assert_eq!(recorder.observe(&Event::Resumed, at(13)), None);
+    assert_eq!(
+        recorder.observe(&Event::PhaseEnded(Phase::Working), at(25)),
+        Some(Record {
+            phase: Phase::Working,
+            outcome: Outcome::Completed,
+            started_at: at(0),
+            ended_at: at(25),
+            paused: Duration::from_secs(3 * 60),
+            pauses: 1,
+            reason: None,
+            task: None,
+            interruptions: Vec::new(),
+            overtime: Duration::from_secs(0),
+        })
+    );
+    recorder.observe(&Event::Paused, at(25));
+    recorder.observe(&Event::Resumed, at(26));
+    recorder.observe(&Event::PhaseStarted(Phase::ShortBreak), at(26));
+    recorder.observe(&Event::Paused, at(27));
+    let record = recorder
+        .observe(&Event::PhaseAborted(Phase::ShortBreak), at(29))
+        .unwrap();
+    assert_eq!(record.outcome, Outcome::Aborted);
+    assert_eq!(record.paused, Duration::from_secs(2 * 60));
+    assert_eq!(record.pauses, 1);
+    let record = recorder
+        .observe(&Event::PhaseSkipped(Phase::Working), at(30))
+        .unwrap();
+    assert_eq!(record.started_at, at(30));
+    recorder.observe(&Event::PhaseStarted(Phase::Working), at(30));
+    recorder.observe(&Event::TaskSet("report".to_string()), at(31));
+    let interrupted = Event::Interrupted {
+        phase: Phase::Working,
+        kind: InterruptionKind::Internal,
+        note: "mail".to_string(),
+    };
+    recorder.observe(&interrupted, at(32));
+    let voided = Event::PhaseVoided(Phase::Working, "meeting".to_string());
+    let record = recorder.observe(&voided, at(34)).unwrap();
+    assert_eq!(record.outcome, Outcome::Voided);
+    assert_eq!(record.reason.as_deref(), Some("meeting"));
+    assert_eq!(record.interruptions.len(), 1);
+    assert_eq!(record.interruptions[0].at, at(32));
+    assert_eq!(record.task.as_deref(), Some("report"));
+    assert_eq!(record.started_at, at(30));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn spawn_recorder() {
+    let path = std::env::temp_dir().join(format!("pomo-history-{}.sqlite3", std::process::id()));
+    let (sender, receiver) = broadcast::channel(8);
+    let handle = spawn(History::open(&path).unwrap(), receiver);
+    sender.send(Event::PhaseStarted(Phase::Working)).unwrap();
+    sender.send(Event::PhaseEnded(Phase::Working)).unwrap();
+    sender.send(Event::AwaitingAck(Phase::Working)).unwrap();
+    sender
+        .send(Event::Acknowledged(Duration::from_secs(120)))
+        .unwrap();
+    drop(sender);
+    handle.await.unwrap();
+    let records = History::open(&path).unwrap().records().unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].outcome, Outcome::Completed);
+    assert_eq!(records[0].overtime, Duration::from_secs(120));
+}
+
+#[test]
+fn migrate_old_database() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        "CREATE TABLE phases (
+            id INTEGER PRIMARY KEY,
+            phase TEXT NOT NULL,
+            outcome TEXT NOT NULL,
+            started_at TEXT NOT NULL,
+            ended_at TEXT NOT NULL
+        );
+        INSERT INTO phases (phase, outcome, started_at, ended_at)
+        VALUES ('working', 'completed', '2024-01-02T09:00:00+00:00', '2024-01-02T09:25:00+00:00');",
+    )
+    .unwrap();
+    let history = History::setup(conn).unwrap();
+    let records = history.records().unwrap();
+    assert_eq!(records[0].paused, Duration::from_secs(0));
+    assert_eq!(records[0].pauses, 0);
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+fn encrypt_database() {
+    let path = std::env::temp_dir().join(format!("pomo-encrypted-{}.sqlite3", std::process::id()));
+    let now = Local::now();
+    let mut record = Record {
+        phase: Phase::Working,
+        outcome: Outcome::Completed,
+        started_at: now,
+        ended_at: now,
+        paused: Duration::from_secs(0),
+        pauses: 0,
+        reason: None,
+        task: None,
+        interruptions: Vec::new(),
+        overtime: Duration::from_secs(0),
+    };
+    let history = History::open(&path).unwrap();
+    history.record(&record).unwrap();
+    drop(history);
+
+    let history = History::open_encrypted(&path, "correct horse").unwrap();
+    record.task = Some("client: acme".to_string());
+    history.record(&record).unwrap();
+    assert_eq!(history.records().unwrap().len(), 2);
+    drop(history);
+    let contents = std::fs::read(&path).unwrap();
+    assert!(!contents.windows(4).any(|bytes| bytes == b"acme"));
+    assert!(History::open(&path).is_err());
+    assert!(History::open_encrypted(&path, "battery staple").is_err());
+    let records = History::open_encrypted(&path, "correct horse")
+        .unwrap()
+        .records()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(records[1].task.as_deref(), Some("client: acme"));
+}
+
+#[cfg(not(feature = "encryption"))]
+#[test]
+fn refuse_to_encrypt() {
+    let path = std::env::temp_dir().join(format!("pomo-encrypted-{}.sqlite3", std::process::id()));
+    assert!(History::open_encrypted(&path, "correct horse").is_err());
+    assert!(!path.exists());
+}
+
+#[test]
+fn take_passphrase_from_environment() {
+    let set = |value: &str| Some(OsString::from(value));
+    assert_eq!(
+        passphrase_from(set("correct horse")).unwrap().as_deref(),
+        Some("correct horse")
+    );
+    assert_eq!(passphrase_from(None).unwrap(), None);
+    assert!(passphrase_from(set("")).is_err());
+}
@@ -0,0 +1,271 @@
+//! Shell commands run on lifecycle events, configured under `[hooks]` in the config file.
+//!
+//! The commands get the state of the pomodoro in `POMO_*` environment variables. On Unix
+//! they may also use the placeholders of [`template`](crate::template), which are filled
+//! in quoted for `sh`. `cmd` has no quoting to speak of, so on Windows the hooks with
+//! placeholders are not run, and the task is only passed in `POMO_TASK`.
+
+use std::io;
+use std::process::ExitStatus;
+
+use serde::Deserialize;
+use tokio::process::Command;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+
+use crate::event::Event;
+use crate::pomodoro::{Labels, Phase, Status};
+use crate::runtime::Client;
+use crate::template::{self, Values};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Hooks {
+    pub on_work_start: Option<String>,
+    pub on_work_end: Option<String>,
+    pub on_break_start: Option<String>,
+    pub on_break_end: Option<String>,
+    pub on_session_complete: Option<String>,
+}
+
+impl Hooks {
+    fn for_event(&self, event: &Event) -> Option<(&str, Option<Phase>)> {
+        let (hook, phase) = match event {
+            Event::PhaseStarted(Phase::Working) => (&self.on_work_start, Phase::Working),
+            Event::PhaseStarted(phase) => (&self.on_break_start, *phase),
+            Event::PhaseEnded(phase) | Event::PhaseSkipped(phase) | Event::PhaseAborted(phase) => {
+                match phase {
+                    Phase::Working => (&self.on_work_end, *phase),
+                    _ => (&self.on_break_end, *phase),
+                }
+            }
+            Event::Completed => return self.on_session_complete.as_deref().map(|c| (c, None)),
+            _ => return None,
+        };
+        hook.as_deref().map(|command| (command, Some(phase)))
+    }
+}
+
+/// The environment handed to a hook. Ending phases always report no time remaining, and
+/// only starting phases report when they end.
+fn environment(
+    event: &Event,
+    phase: Option<Phase>,
+    status: Option<&Status>,
+) -> Vec<(&'static str, String)> {
+    let phase = phase.or_else(|| status.map(|s| s.phase));
+    let remaining = match (event, status) {
+        (Event::PhaseStarted(_), Some(status)) => status.remaining.as_secs_f64().ceil() as u64,
+        _ => 0,
+    };
+    let mut environment = vec![
+        (
+            "POMO_PHASE",
+            phase.map(|p| p.name()).unwrap_or_default().to_string(),
+        ),
+        ("POMO_REMAINING", remaining.to_string()),
+        (
+            "POMO_COUNT",
+            status.map(|s| s.pomodoros).unwrap_or(0).to_string(),
+        ),
+    ];
+    if let (Event::PhaseStarted(_), Some(ends_at)) = (event, status.and_then(|s| s.ends_at)) {
+        environment.push(("POMO_ENDS_AT", ends_at.format("%H:%M").to_string()));
+    }
+    if let Some(task) = status.and_then(|s| s.task.clone()) {
+        environment.push(("POMO_TASK", task));
+    }
+    environment
+}
+
+/// `value` as a single word for `sh`.
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// `command` with its placeholders filled in from `values`, or `None` when it has some but
+/// runs through `cmd`, where nothing keeps a value such as `x & del *` from running.
+fn fill(command: &str, values: &Values, cmd: bool) -> Option<String> {
+    if cmd && template::has_placeholders(command) {
+        return None;
+    }
+    Some(template::render_with(command, values, quote))
+}
+
+/// A command running `command` through the platform's shell, `sh -c` or `cmd /C`.
+pub fn shell_command(command: &str) -> std::process::Command {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let mut shell = std::process::Command::new(shell);
+    shell.arg(flag).arg(command);
+    shell
+}
+
+async fn run(command: &str, environment: Vec<(&'static str, String)>) -> io::Result<ExitStatus> {
+    Command::from(shell_command(command))
+        .envs(environment)
+        .status()
+        .await
+}
+
+/// Spawns a task running the matching hook for every event without waiting for it to finish.
+pub fn spawn(
+    hooks: Hooks,
+    labels: Labels,
+    mut events: broadcast::Receiver<Event>,
+    client: Client,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            let event = match events.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            };
+            let (command, phase) = match hooks.for_event(&event) {
+                Some((command, phase)) => (command, phase),
+                None => continue,
+            };
+            let status = client.status().await.ok();
+            let values = Values::at(&event, status.as_ref(), &labels);
+            let command = match fill(command, &values, cfg!(windows)) {
+                Some(command) => command,
+                None => {
+                    tracing::warn!(
+                        command = %command,
+                        "not running hook, cmd cannot take placeholders, use POMO_* instead"
+                    );
+                    continue;
+                }
+            };
+            let environment = environment(&event, phase, status.as_ref());
+            tracing::info!(event = event.name(), command = %command, "running hook");
+            tokio::spawn(async move {
+                match run(&command, environment).await {
+                    Ok(status) if !status.success() => {
+                        tracing::warn!(command = %command, %status, "hook failed")
+                    }
+                    Err(e) => tracing::warn!(command = %command, error = %e, "cannot run hook"),
+                    Ok(status) => tracing::debug!(command = %command, %status, "hook finished"),
+                }
+            });
+        }
+    })
+}
+
+#[test]
+fn choose_hooks() {
+    let hooks = Hooks {
+        on_work_start: Some("work".to_string()),
+        on_break_end: Some("back".to_string()),
+        on_session_complete: Some("done".to_string()),
+        ..Hooks::default()
+    };
+    assert_eq!(
+        hooks.for_event(&Event::PhaseStarted(Phase::Working)),
+        Some(("work", Some(Phase::Working)))
+    );
+    assert_eq!(hooks.for_event(&Event::PhaseEnded(Phase::Working)), None);
+    assert_eq!(
+        hooks.for_event(&Event::PhaseSkipped(Phase::LongBreak)),
+        Some(("back", Some(Phase::LongBreak)))
+    );
+    assert_eq!(hooks.for_event(&Event::Completed), Some(("done", None)));
+    assert_eq!(hooks.for_event(&Event::Paused), None);
+}
+
+#[test]
+fn hook_environment() {
+    use chrono::{Local, TimeZone};
+    use std::time::Duration;
+
+    let status = Status {
+        phase: Phase::ShortBreak,
+        elapsed: Duration::from_millis(10),
+        remaining: Duration::from_millis(299_990),
+        pomodoros: 3,
+        short_breaks: 2,
+        long_breaks: 0,
+        paused: false,
+        focused: Duration::from_secs(0),
+        on_break: Duration::from_secs(0),
+        paused_for: Duration::from_secs(0),
+        pauses: 0,
+        label: None,
+        task: Some("report".to_string()),
+        goal: None,
+        awaiting_ack: false,
+        overtime: Duration::from_secs(0),
+        ends_at: Some(Local.with_ymd_and_hms(2024, 1, 2, 14, 35, 0).unwrap()),
+        session_ends_at: None,
+    };
+    let started = Event::PhaseStarted(Phase::ShortBreak);
+    assert_eq!(
+        environment(&started, Some(Phase::ShortBreak), Some(&status)),
+        vec![
+            ("POMO_PHASE", "short_break".to_string()),
+            ("POMO_REMAINING", "300".to_string()),
+            ("POMO_COUNT", "3".to_string()),
+            ("POMO_ENDS_AT", "14:35".to_string()),
+            ("POMO_TASK", "report".to_string()),
+        ]
+    );
+    let ended = Event::PhaseEnded(Phase::Working);
+    assert_eq!(
+        environment(&ended, Some(Phase::Working), Some(&status))[..2],
+        [
+            ("POMO_PHASE", "working".to_string()),
+            ("POMO_REMAINING", "0".to_string())
+        ]
+    );
+}
+
+#[test]
+fn refuse_placeholders_for_cmd() {
+    let values = Values {
+        task: Some("x & del /q *".to_string()),
+        ..Values::default()
+    };
+    assert_eq!(fill("echo {task}", &values, true), None);
+    assert_eq!(
+        fill("echo %POMO_TASK%", &values, true).as_deref(),
+        Some("echo %POMO_TASK%")
+    );
+    assert_eq!(
+        fill("echo {task}", &values, false).as_deref(),
+        Some("echo 'x & del /q *'")
+    );
+}
+
+#[cfg(unix)]
+#[tokio::test(flavor = "current_thread")]
+async fn run_hook_command() {
+    let values = Values {
+        task: Some("it's done; rm -rf /".to_string()),
+        ..Values::default()
+    };
+    let command = template::render_with(r#"test {task} = "it's done; rm -rf /""#, &values, quote);
+    assert!(run(&command, Vec::new()).await.unwrap().success());
+
+    let status = run(
+        r#"test "$POMO_PHASE" = working && test "$POMO_COUNT" = 2"#,
+        vec![
+            ("POMO_PHASE", "working".to_string()),
+            ("POMO_COUNT", "2".to_string()),
+        ],
+    )
+    .await
+    .unwrap();
+    assert!(status.success());
+}
+
+#[cfg(unix)]
+#[test]
+fn run_through_the_shell() {
+    let shell = shell_command("echo $0");
+    assert_eq!(shell.get_program(), "sh");
+    assert_eq!(shell.get_args().collect::<Vec<_>>(), ["-c", "echo $0"]);
+}
@@ -0,0 +1,296 @@
+//! Pauses working phases once the user has been away from the keyboard and mouse for a
+//! while, moving the time they were away from the phase into the pause.
+//!
+//! How long the user has been idle comes from an [`IdleSource`]: GNOME's idle monitor on
+//! Wayland, `xprintidle` on X11, the HID system on macOS, `GetLastInputInfo` on Windows, or
+//! any command printing the idle time in milliseconds.
+
+use std::error::Error;
+use std::process;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Deserialize;
+use tokio::task::JoinHandle;
+
+use crate::error::PomoError;
+use crate::pomodoro::Phase;
+use crate::runtime::Client;
+
+/// Idle detection settings, read from the `[idle]` table of the config file.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Idle {
+    /// Pause after this long without input, e.g. `5m`. Nothing is paused when unset.
+    #[serde(with = "humantime_serde")]
+    pub after: Option<Duration>,
+    /// How often to look at the idle time.
+    #[serde(with = "humantime_serde")]
+    pub poll: Duration,
+    /// The command printing the idle time in milliseconds, instead of the platform's own
+    /// source, e.g. `["xprintidle"]`.
+    pub command: Vec<String>,
+}
+
+impl Default for Idle {
+    fn default() -> Self {
+        Self {
+            after: None,
+            poll: Duration::from_secs(10),
+            command: Vec::new(),
+        }
+    }
+}
+
+/// Something able to tell how long the user has not touched the keyboard or mouse.
+pub trait IdleSource: Send + Sync + 'static {
+    fn idle_time(&self) -> Result<Duration, Box<dyn Error + Send + Sync>>;
+}
+
+impl IdleSource for Box<dyn IdleSource> {
+    fn idle_time(&self) -> Result<Duration, Box<dyn Error + Send + Sync>> {
+        (**self).idle_time()
+    }
+}
+
+/// Runs `program` and reads what it prints with `parse`.
+fn run(
+    program: &str,
+    args: &[String],
+    parse: fn(&str) -> Option<Duration>,
+) -> Result<Duration, Box<dyn Error + Send + Sync>> {
+    let output = process::Command::new(program).args(args).output()?;
+    if !output.status.success() {
+        return Err(format!("{} failed with {}", program, output.status).into());
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    parse(&stdout).ok_or_else(|| format!("unexpected output from {}: {:?}", program, stdout).into())
+}
+
+/// A command printing the idle time in milliseconds, such as `xprintidle`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IdleCommand {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+fn parse_millis(output: &str) -> Option<Duration> {
+    output.trim().parse().ok().map(Duration::from_millis)
+}
+
+impl IdleSource for IdleCommand {
+    fn idle_time(&self) -> Result<Duration, Box<dyn Error + Send + Sync>> {
+        run(&self.program, &self.args, parse_millis)
+    }
+}
+
+/// GNOME's idle monitor, asked over D-Bus through `gdbus`, which works on Wayland too.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Mutter;
+
+/// Reads the `(uint64 1234,)` `gdbus` prints.
+fn parse_gdbus(output: &str) -> Option<Duration> {
+    let millis = output.trim().strip_prefix("(uint64 ")?.strip_suffix(",)")?;
+    millis.parse().ok().map(Duration::from_millis)
+}
+
+impl IdleSource for Mutter {
+    fn idle_time(&self) -> Result<Duration, Box<dyn Error + Send + Sync>> {
+        let args = [
+            "call",
+            "--session",
+            "--dest",
+            "org.gnome.Mutter.IdleMonitor",
+            "--object-path",
+            "/org/gnome/Mutter/IdleMonitor/Core",
+            "--method",
+            "org.gnome.Mutter.IdleMonitor.GetIdletime",
+        ];
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        run("gdbus", &args, parse_gdbus)
+    }
+}
+
+/// The HID system of macOS, asked through `ioreg`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IoReg;
+
+/// Finds the `"HIDIdleTime" = <nanoseconds>` line `ioreg` prints.
+fn parse_ioreg(output: &str) -> Option<Duration> {
+    let line = output
+        .lines()
+        .find(|line| line.contains("\"HIDIdleTime\""))?;
+    let nanos = line.split('=').nth(1)?.trim();
+    nanos.parse().ok().map(Duration::from_nanos)
+}
+
+impl IdleSource for IoReg {
+    fn idle_time(&self) -> Result<Duration, Box<dyn Error + Send + Sync>> {
+        let args = ["-c".to_string(), "IOHIDSystem".to_string()];
+        run("ioreg", &args, parse_ioreg)
+    }
+}
+
+/// The last input Windows saw, asked through `GetLastInputInfo`.
+#[cfg(windows)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LastInput;
+
+#[cfg(windows)]
+#[repr(C)]
+struct LastInputInfo {
+    size: u32,
+    time: u32,
+}
+
+#[cfg(windows)]
+#[link(name = "user32")]
+extern "system" {
+    fn GetLastInputInfo(info: *mut LastInputInfo) -> i32;
+}
+
+#[cfg(windows)]
+#[link(name = "kernel32")]
+extern "system" {
+    fn GetTickCount() -> u32;
+}
+
+#[cfg(windows)]
+impl IdleSource for LastInput {
+    fn idle_time(&self) -> Result<Duration, Box<dyn Error + Send + Sync>> {
+        let mut info = LastInputInfo {
+            size: std::mem::size_of::<LastInputInfo>() as u32,
+            time: 0,
+        };
+        if unsafe { GetLastInputInfo(&mut info) } == 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        // Both count milliseconds since boot, wrapping around every 49.7 days.
+        let now = unsafe { GetTickCount() };
+        Ok(Duration::from_millis(u64::from(
+            now.wrapping_sub(info.time),
+        )))
+    }
+}
+
+/// The source `settings` name, or else the platform's own one when it has one.
+pub fn source(settings: &Idle) -> Option<Box<dyn IdleSource>> {
+    if let Some((program, args)) = settings.command.split_first() {
+        return Some(Box::new(IdleCommand {
+            program: program.clone(),
+            args: args.to_vec(),
+        }));
+    }
+    platform()
+}
+
+#[cfg(windows)]
+fn platform() -> Option<Box<dyn IdleSource>> {
+    Some(Box::new(LastInput))
+}
+
+#[cfg(not(windows))]
+fn platform() -> Option<Box<dyn IdleSource>> {
+    if cfg!(target_os = "macos") {
+        Some(Box::new(IoReg))
+    } else if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        Some(Box::new(Mutter))
+    } else if std::env::var_os("DISPLAY").is_some() {
+        Some(Box::new(IdleCommand {
+            program: "xprintidle".to_string(),
+            args: Vec::new(),
+        }))
+    } else {
+        None
+    }
+}
+
+/// Pauses the working phases of the pomodoro behind `client` whenever `source` tells the
+/// user has been idle for longer than `settings.after`.
+pub async fn follow<S: IdleSource>(settings: Idle, client: Client, source: S) {
+    let threshold = match settings.after {
+        Some(threshold) => threshold,
+        None => return,
+    };
+    let source = Arc::new(source);
+    let mut warned = false;
+    loop {
+        tokio::time::sleep(settings.poll).await;
+        match client.status().await {
+            Ok(status) if status.phase == Phase::Working && !status.paused => {}
+            Ok(_) => continue,
+            Err(_) => break,
+        }
+        let source = source.clone();
+        let idle = match tokio::task::spawn_blocking(move || source.idle_time()).await {
+            Ok(Ok(idle)) => idle,
+            Ok(Err(e)) => {
+                if !std::mem::replace(&mut warned, true) {
+                    tracing::warn!(error = %e, "cannot tell how long the user has been idle");
+                }
+                continue;
+            }
+            Err(_) => continue,
+        };
+        if idle < threshold {
+            continue;
+        }
+        if let Err(PomoError::Stopped) = client.pause_idle(idle).await {
+            break;
+        }
+    }
+}
+
+/// Spawns a task pausing the pomodoro behind `client` while the user is away, when idle
+/// detection is on and the platform can tell.
+pub fn spawn(settings: Idle, client: Client) -> Option<JoinHandle<()>> {
+    settings.after?;
+    let source = source(&settings)?;
+    Some(tokio::spawn(follow(settings, client, source)))
+}
+
+#[test]
+fn parse_idle_times() {
+    assert_eq!(parse_millis("1500\n"), Some(Duration::from_millis(1500)));
+    assert_eq!(
+        parse_gdbus("(uint64 61000,)\n"),
+        Some(Duration::from_secs(61))
+    );
+    assert_eq!(parse_gdbus("Error: no such service"), None);
+    let ioreg = "  | |   \"HIDIdleTime\" = 2500000000\n  | |   \"HIDKind\" = 1\n";
+    assert_eq!(parse_ioreg(ioreg), Some(Duration::from_millis(2500)));
+}
+
+#[cfg(test)]
+struct Away(Duration);
+
+#[cfg(test)]
+impl IdleSource for Away {
+    fn idle_time(&self) -> Result<Duration, Box<dyn Error + Send + Sync>> {
+        Ok(self.0)
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn pause_while_away() {
+    let pomodoro = crate::Pomodoro::builder().build().unwrap();
+    let (runtime, client, _events) = crate::start(pomodoro).await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let settings = Idle {
+        after: Some(Duration::from_millis(30)),
+        poll: Duration::from_millis(10),
+        command: Vec::new(),
+    };
+    let follower = tokio::spawn(follow(
+        settings,
+        client.clone(),
+        Away(Duration::from_millis(50)),
+    ));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let status = client.status().await.unwrap();
+    assert!(status.paused);
+    assert!(status.paused_for >= Duration::from_millis(50));
+    assert!(status.elapsed < Duration::from_millis(100));
+    runtime.shutdown().await.unwrap();
+    follower.await.unwrap();
+}
@@ -0,0 +1,463 @@
+//! Reading the phases of other histories, from CSV or JSON files, to
+//! [merge](crate::history::History::merge) into pomo's.
+//!
+//! The files `pomo export` writes are read as they are. The exports of other timers, such
+//! as pomotroid or flowtime apps, are read through a [`Mapping`] naming the fields which
+//! hold each part of a phase and what their phases are called.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::history::{Interruption, Outcome, Record};
+use crate::pomodoro::Phase;
+
+/// The kind of file phases are imported from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// A header line naming the fields, and a line for every phase.
+    Csv,
+    /// An array of an object for every phase.
+    Json,
+}
+
+impl Format {
+    /// The format told by the extension of `path`, if it tells one.
+    pub fn of(path: impl AsRef<Path>) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?;
+        extension.to_ascii_lowercase().parse().ok()
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("unknown import format: {}", s)),
+        }
+    }
+}
+
+/// The unit of a duration given as a bare number.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Unit {
+    Seconds,
+    #[default]
+    Minutes,
+}
+
+/// Where the parts of a phase are in the records of another timer, read from a TOML file.
+/// The defaults read what `pomo export` writes.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Mapping {
+    /// The field naming the phase, which is a working phase without one.
+    pub phase: String,
+    /// The field telling how the phase ended, which completed without one.
+    pub outcome: String,
+    pub started_at: String,
+    pub ended_at: String,
+    /// The field telling how long the phase lasted, for sources which do not tell when it
+    /// ended.
+    pub duration: String,
+    /// The unit of the `duration` when it is a bare number; `25m` and the like are read too.
+    pub duration_unit: Unit,
+    /// The field of the seconds the phase spent paused.
+    pub paused: String,
+    pub pauses: String,
+    /// The field of the seconds the phase was over before the next one started.
+    pub overtime: String,
+    pub task: String,
+    pub reason: String,
+    /// The field of the interruptions when it lists them, as a JSON export does.
+    pub interruptions: String,
+    /// How times are written, e.g. `%d/%m/%Y %H:%M`. Without one, RFC 3339 times, local
+    /// `2024-01-02 09:00:00` ones and seconds or milliseconds since the epoch are read.
+    pub time_format: Option<String>,
+    /// What the source calls its phases, e.g. `focus = "working"`, besides pomo's own names
+    /// and the common ones.
+    pub phases: BTreeMap<String, Phase>,
+}
+
+impl Default for Mapping {
+    fn default() -> Self {
+        Self {
+            phase: "phase".to_string(),
+            outcome: "outcome".to_string(),
+            started_at: "started_at".to_string(),
+            ended_at: "ended_at".to_string(),
+            duration: "duration".to_string(),
+            duration_unit: Unit::default(),
+            paused: "paused_secs".to_string(),
+            pauses: "pauses".to_string(),
+            overtime: "overtime_secs".to_string(),
+            task: "task".to_string(),
+            reason: "reason".to_string(),
+            interruptions: "interruptions".to_string(),
+            time_format: None,
+            phases: BTreeMap::new(),
+        }
+    }
+}
+
+/// A failure reading the phases of a file.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ImportError {
+    /// The file is not of the format it was read as.
+    Malformed(String),
+    /// The record at this position, counted from 1, does not describe a phase.
+    Record(usize, String),
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Malformed(reason) => write!(f, "malformed file: {}", reason),
+            ImportError::Record(number, reason) => write!(f, "record {}: {}", number, reason),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+/// The fields of a record, by name.
+type Fields = BTreeMap<String, Value>;
+
+/// The lines of `content`, split into fields, with quoted fields holding commas, quotes
+/// and line breaks.
+fn csv_lines(content: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut lines = Vec::new();
+    let mut line = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => line.push(std::mem::take(&mut field)),
+            '\r' if !quoted && chars.peek() == Some(&'\n') => {}
+            '\n' if !quoted => {
+                line.push(std::mem::take(&mut field));
+                lines.push(std::mem::take(&mut line));
+            }
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        return Err("a quoted field is never closed".to_string());
+    }
+    if !field.is_empty() || !line.is_empty() {
+        line.push(field);
+        lines.push(line);
+    }
+    lines.retain(|line| line.iter().any(|field| !field.is_empty()));
+    Ok(lines)
+}
+
+fn csv_records(content: &str) -> Result<Vec<Fields>, ImportError> {
+    let mut lines = csv_lines(content)
+        .map_err(ImportError::Malformed)?
+        .into_iter();
+    let header = match lines.next() {
+        Some(header) => header,
+        None => return Ok(Vec::new()),
+    };
+    let header: Vec<String> = header.iter().map(|name| name.trim().to_string()).collect();
+    Ok(lines
+        .map(|line| {
+            header
+                .iter()
+                .cloned()
+                .zip(line.into_iter().map(Value::String))
+                .collect()
+        })
+        .collect())
+}
+
+fn json_records(content: &str) -> Result<Vec<Fields>, ImportError> {
+    let malformed = |e: serde_json::Error| ImportError::Malformed(e.to_string());
+    let value: Value = serde_json::from_str(content).map_err(malformed)?;
+    let records = match value {
+        Value::Array(records) => records,
+        _ => return Err(ImportError::Malformed("expected an array".to_string())),
+    };
+    records
+        .into_iter()
+        .enumerate()
+        .map(|(index, record)| match record {
+            Value::Object(fields) => Ok(fields.into_iter().collect()),
+            _ => Err(ImportError::Record(
+                index + 1,
+                "expected an object".to_string(),
+            )),
+        })
+        .collect()
+}
+
+/// The common names of phases in the exports of other timers.
+fn common_phase(name: &str) -> Option<Phase> {
+    let name = name.trim().to_lowercase().replace(['-', ' '], "_");
+    match name.as_str() {
+        "working" | "work" | "focus" | "pomodoro" => Some(Phase::Working),
+        "short_break" | "break" | "short" => Some(Phase::ShortBreak),
+        "long_break" | "long" => Some(Phase::LongBreak),
+        _ => None,
+    }
+}
+
+fn parse_time(value: &str, format: Option<&str>) -> Result<DateTime<Local>, String> {
+    let value = value.trim();
+    let local = |naive: NaiveDateTime| Local.from_local_datetime(&naive).earliest();
+    let read = match format {
+        Some(format) => DateTime::parse_from_str(value, format)
+            .map(|at| at.with_timezone(&Local))
+            .ok()
+            .or_else(|| local(NaiveDateTime::parse_from_str(value, format).ok()?)),
+        None => DateTime::parse_from_rfc3339(value)
+            .map(|at| at.with_timezone(&Local))
+            .ok()
+            .or_else(|| {
+                let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"]
+                    .iter()
+                    .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())?;
+                local(naive)
+            })
+            .or_else(|| {
+                let since_epoch: i64 = value.parse().ok()?;
+                match since_epoch {
+                    millis if millis.abs() >= 100_000_000_000 => {
+                        Local.timestamp_millis_opt(millis).single()
+                    }
+                    secs => Local.timestamp_opt(secs, 0).single(),
+                }
+            }),
+    };
+    read.ok_or_else(|| format!("cannot read the time {:?}", value))
+}
+
+fn parse_duration(value: &str, unit: Unit) -> Result<Duration, String> {
+    let value = value.trim();
+    if let Ok(number) = value.parse::<f64>() {
+        let secs = match unit {
+            Unit::Seconds => number,
+            Unit::Minutes => number * 60.0,
+        };
+        return Duration::try_from_secs_f64(secs).map_err(|e| e.to_string());
+    }
+    humantime::parse_duration(value)
+        .map_err(|e| format!("cannot read the duration {:?}: {}", value, e))
+}
+
+impl Mapping {
+    /// The phase called `name` in the source.
+    fn phase(&self, name: &str) -> Result<Phase, String> {
+        self.phases
+            .get(name)
+            .copied()
+            .or_else(|| common_phase(name))
+            .ok_or_else(|| format!("unknown phase {:?}", name))
+    }
+
+    fn record(&self, fields: &Fields) -> Result<Record, String> {
+        let text = |name: &str| -> Option<String> {
+            match fields.get(name)? {
+                Value::String(text) if text.trim().is_empty() => None,
+                Value::String(text) => Some(text.clone()),
+                Value::Null | Value::Array(_) | Value::Object(_) => None,
+                value => Some(value.to_string()),
+            }
+        };
+        let secs = |name: &str| -> Result<Duration, String> {
+            text(name).map_or(Ok(Duration::from_secs(0)), |secs| {
+                parse_duration(&secs, Unit::Seconds)
+            })
+        };
+        let time_format = self.time_format.as_deref();
+        let phase = text(&self.phase).map_or(Ok(Phase::Working), |name| self.phase(&name))?;
+        let outcome = match text(&self.outcome) {
+            Some(outcome) => outcome.trim().to_lowercase().parse()?,
+            None => Outcome::Completed,
+        };
+        let started_at = text(&self.started_at)
+            .ok_or_else(|| format!("no {} field", self.started_at))
+            .and_then(|at| parse_time(&at, time_format))?;
+        let ended_at = match (text(&self.ended_at), text(&self.duration)) {
+            (Some(at), _) => parse_time(&at, time_format)?,
+            (None, Some(lasted)) => {
+                let lasted = parse_duration(&lasted, self.duration_unit)?;
+                started_at + chrono::Duration::from_std(lasted).map_err(|e| e.to_string())?
+            }
+            (None, None) => {
+                return Err(format!("no {} or {} field", self.ended_at, self.duration));
+            }
+        };
+        if ended_at < started_at {
+            return Err("the phase ends before it starts".to_string());
+        }
+        let interruptions = match fields.get(&self.interruptions) {
+            Some(Value::Array(listed)) => listed
+                .iter()
+                .map(|interruption| self.interruption(interruption))
+                .collect::<Result<_, _>>()?,
+            _ => Vec::new(),
+        };
+        Ok(Record {
+            phase,
+            outcome,
+            started_at,
+            ended_at,
+            paused: secs(&self.paused)?,
+            pauses: text(&self.pauses)
+                .map_or(Ok(0), |pauses| pauses.trim().parse())
+                .map_err(|e| format!("cannot read the pauses: {}", e))?,
+            reason: text(&self.reason),
+            task: text(&self.task),
+            interruptions,
+            overtime: secs(&self.overtime)?,
+        })
+    }
+
+    /// An interruption as `pomo export` lists them, `{"kind": …, "note": …, "at": …}`.
+    fn interruption(&self, value: &Value) -> Result<Interruption, String> {
+        let field = |name: &str| value.get(name).and_then(Value::as_str);
+        Ok(Interruption {
+            kind: field("kind").unwrap_or("internal").parse()?,
+            note: field("note").unwrap_or_default().to_string(),
+            at: parse_time(field("at").unwrap_or_default(), None)?,
+        })
+    }
+}
+
+/// The phases `content` holds in `format`, read through `mapping`.
+pub fn read(content: &str, format: Format, mapping: &Mapping) -> Result<Vec<Record>, ImportError> {
+    let records = match format {
+        Format::Csv => csv_records(content)?,
+        Format::Json => json_records(content)?,
+    };
+    records
+        .iter()
+        .enumerate()
+        .map(|(index, fields)| {
+            mapping
+                .record(fields)
+                .map_err(|reason| ImportError::Record(index + 1, reason))
+        })
+        .collect()
+}
+
+#[test]
+fn read_csv_lines() {
+    assert_eq!(
+        csv_lines("a,b\r\n\"x, \"\"y\"\"\",\"two\nlines\"\n\n1,\n").unwrap(),
+        vec![
+            vec!["a", "b"],
+            vec!["x, \"y\"", "two\nlines"],
+            vec!["1", ""]
+        ]
+    );
+    assert!(csv_lines("a,\"b\n").is_err());
+}
+
+#[test]
+fn read_own_exports() {
+    let csv = "phase,outcome,started_at,ended_at,paused_secs,pauses,overtime_secs,task,reason,\
+               interruptions\n\
+               working,voided,2024-01-02T09:00:00+00:00,2024-01-02T09:10:00+00:00,30,1,0,\
+               \"report, draft\",phone call,2\n";
+    let records = read(csv, Format::Csv, &Mapping::default()).unwrap();
+    assert_eq!(records.len(), 1);
+    let record = &records[0];
+    assert_eq!(
+        (record.phase, record.outcome),
+        (Phase::Working, Outcome::Voided)
+    );
+    assert_eq!(
+        record.ended_at - record.started_at,
+        chrono::Duration::minutes(10)
+    );
+    assert_eq!((record.paused, record.pauses), (Duration::from_secs(30), 1));
+    assert_eq!(record.task.as_deref(), Some("report, draft"));
+    assert_eq!(record.reason.as_deref(), Some("phone call"));
+    assert!(record.interruptions.is_empty());
+
+    let json = r#"[{"phase": "short_break", "outcome": "skipped",
+        "started_at": "2024-01-02T09:30:00+00:00", "ended_at": "2024-01-02T09:33:00+00:00",
+        "paused_secs": 0, "pauses": 0, "overtime_secs": 45, "task": null, "reason": null,
+        "interruptions": [{"kind": "external", "note": "door", "at": "2024-01-02T09:31:00+00:00"}]}]"#;
+    let records = read(json, Format::Json, &Mapping::default()).unwrap();
+    assert_eq!(records[0].phase, Phase::ShortBreak);
+    assert_eq!(records[0].overtime, Duration::from_secs(45));
+    assert_eq!(records[0].task, None);
+    assert_eq!(records[0].interruptions[0].note, "door");
+}
+
+#[test]
+fn map_other_timers() {
+    let mapping: Mapping = toml::from_str(
+        r#"
+        phase = "type"
+        started_at = "date"
+        duration = "minutes"
+        task = "label"
+        time_format = "%d/%m/%Y %H:%M"
+
+        [phases]
+        rest = "long_break"
+        "#,
+    )
+    .unwrap();
+    let csv = "date,type,minutes,label\n\
+               02/01/2024 09:00,Focus,25,writing\n\
+               02/01/2024 09:25,Short Break,5,\n\
+               02/01/2024 09:30,rest,15,\n";
+    let records = read(csv, Format::Csv, &mapping).unwrap();
+    let phases: Vec<Phase> = records.iter().map(|record| record.phase).collect();
+    assert_eq!(
+        phases,
+        [Phase::Working, Phase::ShortBreak, Phase::LongBreak]
+    );
+    assert_eq!(
+        records[0].started_at,
+        Local.with_ymd_and_hms(2024, 1, 2, 9, 0, 0).unwrap()
+    );
+    assert_eq!(records[0].ended_at, records[1].started_at);
+    assert_eq!(records[0].task.as_deref(), Some("writing"));
+    assert_eq!(records[1].task, None);
+
+    assert_eq!(
+        read(
+            "date,type,minutes\n02/01/2024 09:00,nap,20\n",
+            Format::Csv,
+            &mapping
+        ),
+        Err(ImportError::Record(1, "unknown phase \"nap\"".to_string()))
+    );
+    let json = r#"[{"start": 1704186000, "end": 1704187500000}]"#;
+    let mapping = Mapping {
+        started_at: "start".to_string(),
+        ended_at: "end".to_string(),
+        ..Mapping::default()
+    };
+    let records = read(json, Format::Json, &mapping).unwrap();
+    assert_eq!(records[0].phase, Phase::Working);
+    assert_eq!(
+        records[0].ended_at - records[0].started_at,
+        chrono::Duration::minutes(25)
+    );
+    assert_eq!(Format::of("pomotroid.JSON"), Some(Format::Json));
+}
@@ -0,0 +1,187 @@
+//! Keeps the machine from going to sleep while a working phase runs, and lets it sleep
+//! again during breaks and pauses.
+//!
+//! The platforms' own tools hold the inhibitor for as long as they run: `systemd-inhibit`
+//! on Linux, `caffeinate` and its IOKit assertion on macOS, and a PowerShell calling
+//! `SetThreadExecutionState` on Windows.
+
+use std::error::Error;
+use std::process::{Child, Command, Stdio};
+
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+
+use crate::event::Event;
+use crate::pomodoro::Phase;
+use crate::runtime::Client;
+
+/// Something able to keep the machine awake until the guard it returns is dropped.
+pub trait Inhibitor: Send + Sync + 'static {
+    type Guard: Send + 'static;
+
+    fn inhibit(&self) -> Result<Self::Guard, Box<dyn Error + Send + Sync>>;
+}
+
+/// A command which keeps the machine awake for as long as it runs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InhibitCommand {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl InhibitCommand {
+    /// The platform's own way of holding an inhibitor, if it has one.
+    pub fn platform() -> Option<Self> {
+        let (program, args): (&str, &[&str]) = if cfg!(target_os = "linux") {
+            (
+                "systemd-inhibit",
+                &[
+                    "--what=sleep:idle",
+                    "--who=pomo",
+                    "--why=A pomodoro is running",
+                    "--mode=block",
+                    "sleep",
+                    "infinity",
+                ],
+            )
+        } else if cfg!(target_os = "macos") {
+            ("caffeinate", &["-i"])
+        } else if cfg!(windows) {
+            (
+                "powershell",
+                &[
+                    "-NoProfile",
+                    "-Command",
+                    "$k = Add-Type -Name Power -PassThru -MemberDefinition \
+                     '[DllImport(\"kernel32.dll\")] public static extern uint \
+                     SetThreadExecutionState(uint flags);'; \
+                     $k::SetThreadExecutionState(0x80000001) | Out-Null; \
+                     while ($true) { Start-Sleep -Seconds 3600 }",
+                ],
+            )
+        } else {
+            return None;
+        };
+        Some(Self {
+            program: program.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        })
+    }
+}
+
+/// The running command of an [`InhibitCommand`], which is stopped when dropped.
+pub struct Inhibition(Child);
+
+impl Drop for Inhibition {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+impl Inhibitor for InhibitCommand {
+    type Guard = Inhibition;
+
+    fn inhibit(&self) -> Result<Inhibition, Box<dyn Error + Send + Sync>> {
+        let child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .spawn()?;
+        Ok(Inhibition(child))
+    }
+}
+
+/// Holds an inhibitor from `inhibitor` while the pomodoro behind `client` runs a working
+/// phase, looking again whenever it emits one of `events`.
+pub async fn follow<I: Inhibitor>(
+    client: Client,
+    mut events: broadcast::Receiver<Event>,
+    inhibitor: I,
+) {
+    let mut held = None;
+    loop {
+        let working = match client.status().await {
+            Ok(status) => status.phase == Phase::Working && !status.paused && !status.awaiting_ack,
+            Err(_) => false,
+        };
+        if !working {
+            held = None;
+        } else if held.is_none() {
+            match inhibitor.inhibit() {
+                Ok(guard) => held = Some(guard),
+                Err(e) => tracing::warn!(error = %e, "cannot keep the machine awake"),
+            }
+        }
+        loop {
+            match events.recv().await {
+                Ok(Event::Tick { .. }) => continue,
+                Ok(_) | Err(RecvError::Lagged(_)) => break,
+                Err(RecvError::Closed) => return,
+            }
+        }
+    }
+}
+
+/// Spawns a task keeping the machine awake during the working phases of the pomodoro
+/// behind `client`, when the platform has a way to.
+pub fn spawn(client: Client) -> Option<JoinHandle<()>> {
+    let inhibitor = InhibitCommand::platform()?;
+    let events = client.subscribe();
+    Some(tokio::spawn(follow(client, events, inhibitor)))
+}
+
+#[cfg(test)]
+struct Counting(std::sync::Arc<std::sync::atomic::AtomicUsize>);
+
+#[cfg(test)]
+struct Held(std::sync::Arc<std::sync::atomic::AtomicUsize>);
+
+#[cfg(test)]
+impl Drop for Held {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+impl Inhibitor for Counting {
+    type Guard = Held;
+
+    fn inhibit(&self) -> Result<Held, Box<dyn Error + Send + Sync>> {
+        self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        Ok(Held(self.0.clone()))
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn inhibit_while_working() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    let pomodoro = crate::Pomodoro::builder().build().unwrap();
+    let (runtime, client, _events) = crate::start(pomodoro).await;
+    let held = Arc::new(AtomicUsize::new(0));
+    let follower = tokio::spawn(follow(
+        client.clone(),
+        client.subscribe(),
+        Counting(held.clone()),
+    ));
+    let settle = || tokio::time::sleep(Duration::from_millis(50));
+
+    settle().await;
+    assert_eq!(held.load(Ordering::SeqCst), 1);
+    client.pause().await.unwrap();
+    settle().await;
+    assert_eq!(held.load(Ordering::SeqCst), 0);
+    client.resume().await.unwrap();
+    settle().await;
+    assert_eq!(held.load(Ordering::SeqCst), 1);
+    client.skip().await.unwrap();
+    settle().await;
+    assert_eq!(held.load(Ordering::SeqCst), 0);
+    runtime.shutdown().await.unwrap();
+    follower.await.unwrap();
+    assert_eq!(held.load(Ordering::SeqCst), 0);
+}
@@ -0,0 +1,129 @@
+//! The lock file which keeps a second daemon from running over the same data directory.
+//!
+//! The daemon holding the lock writes its pid and socket into a file beside it, so clients
+//! find it wherever it listens and a new daemon can ask it to hand over. That file is never
+//! locked itself, as Windows keeps other processes from reading a locked file.
+
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// The daemon holding the lock.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Owner {
+    pub pid: u32,
+    pub socket: PathBuf,
+}
+
+/// The lock, held until dropped or until the process exits, however it does.
+#[derive(Debug)]
+pub struct Instance {
+    _file: File,
+}
+
+impl Instance {
+    /// The default file, `$XDG_DATA_HOME/pomo/daemon.lock` on Linux.
+    pub fn path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("pomo").join("daemon.lock"))
+    }
+
+    /// Takes the lock at `path` for a daemon listening on `socket`, or tells who holds it.
+    pub fn acquire(path: impl AsRef<Path>, socket: &Path) -> io::Result<Result<Self, Owner>> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => return Ok(Err(read_owner(path)?)),
+            Err(TryLockError::Error(e)) => return Err(e),
+        }
+        let owner = Owner {
+            pid: std::process::id(),
+            socket: socket.to_path_buf(),
+        };
+        // Written aside and renamed, so the owner is never read half written.
+        let owner_path = owner_path(path);
+        let mut partial = owner_path.clone().into_os_string();
+        partial.push(".partial");
+        fs::write(&partial, serde_json::to_string(&owner)?)?;
+        fs::rename(&partial, &owner_path)?;
+        Ok(Ok(Self { _file: file }))
+    }
+}
+
+/// The file beside the lock at `path` which names the daemon holding it.
+fn owner_path(path: &Path) -> PathBuf {
+    path.with_extension("json")
+}
+
+/// The daemon holding the lock at `path`, or `None` when none runs.
+pub fn running(path: impl AsRef<Path>) -> io::Result<Option<Owner>> {
+    let path = path.as_ref();
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    match file.try_lock_shared() {
+        Ok(()) => Ok(None),
+        Err(TryLockError::WouldBlock) => read_owner(path).map(Some),
+        Err(TryLockError::Error(e)) => Err(e),
+    }
+}
+
+fn read_owner(path: &Path) -> io::Result<Owner> {
+    let content = fs::read_to_string(owner_path(path))?;
+    serde_json::from_str(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[test]
+fn hold_a_single_instance() {
+    let dir = std::env::temp_dir().join(format!("pomo-instance-{}", std::process::id()));
+    let path = dir.join("daemon.lock");
+    let socket = Path::new("/tmp/pomo-test.sock");
+    assert_eq!(running(&path).unwrap(), None);
+    let instance = Instance::acquire(&path, socket).unwrap().unwrap();
+    let owner = Owner {
+        pid: std::process::id(),
+        socket: socket.to_path_buf(),
+    };
+    assert_eq!(running(&path).unwrap(), Some(owner.clone()));
+    assert_eq!(
+        Instance::acquire(&path, Path::new("/tmp/other.sock"))
+            .unwrap()
+            .unwrap_err(),
+        owner
+    );
+    drop(instance);
+    assert_eq!(running(&path).unwrap(), None);
+    assert!(Instance::acquire(&path, socket).unwrap().is_ok());
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn read_owner_while_locked() {
+    let dir = std::env::temp_dir().join(format!("pomo-instance-owner-{}", std::process::id()));
+    let path = dir.join("daemon.lock");
+    let socket = Path::new(r"\\.\pipe\pomo-test");
+    let _instance = Instance::acquire(&path, socket).unwrap().unwrap();
+    // A client opens the lock on its own, and reads the owner while the daemon holds it.
+    let other = File::open(&path).unwrap();
+    assert!(matches!(
+        other.try_lock_shared(),
+        Err(TryLockError::WouldBlock)
+    ));
+    assert_eq!(read_owner(&path).unwrap().socket, socket);
+    assert_eq!(
+        running(&path).unwrap().map(|owner| owner.socket),
+        Some(socket.into())
+    );
+    let _ = fs::remove_dir_all(dir);
+}
@@ -0,0 +1,124 @@
+//! Keystrokes controlling a pomodoro running on the terminal.
+
+use pomo::InterruptionKind;
+#[cfg(feature = "tui")]
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use tokio::sync::mpsc;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(not(feature = "tui"), allow(dead_code))]
+pub enum Action {
+    TogglePause,
+    Skip,
+    Extend,
+    Void,
+    Interrupt(InterruptionKind),
+    Quit,
+}
+
+#[cfg(feature = "tui")]
+pub fn action(key: KeyEvent) -> Option<Action> {
+    if key.kind != KeyEventKind::Press {
+        return None;
+    }
+    match key.code {
+        KeyCode::Char(' ') => Some(Action::TogglePause),
+        KeyCode::Char('s') => Some(Action::Skip),
+        KeyCode::Char('e') => Some(Action::Extend),
+        KeyCode::Char('v') => Some(Action::Void),
+        KeyCode::Char('i') => Some(Action::Interrupt(InterruptionKind::Internal)),
+        KeyCode::Char('x') => Some(Action::Interrupt(InterruptionKind::External)),
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Some(Action::Quit),
+        KeyCode::Char('q') | KeyCode::Esc => Some(Action::Quit),
+        _ => None,
+    }
+}
+
+/// Reads keystrokes on a dedicated thread, which stops once the receiver is dropped.
+#[cfg(feature = "tui")]
+pub fn read_actions() -> mpsc::UnboundedReceiver<Action> {
+    use std::thread;
+    use std::time::Duration;
+
+    use ratatui::crossterm::event::{self, Event};
+
+    let (sender, receiver) = mpsc::unbounded_channel();
+    thread::spawn(move || {
+        while !sender.is_closed() {
+            if !event::poll(Duration::from_millis(100)).unwrap_or(false) {
+                continue;
+            }
+            if let Ok(Event::Key(key)) = event::read() {
+                if let Some(action) = action(key) {
+                    if sender.send(action).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+    receiver
+}
+
+/// The keystrokes typed into the terminal the process runs in, which is kept in raw mode
+/// until this is dropped. Lines printed meanwhile have to end with `\r\n`.
+#[cfg_attr(not(feature = "tui"), allow(dead_code))]
+pub struct Keys {
+    actions: mpsc::UnboundedReceiver<Action>,
+}
+
+impl Keys {
+    /// Starts reading keystrokes, unless the standard input is not a terminal or this build
+    /// lacks the tui feature.
+    pub fn listen() -> Option<Self> {
+        #[cfg(feature = "tui")]
+        {
+            use std::io::IsTerminal;
+
+            if !std::io::stdin().is_terminal() {
+                return None;
+            }
+            ratatui::crossterm::terminal::enable_raw_mode().ok()?;
+            Some(Self {
+                actions: read_actions(),
+            })
+        }
+        #[cfg(not(feature = "tui"))]
+        None
+    }
+
+    /// The next action typed, waiting forever without `keys`.
+    pub async fn next(keys: &mut Option<Self>) -> Action {
+        match keys {
+            Some(keys) => keys.actions.recv().await.unwrap_or(Action::Quit),
+            None => std::future::pending().await,
+        }
+    }
+}
+
+impl Drop for Keys {
+    fn drop(&mut self) {
+        #[cfg(feature = "tui")]
+        let _ = ratatui::crossterm::terminal::disable_raw_mode();
+    }
+}
+
+#[cfg(feature = "tui")]
+#[test]
+fn map_keys() {
+    let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+    assert_eq!(action(press(KeyCode::Char(' '))), Some(Action::TogglePause));
+    assert_eq!(action(press(KeyCode::Char('s'))), Some(Action::Skip));
+    assert_eq!(action(press(KeyCode::Char('e'))), Some(Action::Extend));
+    assert_eq!(action(press(KeyCode::Char('v'))), Some(Action::Void));
+    assert_eq!(
+        action(press(KeyCode::Char('x'))),
+        Some(Action::Interrupt(InterruptionKind::External))
+    );
+    assert_eq!(action(press(KeyCode::Char('q'))), Some(Action::Quit));
+    assert_eq!(
+        action(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+        Some(Action::Quit)
+    );
+    assert_eq!(action(press(KeyCode::Char('y'))), None);
+}
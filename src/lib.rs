@@ -0,0 +1,64 @@
+//! A pomodoro timer engine which can be embedded into other tools.
+//!
+//! Build a [`Pomodoro`] with [`Pomodoro::builder`] and hand it to [`start`] to
+//! drive it on the tokio runtime, then control it through the returned
+//! [`Client`] and stop it with [`Runtime::shutdown`]. Every state change is
+//! broadcast as an [`Event`]. Applications without tokio can use the
+//! [`blocking::Client`] instead, or drive the state machine themselves with
+//! [`Pomodoro::drive_with`] and a [`timer::Sleeper`] of their runtime.
+
+pub mod alarm;
+pub mod blocking;
+pub mod config;
+pub mod daemon;
+#[cfg(all(target_os = "linux", feature = "dbus"))]
+pub mod dbus;
+mod error;
+mod event;
+pub mod feed;
+pub mod history;
+pub mod hooks;
+pub mod idle;
+pub mod import;
+pub mod inhibit;
+pub mod instance;
+pub mod mqtt;
+pub mod notify;
+pub mod org;
+#[cfg(all(target_os = "linux", feature = "overlay"))]
+pub mod overlay;
+mod pomodoro;
+pub mod protocol;
+pub mod quiet;
+pub mod recovery;
+#[cfg(feature = "reload")]
+pub mod reload;
+mod runtime;
+pub mod schedule;
+pub mod screen_lock;
+pub mod signals;
+pub mod slack;
+pub mod sound;
+pub mod speech;
+pub mod stats;
+pub mod status_file;
+pub mod tasks;
+pub mod taskwarrior;
+pub mod template;
+pub mod timer;
+pub mod timetrack;
+pub mod tmux;
+pub mod transport;
+#[cfg(all(target_os = "linux", feature = "tray"))]
+pub mod tray;
+#[cfg(feature = "web")]
+pub mod web;
+pub mod webhooks;
+
+pub use error::PomoError;
+pub use event::{Event, InterruptionKind, Until};
+pub use pomodoro::{
+    Clock, Counter, Durations, Goal, Labels, Phase, Pomodoro, PomodoroBuilder, PomodoroSnapshot,
+    Settings, Slot, State, Status, Step, SuspendPolicy, Switch,
+};
+pub use runtime::{start, start_idle, terminated, Client, Runtime};
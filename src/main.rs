@@ -1,16 +1,1359 @@
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::OnceLock;
 use std::time::Duration;
 
-mod pomodoro;
-mod runtime;
+use chrono::{Local, NaiveDate};
+use clap::Parser;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
 
-use pomodoro::{Clock, Pomodoro};
-use runtime::start;
+use pomo::alarm;
+use pomo::config::{Config, ConfigError};
+use pomo::daemon::{self, Control, Request, Response};
+#[cfg(all(target_os = "linux", feature = "dbus"))]
+use pomo::dbus;
+use pomo::feed;
+use pomo::history::{self, History, Store};
+use pomo::hooks;
+use pomo::idle;
+use pomo::import;
+use pomo::inhibit;
+use pomo::instance::{self, Instance};
+#[cfg(feature = "mqtt")]
+use pomo::mqtt;
+use pomo::notify;
+use pomo::org;
+#[cfg(all(target_os = "linux", feature = "overlay"))]
+use pomo::overlay;
+use pomo::recovery::{self, Checkpoint};
+#[cfg(feature = "reload")]
+use pomo::reload;
+use pomo::schedule;
+#[cfg(all(target_os = "linux", feature = "logind"))]
+use pomo::screen_lock;
+#[cfg(unix)]
+use pomo::signals;
+#[cfg(feature = "slack")]
+use pomo::slack;
+#[cfg(feature = "sound")]
+use pomo::sound;
+use pomo::speech;
+use pomo::stats::{self, Period, Report, Stats, TaskStats};
+use pomo::status_file::{self, StatusFile};
+use pomo::tasks;
+#[cfg(feature = "taskwarrior")]
+use pomo::taskwarrior::{self, Taskwarrior};
+#[cfg(feature = "timetrack")]
+use pomo::timetrack;
+#[cfg(unix)]
+use pomo::tmux;
+#[cfg(all(target_os = "linux", feature = "tray"))]
+use pomo::tray;
+#[cfg(feature = "web")]
+use pomo::web;
+#[cfg(feature = "webhooks")]
+use pomo::webhooks;
+use pomo::{
+    start, start_idle, terminated, Client, Durations, Event, Labels, PomoError, Pomodoro, Runtime,
+};
 
-#[tokio::main(flavor = "multi_thread")]
-async fn main() {
-    let working = Clock::new(Duration::from_secs(5), Duration::from_secs(1));
-    let short_break = Clock::new(Duration::from_secs(3), Duration::from_secs(1));
-    let long_break = Clock::new(Duration::from_secs(4), Duration::from_secs(1));
-    let pomo = Pomodoro::new(working, short_break, long_break, 2, true, None);
-    let client = start(pomo).await;
+mod cli;
+mod completions;
+mod keys;
+mod output;
+#[cfg(feature = "tui")]
+mod tui;
+
+use cli::{
+    BarStyle, Cli, Command, ExportFormat, Format, GraphKind, LogFormat, Names, ReportFormat,
+    SessionArgs, Shell,
+};
+use keys::{Action, Keys};
+use output::Failure;
+
+/// The format errors are printed in, that of the command's output.
+static ERROR_FORMAT: OnceLock<Format> = OnceLock::new();
+
+/// The passphrase of the history given in the environment, or why it cannot be used.
+static ENV_PASSPHRASE: OnceLock<Result<Option<String>, String>> = OnceLock::new();
+
+fn main() {
+    // Taken before the runtime starts its threads, as the variable is removed.
+    let _ = ENV_PASSPHRASE.set(history::take_passphrase().map_err(|e| e.to_string()));
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap_or_else(|e| exit_with(format!("cannot start the runtime: {}", e)))
+        .block_on(run());
+}
+
+async fn run() {
+    let cli = Cli::parse();
+    let format = cli.command.as_ref().map_or(Format::Text, Command::format);
+    let _ = ERROR_FORMAT.set(format);
+    init_logging(&cli.log_level, cli.log_format);
+    let socket = cli.socket.clone().unwrap_or_else(running_socket);
+    let timer = cli.timer.clone();
+    let to = |request| match &timer {
+        Some(name) => Request::Timer(name.clone(), Box::new(request)),
+        None => request,
+    };
+    match cli.command {
+        None => run_foreground(&cli.session).await,
+        Some(Command::Start(session)) => match &timer {
+            Some(name) => send(&socket, Request::Start(name.clone()), Format::Text).await,
+            None => run_foreground(&session).await,
+        },
+        Some(Command::Run(session)) => run_in_place(&session).await,
+        Some(Command::ResumeSession { yes, session }) => resume_session(&session, yes).await,
+        Some(Command::Daemon { takeover, session }) => {
+            let socket = cli.socket.clone().unwrap_or_else(daemon::socket_path);
+            run_daemon(&session, &socket, takeover).await
+        }
+        #[cfg(feature = "tui")]
+        Some(Command::Tui { tick_rate, session }) => run_tui(&session, tick_rate).await,
+        Some(Command::Pause) => send(&socket, to(Request::Pause), Format::Text).await,
+        Some(Command::Resume) => send(&socket, to(Request::Resume), Format::Text).await,
+        Some(Command::Status { format }) => send(&socket, to(Request::Status), format).await,
+        Some(Command::Toggle { format }) => send(&socket, to(Request::Toggle), format).await,
+        Some(Command::Snapshot) => send(&socket, to(Request::Snapshot), Format::Json).await,
+        Some(Command::Skip) => send(&socket, to(Request::Skip), Format::Text).await,
+        Some(Command::Restart) => send(&socket, to(Request::Restart), Format::Text).await,
+        Some(Command::ResetCounters) => {
+            send(&socket, to(Request::ResetCounters), Format::Text).await
+        }
+        Some(Command::Goto { phase }) => {
+            send(&socket, to(Request::Goto(phase)), Format::Text).await
+        }
+        Some(Command::Ack) => send(&socket, to(Request::Ack), Format::Text).await,
+        Some(Command::Task { task }) => {
+            let request = Request::SetTask(task.unwrap_or_default());
+            send(&socket, to(request), Format::Text).await
+        }
+        Some(Command::Interrupt { kind, note }) => {
+            let request = Request::Interrupt(kind, note.unwrap_or_default());
+            send(&socket, to(request), Format::Text).await
+        }
+        Some(Command::Void { reason }) => {
+            let request = Request::Void(reason.unwrap_or_default());
+            send(&socket, to(request), Format::Text).await
+        }
+        Some(Command::Reconfigure {
+            profile,
+            work,
+            short,
+            long,
+            interval,
+        }) => {
+            let durations = Durations {
+                work,
+                short_break: short,
+                long_break: long,
+                long_break_interval: interval,
+            };
+            let request = Request::SetDurations(reconfigure(profile.as_deref(), durations));
+            send(&socket, to(request), Format::Text).await
+        }
+        Some(Command::Stop) => match timer {
+            Some(name) => send(&socket, Request::Stop(name), Format::Text).await,
+            None => exit_with("name the timer to stop with --timer"),
+        },
+        Some(Command::Timers { format }) => send(&socket, Request::Timers, format).await,
+        Some(Command::Wait { until }) => {
+            send(&socket, to(Request::Wait(until)), Format::Text).await
+        }
+        Some(Command::Statusbar {
+            style,
+            template,
+            watch,
+        }) => {
+            let template = template.as_deref();
+            show_statusbar(&socket, to(Request::Status), style, template, watch).await
+        }
+        Some(Command::Tmux { template }) => {
+            let template = template.as_deref();
+            show_statusbar(
+                &socket,
+                to(Request::Status),
+                BarStyle::Tmux,
+                template,
+                false,
+            )
+            .await
+        }
+        Some(Command::Prompt { template }) => show_prompt(template.as_deref()),
+        Some(Command::Plan { format, session }) => show_plan(&session, format),
+        Some(Command::Export { format, from, to }) => export_history(format, from, to),
+        Some(Command::Import {
+            path,
+            format,
+            mapping,
+        }) => import_history(&path, format, mapping.as_deref()),
+        Some(Command::Report {
+            period,
+            format,
+            output,
+        }) => write_report(period, format, output.as_deref()),
+        Some(Command::Graph { by, days }) => draw_graph(by, days),
+        Some(Command::Stats { format, by_task }) => show_stats(format, by_task),
+        Some(Command::Completions {
+            list: Some(names), ..
+        }) => list_names(&socket, names).await,
+        Some(Command::Completions { shell, .. }) => {
+            print!("{}", completions::script(shell.unwrap_or(Shell::Bash)))
+        }
+    }
+}
+
+/// Prints the log messages to stderr, keeping stdout for the output the commands exist for.
+/// `RUST_LOG` is read instead of `level` when set.
+fn init_logging(level: &str, format: LogFormat) {
+    use tracing_subscriber::EnvFilter;
+
+    let filter = EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new(level))
+        .unwrap_or_else(|e| exit_with(format!("invalid --log-level `{}`: {}", level, e)));
+    let logs = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(io::stderr);
+    match format {
+        LogFormat::Text => logs.init(),
+        LogFormat::Json => logs.json().init(),
+    }
+}
+
+/// The `durations` given on the command line over those of `profile`.
+fn reconfigure(profile: Option<&str>, durations: Durations) -> Durations {
+    let base = match profile {
+        Some(name) => Config::load()
+            .and_then(|config| config.find_profile(name))
+            .map(Durations::from)
+            .unwrap_or_else(|e| fail(Failure::InvalidConfig, e)),
+        None => Durations::default(),
+    };
+    let durations = Durations {
+        work: durations.work.or(base.work),
+        short_break: durations.short_break.or(base.short_break),
+        long_break: durations.long_break.or(base.long_break),
+        long_break_interval: durations.long_break_interval.or(base.long_break_interval),
+    };
+    if durations == Durations::default() {
+        exit_with("name a profile or a duration to change");
+    }
+    durations
+}
+
+fn load_config(session: &SessionArgs) -> Config {
+    read_config(session).unwrap_or_else(|e| fail(Failure::InvalidConfig, e))
+}
+
+/// The config file with the profile and the options of the command line applied.
+fn read_config(session: &SessionArgs) -> Result<Config, ConfigError> {
+    let mut config = match &session.config {
+        Some(path) => Config::from_file(path)?,
+        None => Config::load()?,
+    };
+    if let Some(profile) = &session.profile {
+        config.apply_profile(profile)?;
+    }
+    session.apply(&mut config);
+    config.validate()?;
+    Ok(config)
+}
+
+/// Watches the config file the daemon was started from, when this build can.
+#[cfg(feature = "reload")]
+fn watch_config(session: &SessionArgs) -> Option<reload::ConfigWatcher> {
+    let path = session.config.clone().or_else(Config::path)?;
+    match reload::ConfigWatcher::new(&path) {
+        Ok(watcher) => Some(watcher),
+        Err(e) => {
+            tracing::warn!(path = %path.display(), error = %e, "cannot watch the config");
+            None
+        }
+    }
+}
+
+/// Waits for the watched config file to change, or forever without a watcher.
+async fn config_changed(#[cfg(feature = "reload")] watcher: &mut Option<reload::ConfigWatcher>) {
+    #[cfg(feature = "reload")]
+    if let Some(watcher) = watcher {
+        return watcher.changed().await;
+    }
+    std::future::pending().await
+}
+
+/// Hands the changed config file over to the default timer, keeping the old settings when
+/// it is invalid.
+async fn reload_config(session: &SessionArgs, client: &Client) {
+    let config = match read_config(session) {
+        Ok(config) => config,
+        Err(e) => return tracing::warn!(error = %e, "ignoring the changed config"),
+    };
+    if let Err(e) = client.reload(&config).await {
+        tracing::warn!(error = %e, "cannot reload the config");
+    }
+}
+
+/// Notifies through the terminal when it can show notifications itself, and through the
+/// desktop otherwise.
+fn notify_transitions(session: &SessionArgs, config: &Config, client: &Client) {
+    if session.no_notify {
+        return;
+    }
+    let messages = notify::Messages::default()
+        .with_texts(&config.notifications)
+        .extending_by(config.extend_step);
+    let labels = config.labels.clone();
+    let quiet = config.quiet.clone();
+    let control = Some(client.clone());
+    match notify::Terminal::detect().filter(|_| io::stdout().is_terminal()) {
+        Some(terminal) => {
+            notify::spawn(
+                terminal,
+                messages,
+                labels,
+                quiet,
+                control,
+                alerts(config, client),
+            );
+        }
+        #[cfg(feature = "desktop-notify")]
+        None => {
+            notify::spawn(
+                notify::Desktop,
+                messages,
+                labels,
+                quiet,
+                control,
+                alerts(config, client),
+            );
+        }
+        #[cfg(not(feature = "desktop-notify"))]
+        None => {}
+    }
+}
+
+/// The events of the pomodoro behind `client` for notifications and sounds, with the phase
+/// ends repeated while they wait to be acknowledged when `config` says so.
+fn alerts(config: &Config, client: &Client) -> broadcast::Receiver<Event> {
+    let events = client.subscribe();
+    match config.repeat_alarm {
+        Some(every) => alarm::repeat(every, events),
+        None => events,
+    }
+}
+
+#[cfg_attr(not(feature = "sound"), allow(unused_variables))]
+fn play_sounds(config: &Config, client: &Client) {
+    #[cfg(feature = "sound")]
+    sound::spawn(
+        sound::Speaker,
+        config.sound.clone(),
+        config.quiet.clone(),
+        alerts(config, client),
+    );
+}
+
+fn announce_phases(config: &Config, client: &Client) {
+    if let Some(settings) = &config.speech {
+        speech::spawn(
+            speech::SpeechCommand::new(settings),
+            settings.clone(),
+            config.labels.clone(),
+            config.quiet.clone(),
+            client.clone(),
+        );
+    }
+}
+
+#[cfg_attr(not(feature = "mqtt"), allow(unused_variables))]
+fn publish_mqtt(config: &Config, pomo: &Pomodoro) {
+    let settings = match &config.mqtt {
+        Some(settings) => settings,
+        None => return,
+    };
+    #[cfg(feature = "mqtt")]
+    mqtt::spawn(settings.clone(), pomo.subscribe());
+    #[cfg(not(feature = "mqtt"))]
+    tracing::warn!("ignoring [mqtt], this build lacks the mqtt feature");
+}
+
+#[cfg_attr(
+    not(all(target_os = "linux", feature = "logind")),
+    allow(unused_variables)
+)]
+fn follow_screen_lock(config: &Config, client: &Client) {
+    #[cfg(all(target_os = "linux", feature = "logind"))]
+    if config.screen_lock.pause {
+        screen_lock::spawn(config.screen_lock.clone(), client.clone());
+    }
+}
+
+fn follow_idle(config: &Config, client: &Client) {
+    if config.idle.after.is_some() && idle::spawn(config.idle.clone(), client.clone()).is_none() {
+        tracing::warn!("ignoring [idle], there is no way to tell the idle time here");
+    }
+}
+
+fn inhibit_sleep(config: &Config, client: &Client) {
+    if config.inhibit_sleep && inhibit::spawn(client.clone()).is_none() {
+        tracing::warn!("ignoring inhibit_sleep, there is no way to keep this machine awake");
+    }
+}
+
+#[cfg_attr(
+    not(all(target_os = "linux", feature = "tray")),
+    allow(unused_variables)
+)]
+fn show_tray(config: &Config, client: &Client) {
+    if !config.tray {
+        return;
+    }
+    #[cfg(all(target_os = "linux", feature = "tray"))]
+    tray::spawn(client.clone(), config.labels.clone());
+    #[cfg(not(all(target_os = "linux", feature = "tray")))]
+    tracing::warn!("ignoring tray, this build lacks the tray feature");
+}
+
+#[cfg_attr(
+    not(all(target_os = "linux", feature = "overlay")),
+    allow(unused_variables)
+)]
+fn cover_breaks(config: &Config, client: &Client) {
+    if !config.hard_breaks {
+        return;
+    }
+    #[cfg(all(target_os = "linux", feature = "overlay"))]
+    overlay::spawn(client.clone(), config.labels.clone());
+    #[cfg(not(all(target_os = "linux", feature = "overlay")))]
+    tracing::warn!("ignoring hard_breaks, this build lacks the overlay feature");
+}
+
+#[cfg_attr(not(unix), allow(unused_variables))]
+fn listen_for_signals(config: &Config, client: &Client) {
+    #[cfg(unix)]
+    if let Err(e) = signals::spawn(config.signals, client.clone(), config.extend_step) {
+        tracing::warn!(error = %e, "cannot handle SIGUSR1 and SIGUSR2");
+    }
+}
+
+/// The history database at the default path, with the passphrase of `config` when it is
+/// encrypted. The passphrase is asked for once, however many pomodoros are launched.
+fn history_store(config: &Config) -> Option<Store> {
+    static PASSPHRASE: OnceLock<Option<String>> = OnceLock::new();
+    let passphrase = PASSPHRASE.get_or_init(|| {
+        let from_env = ENV_PASSPHRASE.get().cloned().unwrap_or(Ok(None));
+        let from_env = from_env.unwrap_or_else(|e| {
+            exit_with(format!("cannot get the passphrase of the history: {}", e))
+        });
+        if from_env.is_some() {
+            return from_env;
+        }
+        let encryption = config.encryption.as_ref()?;
+        Some(encryption.passphrase().unwrap_or_else(|e| {
+            exit_with(format!("cannot get the passphrase of the history: {}", e))
+        }))
+    });
+    Some(Store::new(History::path()?, passphrase.clone()))
+}
+
+/// Whether the history is encrypted, in which case the task is kept out of the files
+/// written in plain text next to it.
+fn encrypted(config: &Config) -> bool {
+    config.encryption.is_some() || matches!(ENV_PASSPHRASE.get(), Some(Ok(Some(_))))
+}
+
+fn record_history(
+    session: &SessionArgs,
+    config: &Config,
+    pomo: &Pomodoro,
+) -> Option<JoinHandle<()>> {
+    if session.no_history {
+        return None;
+    }
+    let store = history_store(config)?;
+    match store.open() {
+        Ok(history) => Some(history::spawn(history, pomo.subscribe())),
+        Err(e) => {
+            tracing::warn!(path = %store.path.display(), error = %e, "cannot open history");
+            None
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "web"), allow(unused_variables))]
+fn serve_web(config: &Config, client: &Client) {
+    let addr = match config.web_address {
+        Some(addr) => addr,
+        None => return,
+    };
+    #[cfg(feature = "web")]
+    {
+        let client = client.clone();
+        let history = history_store(config);
+        tokio::spawn(async move {
+            let served = match web::bind(addr).await {
+                Ok(listener) => web::serve(listener, client, history).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = served {
+                tracing::warn!(address = %addr, error = %e, "cannot serve the events");
+            }
+        });
+    }
+    #[cfg(not(feature = "web"))]
+    tracing::warn!("ignoring web_address, this build lacks the web feature");
+}
+
+#[cfg_attr(not(feature = "webhooks"), allow(unused_variables))]
+fn post_webhooks(config: &Config, events: broadcast::Receiver<Event>, client: &Client) {
+    if config.webhooks.is_empty() {
+        return;
+    }
+    #[cfg(feature = "webhooks")]
+    webhooks::spawn(
+        config.webhooks.clone(),
+        config.labels.clone(),
+        events,
+        client.clone(),
+    );
+    #[cfg(not(feature = "webhooks"))]
+    tracing::warn!("ignoring [[webhooks]], this build lacks the webhooks feature");
+}
+
+#[cfg_attr(not(feature = "slack"), allow(unused_variables))]
+fn follow_on_slack(
+    config: &Config,
+    events: broadcast::Receiver<Event>,
+    client: &Client,
+) -> Option<JoinHandle<()>> {
+    let settings = config.slack.as_ref()?;
+    #[cfg(feature = "slack")]
+    return Some(slack::spawn(
+        settings.clone(),
+        config.labels.clone(),
+        events,
+        client.clone(),
+    ));
+    #[cfg(not(feature = "slack"))]
+    {
+        tracing::warn!("ignoring [slack], this build lacks the slack feature");
+        None
+    }
+}
+
+/// Follows the Taskwarrior task picked with `--taskwarrior`, returning its description to
+/// work on.
+#[cfg_attr(not(feature = "taskwarrior"), allow(unused_variables))]
+async fn follow_taskwarrior(
+    session: &SessionArgs,
+    pomo: &Pomodoro,
+) -> Option<(String, JoinHandle<()>)> {
+    #[cfg(feature = "taskwarrior")]
+    if let Some(id) = &session.taskwarrior {
+        let description = taskwarrior::description(id)
+            .await
+            .unwrap_or_else(|e| exit_with(format!("cannot read the task {}: {}", id, e)));
+        let taskwarrior = Taskwarrior::new(id.as_str(), pomo.status().phase);
+        return Some((
+            description,
+            taskwarrior::spawn(taskwarrior, pomo.subscribe()),
+        ));
+    }
+    None
+}
+
+#[cfg_attr(not(feature = "timetrack"), allow(unused_variables))]
+fn log_time_entries(config: &Config, events: broadcast::Receiver<Event>) -> Option<JoinHandle<()>> {
+    let settings = config.time_tracking.as_ref()?;
+    #[cfg(feature = "timetrack")]
+    return Some(timetrack::spawn(settings.clone(), events));
+    #[cfg(not(feature = "timetrack"))]
+    {
+        tracing::warn!("ignoring [time_tracking], this build lacks the timetrack feature");
+        None
+    }
+}
+
+/// The pomodoros the history has completed today, which count towards the daily goal.
+fn completed_today(config: &Config) -> Option<u32> {
+    let records = history_store(config)?
+        .open()
+        .and_then(|history| history.records())
+        .map_err(|e| tracing::warn!(error = %e, "cannot read the history"))
+        .ok()?;
+    Some(Stats::from_records(&records, Local::now()).today as u32)
+}
+
+/// A pomodoro running with the session's notifications, history and hooks attached.
+struct Session {
+    runtime: Runtime,
+    client: Client,
+    events: broadcast::Receiver<Event>,
+    recorder: Option<JoinHandle<()>>,
+    feed: Option<JoinHandle<()>>,
+    /// Clears the Slack status once the engine stops.
+    slack: Option<JoinHandle<()>>,
+    /// Sends the time entries still queued once the engine stops.
+    time_tracking: Option<JoinHandle<()>>,
+    /// Stops the Taskwarrior task once the engine stops.
+    taskwarrior: Option<JoinHandle<()>>,
+    saver: Option<(PathBuf, JoinHandle<()>)>,
+    labels: Labels,
+    extend_step: Duration,
+}
+
+/// How [`launch`] starts a pomodoro.
+#[derive(Default)]
+struct Launch<'a> {
+    /// Carry on from this checkpoint.
+    checkpoint: Option<&'a Checkpoint>,
+    /// Wait to be resumed before the first phase.
+    idle: bool,
+    /// The name of an additional timer of the daemon, which neither asks for a task,
+    /// answers signals nor saves checkpoints.
+    timer: Option<&'a str>,
+}
+
+/// Starts a pomodoro from `config` as `how` says.
+async fn launch(session: &SessionArgs, config: Config, how: Launch<'_>) -> Session {
+    let checkpoint = how.checkpoint;
+    let mut pomo = config
+        .pomodoro()
+        .unwrap_or_else(|e| fail(Failure::InvalidConfig, e));
+    if let Some(checkpoint) = checkpoint {
+        pomo.restore(checkpoint);
+    }
+    if config.daily_goal.is_some() {
+        if let Some(done) = completed_today(&config) {
+            pomo.count_done_today(done);
+        }
+    }
+    publish_mqtt(&config, &pomo);
+    let recorder = record_history(session, &config, &pomo);
+    let feed = config
+        .event_file
+        .clone()
+        .map(|path| feed::spawn(path, pomo.subscribe()));
+    let (taskwarrior_task, taskwarrior) = match how.timer {
+        Some(_) => None,
+        None => follow_taskwarrior(session, &pomo).await,
+    }
+    .unzip();
+    let task = match how.timer {
+        Some(_) => None,
+        None => session
+            .task
+            .clone()
+            .or(taskwarrior_task)
+            .or_else(|| checkpoint.and_then(|checkpoint| checkpoint.snapshot.task.clone()))
+            .or_else(|| config.task_file.as_deref().and_then(pick_task)),
+    };
+    if let Some(path) = &config.task_file {
+        tasks::spawn(path.clone(), pomo.subscribe());
+    }
+    if let Some(settings) = &config.org {
+        org::spawn(settings.clone(), pomo.subscribe());
+    }
+    let hook_events = pomo.subscribe();
+    let webhook_events = pomo.subscribe();
+    let slack_events = pomo.subscribe();
+    let time_tracking = log_time_entries(&config, pomo.subscribe());
+    let saver_events = pomo.subscribe();
+    let (runtime, client, events) = if how.idle {
+        start_idle(pomo).await
+    } else {
+        start(pomo).await
+    };
+    if let Some(task) = task {
+        let _ = client.set_task(task).await;
+    }
+    notify_transitions(session, &config, &client);
+    play_sounds(&config, &client);
+    announce_phases(&config, &client);
+    follow_screen_lock(&config, &client);
+    follow_idle(&config, &client);
+    inhibit_sleep(&config, &client);
+    show_tray(&config, &client);
+    cover_breaks(&config, &client);
+    if how.timer.is_none() {
+        listen_for_signals(&config, &client);
+    }
+    serve_web(&config, &client);
+    post_webhooks(&config, webhook_events, &client);
+    let slack = follow_on_slack(&config, slack_events, &client);
+    let with_task = !encrypted(&config);
+    hooks::spawn(
+        config.hooks,
+        config.labels.clone(),
+        hook_events,
+        client.clone(),
+    );
+    let saver = Checkpoint::path()
+        .filter(|_| how.timer.is_none())
+        .map(|path| {
+            let saver = recovery::spawn(path.clone(), client.clone(), saver_events, with_task);
+            (path, saver)
+        });
+    Session {
+        runtime,
+        client,
+        events,
+        recorder,
+        feed,
+        slack,
+        time_tracking,
+        taskwarrior,
+        saver,
+        labels: config.labels,
+        extend_step: config.extend_step,
+    }
+}
+
+impl Session {
+    /// Stops the engine and waits until every finished phase has been recorded.
+    async fn finish(self) {
+        if let Err(e) = self.runtime.shutdown().await {
+            tracing::warn!(error = %e, "cannot shut the engine down");
+        }
+        drop(self.client);
+        if let Some(recorder) = self.recorder {
+            let _ = recorder.await;
+        }
+        if let Some((_, saver)) = self.saver {
+            let _ = saver.await;
+        }
+        if let Some(slack) = self.slack {
+            let _ = tokio::time::timeout(Duration::from_secs(10), slack).await;
+        }
+        if let Some(time_tracking) = self.time_tracking {
+            let _ = tokio::time::timeout(Duration::from_secs(10), time_tracking).await;
+        }
+        if let Some(taskwarrior) = self.taskwarrior {
+            let _ = tokio::time::timeout(Duration::from_secs(10), taskwarrior).await;
+        }
+        // A FIFO nobody reads from would block the feed forever.
+        if let Some(feed) = self.feed {
+            let _ = tokio::time::timeout(Duration::from_secs(1), feed).await;
+        }
+    }
+
+    /// Pauses the engine, records the phase in progress, saves a checkpoint to resume it
+    /// from and reports where the session stopped.
+    async fn interrupt(self) {
+        // Taken before pausing, so resuming does not count the interruption as a pause.
+        let checkpoint = self.client.checkpoint().await.ok();
+        // A strict pomodoro refuses, but stopping records the phase all the same.
+        let _ = self.client.pause().await;
+        let status = self.client.status().await;
+        let path = self.saver.as_ref().map(|(path, _)| path.clone());
+        let labels = self.labels.clone();
+        self.finish().await;
+        if let (Some(checkpoint), Some(path)) = (checkpoint, path) {
+            if let Err(e) = checkpoint.save(&path) {
+                tracing::warn!(path = %path.display(), error = %e, "cannot save the session");
+            }
+        }
+        if let Ok(status) = status {
+            println!("Interrupted");
+            output::print_status(&status, Format::Text, &labels);
+        }
+    }
+}
+
+fn listen_for_termination() -> impl std::future::Future<Output = ()> {
+    terminated().unwrap_or_else(|e| exit_with(format!("cannot handle signals: {}", e)))
+}
+
+async fn run_foreground(session: &SessionArgs) {
+    let running = launch(session, load_config(session), Launch::default()).await;
+    follow(running, Progress::Lines).await
+}
+
+async fn run_in_place(session: &SessionArgs) {
+    let running = launch(session, load_config(session), Launch::default()).await;
+    follow(running, Progress::InPlace).await
+}
+
+/// How a foreground pomodoro shows its progress.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Progress {
+    /// A line for every tick.
+    Lines,
+    /// A single line with a progress bar redrawn on every tick, and the bell at the end of
+    /// each phase.
+    InPlace,
+}
+
+/// Prints the progress of `running` until it completes, the process is told to stop or `q`
+/// is typed.
+async fn follow(mut running: Session, progress: Progress) {
+    let terminated = listen_for_termination();
+    tokio::pin!(terminated);
+    let mut keys = Keys::listen();
+    let newline = if keys.is_some() { "\r\n" } else { "\n" };
+    let clear = match progress {
+        Progress::InPlace => "\r\x1b[2K",
+        Progress::Lines => "",
+    };
+    // The label of the running plan step, which ticks do not carry.
+    let mut step_label = None;
+    loop {
+        let event = tokio::select! {
+            event = running.events.recv() => event,
+            action = Keys::next(&mut keys) => {
+                if action == Action::Quit {
+                    break;
+                }
+                if let Err(e) = act(&running, action).await {
+                    eprint!("{}pomo: {}{}", clear, e, newline);
+                }
+                continue;
+            }
+            _ = &mut terminated => break,
+        };
+        let label = |phase: pomo::Phase| {
+            step_label
+                .clone()
+                .unwrap_or_else(|| phase.label(&running.labels).to_string())
+        };
+        match (event, progress) {
+            (Ok(Event::PhaseStarted(_)), _) => {
+                step_label = running.client.status().await.ok().and_then(|s| s.label)
+            }
+            (
+                Ok(Event::Tick {
+                    phase, remaining, ..
+                }),
+                Progress::Lines,
+            ) => {
+                print!(
+                    "{}{}",
+                    output::progress_line(&label(phase), remaining),
+                    newline
+                )
+            }
+            (
+                Ok(Event::Tick {
+                    phase,
+                    elapsed,
+                    remaining,
+                }),
+                Progress::InPlace,
+            ) => {
+                let bar = output::progress_bar(&label(phase), phase, elapsed, remaining);
+                print!("{}{}", clear, bar);
+                let _ = io::stdout().flush();
+            }
+            (Ok(Event::PhaseEnded(phase)), Progress::InPlace) => {
+                print!("{}", output::phase_end_alert(&label(phase)));
+                let _ = io::stdout().flush();
+            }
+            (Ok(Event::Paused), Progress::InPlace) => {
+                print!(" (paused)");
+                let _ = io::stdout().flush();
+            }
+            (Ok(Event::AwaitingAck(phase)), _) if keys.is_some() => {
+                let waiting = format!("{} is over, press space to go on", label(phase));
+                match progress {
+                    Progress::InPlace => print!("{}{}", clear, waiting),
+                    Progress::Lines => print!("{}{}", waiting, newline),
+                }
+                let _ = io::stdout().flush();
+            }
+            (Ok(Event::Completed), _) | (Err(RecvError::Closed), _) => {
+                drop(keys);
+                if progress == Progress::InPlace {
+                    println!();
+                }
+                return running.finish().await;
+            }
+            (Ok(_), _) | (Err(RecvError::Lagged(_)), _) => {}
+        }
+    }
+    drop(keys);
+    if progress == Progress::InPlace {
+        println!();
+    }
+    running.interrupt().await
+}
+
+/// Carries out a keystroke typed into a foreground pomodoro.
+async fn act(running: &Session, action: Action) -> Result<(), PomoError> {
+    let client = &running.client;
+    match action {
+        Action::TogglePause => client.toggle().await.map(|_| ()),
+        Action::Skip => client.skip().await,
+        Action::Extend => client.extend(running.extend_step).await,
+        Action::Void => client.void("").await,
+        Action::Interrupt(kind) => client.interrupt(kind, "").await,
+        Action::Quit => Ok(()),
+    }
+}
+
+#[cfg(feature = "tui")]
+async fn run_tui(session: &SessionArgs, tick_rate: Option<Duration>) {
+    let mut config = load_config(session);
+    if let Some(tick_rate) = tick_rate {
+        config.tick_rate = tick_rate;
+        config
+            .validate()
+            .unwrap_or_else(|e| fail(Failure::InvalidConfig, e));
+    }
+    let mut running = launch(session, config, Launch::default()).await;
+    let terminated = listen_for_termination();
+    let result = tui::run(
+        &running.client,
+        &mut running.events,
+        &running.labels,
+        running.extend_step,
+        terminated,
+    )
+    .await;
+    running.finish().await;
+    result.unwrap_or_else(|e| exit_with(e));
+}
+
+async fn run_daemon(session: &SessionArgs, socket: &Path, takeover: bool) {
+    let config = load_config(session);
+    let (_instance, checkpoint) = lock_instance(socket, takeover).await;
+    let listener = daemon::bind(socket).await.unwrap_or_else(|e| exit_with(e));
+    let schedule = config.schedule.clone();
+    let how = Launch {
+        checkpoint: checkpoint.as_ref(),
+        idle: checkpoint.is_none() && !schedule.start.is_empty(),
+        ..Launch::default()
+    };
+    let running = launch(session, config.clone(), how).await;
+    if !schedule.is_empty() {
+        tokio::spawn(schedule::follow(schedule, running.client.clone()));
+    }
+    #[cfg(all(target_os = "linux", feature = "dbus"))]
+    dbus::spawn(running.client.clone());
+    #[cfg(unix)]
+    if config.tmux || std::env::var_os("TMUX").is_some() {
+        tmux::spawn(running.client.clone());
+    }
+    let status_file = StatusFile::path().map(|path| {
+        let with_task = !encrypted(&config);
+        status_file::spawn(
+            path,
+            config.labels.clone(),
+            running.client.clone(),
+            with_task,
+        )
+    });
+    let export = config
+        .status_file
+        .clone()
+        .map(|export| status_file::export(export, config.labels.clone(), running.client.clone()));
+    let (timers, mut control) = daemon::Timers::new();
+    timers.insert(daemon::DEFAULT_TIMER, running.client.clone());
+    let mut others = BTreeMap::new();
+    let served = daemon::serve(listener, timers.clone());
+    tokio::pin!(served);
+    let terminated = listen_for_termination();
+    tokio::pin!(terminated);
+    #[cfg(feature = "reload")]
+    let mut watcher = watch_config(session);
+    loop {
+        tokio::select! {
+            result = &mut served => {
+                if let Err(e) = result {
+                    tracing::warn!(error = %e, "stopped serving the daemon");
+                }
+                running.finish().await;
+                break;
+            }
+            Some((request, reply)) = control.recv() => {
+                if request == Control::Quit {
+                    let _ = reply.send(Ok(()));
+                    running.interrupt().await;
+                    break;
+                }
+                let done = control_timer(session, &config, &timers, &mut others, request).await;
+                let _ = reply.send(done);
+            }
+            _ = config_changed(#[cfg(feature = "reload")] &mut watcher) => {
+                reload_config(session, &running.client).await;
+            }
+            _ = &mut terminated => {
+                running.interrupt().await;
+                break;
+            }
+        }
+    }
+    for (_, timer) in others {
+        timer.finish().await;
+    }
+    for writer in status_file.into_iter().chain(export) {
+        let _ = writer.await;
+    }
+    daemon::clean_up(socket);
+}
+
+/// Starts or stops one of the daemon's named timers, which run from their own config file
+/// when there is one and from the daemon's otherwise.
+async fn control_timer(
+    session: &SessionArgs,
+    config: &Config,
+    timers: &daemon::Timers,
+    others: &mut BTreeMap<String, Session>,
+    control: Control,
+) -> Result<(), String> {
+    match control {
+        Control::Start(name) => {
+            if timers.get(&name).is_some() {
+                return Err(format!("the timer {} is already running", name));
+            }
+            let config = match Config::timer_path(&name) {
+                Some(path) if path.exists() => {
+                    let config = Config::from_file(&path).map_err(|e| e.to_string())?;
+                    config.validate().map_err(|e| e.to_string())?;
+                    config
+                }
+                _ => config.clone(),
+            };
+            let how = Launch {
+                timer: Some(&name),
+                ..Launch::default()
+            };
+            let running = launch(session, config, how).await;
+            timers.insert(name.clone(), running.client.clone());
+            others.insert(name, running);
+            Ok(())
+        }
+        Control::Stop(name) => match others.remove(&name) {
+            Some(running) => {
+                timers.remove(&name);
+                running.finish().await;
+                Ok(())
+            }
+            None if name == daemon::DEFAULT_TIMER => {
+                Err("the default timer only stops with the daemon".to_string())
+            }
+            None => Err(format!("there is no timer named {}", name)),
+        },
+        Control::Quit => Err("only the daemon quits".to_string()),
+    }
+}
+
+/// How long a daemon taking over waits for the one it replaces to quit.
+const TAKEOVER_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Locks the data directory for a daemon on `socket`. With `takeover`, the daemon holding
+/// the lock is asked to quit first, and the session it leaves is returned to carry on with.
+async fn lock_instance(socket: &Path, takeover: bool) -> (Instance, Option<Checkpoint>) {
+    let path = Instance::path().unwrap_or_else(|| exit_with("cannot locate the data directory"));
+    let acquire = || {
+        Instance::acquire(&path, socket)
+            .unwrap_or_else(|e| exit_with(format!("cannot lock {}: {}", path.display(), e)))
+    };
+    let owner = match acquire() {
+        Ok(instance) => return (instance, None),
+        Err(owner) => owner,
+    };
+    if !takeover {
+        exit_with(format!(
+            "a daemon is already running as pid {} on {}, pass --takeover to replace it",
+            owner.pid,
+            owner.socket.display()
+        ));
+    }
+    match daemon::request(&owner.socket, &Request::Quit).await {
+        Ok(Response::Refused(reason)) | Ok(Response::Failed(reason)) => exit_with(reason),
+        Ok(_) => {}
+        Err(e) => exit_with(format!(
+            "cannot reach the daemon running as pid {} on {}: {}",
+            owner.pid,
+            owner.socket.display(),
+            e
+        )),
+    }
+    // The lock goes once the daemon has saved its session and exited.
+    let deadline = tokio::time::Instant::now() + TAKEOVER_TIMEOUT;
+    let instance = loop {
+        if let Ok(instance) = acquire() {
+            break instance;
+        }
+        if tokio::time::Instant::now() >= deadline {
+            exit_with(format!(
+                "the daemon running as pid {} did not quit",
+                owner.pid
+            ));
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    };
+    let checkpoint = Checkpoint::path().and_then(|path| Checkpoint::load(path).ok().flatten());
+    (instance, checkpoint)
+}
+
+/// The socket of the daemon running, or the default one when none runs.
+fn running_socket() -> PathBuf {
+    let owner = Instance::path().and_then(|path| match instance::running(&path) {
+        Ok(owner) => owner,
+        Err(e) => {
+            tracing::warn!(path = %path.display(), error = %e, "cannot tell which daemon runs");
+            None
+        }
+    });
+    owner
+        .map(|owner| owner.socket)
+        .unwrap_or_else(daemon::socket_path)
+}
+
+async fn resume_session(session: &SessionArgs, yes: bool) {
+    let path = Checkpoint::path().unwrap_or_else(|| exit_with("cannot locate the data directory"));
+    let checkpoint = match Checkpoint::load(&path) {
+        Ok(Some(checkpoint)) => checkpoint,
+        Ok(None) => exit_with("there is no interrupted session to resume"),
+        Err(e) => exit_with(format!("cannot read {}: {}", path.display(), e)),
+    };
+    let config = load_config(session);
+    println!(
+        "{}",
+        output::checkpoint_summary(&checkpoint, &config.labels)
+    );
+    if !yes && !confirm("Continue? [Y/n] ") {
+        return;
+    }
+    follow(
+        launch(
+            session,
+            config,
+            Launch {
+                checkpoint: Some(&checkpoint),
+                ..Launch::default()
+            },
+        )
+        .await,
+        Progress::Lines,
+    )
+    .await
+}
+
+/// Asks which of the open tasks listed in `path` the session is spent on.
+fn pick_task(path: &Path) -> Option<String> {
+    let mut tasks = match tasks::read(path) {
+        Ok(tasks) => tasks,
+        Err(e) => {
+            tracing::warn!(path = %path.display(), error = %e, "cannot read tasks");
+            return None;
+        }
+    };
+    if tasks.is_empty() || !io::stdin().is_terminal() {
+        return None;
+    }
+    for (number, task) in tasks.iter().enumerate() {
+        println!("{:>3}. {} ({})", number + 1, task.title, task.pomodoros);
+    }
+    print!("Task for this session [1-{}, none if empty]: ", tasks.len());
+    let _ = io::stdout().flush();
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer).ok()?;
+    let number: usize = answer.trim().parse().ok()?;
+    if number == 0 || number > tasks.len() {
+        return None;
+    }
+    Some(tasks.swap_remove(number - 1).title)
+}
+
+/// Asks a yes or no question on the terminal, defaulting to yes.
+fn confirm(question: &str) -> bool {
+    print!("{}", question);
+    let _ = io::stdout().flush();
+    let mut answer = String::new();
+    match io::stdin().lock().read_line(&mut answer) {
+        Ok(0) | Err(_) => return false,
+        Ok(_) => {}
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "" | "y" | "yes")
+}
+
+async fn send(socket: &Path, request: Request, format: Format) {
+    let response = daemon::request(socket, &request).await.unwrap_or_else(|e| {
+        let failure = match e.kind() {
+            io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused => Failure::NoDaemon,
+            _ => Failure::Failed,
+        };
+        let message = format!("cannot reach the daemon at {}: {}", socket.display(), e);
+        fail(failure, message)
+    });
+    match response {
+        Response::Done => {}
+        Response::Status(status) => output::print_status(&status, format, &display_labels()),
+        Response::Timers(timers) => output::print_timers(&timers, format, &display_labels()),
+        Response::Snapshot(snapshot) => println!("{}", serde_json::to_string(&snapshot).unwrap()),
+        Response::Refused(reason) => fail(Failure::Refused, reason),
+        Response::Failed(reason) => exit_with(reason),
+    }
+}
+
+/// The labels from the default config, for commands which only talk to the daemon.
+fn display_labels() -> Labels {
+    Config::load().map(|c| c.labels).unwrap_or_default()
+}
+
+async fn show_statusbar(
+    socket: &Path,
+    request: Request,
+    style: BarStyle,
+    template: Option<&str>,
+    watch: bool,
+) {
+    let labels = display_labels();
+    loop {
+        let status = match daemon::request(socket, &request).await {
+            Ok(Response::Status(status)) => Some(status),
+            _ => None,
+        };
+        println!(
+            "{}",
+            output::statusbar(status.as_ref(), style, template, &labels)
+        );
+        if !watch {
+            break;
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+/// Prints the `names` for completion scripts, or nothing when they cannot be told.
+async fn list_names(socket: &Path, names: Names) {
+    match names {
+        Names::Profiles => {
+            let config = Config::load().unwrap_or_default();
+            for name in config.profile_names() {
+                println!("{}", name);
+            }
+        }
+        Names::Timers => {
+            if let Ok(Response::Timers(timers)) = daemon::request(socket, &Request::Timers).await {
+                for (name, _) in timers {
+                    println!("{}", name);
+                }
+            }
+        }
+    }
+}
+
+/// Prints the prompt segment, or nothing at all when the daemon is not running, so that a
+/// prompt never waits on it.
+fn show_prompt(template: Option<&str>) {
+    let file = StatusFile::path().and_then(|path| StatusFile::load(path).ok().flatten());
+    if let Some(file) = file {
+        println!("{}", output::prompt_segment(&file, Local::now(), template));
+    }
+}
+
+fn open_history() -> History {
+    let config = Config::load().unwrap_or_else(|e| fail(Failure::InvalidConfig, e));
+    let store =
+        history_store(&config).unwrap_or_else(|| exit_with("cannot locate the data directory"));
+    store.open().unwrap_or_else(|e| {
+        exit_with(format!(
+            "cannot open history at {}: {}",
+            store.path.display(),
+            e
+        ))
+    })
+}
+
+fn show_plan(session: &SessionArgs, format: Format) {
+    let config = load_config(session);
+    let pomo = config
+        .pomodoro()
+        .unwrap_or_else(|e| fail(Failure::InvalidConfig, e));
+    output::print_schedule(&pomo.schedule(), Local::now(), format, &config.labels);
+}
+
+/// Prints the records of the phases started from `from` through `to`.
+fn export_history(format: ExportFormat, from: Option<NaiveDate>, to: Option<NaiveDate>) {
+    let mut records = open_history().records().unwrap_or_else(|e| exit_with(e));
+    records.retain(|record| {
+        let day = record.started_at.date_naive();
+        from.is_none_or(|from| day >= from) && to.is_none_or(|to| day <= to)
+    });
+    output::print_records(&records, format);
+}
+
+/// Adds the phases of the file at `path` to the history, read through the mapping at
+/// `mapping` if there is one.
+fn import_history(path: &Path, format: Option<import::Format>, mapping: Option<&Path>) {
+    let read = |path: &Path| {
+        std::fs::read_to_string(path)
+            .unwrap_or_else(|e| exit_with(format!("cannot read {}: {}", path.display(), e)))
+    };
+    let mapping = match mapping {
+        Some(file) => toml::from_str(&read(file)).unwrap_or_else(|e| {
+            fail(
+                Failure::InvalidConfig,
+                format!("invalid mapping {}: {}", file.display(), e),
+            )
+        }),
+        None => import::Mapping::default(),
+    };
+    let format = format
+        .or_else(|| import::Format::of(path))
+        .unwrap_or_else(|| exit_with("tell the format of the file with --format"));
+    let records = import::read(&read(path), format, &mapping)
+        .unwrap_or_else(|e| exit_with(format!("cannot import {}: {}", path.display(), e)));
+    let merged = open_history()
+        .merge(&records)
+        .unwrap_or_else(|e| exit_with(format!("cannot record the phases: {}", e)));
+    println!(
+        "imported {} phases, {} were already recorded",
+        merged,
+        records.len() - merged
+    );
+}
+
+/// Writes the report of `period` to `path`, or prints it without one.
+fn write_report(period: Period, format: ReportFormat, path: Option<&Path>) {
+    let records = open_history().records().unwrap_or_else(|e| exit_with(e));
+    let report = output::report(
+        &Report::from_records(&records, period, Local::now()),
+        format,
+    );
+    match path {
+        Some(path) => std::fs::write(path, report)
+            .unwrap_or_else(|e| exit_with(format!("cannot write {}: {}", path.display(), e))),
+        None => print!("{}", report),
+    }
+}
+
+fn draw_graph(by: GraphKind, days: usize) {
+    let records = open_history().records().unwrap_or_else(|e| exit_with(e));
+    let now = Local::now();
+    let lines = match by {
+        GraphKind::Day => output::sparkline(&stats::daily_counts(&records, days, now), now),
+        GraphKind::Hour => output::heatmap(&stats::heatmap(&records, days, now)),
+    };
+    for line in lines {
+        println!("{}", line);
+    }
+}
+
+fn show_stats(format: Format, by_task: bool) {
+    let records = open_history().records().unwrap_or_else(|e| exit_with(e));
+    if by_task {
+        output::print_task_stats(&TaskStats::from_records(&records), format);
+    } else {
+        output::print_stats(&Stats::from_records(&records, Local::now()), format);
+    }
+}
+
+fn exit_with(e: impl std::fmt::Display) -> ! {
+    fail(Failure::Failed, e)
+}
+
+/// Prints `e` in the format of the command's output and exits with the code of `failure`.
+fn fail(failure: Failure, e: impl std::fmt::Display) -> ! {
+    let format = ERROR_FORMAT.get().copied().unwrap_or(Format::Text);
+    eprintln!("{}", output::error_message(failure, &e.to_string(), format));
+    process::exit(failure.exit_code())
 }
@@ -0,0 +1,186 @@
+//! Publishing to an MQTT broker, so home automation such as Home Assistant can follow the
+//! pomodoro.
+//!
+//! Under the configured prefix, `phase` holds the running phase, `paused` whether it is
+//! paused and `remaining` the seconds left in it, while `event` gets the JSON line of the
+//! [`feed`](crate::feed) for every event but the ticks.
+
+use chrono::Local;
+use serde::Deserialize;
+
+use crate::event::Event;
+use crate::feed;
+
+/// Broker settings, read from the `[mqtt]` table of the config file.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Mqtt {
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Prepended to every topic, e.g. `pomo` for `pomo/phase`.
+    pub prefix: String,
+}
+
+impl Default for Mqtt {
+    fn default() -> Self {
+        Self {
+            host: "localhost".to_string(),
+            port: 1883,
+            client_id: "pomo".to_string(),
+            username: None,
+            password: None,
+            prefix: "pomo".to_string(),
+        }
+    }
+}
+
+/// A message to publish.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Publication {
+    pub topic: String,
+    pub payload: String,
+    /// Whether the broker keeps it for clients subscribing later.
+    pub retain: bool,
+}
+
+impl Mqtt {
+    fn publication(&self, topic: &str, payload: impl Into<String>, retain: bool) -> Publication {
+        Publication {
+            topic: format!("{}/{}", self.prefix, topic),
+            payload: payload.into(),
+            retain,
+        }
+    }
+
+    /// What to publish for `event`.
+    pub fn publications(&self, event: &Event) -> Vec<Publication> {
+        let state = match event {
+            Event::Tick { remaining, .. } => {
+                let secs = remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0);
+                return vec![self.publication("remaining", secs.to_string(), false)];
+            }
+            Event::PhaseStarted(phase) => Some(self.publication("phase", phase.name(), true)),
+            Event::Completed => Some(self.publication("phase", "completed", true)),
+            Event::Paused => Some(self.publication("paused", "true", true)),
+            Event::Resumed => Some(self.publication("paused", "false", true)),
+            _ => None,
+        };
+        let line = feed::encode(event, Local::now());
+        state
+            .into_iter()
+            .chain(Some(self.publication("event", line, false)))
+            .collect()
+    }
+}
+
+/// Spawns a task publishing every event to the broker `settings` point at. The connection
+/// is retried for as long as the pomodoro runs.
+#[cfg(feature = "mqtt")]
+pub fn spawn(
+    settings: Mqtt,
+    mut events: tokio::sync::broadcast::Receiver<Event>,
+) -> tokio::task::JoinHandle<()> {
+    use std::time::Duration;
+
+    use rumqttc::{AsyncClient, MqttOptions, QoS};
+    use tokio::sync::broadcast::error::RecvError;
+
+    let mut options = MqttOptions::new(&settings.client_id, &settings.host, settings.port);
+    options.set_keep_alive(Duration::from_secs(30));
+    if let Some(username) = &settings.username {
+        options.set_credentials(username, settings.password.as_deref().unwrap_or_default());
+    }
+    let (client, mut connection) = AsyncClient::new(options, 16);
+    let broker = format!("{}:{}", settings.host, settings.port);
+    let driver = tokio::spawn(async move {
+        let mut connected = true;
+        loop {
+            match connection.poll().await {
+                Ok(_) => connected = true,
+                Err(e) => {
+                    if std::mem::take(&mut connected) {
+                        tracing::warn!(broker = %broker, error = %e, "cannot reach the MQTT broker");
+                    }
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+            }
+        }
+    });
+    tokio::spawn(async move {
+        loop {
+            let event = match events.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            };
+            for publication in settings.publications(&event) {
+                // Ticks are stale a second later, so losing one is better than waiting.
+                let published = if let Event::Tick { .. } = event {
+                    client.try_publish(
+                        publication.topic,
+                        QoS::AtMostOnce,
+                        publication.retain,
+                        publication.payload,
+                    )
+                } else {
+                    client
+                        .publish(
+                            publication.topic,
+                            QoS::AtLeastOnce,
+                            publication.retain,
+                            publication.payload,
+                        )
+                        .await
+                };
+                if published.is_err() {
+                    break;
+                }
+            }
+        }
+        let _ = client.disconnect().await;
+        driver.abort();
+    })
+}
+
+#[test]
+fn publish_state_changes() {
+    use std::time::Duration;
+
+    use crate::pomodoro::Phase;
+
+    let settings = Mqtt {
+        prefix: "desk/pomo".to_string(),
+        ..Mqtt::default()
+    };
+    let topics = |event| {
+        settings
+            .publications(&event)
+            .into_iter()
+            .map(|p| (p.topic, p.payload, p.retain))
+            .filter(|(topic, ..)| topic != "desk/pomo/event")
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        topics(Event::PhaseStarted(Phase::Working)),
+        vec![("desk/pomo/phase".to_string(), "working".to_string(), true)]
+    );
+    assert_eq!(
+        topics(Event::Tick {
+            phase: Phase::Working,
+            elapsed: Duration::from_secs(1),
+            remaining: Duration::from_millis(1_499_500),
+        }),
+        vec![("desk/pomo/remaining".to_string(), "1500".to_string(), false)]
+    );
+    assert_eq!(
+        topics(Event::Paused),
+        vec![("desk/pomo/paused".to_string(), "true".to_string(), true)]
+    );
+    let published = settings.publications(&Event::PhaseSkipped(Phase::ShortBreak));
+    assert_eq!(published.len(), 1);
+    assert_eq!(published[0].topic, "desk/pomo/event");
+    assert!(published[0].payload.contains("\"phase_skipped\""));
+}
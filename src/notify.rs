@@ -0,0 +1,547 @@
+//! Desktop and terminal notifications fired on phase transitions.
+
+use std::error::Error;
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Deserialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+
+use crate::error::PomoError;
+use crate::event::Event;
+use crate::pomodoro::{Labels, Phase};
+use crate::quiet::Quiet;
+use crate::runtime::Client;
+use crate::template::{self, Values};
+
+/// A button on a notification which controls the timer, where the notification service
+/// supports them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Button {
+    /// Start the break a working phase waits with.
+    StartBreak,
+    /// Skip the break which follows or is running.
+    SkipBreak,
+    /// Lengthen the running phase.
+    Extend(Duration),
+}
+
+impl Button {
+    /// The identifier of the button's action.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Button::StartBreak => "start_break",
+            Button::SkipBreak => "skip_break",
+            Button::Extend(_) => "extend",
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            Button::StartBreak => "Start break".to_string(),
+            Button::SkipBreak => "Skip break".to_string(),
+            Button::Extend(by) => format!("+{} min", by.as_secs().div_ceil(60)),
+        }
+    }
+
+    /// Carries out the button on the pomodoro behind `client`.
+    pub async fn press(&self, client: &Client) -> Result<(), PomoError> {
+        match self {
+            Button::StartBreak => match client.ack().await {
+                Err(PomoError::NotAwaitingAck) => Ok(()),
+                acked => acked,
+            },
+            Button::SkipBreak => {
+                if client.status().await?.awaiting_ack {
+                    client.ack().await?;
+                }
+                if client.status().await?.phase == Phase::Working {
+                    return Ok(());
+                }
+                client.skip().await
+            }
+            Button::Extend(by) => client.extend(*by).await,
+        }
+    }
+}
+
+/// How much the [`Button::Extend`] of the default messages adds.
+const EXTEND_STEP: Duration = Duration::from_secs(5 * 60);
+
+/// The title and body shown in a notification.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Message {
+    pub title: String,
+    pub body: String,
+    pub buttons: Vec<Button>,
+}
+
+impl Message {
+    pub fn new(title: impl Into<String>, body: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            body: body.into(),
+            buttons: Vec::new(),
+        }
+    }
+
+    pub fn with_buttons(mut self, buttons: Vec<Button>) -> Self {
+        self.buttons = buttons;
+        self
+    }
+
+    /// The message with the placeholders of its title and body filled in from `values`.
+    pub fn render(&self, values: &Values) -> Self {
+        Self {
+            title: template::render(&self.title, values),
+            body: template::render(&self.body, values),
+            buttons: self.buttons.clone(),
+        }
+    }
+}
+
+/// A title or body replacing the one of a default message, which may use the placeholders
+/// of [`template`](crate::template).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Text {
+    pub title: Option<String>,
+    pub body: Option<String>,
+}
+
+/// The texts of the notifications, read from the `[notifications]` table of the config
+/// file, e.g. `work_end = { title = "Pomodoro {count} done" }`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Texts {
+    pub work_end: Text,
+    pub short_break_start: Text,
+    pub long_break_start: Text,
+    pub goal_reached: Text,
+    pub break_end: Text,
+}
+
+/// The notifications to show for each transition. `None` disables a notification.
+#[derive(Clone, Debug)]
+pub struct Messages {
+    pub work_end: Option<Message>,
+    pub short_break_start: Option<Message>,
+    pub long_break_start: Option<Message>,
+    pub goal_reached: Option<Message>,
+    /// Shown when a break is over and waits to be acknowledged.
+    pub break_end: Option<Message>,
+}
+
+impl Default for Messages {
+    fn default() -> Self {
+        Self {
+            work_end: Some(
+                Message::new("Pomodoro finished", "Nice work!")
+                    .with_buttons(vec![Button::StartBreak, Button::SkipBreak]),
+            ),
+            short_break_start: Some(
+                Message::new("Short break", "Stretch for a few minutes.")
+                    .with_buttons(vec![Button::SkipBreak, Button::Extend(EXTEND_STEP)]),
+            ),
+            long_break_start: Some(
+                Message::new("Long break", "You earned a longer rest.")
+                    .with_buttons(vec![Button::SkipBreak, Button::Extend(EXTEND_STEP)]),
+            ),
+            goal_reached: Some(Message::new(
+                "Daily goal reached",
+                "That is every pomodoro you planned for today.",
+            )),
+            break_end: Some(Message::new(
+                "Break is over",
+                "Acknowledge to start the next pomodoro.",
+            )),
+        }
+    }
+}
+
+impl Messages {
+    /// Replaces the titles and bodies of the messages with those of `texts`.
+    pub fn with_texts(mut self, texts: &Texts) -> Self {
+        let messages = [
+            (&mut self.work_end, &texts.work_end),
+            (&mut self.short_break_start, &texts.short_break_start),
+            (&mut self.long_break_start, &texts.long_break_start),
+            (&mut self.goal_reached, &texts.goal_reached),
+            (&mut self.break_end, &texts.break_end),
+        ];
+        for (message, text) in IntoIterator::into_iter(messages) {
+            if let Some(message) = message {
+                if let Some(title) = &text.title {
+                    message.title = title.clone();
+                }
+                if let Some(body) = &text.body {
+                    message.body = body.clone();
+                }
+            }
+        }
+        self
+    }
+
+    /// Makes the [`Button::Extend`] of every message add `by`.
+    pub fn extending_by(mut self, by: Duration) -> Self {
+        let messages = [
+            &mut self.work_end,
+            &mut self.short_break_start,
+            &mut self.long_break_start,
+            &mut self.goal_reached,
+            &mut self.break_end,
+        ];
+        for message in IntoIterator::into_iter(messages).flatten() {
+            for button in &mut message.buttons {
+                if let Button::Extend(step) = button {
+                    *step = by;
+                }
+            }
+        }
+        self
+    }
+
+    fn for_event(&self, event: &Event) -> Option<&Message> {
+        match event {
+            Event::PhaseEnded(Phase::Working) => self.work_end.as_ref(),
+            Event::PhaseStarted(Phase::ShortBreak) => self.short_break_start.as_ref(),
+            Event::PhaseStarted(Phase::LongBreak) => self.long_break_start.as_ref(),
+            Event::GoalReached(_) => self.goal_reached.as_ref(),
+            Event::AwaitingAck(Phase::ShortBreak | Phase::LongBreak) => self.break_end.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+/// Something able to show a [`Message`] to the user.
+pub trait Notifier: Send + Sync + 'static {
+    fn notify(&self, message: &Message) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+    /// Shows `message` with its buttons and waits for the user to press one, or to close
+    /// the notification. Notifiers without buttons show the message alone.
+    fn notify_with_buttons(
+        &self,
+        message: &Message,
+    ) -> Result<Option<Button>, Box<dyn Error + Send + Sync>> {
+        self.notify(message).map(|_| None)
+    }
+}
+
+/// Shows notifications through the platform's native notification service.
+#[cfg(feature = "desktop-notify")]
+pub struct Desktop;
+
+#[cfg(feature = "desktop-notify")]
+impl Notifier for Desktop {
+    fn notify(&self, message: &Message) -> Result<(), Box<dyn Error + Send + Sync>> {
+        notify_rust::Notification::new()
+            .appname("pomo")
+            .summary(&message.title)
+            .body(&message.body)
+            .show()?;
+        Ok(())
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    fn notify_with_buttons(
+        &self,
+        message: &Message,
+    ) -> Result<Option<Button>, Box<dyn Error + Send + Sync>> {
+        let mut notification = notify_rust::Notification::new();
+        notification
+            .appname("pomo")
+            .summary(&message.title)
+            .body(&message.body);
+        for button in &message.buttons {
+            notification.action(button.id(), &button.label());
+        }
+        let mut pressed = None;
+        notification.show()?.wait_for_action(|action| {
+            pressed = message
+                .buttons
+                .iter()
+                .find(|button| button.id() == action)
+                .copied();
+        });
+        Ok(pressed)
+    }
+}
+
+/// The escape sequence a terminal shows notifications for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Osc {
+    /// `OSC 9`, understood by iTerm2, kitty, WezTerm and Ghostty.
+    Osc9,
+    /// `OSC 777`, understood by foot and rxvt-unicode.
+    Osc777,
+}
+
+/// Shows notifications by writing escape sequences to the terminal on the standard output,
+/// which needs no notification service.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Terminal {
+    pub osc: Osc,
+    /// Wrap the sequences so tmux passes them on to the terminal it runs in.
+    pub tmux: bool,
+}
+
+impl Terminal {
+    /// The terminal the process runs in, when it is known to show notifications.
+    pub fn detect() -> Option<Self> {
+        Self::detect_from(|name| std::env::var(name).ok())
+    }
+
+    fn detect_from(var: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        let program = var("TERM_PROGRAM").unwrap_or_default();
+        let term = var("TERM").unwrap_or_default();
+        let osc = if matches!(program.as_str(), "iTerm.app" | "WezTerm" | "ghostty")
+            || var("KITTY_WINDOW_ID").is_some()
+            || term == "xterm-kitty"
+        {
+            Osc::Osc9
+        } else if term.starts_with("foot") || term.starts_with("rxvt-unicode") {
+            Osc::Osc777
+        } else {
+            return None;
+        };
+        Some(Self {
+            osc,
+            tmux: var("TMUX").is_some(),
+        })
+    }
+
+    fn sequence(&self, message: &Message) -> String {
+        // Control characters would end the sequence early, and so would `;` in an OSC 777
+        // title.
+        let clean = |s: &str, separator: bool| -> String {
+            s.chars()
+                .filter(|c| !(c.is_control() || separator && *c == ';'))
+                .collect()
+        };
+        let sequence = match self.osc {
+            Osc::Osc9 => format!(
+                "\x1b]9;{}: {}\x1b\\",
+                clean(&message.title, false),
+                clean(&message.body, false)
+            ),
+            Osc::Osc777 => format!(
+                "\x1b]777;notify;{};{}\x1b\\",
+                clean(&message.title, true),
+                clean(&message.body, false)
+            ),
+        };
+        if self.tmux {
+            format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+        } else {
+            sequence
+        }
+    }
+}
+
+impl Notifier for Terminal {
+    fn notify(&self, message: &Message) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(self.sequence(message).as_bytes())?;
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+/// Spawns a task which shows a notification for every matching event, unless it is
+/// `quiet`. The placeholders of the messages are filled in from the status of the pomodoro
+/// behind `client`, and its buttons control it. Without a client, only the phase is filled
+/// in and the buttons are left out.
+pub fn spawn<N: Notifier>(
+    notifier: N,
+    messages: Messages,
+    labels: Labels,
+    quiet: Quiet,
+    client: Option<Client>,
+    mut events: broadcast::Receiver<Event>,
+) -> JoinHandle<()> {
+    let notifier = Arc::new(notifier);
+    tokio::spawn(async move {
+        loop {
+            let event = match events.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            };
+            if let Some(message) = messages.for_event(&event) {
+                if quiet.is_quiet().await {
+                    continue;
+                }
+                let status = match &client {
+                    Some(client) => client.status().await.ok(),
+                    None => None,
+                };
+                let message = message.render(&Values::at(&event, status.as_ref(), &labels));
+                let notifier = notifier.clone();
+                match (&client, message.buttons.is_empty()) {
+                    (Some(client), false) => {
+                        // Waiting for a button must not hold the next notifications back.
+                        let client = client.clone();
+                        tokio::spawn(async move {
+                            let shown = tokio::task::spawn_blocking(move || {
+                                notifier.notify_with_buttons(&message)
+                            })
+                            .await;
+                            match shown {
+                                Ok(Ok(Some(button))) => {
+                                    let _ = button.press(&client).await;
+                                }
+                                Ok(Err(e)) => {
+                                    tracing::warn!(error = %e, "failed to show a notification")
+                                }
+                                Ok(Ok(None)) | Err(_) => {}
+                            }
+                        });
+                    }
+                    _ => {
+                        let shown =
+                            tokio::task::spawn_blocking(move || notifier.notify(&message)).await;
+                        if let Ok(Err(e)) = shown {
+                            tracing::warn!(error = %e, "failed to show a notification");
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+struct Recorder(std::sync::Mutex<Vec<Message>>);
+
+#[cfg(test)]
+impl Notifier for Arc<Recorder> {
+    fn notify(&self, message: &Message) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.0.lock().unwrap().push(message.clone());
+        Ok(())
+    }
+}
+
+#[test]
+fn terminal_sequences() {
+    let env = |vars: &'static [(&'static str, &'static str)]| {
+        move |name: &str| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string())
+        }
+    };
+    let kitty = Terminal::detect_from(env(&[("TERM", "xterm-kitty")])).unwrap();
+    assert_eq!(
+        kitty,
+        Terminal {
+            osc: Osc::Osc9,
+            tmux: false
+        }
+    );
+    let message = Message::new("Short break", "Stretch;\nnow");
+    assert_eq!(
+        kitty.sequence(&message),
+        "\x1b]9;Short break: Stretch;now\x1b\\"
+    );
+    let foot = Terminal::detect_from(env(&[("TERM", "foot"), ("TMUX", "/tmp/tmux-0")])).unwrap();
+    assert_eq!(
+        foot.sequence(&Message::new("a;b", "c")),
+        "\x1bPtmux;\x1b\x1b]777;notify;ab;c\x1b\x1b\\\x1b\\"
+    );
+    assert_eq!(
+        Terminal::detect_from(env(&[("TERM", "xterm-256color")])),
+        None
+    );
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn notify_on_transitions() {
+    let recorder = Arc::new(Recorder(std::sync::Mutex::new(Vec::new())));
+    let messages = Messages {
+        work_end: Some(Message::new("done", "")),
+        short_break_start: None,
+        long_break_start: Some(Message::new("{phase}", "")),
+        goal_reached: Some(Message::new("goal", "")),
+        break_end: Some(Message::new("back", "")),
+    };
+    let (sender, receiver) = broadcast::channel(8);
+    let handle = spawn(
+        recorder.clone(),
+        messages,
+        Labels::default(),
+        Quiet::default(),
+        None,
+        receiver,
+    );
+    sender.send(Event::PhaseStarted(Phase::Working)).unwrap();
+    sender.send(Event::PhaseEnded(Phase::Working)).unwrap();
+    sender.send(Event::PhaseStarted(Phase::ShortBreak)).unwrap();
+    sender.send(Event::PhaseStarted(Phase::LongBreak)).unwrap();
+    sender.send(Event::GoalReached(8)).unwrap();
+    sender.send(Event::AwaitingAck(Phase::Working)).unwrap();
+    sender.send(Event::AwaitingAck(Phase::LongBreak)).unwrap();
+    drop(sender);
+    handle.await.unwrap();
+    let titles: Vec<String> = recorder
+        .0
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|m| m.title.clone())
+        .collect();
+    assert_eq!(titles, vec!["done", "long break", "goal", "back"]);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn press_buttons() {
+    let pomodoro = crate::Pomodoro::builder()
+        .work(Duration::from_millis(50))
+        .tick(Duration::from_millis(10))
+        .continuous(false)
+        .build()
+        .unwrap();
+    let (runtime, client, mut events) = crate::start(pomodoro).await;
+    while events.recv().await.unwrap() != Event::AwaitingAck(Phase::Working) {}
+    Button::SkipBreak.press(&client).await.unwrap();
+    let status = client.status().await.unwrap();
+    assert_eq!(status.phase, Phase::Working);
+    assert_eq!((status.pomodoros, status.short_breaks), (1, 1));
+
+    client.resume().await.unwrap();
+    let before = client.status().await.unwrap().remaining;
+    Button::Extend(Duration::from_secs(60))
+        .press(&client)
+        .await
+        .unwrap();
+    assert!(client.status().await.unwrap().remaining > before);
+    Button::StartBreak.press(&client).await.unwrap();
+    runtime.shutdown().await.unwrap();
+
+    let messages = Messages::default().extending_by(Duration::from_secs(120));
+    let buttons = messages.short_break_start.unwrap().buttons;
+    assert_eq!(buttons[1], Button::Extend(Duration::from_secs(120)));
+    assert_eq!(buttons[1].label(), "+2 min");
+}
+
+#[test]
+fn override_texts() {
+    let texts: Texts = toml::from_str(
+        r#"
+        work_end = { title = "Pomodoro {count} done" }
+        break_end = { body = "" }
+        "#,
+    )
+    .unwrap();
+    let messages = Messages::default().with_texts(&texts);
+    let work_end = messages.work_end.unwrap();
+    assert_eq!(work_end.title, "Pomodoro {count} done");
+    assert_eq!(work_end.body, "Nice work!");
+    assert_eq!(messages.break_end.unwrap().body, "");
+    let values = Values {
+        count: 3,
+        ..Values::default()
+    };
+    assert_eq!(work_end.render(&values).title, "Pomodoro 3 done");
+}
@@ -0,0 +1,193 @@
+//! Clocking the completed pomodoros into an Emacs org file, configured under `[org]` in the
+//! config file, so they show up in the agenda's log and in clocktables.
+//!
+//! Every pomodoro adds a `CLOCK:` line to the `:LOGBOOK:` drawer of the heading named
+//! after its task, or of the configured `heading` when it has none. A missing heading is
+//! added at the end of the file.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+use serde::Deserialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+
+use crate::event::Event;
+use crate::timetrack::Tracker;
+
+/// The org settings, read from the `[org]` table of the config file.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Org {
+    pub file: PathBuf,
+    /// The heading to clock the pomodoros without a task into.
+    #[serde(default = "pomodoros")]
+    pub heading: String,
+}
+
+fn pomodoros() -> String {
+    "Pomodoros".to_string()
+}
+
+/// The `CLOCK:` line of a pomodoro from `start` to `stop`.
+pub fn clock_line(start: DateTime<Local>, stop: DateTime<Local>) -> String {
+    let stamp = |at: DateTime<Local>| at.format("[%Y-%m-%d %a %H:%M]").to_string();
+    let minutes = (stop - start).num_minutes().max(0);
+    format!(
+        "CLOCK: {}--{} => {:>2}:{:02}",
+        stamp(start),
+        stamp(stop),
+        minutes / 60,
+        minutes % 60
+    )
+}
+
+/// The title of the heading on `line`, without its stars, a `TODO` or `DONE` keyword and
+/// its tags.
+fn heading_title(line: &str) -> Option<&str> {
+    let rest = line.trim_start_matches('*');
+    if rest.len() == line.len() || !rest.starts_with(' ') {
+        return None;
+    }
+    let rest = rest.trim();
+    let rest = ["TODO ", "DONE "]
+        .iter()
+        .find_map(|keyword| rest.strip_prefix(keyword))
+        .unwrap_or(rest);
+    let title = match rest.rfind(" :") {
+        Some(at) if rest.ends_with(':') => &rest[..at],
+        _ => rest,
+    };
+    Some(title.trim())
+}
+
+/// Adds `clock` to the logbook of the heading titled `title` in the org text `content`,
+/// newest first as org does.
+pub fn clock_in(content: &str, title: &str, clock: &str) -> String {
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let heading = match lines
+        .iter()
+        .position(|line| heading_title(line) == Some(title))
+    {
+        Some(heading) => heading,
+        None => {
+            lines.push(format!("* {}", title));
+            lines.len() - 1
+        }
+    };
+    // The logbook follows the planning line and the properties drawer.
+    let mut at = heading + 1;
+    let is_planning = |line: &str| {
+        ["SCHEDULED:", "DEADLINE:", "CLOSED:"]
+            .iter()
+            .any(|keyword| line.trim_start().starts_with(keyword))
+    };
+    while lines.get(at).is_some_and(|line| is_planning(line)) {
+        at += 1;
+    }
+    if lines.get(at).map(|line| line.trim()) == Some(":PROPERTIES:") {
+        while lines.get(at).is_some_and(|line| line.trim() != ":END:") {
+            at += 1;
+        }
+        at += 1;
+    }
+    if lines.get(at).map(|line| line.trim()) == Some(":LOGBOOK:") {
+        lines.insert(at + 1, clock.to_string());
+    } else {
+        lines.splice(
+            at..at,
+            vec![
+                ":LOGBOOK:".to_string(),
+                clock.to_string(),
+                ":END:".to_string(),
+            ],
+        );
+    }
+    let mut clocked = lines.join("\n");
+    clocked.push('\n');
+    clocked
+}
+
+/// Clocks a pomodoro from `start` to `stop` into the heading `title` of the file at
+/// `path`, which is created when missing.
+pub fn record_pomodoro(
+    path: impl AsRef<Path>,
+    title: &str,
+    start: DateTime<Local>,
+    stop: DateTime<Local>,
+) -> io::Result<()> {
+    let path = path.as_ref();
+    let content = match fs::read_to_string(path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        content => content?,
+    };
+    fs::write(path, clock_in(&content, title, &clock_line(start, stop)))
+}
+
+/// Spawns a task clocking every completed working phase into the org file.
+pub fn spawn(org: Org, mut events: broadcast::Receiver<Event>) -> JoinHandle<()> {
+    tokio::task::spawn_blocking(move || {
+        let mut tracker = Tracker::default();
+        loop {
+            let event = match events.blocking_recv() {
+                Ok(event) => event,
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            };
+            if let Some((task, start, stop)) = tracker.observe(&event, Local::now()) {
+                let title = task.as_deref().unwrap_or(&org.heading);
+                if let Err(e) = record_pomodoro(&org.file, title, start, stop) {
+                    tracing::warn!(path = %org.file.display(), error = %e, "cannot update the org file");
+                }
+            }
+        }
+    })
+}
+
+#[test]
+fn clock_into_headings() {
+    use chrono::TimeZone;
+
+    let start = Local.with_ymd_and_hms(2024, 1, 2, 9, 0, 0).unwrap();
+    let clock = clock_line(start, start + chrono::Duration::minutes(25));
+    assert_eq!(
+        clock,
+        "CLOCK: [2024-01-02 Tue 09:00]--[2024-01-02 Tue 09:25] =>  0:25"
+    );
+    let content = "#+TITLE: work\n\
+                   * TODO Write report :work:\n\
+                   SCHEDULED: <2024-01-02 Tue>\n\
+                   :PROPERTIES:\n\
+                   :ID: 1\n\
+                   :END:\n\
+                   Some notes.\n\
+                   * Pomodoros\n\
+                   :LOGBOOK:\n\
+                   CLOCK: old\n\
+                   :END:\n";
+    assert_eq!(
+        clock_in(content, "Write report", "CLOCK: new"),
+        "#+TITLE: work\n\
+         * TODO Write report :work:\n\
+         SCHEDULED: <2024-01-02 Tue>\n\
+         :PROPERTIES:\n\
+         :ID: 1\n\
+         :END:\n\
+         :LOGBOOK:\n\
+         CLOCK: new\n\
+         :END:\n\
+         Some notes.\n\
+         * Pomodoros\n\
+         :LOGBOOK:\n\
+         CLOCK: old\n\
+         :END:\n"
+    );
+    assert!(clock_in(content, "Pomodoros", "CLOCK: new")
+        .ends_with("* Pomodoros\n:LOGBOOK:\nCLOCK: new\nCLOCK: old\n:END:\n"));
+    assert_eq!(
+        clock_in("", "Mail", "CLOCK: new"),
+        "* Mail\n:LOGBOOK:\nCLOCK: new\n:END:\n"
+    );
+}
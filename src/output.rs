@@ -0,0 +1,1243 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Local, SecondsFormat};
+use serde::Serialize;
+
+use pomo::history::{Outcome, Record};
+use pomo::recovery::Checkpoint;
+use pomo::stats::{Period, Report, Stats, TaskStats};
+use pomo::status_file::StatusFile;
+use pomo::template::{self, Values};
+use pomo::{Goal, InterruptionKind, Labels, Phase, Slot, Status};
+
+use crate::cli::{BarStyle, ExportFormat, Format, ReportFormat};
+
+#[derive(Serialize)]
+struct StatusOutput {
+    phase: Phase,
+    elapsed_secs: u64,
+    remaining_secs: u64,
+    pomodoros: u8,
+    short_breaks: u8,
+    long_breaks: u8,
+    paused: bool,
+    focused_secs: u64,
+    break_secs: u64,
+    paused_secs: u64,
+    pauses: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    task: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    goal: Option<Goal>,
+    /// How long the end of the phase has been waiting to be acknowledged, if it is.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    overtime_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ends_at: Option<DateTime<Local>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    session_ends_at: Option<DateTime<Local>>,
+}
+
+impl From<&Status> for StatusOutput {
+    fn from(status: &Status) -> Self {
+        Self {
+            phase: status.phase,
+            elapsed_secs: status.elapsed.as_secs(),
+            remaining_secs: countdown_secs(status.remaining),
+            pomodoros: status.pomodoros,
+            short_breaks: status.short_breaks,
+            long_breaks: status.long_breaks,
+            paused: status.paused,
+            focused_secs: status.focused.as_secs(),
+            break_secs: status.on_break.as_secs(),
+            paused_secs: status.paused_for.as_secs(),
+            pauses: status.pauses,
+            label: status.label.clone(),
+            task: status.task.clone(),
+            goal: status.goal,
+            overtime_secs: Some(status.overtime.as_secs()).filter(|_| status.awaiting_ack),
+            ends_at: status.ends_at.filter(|_| !status.awaiting_ack),
+            session_ends_at: status.session_ends_at,
+        }
+    }
+}
+
+/// The line printed for every tick of a foreground pomodoro.
+pub fn progress_line(label: &str, remaining: Duration) -> String {
+    format!("Phase: {}, remaining: {}", label, clock_face(remaining))
+}
+
+const BAR_WIDTH: usize = 30;
+
+/// The line `pomo run` keeps redrawing: the phase, a bar colored after it filling up as
+/// time goes by, and the time left.
+pub fn progress_bar(label: &str, phase: Phase, elapsed: Duration, remaining: Duration) -> String {
+    let total = (elapsed + remaining).as_secs_f64();
+    let done = if total > 0.0 {
+        elapsed.as_secs_f64() / total
+    } else {
+        1.0
+    };
+    let filled = ((done * BAR_WIDTH as f64).round() as usize).min(BAR_WIDTH);
+    let color = match phase {
+        Phase::Working => 31,
+        Phase::ShortBreak | Phase::LongBreak => 32,
+    };
+    format!(
+        "{} \x1b[{}m{}\x1b[90m{}\x1b[0m {}",
+        label,
+        color,
+        "█".repeat(filled),
+        "░".repeat(BAR_WIDTH - filled),
+        clock_face(remaining)
+    )
+}
+
+/// Rings the terminal bell and writes the phase which just ended into the title bar.
+pub fn phase_end_alert(label: &str) -> String {
+    format!("\x07\x1b]0;pomo: {} is over\x07", label)
+}
+
+pub fn print_status(status: &Status, format: Format, labels: &Labels) {
+    match format {
+        Format::Text => {
+            println!(
+                "{}{}",
+                progress_line(status.label(labels), status.remaining),
+                match (status.awaiting_ack, status.paused) {
+                    (true, _) => format!(
+                        " (over by {}, waiting to go on)",
+                        clock_face(status.overtime)
+                    ),
+                    (false, true) => " (paused)".to_string(),
+                    (false, false) => String::new(),
+                }
+            );
+            println!(
+                "pomodoros: {}, short breaks: {}, long breaks: {}",
+                status.pomodoros, status.short_breaks, status.long_breaks
+            );
+            println!(
+                "focused: {}, on break: {}, paused: {} ({} pauses)",
+                minutes(status.focused),
+                minutes(status.on_break),
+                minutes(status.paused_for),
+                status.pauses
+            );
+            let ends_at = status.ends_at.filter(|_| !status.awaiting_ack);
+            match (ends_at, status.session_ends_at) {
+                (Some(phase), Some(session)) => println!(
+                    "ends at: {}, session ends at: {}",
+                    phase.format("%H:%M"),
+                    session.format("%H:%M")
+                ),
+                (Some(phase), None) => println!("ends at: {}", phase.format("%H:%M")),
+                (None, Some(session)) => println!("session ends at: {}", session.format("%H:%M")),
+                (None, None) => {}
+            }
+            if let Some(task) = &status.task {
+                println!("task: {}", task);
+            }
+            if let Some(goal) = &status.goal {
+                println!("goal: {}/{}", goal.done, goal.target);
+            }
+        }
+        Format::Json => println!("{}", status_json(status)),
+    }
+}
+
+#[derive(Serialize)]
+struct TimerOutput<'a> {
+    timer: &'a str,
+    #[serde(flatten)]
+    status: StatusOutput,
+}
+
+/// One line for each of a daemon's timers.
+pub fn print_timers(timers: &[(String, Status)], format: Format, labels: &Labels) {
+    match format {
+        Format::Text => {
+            for (name, status) in timers {
+                println!(
+                    "{:<16}{}{}",
+                    name,
+                    progress_line(status.label(labels), status.remaining),
+                    if status.paused { " (paused)" } else { "" }
+                );
+            }
+        }
+        Format::Json => println!("{}", timers_json(timers)),
+    }
+}
+
+fn timers_json(timers: &[(String, Status)]) -> String {
+    let timers: Vec<TimerOutput> = timers
+        .iter()
+        .map(|(name, status)| TimerOutput {
+            timer: name,
+            status: StatusOutput::from(status),
+        })
+        .collect();
+    serde_json::to_string(&timers).unwrap()
+}
+
+/// Where an interrupted session stopped, e.g.
+/// `working 12:30 in, 2 pomodoros done, interrupted at 2024-01-02 09:05`.
+pub fn checkpoint_summary(checkpoint: &Checkpoint, labels: &Labels) -> String {
+    format!(
+        "{} {} in, {} pomodoros done, interrupted at {}{}",
+        checkpoint.snapshot.phase.label(labels),
+        clock_face(checkpoint.snapshot.elapsed),
+        checkpoint.snapshot.counter.working,
+        checkpoint.saved_at.format("%Y-%m-%d %H:%M"),
+        if checkpoint.snapshot.paused {
+            " (paused)"
+        } else {
+            ""
+        }
+    )
+}
+
+fn status_json(status: &Status) -> String {
+    serde_json::to_string(&StatusOutput::from(status)).unwrap()
+}
+
+#[derive(Serialize)]
+struct StatsOutput {
+    today: usize,
+    this_week: usize,
+    focused_minutes: u64,
+    longest_streak_days: usize,
+    average_session_minutes: u64,
+    overtime_minutes: u64,
+    internal_interruptions: usize,
+    external_interruptions: usize,
+}
+
+impl From<&Stats> for StatsOutput {
+    fn from(stats: &Stats) -> Self {
+        Self {
+            today: stats.today,
+            this_week: stats.this_week,
+            focused_minutes: stats.focused.as_secs() / 60,
+            longest_streak_days: stats.longest_streak,
+            average_session_minutes: stats.average_session.as_secs() / 60,
+            overtime_minutes: stats.overtime.as_secs() / 60,
+            internal_interruptions: stats.internal_interruptions,
+            external_interruptions: stats.external_interruptions,
+        }
+    }
+}
+
+pub fn print_stats(stats: &Stats, format: Format) {
+    match format {
+        Format::Text => {
+            println!("{:<16}{}", "today", stats.today);
+            println!("{:<16}{}", "this week", stats.this_week);
+            println!("{:<16}{}", "focused", minutes(stats.focused));
+            println!("{:<16}{} days", "longest streak", stats.longest_streak);
+            println!(
+                "{:<16}{}",
+                "average session",
+                minutes(stats.average_session)
+            );
+            println!("{:<16}{}", "overtime", minutes(stats.overtime));
+            println!(
+                "{:<16}{} internal, {} external",
+                "interruptions", stats.internal_interruptions, stats.external_interruptions
+            );
+        }
+        Format::Json => println!("{}", stats_json(stats)),
+    }
+}
+
+fn stats_json(stats: &Stats) -> String {
+    serde_json::to_string(&StatsOutput::from(stats)).unwrap()
+}
+
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+const SHADES: [char; 4] = ['░', '▒', '▓', '█'];
+
+/// The level of `count` out of `levels`, from 1 for the fewest to `levels` for `most`.
+fn level(count: usize, most: usize, levels: usize) -> usize {
+    ((count * levels).div_ceil(most.max(1))).clamp(1, levels)
+}
+
+/// A line of a bar for each of `counts`, the last of which is the count of `today`.
+pub fn sparkline(counts: &[usize], today: DateTime<Local>) -> Vec<String> {
+    let most = counts.iter().copied().max().unwrap_or(0);
+    let first = today.date_naive() - chrono::Days::new(counts.len().saturating_sub(1) as u64);
+    let bars: String = counts
+        .iter()
+        .map(|&count| match count {
+            0 => ' ',
+            _ => SPARKS[level(count, most, SPARKS.len()) - 1],
+        })
+        .collect();
+    vec![
+        format!(
+            "{} to {}, at most {} a day",
+            first,
+            today.date_naive(),
+            most
+        ),
+        bars,
+    ]
+}
+
+/// A row of a cell for each hour of every weekday, shaded after its count, under a ruler
+/// of the hours.
+pub fn heatmap(grid: &[[usize; 24]; 7]) -> Vec<String> {
+    let most = grid.iter().flatten().copied().max().unwrap_or(0);
+    let ruler: String = (0..24)
+        .step_by(3)
+        .map(|hour| format!("{:<3}", hour))
+        .collect();
+    let mut lines = vec![format!("    {}", ruler.trim_end())];
+    let weekdays = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+    for (weekday, hours) in weekdays.iter().zip(grid) {
+        let cells: String = hours
+            .iter()
+            .map(|&count| match count {
+                0 => '·',
+                _ => SHADES[level(count, most, SHADES.len()) - 1],
+            })
+            .collect();
+        lines.push(format!("{} {}", weekday, cells));
+    }
+    lines
+}
+
+/// How many tasks a report lists.
+const TOP_TASKS: usize = 5;
+
+/// `report` as text to read in a terminal, or as a Markdown document.
+pub fn report(report: &Report, format: ReportFormat) -> String {
+    let (first, last) = match (report.days.first(), report.days.last()) {
+        (Some(first), Some(last)) => (first.day, last.day),
+        _ => return String::new(),
+    };
+    let title = match report.period {
+        Period::Day => format!("Pomodoros of {}", first.format("%a %Y-%m-%d")),
+        Period::Week => format!("Pomodoros of {} to {}", first, last),
+    };
+    let interruptions = format!(
+        "{} internal, {} external",
+        report.internal_interruptions, report.external_interruptions
+    );
+    let tasks = report.tasks.iter().take(TOP_TASKS);
+    let task_name = |task: &TaskStats| task.task.clone().unwrap_or("(no task)".to_string());
+    let mut lines = Vec::new();
+    match format {
+        ReportFormat::Text => {
+            lines.push(title);
+            lines.push(String::new());
+            for day in &report.days {
+                lines.push(format!(
+                    "{:<16}{:>4}  {}",
+                    day.day.format("%a %Y-%m-%d"),
+                    day.pomodoros,
+                    minutes(day.focused)
+                ));
+            }
+            lines.push(format!(
+                "{:<16}{:>4}  {}",
+                "total",
+                report.pomodoros(),
+                minutes(report.focused())
+            ));
+            lines.push(format!("{:<16}{}", "interruptions", interruptions));
+            if !report.tasks.is_empty() {
+                lines.push(String::new());
+                lines.push("top tasks".to_string());
+                for task in tasks {
+                    lines.push(format!(
+                        "{:<24}{:>4}  {}",
+                        task_name(task),
+                        task.pomodoros,
+                        minutes(task.focused)
+                    ));
+                }
+            }
+        }
+        ReportFormat::Markdown => {
+            lines.push(format!("# {}", title));
+            lines.push(String::new());
+            lines.push("| Day | Pomodoros | Focused |".to_string());
+            lines.push("| --- | ---: | ---: |".to_string());
+            for day in &report.days {
+                lines.push(format!(
+                    "| {} | {} | {} |",
+                    day.day.format("%a %Y-%m-%d"),
+                    day.pomodoros,
+                    minutes(day.focused)
+                ));
+            }
+            lines.push(format!(
+                "| **Total** | **{}** | **{}** |",
+                report.pomodoros(),
+                minutes(report.focused())
+            ));
+            lines.push(String::new());
+            lines.push(format!("Interruptions: {}", interruptions));
+            if !report.tasks.is_empty() {
+                lines.push(String::new());
+                lines.push("## Top tasks".to_string());
+                lines.push(String::new());
+                for (rank, task) in tasks.enumerate() {
+                    lines.push(format!(
+                        "{}. {}: {} pomodoros, {}",
+                        rank + 1,
+                        task_name(task),
+                        task.pomodoros,
+                        minutes(task.focused)
+                    ));
+                }
+            }
+        }
+    }
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
+
+#[derive(Serialize)]
+struct TaskOutput<'a> {
+    task: Option<&'a str>,
+    pomodoros: usize,
+    focused_minutes: u64,
+}
+
+pub fn print_task_stats(tasks: &[TaskStats], format: Format) {
+    match format {
+        Format::Text => {
+            for task in tasks {
+                println!(
+                    "{:<24}{:>4}  {}",
+                    task.task.as_deref().unwrap_or("(no task)"),
+                    task.pomodoros,
+                    minutes(task.focused)
+                );
+            }
+        }
+        Format::Json => println!("{}", task_stats_json(tasks)),
+    }
+}
+
+fn task_stats_json(tasks: &[TaskStats]) -> String {
+    let tasks: Vec<TaskOutput> = tasks
+        .iter()
+        .map(|task| TaskOutput {
+            task: task.task.as_deref(),
+            pomodoros: task.pomodoros,
+            focused_minutes: task.focused.as_secs() / 60,
+        })
+        .collect();
+    serde_json::to_string(&tasks).unwrap()
+}
+
+const RECORD_COLUMNS: &str = "phase,outcome,started_at,ended_at,paused_secs,pauses,\
+    overtime_secs,task,reason,interruptions";
+
+#[derive(Serialize)]
+struct RecordOutput<'a> {
+    phase: Phase,
+    outcome: &'static str,
+    started_at: DateTime<Local>,
+    ended_at: DateTime<Local>,
+    paused_secs: u64,
+    pauses: u32,
+    overtime_secs: u64,
+    task: Option<&'a str>,
+    reason: Option<&'a str>,
+    interruptions: Vec<InterruptionOutput<'a>>,
+}
+
+#[derive(Serialize)]
+struct InterruptionOutput<'a> {
+    kind: InterruptionKind,
+    note: &'a str,
+    at: DateTime<Local>,
+}
+
+impl<'a> From<&'a Record> for RecordOutput<'a> {
+    fn from(record: &'a Record) -> Self {
+        Self {
+            phase: record.phase,
+            outcome: record.outcome.name(),
+            started_at: record.started_at,
+            ended_at: record.ended_at,
+            paused_secs: record.paused.as_secs(),
+            pauses: record.pauses,
+            overtime_secs: record.overtime.as_secs(),
+            task: record.task.as_deref(),
+            reason: record.reason.as_deref(),
+            interruptions: record
+                .interruptions
+                .iter()
+                .map(|interruption| InterruptionOutput {
+                    kind: interruption.kind,
+                    note: &interruption.note,
+                    at: interruption.at,
+                })
+                .collect(),
+        }
+    }
+}
+
+pub fn print_records(records: &[Record], format: ExportFormat) {
+    match format {
+        ExportFormat::Csv => print!("{}", records_csv(records)),
+        ExportFormat::Json => println!("{}", records_json(records)),
+        ExportFormat::Ics => print!("{}", records_ics(records)),
+    }
+}
+
+/// Quotes `field` when it holds a comma, a quote or a line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// The records as CSV, with the interruptions counted rather than listed.
+fn records_csv(records: &[Record]) -> String {
+    let mut csv = format!("{}\n", RECORD_COLUMNS);
+    for record in records {
+        let fields = [
+            record.phase.name().to_string(),
+            record.outcome.name().to_string(),
+            record
+                .started_at
+                .to_rfc3339_opts(SecondsFormat::Secs, false),
+            record.ended_at.to_rfc3339_opts(SecondsFormat::Secs, false),
+            record.paused.as_secs().to_string(),
+            record.pauses.to_string(),
+            record.overtime.as_secs().to_string(),
+            csv_field(record.task.as_deref().unwrap_or_default()),
+            csv_field(record.reason.as_deref().unwrap_or_default()),
+            record.interruptions.len().to_string(),
+        ];
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+fn records_json(records: &[Record]) -> String {
+    let records: Vec<RecordOutput> = records.iter().map(RecordOutput::from).collect();
+    serde_json::to_string(&records).unwrap()
+}
+
+/// Escapes the characters iCalendar gives a meaning to in text values.
+fn ics_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Folds `line` into lines of at most 75 bytes, as iCalendar requires.
+fn ics_line(line: &str) -> String {
+    let mut folded = String::new();
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+/// The completed working phases of `records` as an iCalendar file of events.
+fn records_ics(records: &[Record]) -> String {
+    let stamp = |at: &DateTime<Local>| at.to_utc().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//pomo//pomo//EN".to_string(),
+    ];
+    let worked = records
+        .iter()
+        .filter(|r| r.phase == Phase::Working && r.outcome == Outcome::Completed);
+    for record in worked {
+        let summary = match &record.task {
+            Some(task) => format!("Pomodoro: {}", task),
+            None => "Pomodoro".to_string(),
+        };
+        lines.extend(vec![
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}@pomo", record.started_at.timestamp()),
+            format!("DTSTAMP:{}", stamp(&record.ended_at)),
+            format!("DTSTART:{}", stamp(&record.started_at)),
+            format!("DTEND:{}", stamp(&record.ended_at)),
+            format!("SUMMARY:{}", ics_text(&summary)),
+            format!(
+                "DESCRIPTION:{}",
+                ics_text(&format!(
+                    "paused {} times for {}, interrupted {} times",
+                    record.pauses,
+                    minutes(record.paused),
+                    record.interruptions.len()
+                ))
+            ),
+            "END:VEVENT".to_string(),
+        ]);
+    }
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|line| ics_line(line)).collect()
+}
+
+#[derive(Serialize)]
+struct SlotOutput<'a> {
+    phase: Phase,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    label: Option<&'a str>,
+    starts_at: DateTime<Local>,
+    ends_at: DateTime<Local>,
+}
+
+/// Prints the phases of `slots`, one after the other from `now` on.
+pub fn print_schedule(slots: &[Slot], now: DateTime<Local>, format: Format, labels: &Labels) {
+    match format {
+        Format::Text => {
+            for line in schedule_lines(slots, now, labels) {
+                println!("{}", line);
+            }
+        }
+        Format::Json => println!("{}", schedule_json(slots, now)),
+    }
+}
+
+/// Pairs every slot with when it starts and ends.
+fn timed(slots: &[Slot], now: DateTime<Local>) -> Vec<(&Slot, DateTime<Local>, DateTime<Local>)> {
+    let mut starts_at = now;
+    slots
+        .iter()
+        .map(|slot| {
+            let ends_at = starts_at + slot.length;
+            let timed = (slot, starts_at, ends_at);
+            starts_at = ends_at;
+            timed
+        })
+        .collect()
+}
+
+fn schedule_lines(slots: &[Slot], now: DateTime<Local>, labels: &Labels) -> Vec<String> {
+    timed(slots, now)
+        .into_iter()
+        .map(|(slot, starts_at, ends_at)| {
+            format!(
+                "{} - {}  {}",
+                starts_at.format("%H:%M"),
+                ends_at.format("%H:%M"),
+                slot.label(labels)
+            )
+        })
+        .collect()
+}
+
+fn schedule_json(slots: &[Slot], now: DateTime<Local>) -> String {
+    let slots: Vec<SlotOutput> = timed(slots, now)
+        .into_iter()
+        .map(|(slot, starts_at, ends_at)| SlotOutput {
+            phase: slot.phase,
+            label: slot.label.as_deref(),
+            starts_at,
+            ends_at,
+        })
+        .collect();
+    serde_json::to_string(&slots).unwrap()
+}
+
+#[derive(Serialize)]
+struct WaybarOutput {
+    text: String,
+    tooltip: String,
+    class: &'static str,
+    alt: &'static str,
+}
+
+fn icon(status: &Status) -> &'static str {
+    if status.paused {
+        return "⏸";
+    }
+    match status.phase {
+        Phase::Working => "🍅",
+        Phase::ShortBreak | Phase::LongBreak => "☕",
+    }
+}
+
+/// What a status bar shows for `status`, or for an absent daemon when it is `None`. A
+/// `template` replaces the icon, countdown and label.
+pub fn statusbar(
+    status: Option<&Status>,
+    style: BarStyle,
+    template: Option<&str>,
+    labels: &Labels,
+) -> String {
+    let status = match status {
+        Some(status) => status,
+        None if style == BarStyle::Waybar => {
+            return r#"{"text":"","tooltip":"pomo is not running","class":"stopped","alt":"stopped"}"#
+                .to_string()
+        }
+        None => return String::new(),
+    };
+    let label = status.label(labels);
+    let text = format!("{} {}", icon(status), clock_face(status.remaining));
+    let (text, line) = match template {
+        Some(template) => {
+            let text = template::render(template, &Values::of(status, labels));
+            (text.clone(), text)
+        }
+        None => {
+            let line = format!("{} {}", text, label);
+            (text, line)
+        }
+    };
+    match style {
+        BarStyle::Waybar => serde_json::to_string(&WaybarOutput {
+            tooltip: format!("{}, pomodoros: {}", label, status.pomodoros),
+            text,
+            class: if status.paused {
+                "paused"
+            } else {
+                status.phase.name()
+            },
+            alt: status.phase.name(),
+        })
+        .unwrap(),
+        BarStyle::Polybar => line,
+        BarStyle::I3blocks => {
+            let color = match status.phase {
+                Phase::Working => "#ff5555",
+                Phase::ShortBreak | Phase::LongBreak => "#50fa7b",
+            };
+            format!("{}\n{}\n{}", line, text, color)
+        }
+        BarStyle::Tmux => {
+            let color = match status.phase {
+                _ if status.paused => "colour244",
+                Phase::Working => "colour203",
+                Phase::ShortBreak | Phase::LongBreak => "colour114",
+            };
+            // A `#` of the text would start a format of tmux's own.
+            format!("#[fg={}]{}#[default]", color, text.replace('#', "##"))
+        }
+    }
+}
+
+/// The segment a shell prompt shows for the daemon's status `file` at `now`.
+pub fn prompt_segment(file: &StatusFile, now: DateTime<Local>, template: Option<&str>) -> String {
+    let remaining = file.remaining_at(now);
+    match template {
+        Some(template) => {
+            let values = Values {
+                phase: file.name.clone(),
+                remaining,
+                count: file.status.pomodoros,
+                task: file.status.task.clone(),
+                ends_at: file.status.ends_at,
+            };
+            template::render(template, &values)
+        }
+        None => format!("{} {}", icon(&file.status), clock_face(remaining)),
+    }
+}
+
+/// Why a command failed, which scripts can tell from its exit code. Usage errors exit with
+/// 2, as clap has them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Failure {
+    /// Anything not told apart below.
+    Failed,
+    /// No daemon answers on the socket.
+    NoDaemon,
+    /// The config file or the settings given with it are invalid.
+    InvalidConfig,
+    /// The pomodoro cannot do what was asked in its state, e.g. pause in strict mode.
+    Refused,
+}
+
+impl Failure {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Failure::Failed => 1,
+            Failure::NoDaemon => 3,
+            Failure::InvalidConfig => 4,
+            Failure::Refused => 5,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorOutput<'a> {
+    error: ErrorDetails<'a>,
+}
+
+#[derive(Serialize)]
+struct ErrorDetails<'a> {
+    kind: Failure,
+    code: i32,
+    message: &'a str,
+}
+
+/// What a command prints on the standard error when it fails, e.g. `pomo: the pomodoro has
+/// stopped`, or the same as a JSON object under `error` with its kind and exit code.
+pub fn error_message(failure: Failure, message: &str, format: Format) -> String {
+    match format {
+        Format::Text => format!("pomo: {}", message),
+        Format::Json => serde_json::to_string(&ErrorOutput {
+            error: ErrorDetails {
+                kind: failure,
+                code: failure.exit_code(),
+                message,
+            },
+        })
+        .unwrap(),
+    }
+}
+
+/// A duration rounded down to whole minutes, e.g. `2h 5m`.
+fn minutes(duration: Duration) -> String {
+    let minutes = Duration::from_secs(duration.as_secs() / 60 * 60);
+    if minutes.as_secs() == 0 {
+        return "0m".to_string();
+    }
+    humantime::format_duration(minutes).to_string()
+}
+
+/// Whole seconds left, rounded up so a countdown only shows zero once it is over.
+fn countdown_secs(duration: Duration) -> u64 {
+    duration.as_secs() + u64::from(duration.subsec_nanos() > 0)
+}
+
+pub fn clock_face(duration: Duration) -> String {
+    let secs = countdown_secs(duration);
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+}
+
+#[test]
+fn format_clock_face() {
+    assert_eq!(clock_face(Duration::from_secs(0)), "00:00");
+    assert_eq!(clock_face(Duration::from_secs(25 * 60 - 1)), "24:59");
+    assert_eq!(clock_face(Duration::from_millis(59_001)), "01:00");
+}
+
+#[test]
+fn draw_progress_bar() {
+    let bar = progress_bar(
+        "working",
+        Phase::Working,
+        Duration::from_secs(5 * 60),
+        Duration::from_secs(20 * 60),
+    );
+    assert_eq!(
+        bar,
+        format!(
+            "working \x1b[31m{}\x1b[90m{}\x1b[0m 20:00",
+            "█".repeat(6),
+            "░".repeat(24)
+        )
+    );
+    let over = progress_bar(
+        "walk",
+        Phase::ShortBreak,
+        Duration::from_secs(60),
+        Duration::from_secs(0),
+    );
+    assert!(over.starts_with("walk \x1b[32m"));
+    assert!(over.ends_with(&format!("{}\x1b[90m\x1b[0m 00:00", "█".repeat(30))));
+}
+
+#[test]
+fn status_as_json() {
+    let status = Status {
+        phase: Phase::ShortBreak,
+        elapsed: Duration::from_secs(60),
+        remaining: Duration::from_millis(240_500),
+        pomodoros: 3,
+        short_breaks: 2,
+        long_breaks: 0,
+        paused: true,
+        focused: Duration::from_secs(75 * 60),
+        on_break: Duration::from_secs(10 * 60),
+        paused_for: Duration::from_secs(90),
+        pauses: 2,
+        label: None,
+        task: None,
+        goal: Some(Goal { done: 3, target: 8 }),
+        awaiting_ack: false,
+        overtime: Duration::from_secs(0),
+        ends_at: None,
+        session_ends_at: None,
+    };
+    assert_eq!(
+        status_json(&status),
+        r#"{"phase":"short_break","elapsed_secs":60,"remaining_secs":241,"pomodoros":3,"short_breaks":2,"long_breaks":0,"paused":true,"focused_secs":4500,"break_secs":600,"paused_secs":90,"pauses":2,"goal":{"done":3,"target":8}}"#
+    );
+}
+
+#[test]
+fn timers_as_json() {
+    let status = Status {
+        phase: Phase::Working,
+        elapsed: Duration::from_secs(0),
+        remaining: Duration::from_secs(50 * 60),
+        pomodoros: 0,
+        short_breaks: 0,
+        long_breaks: 0,
+        paused: false,
+        focused: Duration::from_secs(0),
+        on_break: Duration::from_secs(0),
+        paused_for: Duration::from_secs(0),
+        pauses: 0,
+        label: None,
+        task: None,
+        goal: None,
+        awaiting_ack: false,
+        overtime: Duration::from_secs(0),
+        ends_at: None,
+        session_ends_at: None,
+    };
+    let json = timers_json(&[("study".to_string(), status)]);
+    assert!(json.starts_with(
+        r#"[{"timer":"study","phase":"working","elapsed_secs":0,"remaining_secs":3000,"#
+    ));
+}
+
+#[test]
+fn summarize_checkpoint() {
+    use chrono::{Local, TimeZone};
+    use pomo::{Counter, PomodoroSnapshot, Settings};
+
+    let checkpoint = Checkpoint {
+        snapshot: PomodoroSnapshot {
+            phase: Phase::Working,
+            elapsed: Duration::from_secs(12 * 60 + 30),
+            remaining: Duration::from_secs(12 * 60 + 30),
+            counter: Counter {
+                working: 2,
+                ..Counter::default()
+            },
+            paused: false,
+            position: 0,
+            task: None,
+            config: Settings::default(),
+        },
+        saved_at: Local.with_ymd_and_hms(2024, 1, 2, 9, 5, 0).unwrap(),
+    };
+    assert_eq!(
+        checkpoint_summary(&checkpoint, &Labels::default()),
+        "working 12:30 in, 2 pomodoros done, interrupted at 2024-01-02 09:05"
+    );
+}
+
+#[test]
+fn stats_as_json() {
+    let stats = Stats {
+        today: 2,
+        this_week: 9,
+        focused: Duration::from_secs(125 * 60 + 30),
+        longest_streak: 3,
+        average_session: Duration::from_secs(25 * 60),
+        overtime: Duration::from_secs(12 * 60),
+        internal_interruptions: 4,
+        external_interruptions: 1,
+    };
+    assert_eq!(
+        stats_json(&stats),
+        r#"{"today":2,"this_week":9,"focused_minutes":125,"longest_streak_days":3,"average_session_minutes":25,"overtime_minutes":12,"internal_interruptions":4,"external_interruptions":1}"#
+    );
+    assert_eq!(minutes(stats.focused), "2h 5m");
+    assert_eq!(minutes(Duration::from_secs(59)), "0m");
+}
+
+#[test]
+fn task_stats_as_json() {
+    let tasks = vec![
+        TaskStats {
+            task: Some("report".to_string()),
+            pomodoros: 2,
+            focused: Duration::from_secs(50 * 60),
+        },
+        TaskStats {
+            task: None,
+            pomodoros: 1,
+            focused: Duration::from_secs(25 * 60),
+        },
+    ];
+    assert_eq!(
+        task_stats_json(&tasks),
+        r#"[{"task":"report","pomodoros":2,"focused_minutes":50},{"task":null,"pomodoros":1,"focused_minutes":25}]"#
+    );
+}
+
+#[test]
+fn export_records() {
+    use chrono::TimeZone;
+    use pomo::history::Interruption;
+
+    let started_at = Local.with_ymd_and_hms(2024, 1, 2, 9, 0, 0).unwrap();
+    let record = Record {
+        phase: Phase::Working,
+        outcome: Outcome::Completed,
+        started_at,
+        ended_at: started_at + chrono::Duration::minutes(25),
+        paused: Duration::from_secs(30),
+        pauses: 1,
+        reason: None,
+        task: Some("write \"the\" report, again".to_string()),
+        interruptions: vec![Interruption {
+            kind: InterruptionKind::External,
+            note: "call".to_string(),
+            at: started_at,
+        }],
+        overtime: Duration::from_secs(0),
+    };
+    let csv = records_csv(std::slice::from_ref(&record));
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], RECORD_COLUMNS);
+    assert_eq!(
+        lines[1],
+        format!(
+            "working,completed,{},{},30,1,0,\"write \"\"the\"\" report, again\",,1",
+            record
+                .started_at
+                .to_rfc3339_opts(SecondsFormat::Secs, false),
+            record.ended_at.to_rfc3339_opts(SecondsFormat::Secs, false)
+        )
+    );
+    let skipped = Record {
+        outcome: Outcome::Skipped,
+        ..record.clone()
+    };
+    let ics = records_ics(&[record.clone(), skipped]);
+    assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+    assert_eq!(ics.matches("BEGIN:VEVENT").count(), 1);
+    let start = record.started_at.to_utc().format("%Y%m%dT%H%M%SZ");
+    assert!(ics.contains(&format!("\r\nDTSTART:{}\r\n", start)));
+    assert!(ics.contains("\r\nSUMMARY:Pomodoro: write \"the\" report\\, again\r\n"));
+    assert!(ics.lines().all(|line| line.len() <= 75));
+    assert_eq!(
+        ics_line(&"x".repeat(80)),
+        format!("{}\r\n {}\r\n", "x".repeat(75), "x".repeat(5))
+    );
+    let json: serde_json::Value = serde_json::from_str(&records_json(&[record])).unwrap();
+    assert_eq!(json[0]["outcome"], "completed");
+    assert_eq!(json[0]["paused_secs"], 30);
+    assert_eq!(json[0]["reason"], serde_json::Value::Null);
+    assert_eq!(json[0]["interruptions"][0]["kind"], "external");
+}
+
+#[test]
+fn render_reports() {
+    use chrono::NaiveDate;
+    use pomo::stats::DayStats;
+
+    let day = |d, pomodoros, minutes: u64| DayStats {
+        day: NaiveDate::from_ymd_opt(2024, 1, d).unwrap(),
+        pomodoros,
+        focused: Duration::from_secs(minutes * 60),
+    };
+    let week = Report {
+        period: Period::Week,
+        days: vec![day(8, 1, 25), day(9, 0, 0), day(10, 2, 55)],
+        tasks: vec![TaskStats {
+            task: Some("mail".to_string()),
+            pomodoros: 1,
+            focused: Duration::from_secs(30 * 60),
+        }],
+        internal_interruptions: 0,
+        external_interruptions: 1,
+    };
+    assert_eq!(
+        report(&week, ReportFormat::Markdown),
+        "# Pomodoros of 2024-01-08 to 2024-01-10\n\
+         \n\
+         | Day | Pomodoros | Focused |\n\
+         | --- | ---: | ---: |\n\
+         | Mon 2024-01-08 | 1 | 25m |\n\
+         | Tue 2024-01-09 | 0 | 0m |\n\
+         | Wed 2024-01-10 | 2 | 55m |\n\
+         | **Total** | **3** | **1h 20m** |\n\
+         \n\
+         Interruptions: 0 internal, 1 external\n\
+         \n\
+         ## Top tasks\n\
+         \n\
+         1. mail: 1 pomodoros, 30m\n"
+    );
+    let text = report(&week, ReportFormat::Text);
+    assert!(
+        text.starts_with("Pomodoros of 2024-01-08 to 2024-01-10\n\nMon 2024-01-08     1  25m\n")
+    );
+    assert!(text.contains("\ntotal              3  1h 20m\n"));
+}
+
+#[test]
+fn draw_graphs() {
+    use chrono::TimeZone;
+
+    let today = Local.with_ymd_and_hms(2024, 1, 10, 18, 0, 0).unwrap();
+    assert_eq!(
+        sparkline(&[0, 1, 4, 8], today),
+        vec!["2024-01-07 to 2024-01-10, at most 8 a day", " ▁▄█"]
+    );
+    let mut grid = [[0; 24]; 7];
+    grid[0][9] = 4;
+    grid[4][14] = 1;
+    let lines = heatmap(&grid);
+    assert_eq!(lines[0], "    0  3  6  9  12 15 18 21");
+    assert_eq!(
+        lines[1],
+        format!("Mon {}█{}", "·".repeat(9), "·".repeat(14))
+    );
+    assert_eq!(
+        lines[5],
+        format!("Fri {}░{}", "·".repeat(14), "·".repeat(9))
+    );
+    assert_eq!(lines.len(), 8);
+}
+
+#[test]
+fn print_schedules() {
+    use chrono::TimeZone;
+
+    let slot = |phase, label: Option<&str>, minutes: u64| Slot {
+        phase,
+        label: label.map(str::to_string),
+        length: Duration::from_secs(minutes * 60),
+    };
+    let slots = vec![
+        slot(Phase::Working, None, 25),
+        slot(Phase::ShortBreak, Some("walk"), 5),
+        slot(Phase::Working, None, 25),
+    ];
+    let now = Local.with_ymd_and_hms(2024, 1, 2, 14, 0, 0).unwrap();
+    assert_eq!(
+        schedule_lines(&slots, now, &Labels::default()),
+        vec![
+            "14:00 - 14:25  working",
+            "14:25 - 14:30  walk",
+            "14:30 - 14:55  working",
+        ]
+    );
+    let json: serde_json::Value = serde_json::from_str(&schedule_json(&slots, now)).unwrap();
+    assert_eq!(json[1]["phase"], "short_break");
+    assert_eq!(json[1]["label"], "walk");
+    assert!(json[0].get("label").is_none());
+    let ends_at: DateTime<Local> = serde_json::from_value(json[2]["ends_at"].clone()).unwrap();
+    assert_eq!(ends_at, now + chrono::Duration::minutes(55));
+}
+
+#[test]
+fn statusbar_styles() {
+    let status = Status {
+        phase: Phase::Working,
+        elapsed: Duration::from_secs(60),
+        remaining: Duration::from_secs(24 * 60),
+        pomodoros: 1,
+        short_breaks: 1,
+        long_breaks: 0,
+        paused: false,
+        focused: Duration::from_secs(0),
+        on_break: Duration::from_secs(0),
+        paused_for: Duration::from_secs(0),
+        pauses: 0,
+        label: None,
+        task: None,
+        goal: None,
+        awaiting_ack: false,
+        overtime: Duration::from_secs(0),
+        ends_at: None,
+        session_ends_at: None,
+    };
+    let labels = Labels::default();
+    assert_eq!(
+        statusbar(Some(&status), BarStyle::Polybar, None, &labels),
+        "🍅 24:00 working"
+    );
+    assert_eq!(
+        statusbar(Some(&status), BarStyle::I3blocks, None, &labels),
+        "🍅 24:00 working\n🍅 24:00\n#ff5555"
+    );
+    assert_eq!(
+        statusbar(
+            Some(&status),
+            BarStyle::Polybar,
+            Some("{count}: {remaining}"),
+            &labels
+        ),
+        "1: 24:00"
+    );
+    let paused = Status {
+        paused: true,
+        ..status
+    };
+    assert_eq!(
+        statusbar(Some(&paused), BarStyle::Waybar, None, &labels),
+        r#"{"text":"⏸ 24:00","tooltip":"working, pomodoros: 1","class":"paused","alt":"working"}"#
+    );
+    assert_eq!(
+        statusbar(Some(&paused), BarStyle::Tmux, None, &labels),
+        "#[fg=colour244]⏸ 24:00#[default]"
+    );
+    assert_eq!(
+        statusbar(Some(&paused), BarStyle::Tmux, Some("#{count}"), &labels),
+        "#[fg=colour244]##1#[default]"
+    );
+    assert_eq!(statusbar(None, BarStyle::Polybar, None, &labels), "");
+    assert_eq!(statusbar(None, BarStyle::Tmux, None, &labels), "");
+}
+
+#[test]
+fn prompt_segments() {
+    use chrono::TimeZone;
+
+    let now = Local.with_ymd_and_hms(2024, 1, 2, 14, 0, 0).unwrap();
+    let mut status = pomo::Pomodoro::builder().build().unwrap().status();
+    status.paused = false;
+    status.pomodoros = 2;
+    status.ends_at = Some(now + chrono::Duration::seconds(12 * 60 + 34));
+    let file = StatusFile::new(status, &Labels::default());
+    assert_eq!(prompt_segment(&file, now, None), "🍅 12:34");
+    assert_eq!(
+        prompt_segment(&file, now, Some("{phase} #{count}")),
+        "working #2"
+    );
+    let later = now + chrono::Duration::hours(1);
+    assert_eq!(prompt_segment(&file, later, None), "🍅 00:00");
+}
+
+#[test]
+fn error_messages() {
+    assert_eq!(
+        error_message(Failure::Refused, "no pauses", Format::Text),
+        "pomo: no pauses"
+    );
+    let json: serde_json::Value = serde_json::from_str(&error_message(
+        Failure::NoDaemon,
+        "cannot reach the daemon",
+        Format::Json,
+    ))
+    .unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "error": {
+                "kind": "no_daemon",
+                "code": 3,
+                "message": "cannot reach the daemon",
+            }
+        })
+    );
+}
@@ -0,0 +1,516 @@
+//! Covers every monitor with the countdown while a break runs, so that the break is
+//! taken rather than worked through.
+//!
+//! The cover is an override-redirect X11 window as large as the root window, which spans
+//! all the monitors of the screen, and it grabs the keyboard and the pointer for as long
+//! as it is shown. Pressing Ctrl+Alt+Escape lifts it until the next break. Xlib is called
+//! directly from a thread of its own, so that the feature needs nothing more than libX11
+//! at link time. Under Wayland the cover only shows over the X11 clients of XWayland.
+
+use std::ffi::CString;
+use std::os::raw::{c_char, c_int, c_long, c_uint, c_ulong, c_void};
+use std::ptr;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+
+use crate::event::Event;
+use crate::pomodoro::{Labels, Phase, Status};
+use crate::runtime::Client;
+
+/// The key lifting the cover, with Ctrl and Alt held.
+const XK_ESCAPE: c_ulong = 0xff1b;
+/// How often the thread showing the cover looks at the X events.
+const POLL: Duration = Duration::from_millis(100);
+
+fn countdown(remaining: Duration) -> String {
+    let secs = remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0);
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+}
+
+/// What the cover shows for `status`, or `None` when it is lifted: breaks are only covered
+/// while they run, since nothing could resume or acknowledge them from under the cover.
+fn cover(status: &Status, labels: &Labels) -> Option<String> {
+    if status.phase == Phase::Working || status.paused || status.awaiting_ack {
+        return None;
+    }
+    Some(format!(
+        "{} {}",
+        status.label(labels),
+        countdown(status.remaining)
+    ))
+}
+
+/// Sends what the cover shows for the pomodoro behind `client` to `screen`, looking again
+/// whenever it emits one of `events`, until either side is gone.
+pub async fn follow(
+    client: Client,
+    mut events: broadcast::Receiver<Event>,
+    labels: Labels,
+    screen: Sender<Option<String>>,
+) {
+    loop {
+        let shown = match client.status().await {
+            Ok(status) => cover(&status, &labels),
+            Err(_) => None,
+        };
+        if screen.send(shown).is_err() {
+            return;
+        }
+        match events.recv().await {
+            Ok(_) | Err(RecvError::Lagged(_)) => {}
+            Err(RecvError::Closed) => return,
+        }
+    }
+}
+
+type Display = c_void;
+type Window = c_ulong;
+type Gc = *mut c_void;
+
+#[repr(C)]
+struct XSetWindowAttributes {
+    background_pixmap: c_ulong,
+    background_pixel: c_ulong,
+    border_pixmap: c_ulong,
+    border_pixel: c_ulong,
+    bit_gravity: c_int,
+    win_gravity: c_int,
+    backing_store: c_int,
+    backing_planes: c_ulong,
+    backing_pixel: c_ulong,
+    save_under: c_int,
+    event_mask: c_long,
+    do_not_propagate_mask: c_long,
+    override_redirect: c_int,
+    colormap: c_ulong,
+    cursor: c_ulong,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct XKeyEvent {
+    kind: c_int,
+    serial: c_ulong,
+    send_event: c_int,
+    display: *mut Display,
+    window: Window,
+    root: Window,
+    subwindow: Window,
+    time: c_ulong,
+    x: c_int,
+    y: c_int,
+    x_root: c_int,
+    y_root: c_int,
+    state: c_uint,
+    keycode: c_uint,
+    same_screen: c_int,
+}
+
+#[repr(C)]
+union XEvent {
+    kind: c_int,
+    key: XKeyEvent,
+    pad: [c_long; 24],
+}
+
+/// The leading fields of `XFontStruct`, which is only ever handled through a pointer.
+#[repr(C)]
+struct XFontStruct {
+    ext_data: *mut c_void,
+    fid: c_ulong,
+}
+
+const KEY_PRESS: c_int = 2;
+const EXPOSE: c_int = 12;
+const KEY_PRESS_MASK: c_long = 1 << 0;
+const BUTTON_PRESS_MASK: c_long = 1 << 2;
+const EXPOSURE_MASK: c_long = 1 << 15;
+const CONTROL_MASK: c_uint = 1 << 2;
+const MOD1_MASK: c_uint = 1 << 3;
+const CW_BACK_PIXEL: c_ulong = 1 << 1;
+const CW_OVERRIDE_REDIRECT: c_ulong = 1 << 9;
+const CW_EVENT_MASK: c_ulong = 1 << 11;
+const INPUT_OUTPUT: c_uint = 1;
+const GRAB_MODE_ASYNC: c_int = 1;
+const GRAB_SUCCESS: c_int = 0;
+const CURRENT_TIME: c_ulong = 0;
+
+#[link(name = "X11")]
+extern "C" {
+    fn XOpenDisplay(name: *const c_char) -> *mut Display;
+    fn XCloseDisplay(display: *mut Display) -> c_int;
+    fn XSetErrorHandler(
+        handler: Option<unsafe extern "C" fn(*mut Display, *mut c_void) -> c_int>,
+    ) -> *mut c_void;
+    fn XDefaultScreen(display: *mut Display) -> c_int;
+    fn XRootWindow(display: *mut Display, screen: c_int) -> Window;
+    fn XDisplayWidth(display: *mut Display, screen: c_int) -> c_int;
+    fn XDisplayHeight(display: *mut Display, screen: c_int) -> c_int;
+    fn XBlackPixel(display: *mut Display, screen: c_int) -> c_ulong;
+    fn XWhitePixel(display: *mut Display, screen: c_int) -> c_ulong;
+    #[allow(clippy::too_many_arguments)]
+    fn XCreateWindow(
+        display: *mut Display,
+        parent: Window,
+        x: c_int,
+        y: c_int,
+        width: c_uint,
+        height: c_uint,
+        border_width: c_uint,
+        depth: c_int,
+        class: c_uint,
+        visual: *mut c_void,
+        value_mask: c_ulong,
+        attributes: *mut XSetWindowAttributes,
+    ) -> Window;
+    fn XDestroyWindow(display: *mut Display, window: Window) -> c_int;
+    fn XMapRaised(display: *mut Display, window: Window) -> c_int;
+    fn XRaiseWindow(display: *mut Display, window: Window) -> c_int;
+    fn XUnmapWindow(display: *mut Display, window: Window) -> c_int;
+    fn XClearWindow(display: *mut Display, window: Window) -> c_int;
+    fn XCreateGC(
+        display: *mut Display,
+        drawable: Window,
+        value_mask: c_ulong,
+        values: *mut c_void,
+    ) -> Gc;
+    fn XFreeGC(display: *mut Display, gc: Gc) -> c_int;
+    fn XSetForeground(display: *mut Display, gc: Gc, pixel: c_ulong) -> c_int;
+    fn XLoadQueryFont(display: *mut Display, name: *const c_char) -> *mut XFontStruct;
+    fn XFreeFont(display: *mut Display, font: *mut XFontStruct) -> c_int;
+    fn XSetFont(display: *mut Display, gc: Gc, font: c_ulong) -> c_int;
+    fn XTextWidth(font: *mut XFontStruct, text: *const c_char, length: c_int) -> c_int;
+    fn XDrawString(
+        display: *mut Display,
+        drawable: Window,
+        gc: Gc,
+        x: c_int,
+        y: c_int,
+        text: *const c_char,
+        length: c_int,
+    ) -> c_int;
+    fn XGrabKeyboard(
+        display: *mut Display,
+        window: Window,
+        owner_events: c_int,
+        pointer_mode: c_int,
+        keyboard_mode: c_int,
+        time: c_ulong,
+    ) -> c_int;
+    #[allow(clippy::too_many_arguments)]
+    fn XGrabPointer(
+        display: *mut Display,
+        window: Window,
+        owner_events: c_int,
+        event_mask: c_uint,
+        pointer_mode: c_int,
+        keyboard_mode: c_int,
+        confine_to: Window,
+        cursor: c_ulong,
+        time: c_ulong,
+    ) -> c_int;
+    fn XUngrabKeyboard(display: *mut Display, time: c_ulong) -> c_int;
+    fn XUngrabPointer(display: *mut Display, time: c_ulong) -> c_int;
+    fn XPending(display: *mut Display) -> c_int;
+    fn XNextEvent(display: *mut Display, event: *mut XEvent) -> c_int;
+    fn XLookupKeysym(event: *mut XKeyEvent, index: c_int) -> c_ulong;
+    fn XFlush(display: *mut Display) -> c_int;
+}
+
+/// Keeps a failed request, e.g. a grab raced by another client, from exiting the process
+/// as Xlib's own handler would.
+unsafe extern "C" fn ignore_error(_: *mut Display, _: *mut c_void) -> c_int {
+    0
+}
+
+/// The cover window on a connection to the X server, which are both closed when dropped.
+struct Screen {
+    display: *mut Display,
+    window: Window,
+    gc: Gc,
+    font: *mut XFontStruct,
+    width: c_int,
+    height: c_int,
+    shown: bool,
+    grabbed: bool,
+    text: String,
+}
+
+impl Screen {
+    fn open() -> Option<Self> {
+        unsafe {
+            XSetErrorHandler(Some(ignore_error));
+            let display = XOpenDisplay(ptr::null());
+            if display.is_null() {
+                return None;
+            }
+            let screen = XDefaultScreen(display);
+            let width = XDisplayWidth(display, screen);
+            let height = XDisplayHeight(display, screen);
+            let mut attributes = XSetWindowAttributes {
+                background_pixmap: 0,
+                background_pixel: XBlackPixel(display, screen),
+                border_pixmap: 0,
+                border_pixel: 0,
+                bit_gravity: 0,
+                win_gravity: 0,
+                backing_store: 0,
+                backing_planes: 0,
+                backing_pixel: 0,
+                save_under: 0,
+                event_mask: KEY_PRESS_MASK | BUTTON_PRESS_MASK | EXPOSURE_MASK,
+                do_not_propagate_mask: 0,
+                override_redirect: 1,
+                colormap: 0,
+                cursor: 0,
+            };
+            let window = XCreateWindow(
+                display,
+                XRootWindow(display, screen),
+                0,
+                0,
+                width as c_uint,
+                height as c_uint,
+                0,
+                0,
+                INPUT_OUTPUT,
+                ptr::null_mut(),
+                CW_BACK_PIXEL | CW_OVERRIDE_REDIRECT | CW_EVENT_MASK,
+                &mut attributes,
+            );
+            let gc = XCreateGC(display, window, 0, ptr::null_mut());
+            XSetForeground(display, gc, XWhitePixel(display, screen));
+            let mut font = ptr::null_mut();
+            for name in &["-*-*-bold-r-normal--72-*-*-*-*-*-iso8859-1", "fixed"] {
+                let name = CString::new(*name).unwrap();
+                font = XLoadQueryFont(display, name.as_ptr());
+                if !font.is_null() {
+                    XSetFont(display, gc, (*font).fid);
+                    break;
+                }
+            }
+            Some(Self {
+                display,
+                window,
+                gc,
+                font,
+                width,
+                height,
+                shown: false,
+                grabbed: false,
+                text: String::new(),
+            })
+        }
+    }
+
+    /// Covers the monitors with `text`, grabbing the input once nothing else holds it.
+    fn show(&mut self, text: String) {
+        unsafe {
+            if !self.shown {
+                XMapRaised(self.display, self.window);
+                self.shown = true;
+            } else {
+                XRaiseWindow(self.display, self.window);
+            }
+            if !self.grabbed {
+                let keyboard = XGrabKeyboard(
+                    self.display,
+                    self.window,
+                    1,
+                    GRAB_MODE_ASYNC,
+                    GRAB_MODE_ASYNC,
+                    CURRENT_TIME,
+                );
+                let pointer = XGrabPointer(
+                    self.display,
+                    self.window,
+                    1,
+                    BUTTON_PRESS_MASK as c_uint,
+                    GRAB_MODE_ASYNC,
+                    GRAB_MODE_ASYNC,
+                    self.window,
+                    0,
+                    CURRENT_TIME,
+                );
+                self.grabbed = keyboard == GRAB_SUCCESS && pointer == GRAB_SUCCESS;
+            }
+        }
+        if text != self.text {
+            self.text = text;
+            self.draw();
+        }
+        unsafe {
+            XFlush(self.display);
+        }
+    }
+
+    fn hide(&mut self) {
+        if !self.shown {
+            return;
+        }
+        unsafe {
+            XUngrabKeyboard(self.display, CURRENT_TIME);
+            XUngrabPointer(self.display, CURRENT_TIME);
+            XUnmapWindow(self.display, self.window);
+            XFlush(self.display);
+        }
+        self.shown = false;
+        self.grabbed = false;
+        self.text.clear();
+    }
+
+    fn draw(&self) {
+        let lines = [self.text.as_str(), "Ctrl+Alt+Escape to get back to work"];
+        unsafe {
+            XClearWindow(self.display, self.window);
+            for (row, line) in lines.iter().enumerate() {
+                // The core fonts are Latin-1, which labels need not be.
+                let line: String = line
+                    .chars()
+                    .map(|c| if c.is_ascii() { c } else { '?' })
+                    .collect();
+                let width = if self.font.is_null() {
+                    0
+                } else {
+                    XTextWidth(
+                        self.font,
+                        line.as_ptr() as *const c_char,
+                        line.len() as c_int,
+                    )
+                };
+                XDrawString(
+                    self.display,
+                    self.window,
+                    self.gc,
+                    (self.width - width) / 2,
+                    self.height / 2 + row as c_int * 96,
+                    line.as_ptr() as *const c_char,
+                    line.len() as c_int,
+                );
+            }
+        }
+    }
+
+    /// Handles the pending X events, telling whether the cover was asked to be lifted.
+    fn escaped(&mut self) -> bool {
+        let mut escaped = false;
+        unsafe {
+            while XPending(self.display) > 0 {
+                let mut event = XEvent { pad: [0; 24] };
+                XNextEvent(self.display, &mut event);
+                match event.kind {
+                    KEY_PRESS => {
+                        let held = event.key.state & (CONTROL_MASK | MOD1_MASK);
+                        if held == CONTROL_MASK | MOD1_MASK
+                            && XLookupKeysym(&mut event.key, 0) == XK_ESCAPE
+                        {
+                            escaped = true;
+                        }
+                    }
+                    EXPOSE if self.shown => self.draw(),
+                    _ => {}
+                }
+            }
+        }
+        escaped
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        self.hide();
+        unsafe {
+            if !self.font.is_null() {
+                XFreeFont(self.display, self.font);
+            }
+            XFreeGC(self.display, self.gc);
+            XDestroyWindow(self.display, self.window);
+            XCloseDisplay(self.display);
+        }
+    }
+}
+
+/// Shows what `covers` sends until it is gone, leaving a break uncovered once it has been
+/// escaped from.
+fn run(mut screen: Screen, covers: Receiver<Option<String>>) {
+    let mut escaped = false;
+    loop {
+        match covers.recv_timeout(POLL) {
+            Ok(Some(_)) if escaped => {}
+            Ok(Some(text)) => screen.show(text),
+            Ok(None) => {
+                escaped = false;
+                screen.hide();
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+        if screen.escaped() {
+            escaped = true;
+            screen.hide();
+        }
+    }
+}
+
+/// Spawns the thread covering the monitors during the breaks of the pomodoro behind
+/// `client`, and the task telling it what to show.
+pub fn spawn(client: Client, labels: Labels) -> JoinHandle<()> {
+    let (screen, covers) = mpsc::channel();
+    let shown = thread::Builder::new()
+        .name("pomo-overlay".to_string())
+        .spawn(move || match Screen::open() {
+            Some(screen) => run(screen, covers),
+            None => tracing::warn!("cannot cover the screen, there is no X display"),
+        });
+    if let Err(e) = shown {
+        tracing::warn!(error = %e, "cannot cover the screen");
+    }
+    let events = client.subscribe();
+    tokio::spawn(follow(client, events, labels, screen))
+}
+
+#[test]
+fn cover_running_breaks() {
+    let labels = Labels::default();
+    let mut status = crate::Pomodoro::builder().build().unwrap().status();
+    status.paused = false;
+    assert_eq!(cover(&status, &labels), None);
+    status.phase = Phase::ShortBreak;
+    status.remaining = Duration::from_millis(299_001);
+    assert_eq!(
+        cover(&status, &labels).as_deref(),
+        Some("short break 05:00")
+    );
+    status.paused = true;
+    assert_eq!(cover(&status, &labels), None);
+    status.paused = false;
+    status.awaiting_ack = true;
+    assert_eq!(cover(&status, &labels), None);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn follow_the_breaks() {
+    let pomodoro = crate::Pomodoro::builder().build().unwrap();
+    let (runtime, client, _events) = crate::start(pomodoro).await;
+    let (screen, covers) = mpsc::channel();
+    let follower = tokio::spawn(follow(
+        client.clone(),
+        client.subscribe(),
+        Labels::default(),
+        screen,
+    ));
+    let next = || covers.recv_timeout(Duration::from_secs(1)).unwrap();
+
+    assert_eq!(next(), None);
+    client.skip().await.unwrap();
+    let shown = std::iter::repeat_with(next).find(Option::is_some).unwrap();
+    assert!(shown.unwrap().starts_with("short break "));
+    client.skip().await.unwrap();
+    assert!(std::iter::repeat_with(next).any(|shown| shown.is_none()));
+    runtime.shutdown().await.unwrap();
+    follower.await.unwrap();
+}
@@ -1,13 +1,24 @@
-use std::fmt::{self, Formatter, Display};
 use std::cell::Cell;
-use std::time::Duration;
-use std::sync::{Arc, Mutex};
+use std::convert::TryFrom;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, SystemTime};
 
-use tokio::time::sleep;
+use chrono::{DateTime, Local, NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, watch};
+use tokio::time::Instant;
 
+use crate::error::PomoError;
+use crate::event::{self, Event, InterruptionKind};
+use crate::recovery::Checkpoint;
+use crate::timer::Sleeper;
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
-enum Phase {
+/// The stage a [`Pomodoro`] is currently in.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
     Working,
     ShortBreak,
     LongBreak,
@@ -15,31 +26,86 @@ enum Phase {
 
 impl Display for Phase {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        let s = match self {
+        write!(f, "Phase: {}", self.default_label())
+    }
+}
+
+impl Phase {
+    fn default_label(&self) -> &'static str {
+        match self {
             Phase::Working => "working",
             Phase::ShortBreak => "short break",
             Phase::LongBreak => "long break",
-        };
-        write!(f, "Phase: {}", s)
+        }
+    }
+
+    /// The name shown for the phase according to `labels`.
+    pub fn label<'a>(&self, labels: &'a Labels) -> &'a str {
+        match self {
+            Phase::Working => &labels.working,
+            Phase::ShortBreak => &labels.short_break,
+            Phase::LongBreak => &labels.long_break,
+        }
+    }
+
+    /// The identifier used for the phase in config files and machine-readable output.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Phase::Working => "working",
+            Phase::ShortBreak => "short_break",
+            Phase::LongBreak => "long_break",
+        }
+    }
+}
+
+impl FromStr for Phase {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "working" => Ok(Phase::Working),
+            "short_break" => Ok(Phase::ShortBreak),
+            "long_break" => Ok(Phase::LongBreak),
+            _ => Err(format!("unknown phase: {}", s)),
+        }
     }
 }
 
-#[derive(Debug, Clone)]
-struct Counter {
-    working: u8,
-    short_break: u8,
-    long_break: u8,
+/// The names shown for each phase, read from the `[labels]` table of the config file.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Labels {
+    pub working: String,
+    pub short_break: String,
+    pub long_break: String,
 }
 
-impl Counter {
-    fn new() -> Self {
+impl Default for Labels {
+    fn default() -> Self {
         Self {
-            working: 0,
-            short_break: 0,
-            long_break: 0,
+            working: Phase::Working.default_label().to_string(),
+            short_break: Phase::ShortBreak.default_label().to_string(),
+            long_break: Phase::LongBreak.default_label().to_string(),
         }
     }
+}
 
+/// What a [`Pomodoro`] has gone through since it was started.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Counter {
+    pub working: u8,
+    pub short_break: u8,
+    pub long_break: u8,
+    /// Time spent in working phases, pauses excluded.
+    pub focused: Duration,
+    /// Time spent in short and long breaks, pauses excluded.
+    pub on_break: Duration,
+    /// Time spent paused in the middle of a phase.
+    pub paused: Duration,
+    pub pauses: u32,
+}
+
+impl Counter {
     fn increment_working(&mut self) {
         self.working += 1;
     }
@@ -53,45 +119,97 @@ impl Counter {
     }
 }
 
-pub struct Shared {
-    paused: bool,
+/// Progress towards the daily goal of a [`Pomodoro`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Goal {
+    /// Pomodoros completed today, including those of earlier sessions.
+    pub done: u32,
+    pub target: u32,
+}
+
+/// How far the wall clock may run ahead of the monotonic one between two ticks before the
+/// machine is taken to have been suspended.
+const SUSPEND_THRESHOLD: Duration = Duration::from_secs(10);
+
+/// What a [`Pomodoro`] does after the machine wakes up from sleep or suspend.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SuspendPolicy {
+    /// Carry on as if nothing happened.
+    Ignore,
+    /// Pause, counting the time asleep as paused time.
+    #[default]
+    Pause,
+    /// Treat the time asleep as a break, ending a working phase for it.
+    Break,
+    /// Start the interrupted phase over.
+    Discard,
+}
+
+/// Whether a [`Pomodoro`] is counting down.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum State {
+    Running,
+    Paused,
+}
+
+/// Pauses and resumes a [`Pomodoro`] from outside while it is being driven.
+#[derive(Clone, Debug)]
+pub struct Switch {
+    state: watch::Sender<State>,
 }
 
-impl Shared {
-    fn new() -> Self {
-        Self { paused: true }
+impl Switch {
+    pub fn pause(&self) {
+        set_state(&self.state, State::Paused);
     }
 
-    pub fn pause(&mut self) {
-        self.paused = true
+    pub fn resume(&self) {
+        set_state(&self.state, State::Running);
     }
 
-    pub fn resume(&mut self) {
-        self.paused = false
+    pub fn state(&self) -> State {
+        *self.state.borrow()
     }
 }
 
+/// Notifies the watchers only when the state actually changes.
+fn set_state(state: &watch::Sender<State>, next: State) {
+    state.send_if_modified(|current| {
+        let changed = *current != next;
+        *current = next;
+        changed
+    });
+}
+
+/// A countdown lasting `lifespan` which reports its progress every `tick_range`.
+///
+/// While started the clock measures monotonic time, so it stays accurate however late the
+/// ticks are; the time spent stopped is not counted.
 #[derive(Debug)]
 pub struct Clock {
     lifespan: Duration,
     tick_range: Duration,
     elapsed: Arc<Mutex<Cell<Duration>>>,
+    running_since: Cell<Option<Instant>>,
+    extension: Cell<Duration>,
 }
 
 impl Display for Clock {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        let locked = self.elapsed.lock().unwrap();
-        let elapsed = locked.get();
-        write!(f, "now elapsed: {}", elapsed.as_micros())
+        write!(f, "now elapsed: {}", self.elapsed().as_micros())
     }
 }
 
 impl Clock {
+    /// Creates a clock lasting `lifespan` which reports its progress every `tick_range`.
     pub fn new(lifespan: Duration, tick_range: Duration) -> Self {
         Self {
             lifespan,
             tick_range,
             elapsed: Arc::new(Mutex::new(Cell::new(Self::initial_duration()))),
+            running_since: Cell::new(None),
+            extension: Cell::new(Self::initial_duration()),
         }
     }
 
@@ -99,44 +217,391 @@ impl Clock {
         Duration::from_secs(0)
     }
 
+    /// The time banked before the running interval. It is a plain `Duration`, so the value
+    /// behind a poisoned lock is still sound.
+    fn banked(&self) -> MutexGuard<'_, Cell<Duration>> {
+        self.elapsed.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
     fn reset(&self) {
-        let arc = self.elapsed.clone();
-        let locked = arc.lock().unwrap();
+        let locked = self.banked();
         locked.set(Self::initial_duration());
+        self.running_since.set(None);
+        self.extension.set(Self::initial_duration());
+    }
+
+    fn start(&self) {
+        if self.running_since.get().is_none() {
+            self.running_since.set(Some(Instant::now()));
+        }
+    }
+
+    fn stop(&self) {
+        if let Some(since) = self.running_since.take() {
+            let locked = self.banked();
+            locked.set(locked.get() + since.elapsed());
+        }
+    }
+
+    fn restore(&self, elapsed: Duration) {
+        self.reset();
+        self.banked().set(elapsed);
+    }
+
+    fn bank(&self, by: Duration) {
+        let locked = self.banked();
+        locked.set(locked.get() + by);
+    }
+
+    /// Takes `by` back from the time banked, as far as there is any.
+    fn unbank(&self, by: Duration) {
+        let locked = self.banked();
+        locked.set(locked.get().saturating_sub(by));
     }
 
+    fn set_lifespan(&mut self, lifespan: Duration) {
+        self.lifespan = lifespan;
+    }
+
+    fn extend(&self, by: Duration) {
+        self.extension.set(self.extension.get() + by);
+    }
+
+    fn lifespan(&self) -> Duration {
+        self.lifespan + self.extension.get()
+    }
+
+    #[cfg(test)]
     fn tick(&self) {
-        let arc = self.elapsed.clone();
-        let locked = arc.lock().unwrap();
+        let locked = self.banked();
         locked.set(locked.get() + self.tick_range);
     }
 
     fn is_done(&self) -> bool {
-        let arc = self.elapsed.clone();
-        let locked = arc.lock().unwrap();
-        locked.get() >= self.lifespan
+        self.elapsed() >= self.lifespan()
+    }
+
+    fn is_fresh(&self) -> bool {
+        self.elapsed() == Self::initial_duration()
+    }
+
+    fn is_started(&self) -> bool {
+        self.running_since.get().is_some()
+    }
+
+    /// How long the clock has been running.
+    pub fn elapsed(&self) -> Duration {
+        let locked = self.banked();
+        let running = self
+            .running_since
+            .get()
+            .map(|since| since.elapsed())
+            .unwrap_or_else(Self::initial_duration);
+        locked.get() + running
+    }
+
+    /// How long is left until the clock is done.
+    pub fn remaining(&self) -> Duration {
+        self.remaining_after(self.elapsed())
+    }
+
+    fn remaining_after(&self, elapsed: Duration) -> Duration {
+        self.lifespan().saturating_sub(elapsed)
+    }
+
+    /// Until the end of the running tick, so a tick cut short by a pause is only finished
+    /// after resuming and the ticks keep falling on whole multiples of `tick_range`.
+    fn next_wakeup(&self) -> Duration {
+        let elapsed = self.elapsed();
+        let into_tick = elapsed
+            .as_nanos()
+            .checked_rem(self.tick_range.as_nanos())
+            .unwrap_or(0);
+        let into_tick = Duration::from_nanos(u64::try_from(into_tick).unwrap_or(0));
+        (self.tick_range - into_tick).min(self.remaining_after(elapsed))
+    }
+
+    /// The elapsed fraction of the lifespan, from 0.0 to 1.0.
+    pub fn progress(&self) -> f32 {
+        let lifespan = self.lifespan();
+        if lifespan == Self::initial_duration() {
+            return 1.0;
+        }
+        (self.elapsed().as_secs_f32() / lifespan.as_secs_f32()).min(1.0)
+    }
+
+    fn tick_event(&self, phase: Phase) -> Event {
+        let elapsed = self.elapsed();
+        Event::Tick {
+            phase,
+            elapsed,
+            remaining: self.remaining_after(elapsed),
+        }
+    }
+}
+
+/// A point-in-time view of a [`Pomodoro`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Status {
+    pub phase: Phase,
+    pub elapsed: Duration,
+    pub remaining: Duration,
+    pub pomodoros: u8,
+    pub short_breaks: u8,
+    pub long_breaks: u8,
+    pub paused: bool,
+    #[serde(default)]
+    pub focused: Duration,
+    #[serde(default)]
+    pub on_break: Duration,
+    /// Time spent paused in the middle of a phase.
+    #[serde(default)]
+    pub paused_for: Duration,
+    #[serde(default)]
+    pub pauses: u32,
+    /// The label of the running plan step, if it has one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// What the pomodoros are being spent on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub goal: Option<Goal>,
+    /// The phase is over and the next one waits to be acknowledged.
+    #[serde(default)]
+    pub awaiting_ack: bool,
+    /// How long the acknowledgement has been waited for.
+    #[serde(default)]
+    pub overtime: Duration,
+    /// When the phase will be over if it runs on without pausing. `None` once the session
+    /// is complete.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ends_at: Option<DateTime<Local>>,
+    /// When the session set by `until` or `until_duration` will be over if every phase
+    /// follows without pausing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_ends_at: Option<DateTime<Local>>,
+}
+
+impl Status {
+    /// The name to show for the running phase: the step's own label, or else the phase's one
+    /// from `labels`.
+    pub fn label<'a>(&'a self, labels: &'a Labels) -> &'a str {
+        self.label
+            .as_deref()
+            .unwrap_or_else(|| self.phase.label(labels))
+    }
+}
+
+/// The lengths and the rhythm a [`Pomodoro`] runs with.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Settings {
+    pub work: Duration,
+    pub short_break: Duration,
+    pub long_break: Duration,
+    /// How often the progress is reported.
+    pub tick: Duration,
+    /// Working phases before each long break, or 0 for no long breaks.
+    pub long_break_interval: u8,
+    /// Whether working phases are followed by short breaks.
+    #[serde(default = "yes")]
+    pub short_breaks: bool,
+    pub continuous: bool,
+    /// Whether breaks start by themselves, when it differs from `continuous`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_start_breaks: Option<bool>,
+    /// Whether working phases start by themselves, when it differs from `continuous`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_start_work: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until_duration: Option<Duration>,
+}
+
+fn yes() -> bool {
+    true
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        let classic = PomodoroBuilder::default();
+        Self {
+            work: classic.work,
+            short_break: classic.short_break,
+            long_break: classic.long_break,
+            tick: classic.tick,
+            long_break_interval: classic.long_break_interval,
+            short_breaks: classic.short_breaks,
+            continuous: classic.continuous,
+            auto_start_breaks: classic.auto_start_breaks,
+            auto_start_work: classic.auto_start_work,
+            until: classic.until,
+            until_duration: classic.until_duration,
+        }
+    }
+}
+
+/// The state of a [`Pomodoro`]'s engine at one point in time, which status queries are
+/// made of and checkpoints and the daemon hand on.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PomodoroSnapshot {
+    pub phase: Phase,
+    /// Time spent in the current phase.
+    pub elapsed: Duration,
+    #[serde(default)]
+    pub remaining: Duration,
+    /// The totals of the phases before the current one, and of the running pause.
+    pub counter: Counter,
+    pub paused: bool,
+    /// The step of the plan the pomodoro is in.
+    #[serde(default)]
+    pub position: usize,
+    #[serde(default)]
+    pub task: Option<String>,
+    #[serde(default)]
+    pub config: Settings,
+}
+
+/// A phase to come, as laid out by [`Pomodoro::schedule`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Slot {
+    pub phase: Phase,
+    /// The label of the plan step, if it has one.
+    pub label: Option<String>,
+    /// How long the phase lasts, or what is left of it for the running one.
+    pub length: Duration,
+}
+
+impl Slot {
+    /// The plan step's own label, or else the configured label of the phase.
+    pub fn label<'a>(&'a self, labels: &'a Labels) -> &'a str {
+        self.label
+            .as_deref()
+            .unwrap_or_else(|| self.phase.label(labels))
+    }
+}
+
+/// One stage of a custom plan, written as `["work", "50m"]` or `["break", "10m", "walk"]`
+/// in the config file.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "Vec<String>")]
+pub struct Step {
+    pub phase: Phase,
+    pub length: Duration,
+    /// Shown instead of the phase's label while the step runs.
+    pub label: Option<String>,
+}
+
+impl TryFrom<Vec<String>> for Step {
+    type Error = String;
+
+    fn try_from(fields: Vec<String>) -> Result<Self, Self::Error> {
+        let (phase, length, label) = match fields.as_slice() {
+            [phase, length] => (phase, length, None),
+            [phase, length, label] => (phase, length, Some(label.clone())),
+            _ => return Err("a step is [phase, length] or [phase, length, label]".to_string()),
+        };
+        let phase = match phase.as_str() {
+            "work" => Phase::Working,
+            "break" => Phase::ShortBreak,
+            name => name.parse()?,
+        };
+        let length = humantime::parse_duration(length)
+            .map_err(|e| format!("invalid step length {:?}: {}", length, e))?;
+        Ok(Self {
+            phase,
+            length,
+            label,
+        })
+    }
+}
+
+/// New phase lengths and long break interval for a running [`Pomodoro`], leaving the
+/// settings which are `None` as they are.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Durations {
+    pub work: Option<Duration>,
+    pub short_break: Option<Duration>,
+    pub long_break: Option<Duration>,
+    pub long_break_interval: Option<u8>,
+}
+
+impl Durations {
+    fn validate(&self) -> Result<(), PomoError> {
+        let lengths = [
+            ("work", self.work),
+            ("short_break", self.short_break),
+            ("long_break", self.long_break),
+        ];
+        if let Some((name, _)) = lengths
+            .iter()
+            .find(|(_, length)| *length == Some(Duration::ZERO))
+        {
+            return Err(PomoError::ZeroDuration(name));
+        }
+        Ok(())
     }
 }
 
+/// A [`Step`] with its running clock.
 #[derive(Debug)]
-enum Signal {
-    Resume,
-    Pause,
+struct Stage {
+    phase: Phase,
+    clock: Clock,
+    label: Option<String>,
 }
 
+/// The pomodoro state machine cycling through working, short break and long break, or
+/// through the steps of a custom plan.
 pub struct Pomodoro {
     working: Clock,
     short_break: Clock,
     long_break: Clock,
     long_break_interval: u8,
+    short_breaks: bool,
     counter: Counter,
+    paused_since: Option<Instant>,
+    on_suspend: SuspendPolicy,
+    awake: Option<(SystemTime, Instant)>,
     continuous: bool,
+    /// Overrides `continuous` for the breaks.
+    auto_start_breaks: Option<bool>,
+    /// Overrides `continuous` for the working phases.
+    auto_start_work: Option<bool>,
     until: Option<u8>,
+    until_duration: Option<Duration>,
+    count_skipped: bool,
+    strict: bool,
+    ack: bool,
+    count_overtime: bool,
+    /// Sleep until the end of the phase instead of waking up for every tick.
+    power_saving: bool,
+    /// Since when the end of the phase has been waiting to be acknowledged.
+    awaiting: Option<Instant>,
+    /// Phase lengths waiting for the running phase to end.
+    reconfigured: Option<Durations>,
+    task: Option<String>,
+    goal: Option<Goal>,
+    /// The day the goal's progress was counted on.
+    today: NaiveDate,
+    /// The time of day at which the counters start over, if they do.
+    day_start: Option<NaiveTime>,
+    /// The day the counters have been counting, once they start over every day.
+    counters_day: Option<NaiveDate>,
     current_status: Phase,
-    pub shared: Arc<Mutex<Shared>>,
+    plan: Vec<Stage>,
+    position: usize,
+    state: watch::Sender<State>,
+    pub(crate) events: broadcast::Sender<Event>,
 }
 
 impl Pomodoro {
+    /// Creates a pomodoro which takes a long break every `long_break_interval` working phases,
+    /// or never when it is 0.
+    ///
+    /// When `continuous` is false the pomodoro pauses after each phase, and when `until` is set
+    /// it stops after that many working phases.
     pub fn new(
         working: Clock,
         short_break: Clock,
@@ -145,110 +610,1236 @@ impl Pomodoro {
         continuous: bool,
         until: Option<u8>,
     ) -> Self {
+        let (events, _) = broadcast::channel(event::CAPACITY);
         Self {
-            working: working,
+            working,
             short_break,
             long_break,
             long_break_interval,
-            counter: Counter::new(),
+            short_breaks: true,
+            counter: Counter::default(),
+            paused_since: None,
+            on_suspend: SuspendPolicy::default(),
+            awake: None,
             continuous,
+            auto_start_breaks: None,
+            auto_start_work: None,
             until,
+            until_duration: None,
+            count_skipped: false,
+            strict: false,
+            ack: false,
+            count_overtime: false,
+            power_saving: false,
+            awaiting: None,
+            reconfigured: None,
+            task: None,
+            goal: None,
+            today: Local::now().date_naive(),
+            day_start: None,
+            counters_day: None,
             current_status: Phase::Working,
-            shared: Arc::new(Mutex::new(Shared::new())),
+            plan: Vec::new(),
+            position: 0,
+            state: watch::Sender::new(State::Paused),
+            events,
+        }
+    }
+
+    /// Starts configuring a pomodoro by name instead of positional arguments.
+    pub fn builder() -> PomodoroBuilder {
+        PomodoroBuilder::default()
+    }
+
+    /// Creates a pomodoro with the settings of `snapshot`, in the middle of the session it
+    /// was taken from: in its phase, with as much of it elapsed and with its totals. The
+    /// pomodoro is paused until it is started.
+    pub fn from_snapshot(snapshot: &PomodoroSnapshot) -> Result<Self, PomoError> {
+        let settings = &snapshot.config;
+        let builder = Pomodoro::builder()
+            .work(settings.work)
+            .short_break(settings.short_break)
+            .long_break(settings.long_break)
+            .tick(settings.tick)
+            .long_break_interval(settings.long_break_interval)
+            .short_breaks(settings.short_breaks)
+            .continuous(settings.continuous);
+        let builder = match settings.auto_start_breaks {
+            Some(auto_start) => builder.auto_start_breaks(auto_start),
+            None => builder,
+        };
+        let builder = match settings.auto_start_work {
+            Some(auto_start) => builder.auto_start_work(auto_start),
+            None => builder,
+        };
+        let builder = match settings.until {
+            Some(until) => builder.until(until),
+            None => builder,
+        };
+        let mut pomodoro = match settings.until_duration {
+            Some(until_duration) => builder.until_duration(until_duration),
+            None => builder,
+        }
+        .build()?;
+        pomodoro.resume_at(snapshot);
+        Ok(pomodoro)
+    }
+
+    /// Sets whether skipped working phases count as completed pomodoros. Defaults to false.
+    pub fn count_skipped(mut self, count: bool) -> Self {
+        self.count_skipped = count;
+        self
+    }
+
+    /// Counts `done` pomodoros completed earlier today, e.g. in previous sessions, towards
+    /// the daily goal.
+    pub fn count_done_today(&mut self, done: u32) {
+        if let Some(goal) = &mut self.goal {
+            goal.done = done;
+        }
+        self.today = self.day(Local::now());
+    }
+
+    /// Starts the counters over while keeping the current phase running. The long break
+    /// cycle starts over with them. History, and so the daily goal, is not affected.
+    pub fn reset_counters(&mut self) {
+        self.counter = Counter::default();
+        if self.paused_since.is_some() {
+            self.paused_since = Some(Instant::now());
+            self.counter.pauses = 1;
+        }
+        self.emit(Event::CountersReset);
+    }
+
+    /// Starts the counters over when `now` falls on another day than they have been
+    /// counting, if they start over every day.
+    fn roll_over(&mut self, now: DateTime<Local>) {
+        if self.day_start.is_none() {
+            return;
+        }
+        let day = self.day(now);
+        if self
+            .counters_day
+            .replace(day)
+            .is_some_and(|counted| counted != day)
+        {
+            self.reset_counters();
         }
     }
 
-    fn is_consumed(&self) -> bool {
-        self.until
-            .map(|u| self.counter.working >= u)
-            .unwrap_or(false)
+    /// The day `at` belongs to, which starts at the day start rather than at midnight when
+    /// one is set.
+    fn day(&self, at: DateTime<Local>) -> NaiveDate {
+        let start = self.day_start.unwrap_or(NaiveTime::MIN);
+        (at.naive_local() - start.signed_duration_since(NaiveTime::MIN)).date()
+    }
+
+    /// Changes the phase lengths from the next phase on, or right away when the current phase
+    /// has not started yet, and the long break interval right away. Plans are not affected.
+    pub fn reconfigure(&mut self, durations: Durations) -> Result<(), PomoError> {
+        durations.validate()?;
+        if let Some(interval) = durations.long_break_interval {
+            self.long_break_interval = interval;
+        }
+        let pending = self.reconfigured.get_or_insert_with(Durations::default);
+        pending.work = durations.work.or(pending.work);
+        pending.short_break = durations.short_break.or(pending.short_break);
+        pending.long_break = durations.long_break.or(pending.long_break);
+        if self.current_timer().is_fresh() && !self.current_timer().is_started() {
+            self.apply_durations();
+        }
+        Ok(())
+    }
+
+    /// Like [`reconfigure`](Self::reconfigure), announcing it as a reloaded config.
+    pub(crate) fn reload(&mut self, durations: Durations) -> Result<(), PomoError> {
+        self.reconfigure(durations)?;
+        self.emit(Event::ConfigReloaded);
+        Ok(())
+    }
+
+    /// Takes on the lengths given to [`reconfigure`](Self::reconfigure), while the clocks are
+    /// reset between two phases.
+    fn apply_durations(&mut self) {
+        let durations = match self.reconfigured.take() {
+            Some(durations) => durations,
+            None => return,
+        };
+        if let Some(work) = durations.work {
+            self.working.set_lifespan(work);
+        }
+        if let Some(short_break) = durations.short_break {
+            self.short_break.set_lifespan(short_break);
+        }
+        if let Some(long_break) = durations.long_break {
+            self.long_break.set_lifespan(long_break);
+        }
+    }
+
+    /// A handle pausing and resuming the pomodoro, which takes effect immediately even while
+    /// [`run`](Self::run) is waiting for the next tick.
+    pub fn switch(&self) -> Switch {
+        Switch {
+            state: self.state.clone(),
+        }
+    }
+
+    /// Watches the pomodoro being paused and resumed.
+    pub fn watch_state(&self) -> watch::Receiver<State> {
+        self.state.subscribe()
+    }
+
+    /// Subscribes to the [`Event`]s emitted while the pomodoro runs.
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.events.subscribe()
+    }
+
+    fn emit(&self, event: Event) {
+        if !matches!(event, Event::Tick { .. }) {
+            tracing::debug!(event = event.name(), "emitting event");
+        }
+        let _ = self.events.send(event);
+    }
+
+    pub(crate) fn is_consumed(&self) -> bool {
+        let counted = self.until.map(|u| self.counter.working >= u);
+        let focused = self.until_duration.map(|d| self.counter.focused >= d);
+        counted.unwrap_or(false) || focused.unwrap_or(false)
+    }
+
+    /// The lengths and the rhythm the pomodoro runs with, leaving out the lengths it is
+    /// being reconfigured to.
+    pub fn settings(&self) -> Settings {
+        Settings {
+            work: self.working.lifespan,
+            short_break: self.short_break.lifespan,
+            long_break: self.long_break.lifespan,
+            tick: self.working.tick_range,
+            long_break_interval: self.long_break_interval,
+            short_breaks: self.short_breaks,
+            continuous: self.continuous,
+            auto_start_breaks: self.auto_start_breaks,
+            auto_start_work: self.auto_start_work,
+            until: self.until,
+            until_duration: self.until_duration,
+        }
+    }
+
+    /// Takes a snapshot of the engine.
+    pub fn snapshot(&self) -> PomodoroSnapshot {
+        let timer = self.current_timer();
+        let elapsed = timer.elapsed();
+        let mut counter = self.counter.clone();
+        if let Some(since) = self.paused_since {
+            counter.paused += since.elapsed();
+        }
+        PomodoroSnapshot {
+            phase: self.current_status(),
+            elapsed,
+            remaining: timer.remaining_after(elapsed),
+            counter,
+            paused: !self.is_active(),
+            position: self.position,
+            task: self.task.clone(),
+            config: self.settings(),
+        }
+    }
+
+    /// What it takes to carry on with this pomodoro later through [`restore`](Self::restore).
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            snapshot: self.snapshot(),
+            saved_at: Local::now(),
+        }
+    }
+
+    /// Continues from `checkpoint` instead of the beginning of the first phase. Call it
+    /// before [`start`](crate::start), which resumes the pomodoro.
+    pub fn restore(&mut self, checkpoint: &Checkpoint) {
+        self.resume_at(&checkpoint.snapshot);
+    }
+
+    /// Moves to the phase, the elapsed time, the totals and the task of `snapshot`, keeping
+    /// the pomodoro's own settings, and pauses it.
+    pub fn resume_at(&mut self, snapshot: &PomodoroSnapshot) {
+        self.pause();
+        self.current_timer().reset();
+        self.counter = snapshot.counter.clone();
+        self.task = snapshot.task.clone();
+        self.paused_since = None;
+        self.position = if self.plan.is_empty() {
+            0
+        } else {
+            snapshot.position % self.plan.len()
+        };
+        self.current_status = match self.plan.get(self.position) {
+            Some(stage) => stage.phase,
+            None => snapshot.phase,
+        };
+        self.current_timer().restore(snapshot.elapsed);
+    }
+
+    /// The totals so far, including the running phase and pause.
+    pub fn counter(&self) -> Counter {
+        let mut counter = self.counter.clone();
+        let elapsed = self.current_timer().elapsed();
+        match self.current_status() {
+            Phase::Working => counter.focused += elapsed,
+            Phase::ShortBreak | Phase::LongBreak => counter.on_break += elapsed,
+        }
+        if let Some(since) = self.paused_since {
+            counter.paused += since.elapsed();
+        }
+        counter
     }
 
     fn current_status(&self) -> Phase {
-        self.current_status.clone()
+        self.current_status
     }
 
     fn current_timer(&self) -> &Clock {
+        if let Some(stage) = self.plan.get(self.position) {
+            return &stage.clock;
+        }
         match self.current_status() {
             Phase::Working => &self.working,
             Phase::ShortBreak => &self.short_break,
-            Phase::LongBreak =>  &self.long_break,
+            Phase::LongBreak => &self.long_break,
         }
     }
 
     fn increment_current_status_counter(&mut self) {
         match self.current_status() {
-            Phase::Working => self.counter.increment_working(),
+            Phase::Working => {
+                self.counter.increment_working();
+                self.count_towards_goal();
+            }
             Phase::ShortBreak => self.counter.increment_short_break(),
             Phase::LongBreak => self.counter.increment_long_break(),
         };
     }
 
+    /// Adds a completed pomodoro to the daily goal, starting over on a new day.
+    fn count_towards_goal(&mut self) {
+        let today = self.day(Local::now());
+        let reached = match &mut self.goal {
+            Some(goal) => {
+                if self.today != today {
+                    goal.done = 0;
+                }
+                goal.done += 1;
+                Some(goal.target).filter(|target| goal.done == *target)
+            }
+            None => None,
+        };
+        self.today = today;
+        if let Some(target) = reached {
+            self.emit(Event::GoalReached(target));
+        }
+    }
+
     fn is_reached_long_break(&self) -> bool {
-        let v = self.counter.working;
-        v > 0 && v % self.long_break_interval == 0
+        self.takes_long_break_after(u32::from(self.counter.working))
+    }
+
+    /// Whether a long break is due once `working` phases have been completed.
+    fn takes_long_break_after(&self, working: u32) -> bool {
+        self.long_break_interval > 0
+            && working > 0
+            && working.is_multiple_of(u32::from(self.long_break_interval))
+    }
+
+    /// The phase which ends a round of the classic rhythm, `None` when it takes no breaks.
+    fn round_end(&self) -> Option<Phase> {
+        if self.long_break_interval > 0 {
+            Some(Phase::LongBreak)
+        } else if self.short_breaks {
+            Some(Phase::ShortBreak)
+        } else {
+            None
+        }
     }
 
     fn next_status(&mut self) -> Phase {
         if !self.current_timer().is_done() {
             return self.current_status();
-        } else if self.current_status() != Phase::LongBreak && self.is_reached_long_break() {
+        }
+        self.following_status()
+    }
+
+    fn following_status(&self) -> Phase {
+        if !self.plan.is_empty() {
+            return self.plan[(self.position + 1) % self.plan.len()].phase;
+        }
+        if self.current_status() != Phase::LongBreak && self.is_reached_long_break() {
             return Phase::LongBreak;
         }
         match self.current_status() {
-            Phase::Working => Phase::ShortBreak,
+            Phase::Working if self.short_breaks => Phase::ShortBreak,
+            Phase::Working => Phase::Working,
             Phase::ShortBreak => Phase::Working,
             Phase::LongBreak => Phase::Working,
         }
     }
 
-    fn is_active(&self) -> bool {
-        let paused = self.shared.lock().unwrap().paused;
-        !paused
+    pub(crate) fn is_active(&self) -> bool {
+        *self.state.borrow() == State::Running
+    }
+
+    pub(crate) fn is_running(&self) -> bool {
+        !self.is_consumed() && self.is_active()
+    }
+
+    /// How long is left in the current phase.
+    pub fn remaining_in_phase(&self) -> Duration {
+        self.current_timer().remaining()
+    }
+
+    /// The fraction of the working phases requested by `until` done so far, counting the
+    /// running working phase partially, or else of the focused time requested by
+    /// `until_duration`. `None` when the session has no end.
+    pub fn session_progress(&self) -> Option<f32> {
+        let until = match (self.until, self.until_duration) {
+            (Some(until), _) => until,
+            (None, Some(target)) => {
+                let progress = self.counter().focused.as_secs_f32() / target.as_secs_f32();
+                return Some(progress.min(1.0));
+            }
+            (None, None) => return None,
+        };
+        if until == 0 {
+            return Some(1.0);
+        }
+        let mut done = f32::from(self.counter.working);
+        if self.current_status() == Phase::Working && !self.is_consumed() {
+            done += self.current_timer().progress();
+        }
+        Some((done / f32::from(until)).min(1.0))
+    }
+
+    /// The length of the phase at `position`, or the lengths it is being reconfigured to.
+    fn length_of(&self, phase: Phase, position: usize) -> Duration {
+        if let Some(stage) = self.plan.get(position) {
+            return stage.clock.lifespan;
+        }
+        let pending = self.reconfigured.unwrap_or_default();
+        match phase {
+            Phase::Working => pending.work.unwrap_or(self.working.lifespan),
+            Phase::ShortBreak => pending.short_break.unwrap_or(self.short_break.lifespan),
+            Phase::LongBreak => pending.long_break.unwrap_or(self.long_break.lifespan),
+        }
+    }
+
+    /// The phases to come, starting with what is left of the current one, if every phase
+    /// follows the last without waiting. They run until the session set by `until` or
+    /// `until_duration` is over, or else for a round: up to the next long break, or through
+    /// the steps of the plan once.
+    pub fn schedule(&self) -> Vec<Slot> {
+        let bounded = self.until.is_some() || self.until_duration.is_some();
+        // Counted wider than the counter, which a long `until_duration` would overflow.
+        let done = |working: u32, focused: Duration| {
+            let counted = self.until.map(|u| working >= u32::from(u));
+            let spent = self.until_duration.map(|d| focused >= d);
+            counted.unwrap_or(false) || spent.unwrap_or(false)
+        };
+        let mut working = u32::from(self.counter.working);
+        let mut focused = self.counter().focused;
+        let mut slots = Vec::new();
+        if done(working, focused) {
+            return slots;
+        }
+        let mut phase = self.current_status();
+        let mut position = self.position;
+        let mut length = self.current_timer().remaining();
+        // A phase awaiting its acknowledgement has already been counted.
+        let mut counted = self.awaiting.is_some();
+        // Every session ends after finitely many phases, but a huge `until_duration` should
+        // not keep the engine busy.
+        while slots.len() < 10_000 {
+            slots.push(Slot {
+                phase,
+                label: self
+                    .plan
+                    .get(position)
+                    .and_then(|stage| stage.label.clone()),
+                length,
+            });
+            if phase == Phase::Working && !counted {
+                working += 1;
+                focused += length;
+                if done(working, focused) {
+                    break;
+                }
+            }
+            counted = false;
+            let round = if self.plan.is_empty() {
+                self.round_end()
+                    .is_none_or(|end| phase == end && slots.len() > 1)
+            } else {
+                slots.len() == self.plan.len()
+            };
+            if round && !bounded {
+                break;
+            }
+            if self.plan.is_empty() {
+                let long = self.takes_long_break_after(working);
+                phase = match phase {
+                    Phase::ShortBreak | Phase::Working if long => Phase::LongBreak,
+                    Phase::Working if self.short_breaks => Phase::ShortBreak,
+                    Phase::Working => Phase::Working,
+                    Phase::ShortBreak | Phase::LongBreak => Phase::Working,
+                };
+            } else {
+                position = (position + 1) % self.plan.len();
+                phase = self.plan[position].phase;
+            }
+            length = self.length_of(phase, position);
+        }
+        slots
+    }
+
+    /// How long it takes to get through the session set by `until` or `until_duration` if
+    /// every phase follows the last without waiting. `None` when the session has no end.
+    pub fn session_remaining(&self) -> Option<Duration> {
+        if self.until.is_none() && self.until_duration.is_none() {
+            return None;
+        }
+        Some(self.schedule().iter().map(|slot| slot.length).sum())
+    }
+
+    /// Takes a [`snapshot`](Self::snapshot) of the current state, with the totals of the
+    /// running phase and what the user is shown about it.
+    pub fn status(&self) -> Status {
+        let snapshot = self.snapshot();
+        let mut counter = snapshot.counter;
+        match snapshot.phase {
+            Phase::Working => counter.focused += snapshot.elapsed,
+            Phase::ShortBreak | Phase::LongBreak => counter.on_break += snapshot.elapsed,
+        }
+        let remaining = snapshot.remaining;
+        let now = Local::now();
+        let consumed = self.is_consumed();
+        Status {
+            phase: snapshot.phase,
+            elapsed: snapshot.elapsed,
+            remaining,
+            pomodoros: counter.working,
+            short_breaks: counter.short_break,
+            long_breaks: counter.long_break,
+            paused: snapshot.paused,
+            focused: counter.focused,
+            on_break: counter.on_break,
+            paused_for: counter.paused,
+            pauses: counter.pauses,
+            label: self
+                .plan
+                .get(snapshot.position)
+                .and_then(|stage| stage.label.clone()),
+            task: snapshot.task,
+            goal: self.goal.map(|goal| {
+                if self.today == self.day(Local::now()) {
+                    goal
+                } else {
+                    Goal { done: 0, ..goal }
+                }
+            }),
+            awaiting_ack: self.awaiting.is_some(),
+            overtime: self
+                .awaiting
+                .map(|since| since.elapsed())
+                .unwrap_or_default(),
+            ends_at: Some(now + remaining).filter(|_| !consumed),
+            session_ends_at: self
+                .session_remaining()
+                .filter(|_| !consumed)
+                .and_then(|left| chrono::Duration::from_std(left).ok())
+                .map(|left| now + left),
+        }
     }
 
     fn next_cycle(&mut self) {
         self.increment_current_status_counter();
         let next_status = self.next_status();
+        self.switch_to(next_status);
+    }
+
+    fn switch_to(&mut self, next_status: Phase) {
+        let position = if self.plan.is_empty() {
+            0
+        } else {
+            (self.position + 1) % self.plan.len()
+        };
+        self.move_to(next_status, position);
+    }
+
+    /// Leaves the current phase for `next_status`, at `position` of the plan if there is one.
+    fn move_to(&mut self, next_status: Phase, position: usize) {
+        let elapsed = self.current_timer().elapsed();
+        match self.current_status() {
+            Phase::Working => self.counter.focused += elapsed,
+            Phase::ShortBreak | Phase::LongBreak => self.counter.on_break += elapsed,
+        }
         self.current_timer().reset();
+        self.apply_durations();
+        self.position = position;
+        tracing::info!(
+            from = self.current_status().name(),
+            to = next_status.name(),
+            pomodoros = self.counter.working,
+            "switching phase"
+        );
         self.current_status = next_status;
+        if self.is_running() {
+            self.current_timer().start();
+        }
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.pause();
+        let ended = self.is_consumed() || self.awaiting.is_some();
+        if !ended && !self.current_timer().is_fresh() {
+            self.emit(Event::PhaseAborted(self.current_status()));
+        }
+        self.current_timer().reset();
+        self.apply_durations();
+        self.counter = Counter::default();
+        self.paused_since = None;
+        self.awaiting = None;
+        self.position = 0;
+        self.current_status = self.first_phase();
+    }
+
+    fn first_phase(&self) -> Phase {
+        self.plan
+            .first()
+            .map(|stage| stage.phase)
+            .unwrap_or(Phase::Working)
     }
 
+    #[cfg(test)]
     fn proceed(&self) {
         self.current_timer().tick();
     }
 
-    async fn wait(&self) {
-        let tick = self.current_timer().tick_range;
-        sleep(tick).await;
-        self.proceed();
+    pub(crate) fn next_wakeup(&self) -> Duration {
+        if self.power_saving {
+            return self.current_timer().remaining();
+        }
+        self.current_timer().next_wakeup()
+    }
+
+    pub(crate) fn advance(&mut self) {
+        self.roll_over(Local::now());
+        if let Some(gap) = self.slept() {
+            self.suspended(gap);
+            if !self.is_running() {
+                return;
+            }
+        }
+        self.emit(self.current_timer().tick_event(self.current_status()));
+        if !self.current_timer().is_done() {
+            return;
+        }
+        self.emit(Event::PhaseEnded(self.current_status()));
+        if !self.waits_for_ack() {
+            self.next_cycle();
+            return self.enter_phase();
+        }
+        self.increment_current_status_counter();
+        if self.is_consumed() {
+            let next_status = self.following_status();
+            self.switch_to(next_status);
+            return self.enter_phase();
+        }
+        self.await_ack();
+    }
+
+    /// Whether the phase which just ended waits for [`ack`](Self::ack): every phase in ack
+    /// mode, and working phases when breaks do not start by themselves, so their overtime
+    /// shows.
+    fn waits_for_ack(&self) -> bool {
+        self.ack
+            || (self.current_status() == Phase::Working && !self.auto_starts(Phase::ShortBreak))
     }
 
-    fn pause(&self) {
-        let shared = self.shared.clone();
-        shared.lock().unwrap().pause();
+    /// Whether `phase` starts as soon as the one before it ends, or waits to be resumed.
+    fn auto_starts(&self, phase: Phase) -> bool {
+        let auto_start = match phase {
+            Phase::Working => self.auto_start_work,
+            Phase::ShortBreak | Phase::LongBreak => self.auto_start_breaks,
+        };
+        auto_start.unwrap_or(self.continuous)
     }
 
-    fn resume(&self) {
-        let shared = self.shared.clone();
-        shared.lock().unwrap().resume();
+    /// Holds on at the end of the phase, which has been counted, until [`ack`](Self::ack)
+    /// is called.
+    fn await_ack(&mut self) {
+        self.awaiting = Some(Instant::now());
+        self.emit(Event::AwaitingAck(self.current_status()));
+        self.hold();
     }
 
-    pub async fn run(&mut self) {
+    /// Moves on from the phase which is waiting to be acknowledged to the next one, counting
+    /// the time waited as part of the phase when overtime is counted.
+    pub(crate) fn ack(&mut self) -> Result<(), PomoError> {
+        let since = self.awaiting.take().ok_or(PomoError::NotAwaitingAck)?;
+        let overtime = since.elapsed();
+        if self.count_overtime {
+            self.current_timer().bank(overtime);
+        }
+        self.emit(Event::Acknowledged(overtime));
+        let next_status = self.following_status();
+        self.switch_to(next_status);
         self.resume();
-        while !self.is_consumed() && self.is_active() {
-            if !self.current_timer().is_done() {
-                println!("{}, {}", self.current_status(), self.current_timer());
-                self.wait().await;
-                continue;
-            }
-            self.next_cycle();
-            if !self.continuous {
+        Ok(())
+    }
+
+    /// How much further the wall clock ran than the monotonic one since the last check,
+    /// when that is enough to tell the machine was suspended in between.
+    fn slept(&mut self) -> Option<Duration> {
+        let (wall, monotonic) = self.awake.replace((SystemTime::now(), Instant::now()))?;
+        let gap = wall.elapsed().ok()?.checked_sub(monotonic.elapsed())?;
+        Some(gap).filter(|gap| *gap >= SUSPEND_THRESHOLD)
+    }
+
+    /// Applies the [`SuspendPolicy`] after the machine slept for `gap`.
+    pub(crate) fn suspended(&mut self, gap: Duration) {
+        self.emit(Event::Suspended(gap));
+        match self.on_suspend {
+            SuspendPolicy::Ignore => {}
+            SuspendPolicy::Pause => {
                 self.pause();
+                if let Some(since) = self.paused_since {
+                    self.paused_since = Some(since.checked_sub(gap).unwrap_or(since));
+                }
             }
-        }
+            SuspendPolicy::Break => {
+                if self.current_status() == Phase::Working {
+                    self.skip();
+                }
+                if self.current_status() != Phase::Working {
+                    self.current_timer().bank(gap);
+                }
+            }
+            SuspendPolicy::Discard => {
+                let phase = self.current_status();
+                self.emit(Event::PhaseAborted(phase));
+                self.current_timer().reset();
+                if self.is_running() {
+                    self.current_timer().start();
+                }
+                self.emit(Event::PhaseStarted(phase));
+            }
+        }
+    }
+
+    fn enter_phase(&mut self) {
+        if !self.auto_starts(self.current_status()) {
+            self.hold();
+        }
+        if self.is_consumed() {
+            self.emit(Event::Completed);
+        } else if self.is_active() {
+            self.emit(Event::PhaseStarted(self.current_status()));
+        }
+    }
+
+    /// Pauses between two phases, which does not count as a pause within either of them.
+    pub(crate) fn hold(&mut self) {
+        self.pause();
+        if self.paused_since.take().is_some() {
+            self.counter.pauses -= 1;
+        }
+    }
+
+    /// Ends the current phase early and moves on to the next one.
+    ///
+    /// A skipped working phase only counts towards the completed pomodoros when
+    /// [`count_skipped`](Self::count_skipped) is enabled; otherwise it is followed by a short
+    /// break, or by the next step of a plan.
+    pub fn skip(&mut self) {
+        if self.is_consumed() {
+            return;
+        }
+        if self.awaiting.is_some() {
+            let _ = self.ack();
+            return;
+        }
+        self.emit(Event::PhaseSkipped(self.current_status()));
+        let counted = self.current_status() != Phase::Working || self.count_skipped;
+        if counted {
+            self.increment_current_status_counter();
+        }
+        let next_status = if counted || !self.plan.is_empty() {
+            self.following_status()
+        } else {
+            Phase::ShortBreak
+        };
+        self.switch_to(next_status);
+        self.enter_phase();
+    }
+
+    /// Abandons the current phase, unless it has not begun yet, and moves on to `phase` right
+    /// away, e.g. to take a long break now or to start working over. The abandoned phase is
+    /// not counted. A plan moves on to its next step of that phase.
+    pub fn goto(&mut self, phase: Phase) -> Result<(), PomoError> {
+        if self.is_consumed() {
+            return Ok(());
+        }
+        let position = if self.plan.is_empty() {
+            0
+        } else {
+            let len = self.plan.len();
+            (1..=len)
+                .map(|offset| (self.position + offset) % len)
+                .find(|position| self.plan[*position].phase == phase)
+                .ok_or(PomoError::NotInPlan(phase.name()))?
+        };
+        let awaited = self.awaiting.take().is_some();
+        if !awaited && !self.current_timer().is_fresh() {
+            self.emit(Event::PhaseAborted(self.current_status()));
+        }
+        self.move_to(phase, position);
+        if awaited {
+            self.resume();
+        } else if self.is_active() {
+            self.emit(Event::PhaseStarted(phase));
+        }
+        Ok(())
+    }
+
+    /// Starts the current phase over from zero, leaving the totals as they are. Its
+    /// extension is dropped.
+    pub fn restart_phase(&mut self) {
+        if self.is_consumed() || self.awaiting.is_some() {
+            return;
+        }
+        let phase = self.current_status();
+        if !self.current_timer().is_fresh() {
+            self.emit(Event::PhaseAborted(phase));
+        }
+        self.current_timer().reset();
+        if self.is_active() {
+            self.current_timer().start();
+            self.emit(Event::PhaseStarted(phase));
+        }
+    }
+
+    /// Lengthens the current phase by `by`. The extension is dropped once the phase ends.
+    pub fn extend(&self, by: Duration) {
+        if self.is_consumed() || self.awaiting.is_some() {
+            return;
+        }
+        self.current_timer().extend(by);
+        self.emit(Event::PhaseExtended(self.current_status(), by));
+    }
+
+    /// Abandons the running working phase for `reason` and starts it over, without counting
+    /// any of it.
+    pub fn void(&mut self, reason: String) -> Result<(), PomoError> {
+        if self.is_consumed() || self.awaiting.is_some() || self.current_status() != Phase::Working
+        {
+            return Err(PomoError::NotWorking);
+        }
+        let phase = self.current_status();
+        self.emit(Event::PhaseVoided(phase, reason));
+        self.current_timer().reset();
+        if self.is_active() {
+            self.current_timer().start();
+            self.emit(Event::PhaseStarted(phase));
+        }
+        Ok(())
+    }
+
+    /// Tags the pomodoros from now on with `task`, or with nothing when it is empty.
+    pub fn set_task(&mut self, task: String) {
+        self.task = Some(task.clone()).filter(|task| !task.is_empty());
+        self.emit(Event::TaskSet(task));
+    }
+
+    /// Logs an interruption of the running working phase, which carries on regardless.
+    pub fn interrupt(&self, kind: InterruptionKind, note: String) -> Result<(), PomoError> {
+        if self.is_consumed() || self.current_status() != Phase::Working {
+            return Err(PomoError::NotWorking);
+        }
+        self.emit(Event::Interrupted {
+            phase: self.current_status(),
+            kind,
+            note,
+        });
+        Ok(())
+    }
+
+    pub(crate) fn pause(&mut self) {
+        set_state(&self.state, State::Paused);
+        self.settle();
+    }
+
+    /// Pauses on behalf of the user, which a strict pomodoro refuses during working phases.
+    pub(crate) fn request_pause(&mut self) -> Result<(), PomoError> {
+        if self.strict && self.is_active() && self.current_status() == Phase::Working {
+            return Err(PomoError::Strict);
+        }
+        self.pause();
+        Ok(())
+    }
+
+    /// Pauses a running working phase the user has been away from for `idle`, which no
+    /// longer counts as focused but as paused. A strict pomodoro refuses.
+    pub(crate) fn pause_idle(&mut self, idle: Duration) -> Result<(), PomoError> {
+        if !self.is_running() || self.current_status() != Phase::Working {
+            return Ok(());
+        }
+        self.request_pause()?;
+        let idle = idle.min(self.current_timer().elapsed());
+        self.current_timer().unbank(idle);
+        if let Some(since) = self.paused_since {
+            self.paused_since = Some(since.checked_sub(idle).unwrap_or(since));
+        }
+        self.emit(Event::Idle(idle));
+        Ok(())
+    }
+
+    pub(crate) fn resume(&mut self) {
+        if self.is_consumed() {
+            return;
+        }
+        if self.awaiting.is_some() {
+            let _ = self.ack();
+            return;
+        }
+        set_state(&self.state, State::Running);
+        self.settle();
+    }
+
+    /// Brings the clock and the event stream in line with the state, which a [`Switch`]
+    /// may have changed behind the pomodoro's back.
+    pub(crate) fn settle(&mut self) {
+        if self.is_active() && self.is_consumed() {
+            set_state(&self.state, State::Paused);
+        }
+        if self.is_active() == self.current_timer().is_started() {
+            return;
+        }
+        if self.is_active() {
+            if let Some(since) = self.paused_since.take() {
+                self.counter.paused += since.elapsed();
+            }
+            self.awake = Some((SystemTime::now(), Instant::now()));
+            let timer = self.current_timer();
+            let fresh = timer.is_fresh();
+            self.emit(Event::Resumed);
+            timer.start();
+            if fresh {
+                self.emit(Event::PhaseStarted(self.current_status()));
+            }
+        } else {
+            self.current_timer().stop();
+            if !self.is_consumed() {
+                self.paused_since = Some(Instant::now());
+                self.counter.pauses += 1;
+            }
+            self.emit(Event::Paused);
+        }
+    }
+
+    /// Runs the pomodoro on the tokio timer until it is paused or every requested working
+    /// phase is done.
+    #[cfg(feature = "tokio-timer")]
+    pub async fn run(&mut self) {
+        self.run_with(&crate::timer::TokioSleeper).await
+    }
+
+    /// Like [`run`](Self::run), but waits for the ticks through `sleeper`, so any async
+    /// runtime can drive the pomodoro.
+    pub async fn run_with<S: Sleeper>(&mut self, sleeper: &S) {
+        let mut state = self.watch_state();
+        self.resume();
+        while self.is_running() {
+            tokio::select! {
+                _ = sleeper.sleep(self.next_wakeup()) => self.advance(),
+                _ = state.changed() => self.settle(),
+            }
+        }
+    }
+
+    /// Drives the pomodoro on the tokio timer until every requested working phase is done,
+    /// like [`drive_with`](Self::drive_with).
+    #[cfg(feature = "tokio-timer")]
+    pub async fn drive(&mut self) {
+        self.drive_with(&crate::timer::TokioSleeper).await
+    }
+
+    /// Drives the pomodoro through `sleeper` until every requested working phase is done.
+    ///
+    /// Unlike [`run_with`](Self::run_with), which returns once paused, it never resumes the
+    /// pomodoro by itself: while paused it sleeps without waking up until a [`Switch`] resumes
+    /// it, so it does not need to be called in a loop.
+    pub async fn drive_with<S: Sleeper>(&mut self, sleeper: &S) {
+        let mut state = self.watch_state();
+        while !self.is_consumed() {
+            if self.is_active() {
+                tokio::select! {
+                    _ = sleeper.sleep(self.next_wakeup()) => self.advance(),
+                    _ = state.changed() => self.settle(),
+                }
+            } else if state.changed().await.is_ok() {
+                self.settle();
+            }
+        }
+    }
+
+    /// Like [`run`](Self::run), but sleeps on the calling thread between the ticks, without
+    /// any async runtime. A pause through a [`Switch`] takes effect by the next tick.
+    pub fn run_blocking(&mut self) {
+        self.resume();
+        while self.is_running() {
+            std::thread::sleep(self.next_wakeup());
+            self.settle();
+            if self.is_running() {
+                self.advance();
+            }
+        }
+    }
+}
+
+/// Builds a [`Pomodoro`], starting from the classic technique: 25 minutes of work, 5 minute
+/// short breaks and a 15 minute long break after every four pomodoros.
+#[derive(Clone, Debug)]
+pub struct PomodoroBuilder {
+    work: Duration,
+    short_break: Duration,
+    long_break: Duration,
+    tick: Duration,
+    long_break_interval: u8,
+    short_breaks: bool,
+    continuous: bool,
+    auto_start_breaks: Option<bool>,
+    auto_start_work: Option<bool>,
+    until: Option<u8>,
+    until_duration: Option<Duration>,
+    plan: Vec<Step>,
+    on_suspend: SuspendPolicy,
+    strict: bool,
+    ack: bool,
+    count_overtime: bool,
+    power_saving: bool,
+    daily_goal: Option<u32>,
+    day_start: Option<NaiveTime>,
+}
+
+impl Default for PomodoroBuilder {
+    fn default() -> Self {
+        Self {
+            work: Duration::from_secs(25 * 60),
+            short_break: Duration::from_secs(5 * 60),
+            long_break: Duration::from_secs(15 * 60),
+            tick: Duration::from_secs(1),
+            long_break_interval: 4,
+            short_breaks: true,
+            continuous: true,
+            auto_start_breaks: None,
+            auto_start_work: None,
+            until: None,
+            until_duration: None,
+            plan: Vec::new(),
+            on_suspend: SuspendPolicy::default(),
+            strict: false,
+            ack: false,
+            count_overtime: false,
+            power_saving: false,
+            daily_goal: None,
+            day_start: None,
+        }
     }
 }
+
+impl PomodoroBuilder {
+    pub fn work(mut self, work: Duration) -> Self {
+        self.work = work;
+        self
+    }
+
+    pub fn short_break(mut self, short_break: Duration) -> Self {
+        self.short_break = short_break;
+        self
+    }
+
+    pub fn long_break(mut self, long_break: Duration) -> Self {
+        self.long_break = long_break;
+        self
+    }
+
+    /// How often the clocks report their progress. Defaults to a second.
+    pub fn tick(mut self, tick: Duration) -> Self {
+        self.tick = tick;
+        self
+    }
+
+    /// Takes a long break after every `interval` working phases, or never when it is 0.
+    /// Defaults to 4.
+    pub fn long_break_interval(mut self, interval: u8) -> Self {
+        self.long_break_interval = interval;
+        self
+    }
+
+    /// Whether working phases are followed by short breaks. Without them, working phases
+    /// follow each other up to the next long break. Defaults to true.
+    pub fn short_breaks(mut self, short_breaks: bool) -> Self {
+        self.short_breaks = short_breaks;
+        self
+    }
+
+    pub fn continuous(mut self, continuous: bool) -> Self {
+        self.continuous = continuous;
+        self
+    }
+
+    /// Whether breaks start by themselves once a working phase ends, instead of following
+    /// [`continuous`](Self::continuous).
+    pub fn auto_start_breaks(mut self, auto_start: bool) -> Self {
+        self.auto_start_breaks = Some(auto_start);
+        self
+    }
+
+    /// Whether working phases start by themselves once a break ends, instead of following
+    /// [`continuous`](Self::continuous).
+    pub fn auto_start_work(mut self, auto_start: bool) -> Self {
+        self.auto_start_work = Some(auto_start);
+        self
+    }
+
+    /// Stops the pomodoro after `until` working phases. It runs forever by default.
+    pub fn until(mut self, until: u8) -> Self {
+        self.until = Some(until);
+        self
+    }
+
+    /// Stops the pomodoro after the working phase which brings the focused time, pauses
+    /// excluded, to `until_duration`.
+    pub fn until_duration(mut self, until_duration: Duration) -> Self {
+        self.until_duration = Some(until_duration);
+        self
+    }
+
+    /// Cycles through `plan` instead of the working, short break and long break rhythm,
+    /// which makes the phase lengths and the long break interval irrelevant.
+    pub fn plan(mut self, plan: Vec<Step>) -> Self {
+        self.plan = plan;
+        self
+    }
+
+    /// What to do after the machine was suspended. Defaults to pausing.
+    pub fn on_suspend(mut self, policy: SuspendPolicy) -> Self {
+        self.on_suspend = policy;
+        self
+    }
+
+    /// Treats working phases as indivisible: pausing them through a [`Client`](crate::Client)
+    /// is refused, leaving only skipping them. Defaults to false.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Waits at the end of every phase until it is acknowledged through
+    /// [`Client::ack`](crate::Client::ack), instead of moving on by itself. Defaults to false.
+    pub fn ack(mut self, ack: bool) -> Self {
+        self.ack = ack;
+        self
+    }
+
+    /// Counts the time an acknowledgement is waited for as part of the phase which ended,
+    /// e.g. as focused time after a working phase. Defaults to false.
+    pub fn count_overtime(mut self, count: bool) -> Self {
+        self.count_overtime = count;
+        self
+    }
+
+    /// Sleeps once until the end of every phase instead of waking up for every tick, which
+    /// saves power when nothing shows the progress. The only [`Event::Tick`] of a phase then
+    /// comes at its end. Defaults to false.
+    pub fn power_saving(mut self, power_saving: bool) -> Self {
+        self.power_saving = power_saving;
+        self
+    }
+
+    /// Aims for `target` pomodoros a day, which is announced with
+    /// [`Event::GoalReached`] once met.
+    pub fn daily_goal(mut self, target: u32) -> Self {
+        self.daily_goal = Some(target);
+        self
+    }
+
+    /// Starts the counters over every day at `day_start`, e.g. 04:00 for the nights to count
+    /// towards the day before. The day of the daily goal starts then too. By default the
+    /// counters grow for as long as the pomodoro runs.
+    pub fn day_start(mut self, day_start: NaiveTime) -> Self {
+        self.day_start = Some(day_start);
+        self
+    }
+
+    /// Validates the settings and creates the pomodoro.
+    pub fn build(self) -> Result<Pomodoro, PomoError> {
+        let lengths = [
+            ("work", self.work),
+            ("short_break", self.short_break),
+            ("long_break", self.long_break),
+            ("tick", self.tick),
+        ];
+        if let Some((name, _)) = lengths.iter().find(|(_, length)| length.is_zero()) {
+            return Err(PomoError::ZeroDuration(name));
+        }
+        if self.until == Some(0) {
+            return Err(PomoError::ZeroUntil);
+        }
+        if self.daily_goal == Some(0) {
+            return Err(PomoError::ZeroGoal);
+        }
+        if self.until_duration.is_some_and(|d| d.is_zero()) {
+            return Err(PomoError::ZeroDuration("until_duration"));
+        }
+        if !self.plan.is_empty() && !self.plan.iter().any(|step| step.phase == Phase::Working) {
+            return Err(PomoError::NoWorkInPlan);
+        }
+        if self.plan.iter().any(|step| step.length.is_zero()) {
+            return Err(PomoError::ZeroDuration("plan step"));
+        }
+        let mut pomodoro = Pomodoro::new(
+            Clock::new(self.work, self.tick),
+            Clock::new(self.short_break, self.tick),
+            Clock::new(self.long_break, self.tick),
+            self.long_break_interval,
+            self.continuous,
+            self.until,
+        );
+        pomodoro.short_breaks = self.short_breaks;
+        pomodoro.auto_start_breaks = self.auto_start_breaks;
+        pomodoro.auto_start_work = self.auto_start_work;
+        pomodoro.until_duration = self.until_duration;
+        pomodoro.on_suspend = self.on_suspend;
+        pomodoro.strict = self.strict;
+        pomodoro.ack = self.ack;
+        pomodoro.count_overtime = self.count_overtime;
+        pomodoro.power_saving = self.power_saving;
+        pomodoro.goal = self.daily_goal.map(|target| Goal { done: 0, target });
+        pomodoro.day_start = self.day_start;
+        pomodoro.today = pomodoro.day(Local::now());
+        let tick = self.tick;
+        pomodoro.plan = self
+            .plan
+            .into_iter()
+            .map(|step| Stage {
+                phase: step.phase,
+                clock: Clock::new(step.length, tick),
+                label: step.label,
+            })
+            .collect();
+        pomodoro.current_status = pomodoro.first_phase();
+        Ok(pomodoro)
+    }
+}
+
 #[test]
 fn timer_struct() {
     let t = Clock::new(Duration::from_secs(2), Duration::from_secs(1));
@@ -263,6 +1854,63 @@ fn timer_struct() {
     assert!(!t.is_done());
 }
 
+#[test]
+fn clock_progress() {
+    let t = Clock::new(Duration::from_secs(4), Duration::from_secs(1));
+    assert_eq!(t.progress(), 0.0);
+    t.tick();
+    assert_eq!(t.progress(), 0.25);
+    assert_eq!(t.elapsed(), Duration::from_secs(1));
+    assert_eq!(t.remaining(), Duration::from_secs(3));
+    for _ in 0..4 {
+        t.tick();
+    }
+    assert_eq!(t.progress(), 1.0);
+    assert_eq!(t.remaining(), Duration::from_secs(0));
+}
+
+#[test]
+fn session_progress() {
+    let working_timer = Clock::new(Duration::from_secs(2), Duration::from_secs(1));
+    let short_break_timer = Clock::new(Duration::from_secs(1), Duration::from_secs(1));
+    let long_break_timer = Clock::new(Duration::from_secs(1), Duration::from_secs(1));
+    let mut pomodoro = Pomodoro::new(
+        working_timer,
+        short_break_timer,
+        long_break_timer,
+        4,
+        true,
+        Some(2),
+    );
+    assert_eq!(pomodoro.session_progress(), Some(0.0));
+    pomodoro.proceed();
+    assert_eq!(pomodoro.session_progress(), Some(0.25));
+    assert_eq!(pomodoro.remaining_in_phase(), Duration::from_secs(1));
+    pomodoro.proceed();
+    pomodoro.next_cycle();
+    assert_eq!(pomodoro.session_progress(), Some(0.5));
+    pomodoro.proceed();
+    pomodoro.next_cycle();
+    pomodoro.proceed();
+    pomodoro.proceed();
+    pomodoro.next_cycle();
+    assert_eq!(pomodoro.session_progress(), Some(1.0));
+}
+
+#[test]
+fn extend_clock() {
+    let t = Clock::new(Duration::from_secs(1), Duration::from_secs(1));
+    t.extend(Duration::from_secs(2));
+    t.tick();
+    assert!(!t.is_done());
+    assert_eq!(t.remaining(), Duration::from_secs(2));
+    t.tick();
+    t.tick();
+    assert!(t.is_done());
+    t.reset();
+    assert_eq!(t.lifespan(), Duration::from_secs(1));
+}
+
 #[test]
 fn pomodoro_timer_works_fine() {
     let working_timer = Clock::new(Duration::from_micros(1), Duration::from_micros(1));
@@ -301,6 +1949,7 @@ fn pomodoro_timer_works_fine() {
     assert!(pomodoro.is_consumed());
 }
 
+#[cfg(feature = "tokio-timer")]
 #[tokio::test(flavor = "current_thread")]
 async fn trasition() {
     let working_timer = Clock::new(Duration::from_micros(2), Duration::from_micros(1));
@@ -321,6 +1970,7 @@ async fn trasition() {
     assert_eq!(pomodoro.counter.long_break, 1);
 }
 
+#[cfg(feature = "tokio-timer")]
 #[tokio::test(flavor = "current_thread")]
 async fn continuous_option_false() {
     let working_timer = Clock::new(Duration::from_micros(1), Duration::from_micros(1));
@@ -333,10 +1983,1171 @@ async fn continuous_option_false() {
         2,
         false,
         None,
-   );
+    );
     pomodoro.run().await;
     assert!(!pomodoro.is_active());
     assert_eq!(pomodoro.counter.working, 1);
     assert_eq!(pomodoro.counter.short_break, 0);
     assert_eq!(pomodoro.counter.long_break, 0);
-}
\ No newline at end of file
+}
+#[test]
+fn reset_state() {
+    let working_timer = Clock::new(Duration::from_micros(1), Duration::from_micros(1));
+    let short_break_timer = Clock::new(Duration::from_micros(2), Duration::from_micros(1));
+    let long_break_timer = Clock::new(Duration::from_micros(1), Duration::from_micros(1));
+    let mut pomodoro = Pomodoro::new(
+        working_timer,
+        short_break_timer,
+        long_break_timer,
+        2,
+        true,
+        None,
+    );
+    pomodoro.resume();
+    pomodoro.proceed();
+    pomodoro.next_cycle();
+    pomodoro.proceed();
+    assert_eq!(pomodoro.current_status(), Phase::ShortBreak);
+    pomodoro.reset();
+    assert!(!pomodoro.is_active());
+    assert_eq!(pomodoro.current_status(), Phase::Working);
+    assert_eq!(pomodoro.counter.working, 0);
+    assert_eq!(
+        pomodoro.short_break.elapsed.lock().unwrap().get(),
+        Clock::initial_duration()
+    );
+}
+
+#[test]
+fn skip_phases() {
+    let working_timer = Clock::new(Duration::from_micros(2), Duration::from_micros(1));
+    let short_break_timer = Clock::new(Duration::from_micros(2), Duration::from_micros(1));
+    let long_break_timer = Clock::new(Duration::from_micros(2), Duration::from_micros(1));
+    let mut pomodoro = Pomodoro::new(
+        working_timer,
+        short_break_timer,
+        long_break_timer,
+        1,
+        true,
+        None,
+    );
+    pomodoro.proceed();
+    pomodoro.skip();
+    assert_eq!(pomodoro.counter.working, 0);
+    assert_eq!(pomodoro.current_status(), Phase::ShortBreak);
+    assert!(pomodoro.current_timer().is_fresh());
+    assert!(pomodoro.working.is_fresh());
+    pomodoro.skip();
+    assert_eq!(pomodoro.counter.short_break, 1);
+    assert_eq!(pomodoro.current_status(), Phase::Working);
+
+    let mut pomodoro = pomodoro.count_skipped(true);
+    pomodoro.skip();
+    assert_eq!(pomodoro.counter.working, 1);
+    assert_eq!(pomodoro.current_status(), Phase::LongBreak);
+}
+
+#[cfg(feature = "tokio-timer")]
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn emit_events() {
+    let working_timer = Clock::new(Duration::from_secs(2), Duration::from_secs(1));
+    let short_break_timer = Clock::new(Duration::from_secs(1), Duration::from_secs(1));
+    let long_break_timer = Clock::new(Duration::from_secs(1), Duration::from_secs(1));
+    let mut pomodoro = Pomodoro::new(
+        working_timer,
+        short_break_timer,
+        long_break_timer,
+        2,
+        true,
+        Some(1),
+    );
+    let mut events = pomodoro.subscribe();
+    pomodoro.run().await;
+    let mut received = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        received.push(event);
+    }
+    assert_eq!(
+        received,
+        vec![
+            Event::Resumed,
+            Event::PhaseStarted(Phase::Working),
+            Event::Tick {
+                phase: Phase::Working,
+                elapsed: Duration::from_secs(1),
+                remaining: Duration::from_secs(1),
+            },
+            Event::Tick {
+                phase: Phase::Working,
+                elapsed: Duration::from_secs(2),
+                remaining: Duration::from_secs(0),
+            },
+            Event::PhaseEnded(Phase::Working),
+            Event::Completed,
+        ]
+    );
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn measure_monotonic_time() {
+    use tokio::time::advance;
+
+    let t = Clock::new(Duration::from_secs(10), Duration::from_secs(1));
+    t.start();
+    advance(Duration::from_millis(2500)).await;
+    assert_eq!(t.elapsed(), Duration::from_millis(2500));
+    assert_eq!(t.next_wakeup(), Duration::from_millis(500));
+    t.stop();
+    advance(Duration::from_secs(5)).await;
+    assert_eq!(t.elapsed(), Duration::from_millis(2500));
+    t.start();
+    advance(Duration::from_secs(7)).await;
+    assert_eq!(t.next_wakeup(), Duration::from_millis(500));
+    advance(Duration::from_secs(1)).await;
+    assert!(t.is_done());
+    assert_eq!(t.next_wakeup(), Duration::from_secs(0));
+}
+
+#[cfg(feature = "tokio-timer")]
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn pause_through_switch() {
+    let working_timer = Clock::new(Duration::from_secs(60), Duration::from_secs(10));
+    let short_break_timer = Clock::new(Duration::from_secs(1), Duration::from_secs(1));
+    let long_break_timer = Clock::new(Duration::from_secs(1), Duration::from_secs(1));
+    let mut pomodoro = Pomodoro::new(
+        working_timer,
+        short_break_timer,
+        long_break_timer,
+        2,
+        true,
+        None,
+    );
+    let switch = pomodoro.switch();
+    let mut events = pomodoro.subscribe();
+    let started = Instant::now();
+    let pausing = async {
+        tokio::time::sleep(Duration::from_secs(3)).await;
+        switch.pause();
+    };
+    tokio::join!(pomodoro.run(), pausing);
+    assert_eq!(started.elapsed(), Duration::from_secs(3));
+    assert_eq!(switch.state(), State::Paused);
+    assert!(pomodoro.status().paused);
+    assert_eq!(pomodoro.status().elapsed, Duration::from_secs(3));
+    let mut received = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        received.push(event);
+    }
+    assert_eq!(
+        received,
+        vec![
+            Event::Resumed,
+            Event::PhaseStarted(Phase::Working),
+            Event::Paused,
+        ]
+    );
+}
+
+#[test]
+fn build_pomodoro() {
+    let pomodoro = Pomodoro::builder().build().unwrap();
+    assert_eq!(pomodoro.working.lifespan, Duration::from_secs(25 * 60));
+    assert_eq!(pomodoro.short_break.lifespan, Duration::from_secs(5 * 60));
+    assert_eq!(pomodoro.long_break.lifespan, Duration::from_secs(15 * 60));
+    assert_eq!(pomodoro.long_break_interval, 4);
+    assert_eq!(pomodoro.until, None);
+
+    let pomodoro = Pomodoro::builder()
+        .work(Duration::from_secs(50 * 60))
+        .long_break_interval(2)
+        .continuous(false)
+        .until(3)
+        .build()
+        .unwrap();
+    assert_eq!(pomodoro.working.lifespan, Duration::from_secs(50 * 60));
+    assert_eq!(pomodoro.long_break_interval, 2);
+    assert!(!pomodoro.continuous);
+    assert_eq!(pomodoro.until, Some(3));
+
+    let zero = Duration::from_secs(0);
+    assert!(matches!(
+        Pomodoro::builder().short_break(zero).build(),
+        Err(PomoError::ZeroDuration("short_break"))
+    ));
+    assert!(matches!(
+        Pomodoro::builder().until(0).build(),
+        Err(PomoError::ZeroUntil)
+    ));
+}
+
+#[test]
+fn phase_labels() {
+    assert_eq!(Phase::ShortBreak.to_string(), "Phase: short break");
+    assert_eq!(Phase::LongBreak.label(&Labels::default()), "long break");
+    let labels = Labels {
+        working: "focus".to_string(),
+        ..Labels::default()
+    };
+    assert_eq!(Phase::Working.label(&labels), "focus");
+    assert_eq!(Phase::ShortBreak.label(&labels), "short break");
+    assert_eq!("short_break".parse::<Phase>(), Ok(Phase::ShortBreak));
+    assert_eq!(
+        serde_json::to_string(&Phase::LongBreak).unwrap(),
+        r#""long_break""#
+    );
+}
+
+#[test]
+fn parse_steps() {
+    let step =
+        |fields: &[&str]| Step::try_from(fields.iter().map(|f| f.to_string()).collect::<Vec<_>>());
+    assert_eq!(
+        step(&["work", "52m"]),
+        Ok(Step {
+            phase: Phase::Working,
+            length: Duration::from_secs(52 * 60),
+            label: None,
+        })
+    );
+    assert_eq!(
+        step(&["long_break", "30m", "walk"])
+            .unwrap()
+            .label
+            .as_deref(),
+        Some("walk")
+    );
+    assert_eq!(step(&["break", "17m"]).unwrap().phase, Phase::ShortBreak);
+    assert!(step(&["nap", "17m"]).is_err());
+    assert!(step(&["work", "forever"]).is_err());
+    assert!(step(&["work"]).is_err());
+}
+
+#[test]
+fn follow_plan() {
+    let minutes = |m: u64| Duration::from_secs(m * 60);
+    let mut pomodoro = Pomodoro::builder()
+        .tick(minutes(1))
+        .plan(vec![
+            Step {
+                phase: Phase::ShortBreak,
+                length: minutes(1),
+                label: Some("warm up".to_string()),
+            },
+            Step {
+                phase: Phase::Working,
+                length: minutes(2),
+                label: None,
+            },
+        ])
+        .until(2)
+        .build()
+        .unwrap();
+    assert_eq!(pomodoro.current_status(), Phase::ShortBreak);
+    assert_eq!(pomodoro.status().label.as_deref(), Some("warm up"));
+    assert_eq!(pomodoro.remaining_in_phase(), minutes(1));
+    pomodoro.proceed();
+    pomodoro.next_cycle();
+    assert_eq!(pomodoro.current_status(), Phase::Working);
+    assert_eq!(pomodoro.remaining_in_phase(), minutes(2));
+    assert_eq!(pomodoro.status().label, None);
+    assert_eq!(pomodoro.status().label(&Labels::default()), "working");
+    pomodoro.skip();
+    assert_eq!(pomodoro.current_status(), Phase::ShortBreak);
+    assert_eq!(pomodoro.counter.working, 0);
+    pomodoro.skip();
+    assert_eq!(pomodoro.current_status(), Phase::Working);
+    assert_eq!(pomodoro.counter.short_break, 2);
+    pomodoro.reset();
+    assert_eq!(pomodoro.current_status(), Phase::ShortBreak);
+
+    assert!(matches!(
+        Pomodoro::builder()
+            .plan(vec![Step {
+                phase: Phase::ShortBreak,
+                length: minutes(1),
+                label: None,
+            }])
+            .build(),
+        Err(PomoError::NoWorkInPlan)
+    ));
+}
+
+#[cfg(feature = "tokio-timer")]
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn stop_after_focused_time() {
+    use tokio::time::advance;
+
+    let mut pomodoro = Pomodoro::builder()
+        .work(Duration::from_secs(60))
+        .short_break(Duration::from_secs(30))
+        .tick(Duration::from_secs(60))
+        .until_duration(Duration::from_secs(90))
+        .build()
+        .unwrap();
+    pomodoro.resume();
+    advance(Duration::from_secs(20)).await;
+    pomodoro.pause();
+    advance(Duration::from_secs(600)).await;
+    pomodoro.resume();
+    advance(Duration::from_secs(25)).await;
+    assert_eq!(pomodoro.session_progress(), Some(0.5));
+    pomodoro.skip();
+    assert!(!pomodoro.is_consumed());
+    assert_eq!(pomodoro.counter.focused, Duration::from_secs(45));
+    pomodoro.skip();
+    pomodoro.run().await;
+    assert!(pomodoro.is_consumed());
+    assert_eq!(pomodoro.counter.focused, Duration::from_secs(105));
+    assert_eq!(pomodoro.session_progress(), Some(1.0));
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn count_time() {
+    use tokio::time::advance;
+
+    let mut pomodoro = Pomodoro::builder()
+        .work(Duration::from_secs(60))
+        .short_break(Duration::from_secs(30))
+        .build()
+        .unwrap();
+    pomodoro.resume();
+    advance(Duration::from_secs(10)).await;
+    pomodoro.pause();
+    advance(Duration::from_secs(5)).await;
+    assert_eq!(pomodoro.counter().paused, Duration::from_secs(5));
+    pomodoro.resume();
+    advance(Duration::from_secs(20)).await;
+    pomodoro.skip();
+    advance(Duration::from_secs(7)).await;
+    let status = pomodoro.status();
+    assert_eq!(status.focused, Duration::from_secs(30));
+    assert_eq!(status.on_break, Duration::from_secs(7));
+    assert_eq!(status.paused_for, Duration::from_secs(5));
+    assert_eq!(status.pauses, 1);
+    pomodoro.reset();
+    assert_eq!(pomodoro.counter(), Counter::default());
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn restore_checkpoint() {
+    use tokio::time::advance;
+
+    let build = || {
+        Pomodoro::builder()
+            .work(Duration::from_secs(60))
+            .short_break(Duration::from_secs(30))
+            .build()
+            .unwrap()
+    };
+    let mut pomodoro = build();
+    pomodoro.resume();
+    advance(Duration::from_secs(60)).await;
+    pomodoro.advance();
+    advance(Duration::from_secs(10)).await;
+    pomodoro.pause();
+    advance(Duration::from_secs(5)).await;
+    let checkpoint = pomodoro.checkpoint();
+    assert_eq!(checkpoint.snapshot.phase, Phase::ShortBreak);
+    assert_eq!(checkpoint.snapshot.elapsed, Duration::from_secs(10));
+    assert_eq!(checkpoint.snapshot.remaining, Duration::from_secs(20));
+    assert!(checkpoint.snapshot.paused);
+    assert_eq!(
+        checkpoint.snapshot.config.short_break,
+        Duration::from_secs(30)
+    );
+
+    let mut restored = build();
+    restored.restore(&checkpoint);
+    restored.resume();
+    let status = restored.status();
+    assert_eq!(status.phase, Phase::ShortBreak);
+    assert_eq!(status.remaining, Duration::from_secs(20));
+    assert_eq!(status.pomodoros, 1);
+    assert_eq!(status.focused, Duration::from_secs(60));
+    assert_eq!(status.on_break, Duration::from_secs(10));
+    assert_eq!(status.paused_for, Duration::from_secs(5));
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn apply_suspend_policies() {
+    use tokio::time::advance;
+
+    let build = |policy| {
+        let mut pomodoro = Pomodoro::builder()
+            .work(Duration::from_secs(60))
+            .short_break(Duration::from_secs(30 * 60))
+            .on_suspend(policy)
+            .build()
+            .unwrap();
+        pomodoro.resume();
+        pomodoro
+    };
+    let gap = Duration::from_secs(20 * 60);
+
+    let mut pomodoro = build(SuspendPolicy::Pause);
+    advance(Duration::from_secs(10)).await;
+    pomodoro.suspended(gap);
+    assert!(!pomodoro.is_active());
+    assert_eq!(pomodoro.status().elapsed, Duration::from_secs(10));
+    assert_eq!(pomodoro.counter().paused, gap);
+
+    let mut pomodoro = build(SuspendPolicy::Break);
+    advance(Duration::from_secs(10)).await;
+    pomodoro.suspended(gap);
+    let status = pomodoro.status();
+    assert_eq!(status.phase, Phase::ShortBreak);
+    assert_eq!(status.elapsed, gap);
+    assert_eq!(status.focused, Duration::from_secs(10));
+
+    let mut pomodoro = build(SuspendPolicy::Discard);
+    let mut events = pomodoro.subscribe();
+    advance(Duration::from_secs(10)).await;
+    pomodoro.suspended(gap);
+    assert!(pomodoro.is_active());
+    assert_eq!(pomodoro.status().elapsed, Duration::from_secs(0));
+    assert_eq!(events.try_recv().unwrap(), Event::Suspended(gap));
+    assert_eq!(
+        events.try_recv().unwrap(),
+        Event::PhaseAborted(Phase::Working)
+    );
+
+    let mut pomodoro = build(SuspendPolicy::Ignore);
+    advance(Duration::from_secs(10)).await;
+    pomodoro.suspended(gap);
+    assert!(pomodoro.is_active());
+    assert_eq!(pomodoro.status().elapsed, Duration::from_secs(10));
+    assert_eq!(pomodoro.slept(), None);
+}
+
+#[test]
+fn strict_refuses_pausing_work() {
+    let mut pomodoro = Pomodoro::builder().strict(true).build().unwrap();
+    pomodoro.resume();
+    assert!(matches!(pomodoro.request_pause(), Err(PomoError::Strict)));
+    assert!(pomodoro.is_active());
+    pomodoro.skip();
+    assert_eq!(pomodoro.current_status(), Phase::ShortBreak);
+    assert!(pomodoro.request_pause().is_ok());
+    assert!(!pomodoro.is_active());
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn void_working_phase() {
+    let mut pomodoro = Pomodoro::builder()
+        .work(Duration::from_secs(60))
+        .build()
+        .unwrap();
+    let mut events = pomodoro.subscribe();
+    pomodoro.resume();
+    tokio::time::advance(Duration::from_secs(20)).await;
+    pomodoro.void("phone call".to_string()).unwrap();
+    let status = pomodoro.status();
+    assert_eq!(status.phase, Phase::Working);
+    assert_eq!(status.elapsed, Duration::from_secs(0));
+    assert_eq!(status.focused, Duration::from_secs(0));
+    assert!(pomodoro.is_active());
+    let voided =
+        std::iter::from_fn(|| events.try_recv().ok()).find(|e| matches!(e, Event::PhaseVoided(..)));
+    assert_eq!(
+        voided,
+        Some(Event::PhaseVoided(Phase::Working, "phone call".to_string()))
+    );
+    pomodoro.skip();
+    assert!(matches!(
+        pomodoro.void(String::new()),
+        Err(PomoError::NotWorking)
+    ));
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn reach_daily_goal() {
+    let mut pomodoro = Pomodoro::builder()
+        .work(Duration::from_secs(60))
+        .daily_goal(3)
+        .build()
+        .unwrap();
+    pomodoro.count_done_today(1);
+    let mut events = pomodoro.subscribe();
+    pomodoro.resume();
+    tokio::time::advance(Duration::from_secs(60)).await;
+    pomodoro.advance();
+    assert_eq!(pomodoro.status().goal, Some(Goal { done: 2, target: 3 }));
+    pomodoro.skip();
+    pomodoro.skip();
+    assert_eq!(pomodoro.status().goal, Some(Goal { done: 2, target: 3 }));
+    pomodoro.skip();
+    pomodoro = pomodoro.count_skipped(true);
+    pomodoro.skip();
+    assert_eq!(pomodoro.status().goal, Some(Goal { done: 3, target: 3 }));
+    let reached: Vec<Event> = std::iter::from_fn(|| events.try_recv().ok())
+        .filter(|e| matches!(e, Event::GoalReached(_)))
+        .collect();
+    assert_eq!(reached, vec![Event::GoalReached(3)]);
+    assert!(matches!(
+        Pomodoro::builder().daily_goal(0).build(),
+        Err(PomoError::ZeroGoal)
+    ));
+}
+
+#[test]
+fn reconfigure_next_phase() {
+    let mut pomodoro = Pomodoro::builder().build().unwrap();
+    pomodoro
+        .reconfigure(Durations {
+            work: Some(Duration::from_secs(50 * 60)),
+            ..Durations::default()
+        })
+        .unwrap();
+    assert_eq!(pomodoro.remaining_in_phase(), Duration::from_secs(50 * 60));
+    pomodoro.resume();
+    pomodoro
+        .reconfigure(Durations {
+            work: Some(Duration::from_secs(40 * 60)),
+            short_break: Some(Duration::from_secs(10 * 60)),
+            long_break_interval: Some(1),
+            ..Durations::default()
+        })
+        .unwrap();
+    assert!(pomodoro.remaining_in_phase() > Duration::from_secs(45 * 60));
+    pomodoro = pomodoro.count_skipped(true);
+    pomodoro.skip();
+    assert_eq!(pomodoro.current_status(), Phase::LongBreak);
+    assert!(pomodoro.remaining_in_phase() <= Duration::from_secs(15 * 60));
+    pomodoro.skip();
+    assert!(pomodoro.remaining_in_phase() <= Duration::from_secs(40 * 60));
+    assert!(pomodoro.remaining_in_phase() > Duration::from_secs(39 * 60));
+    assert!(matches!(
+        pomodoro.reconfigure(Durations {
+            long_break: Some(Duration::ZERO),
+            ..Durations::default()
+        }),
+        Err(PomoError::ZeroDuration("long_break"))
+    ));
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn wait_for_ack() {
+    let mut pomodoro = Pomodoro::builder()
+        .work(Duration::from_secs(60))
+        .ack(true)
+        .count_overtime(true)
+        .build()
+        .unwrap();
+    let mut events = pomodoro.subscribe();
+    pomodoro.resume();
+    tokio::time::advance(Duration::from_secs(60)).await;
+    pomodoro.advance();
+    tokio::time::advance(Duration::from_secs(90)).await;
+    let status = pomodoro.status();
+    assert_eq!(status.phase, Phase::Working);
+    assert!(status.paused && status.awaiting_ack);
+    assert_eq!(status.overtime, Duration::from_secs(90));
+    assert_eq!(status.pomodoros, 1);
+    assert!(matches!(
+        pomodoro.void(String::new()),
+        Err(PomoError::NotWorking)
+    ));
+    pomodoro.ack().unwrap();
+    let status = pomodoro.status();
+    assert_eq!(status.phase, Phase::ShortBreak);
+    assert!(!status.paused && !status.awaiting_ack);
+    assert_eq!(
+        (status.pomodoros, status.focused),
+        (1, Duration::from_secs(150))
+    );
+    assert_eq!(status.pauses, 0);
+    assert!(matches!(pomodoro.ack(), Err(PomoError::NotAwaitingAck)));
+    let seen: Vec<Event> = std::iter::from_fn(|| events.try_recv().ok())
+        .filter(|e| !matches!(e, Event::Tick { .. } | Event::Paused | Event::Resumed))
+        .collect();
+    assert_eq!(
+        seen,
+        vec![
+            Event::PhaseStarted(Phase::Working),
+            Event::PhaseEnded(Phase::Working),
+            Event::AwaitingAck(Phase::Working),
+            Event::Acknowledged(Duration::from_secs(90)),
+            Event::PhaseStarted(Phase::ShortBreak),
+        ]
+    );
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn count_overtime_of_held_work() {
+    let mut pomodoro = Pomodoro::builder()
+        .work(Duration::from_secs(60))
+        .short_break(Duration::from_secs(30))
+        .continuous(false)
+        .build()
+        .unwrap();
+    pomodoro.resume();
+    tokio::time::advance(Duration::from_secs(60)).await;
+    pomodoro.advance();
+    tokio::time::advance(Duration::from_secs(45)).await;
+    let status = pomodoro.status();
+    assert_eq!((status.phase, status.pomodoros), (Phase::Working, 1));
+    assert!(status.awaiting_ack);
+    assert_eq!(status.overtime, Duration::from_secs(45));
+    assert_eq!(status.focused, Duration::from_secs(60));
+    pomodoro.resume();
+    assert_eq!(pomodoro.status().phase, Phase::ShortBreak);
+    tokio::time::advance(Duration::from_secs(30)).await;
+    pomodoro.advance();
+    let status = pomodoro.status();
+    // Breaks end as before, holding on before the next working phase.
+    assert_eq!((status.phase, status.awaiting_ack), (Phase::Working, false));
+    assert!(status.paused);
+    pomodoro.reset();
+}
+
+#[test]
+fn estimate_session_end() {
+    let minutes = |m: u64| Duration::from_secs(m * 60);
+    let mut pomodoro = Pomodoro::builder()
+        .long_break_interval(2)
+        .until(3)
+        .build()
+        .unwrap();
+    assert_eq!(
+        pomodoro.session_remaining(),
+        Some(minutes(25 + 5 + 25 + 15 + 25))
+    );
+    let status = pomodoro.status();
+    let left = status.session_ends_at.unwrap() - status.ends_at.unwrap();
+    assert_eq!(left.num_minutes(), 5 + 25 + 15 + 25);
+    pomodoro
+        .reconfigure(Durations {
+            short_break: Some(minutes(10)),
+            ..Durations::default()
+        })
+        .unwrap();
+    assert_eq!(
+        pomodoro.session_remaining(),
+        Some(minutes(25 + 10 + 25 + 15 + 25))
+    );
+    pomodoro = pomodoro.count_skipped(true);
+    pomodoro.skip();
+    pomodoro.skip();
+    pomodoro.skip();
+    assert_eq!(pomodoro.current_status(), Phase::LongBreak);
+    assert_eq!(pomodoro.session_remaining(), Some(minutes(15 + 25)));
+    pomodoro.skip();
+    pomodoro.skip();
+    let status = pomodoro.status();
+    assert_eq!(pomodoro.session_remaining(), Some(Duration::ZERO));
+    assert_eq!((status.ends_at, status.session_ends_at), (None, None));
+
+    let pomodoro = Pomodoro::builder()
+        .until_duration(minutes(50))
+        .build()
+        .unwrap();
+    assert_eq!(pomodoro.session_remaining(), Some(minutes(25 + 5 + 25)));
+    let pomodoro = Pomodoro::builder().build().unwrap();
+    assert_eq!(pomodoro.session_remaining(), None);
+    assert!(pomodoro.status().ends_at.is_some());
+}
+
+#[test]
+fn lay_out_schedule() {
+    let phases = |pomodoro: &Pomodoro| -> Vec<Phase> {
+        pomodoro.schedule().iter().map(|slot| slot.phase).collect()
+    };
+    let pomodoro = Pomodoro::builder().long_break_interval(2).build().unwrap();
+    assert_eq!(
+        phases(&pomodoro),
+        vec![
+            Phase::Working,
+            Phase::ShortBreak,
+            Phase::Working,
+            Phase::LongBreak
+        ]
+    );
+    let pomodoro = Pomodoro::builder().until(1).build().unwrap();
+    assert_eq!(phases(&pomodoro), vec![Phase::Working]);
+    let mut pomodoro = Pomodoro::builder()
+        .plan(vec![
+            Step {
+                phase: Phase::Working,
+                length: Duration::from_secs(50 * 60),
+                label: None,
+            },
+            Step {
+                phase: Phase::ShortBreak,
+                length: Duration::from_secs(10 * 60),
+                label: Some("walk".to_string()),
+            },
+        ])
+        .build()
+        .unwrap();
+    pomodoro = pomodoro.count_skipped(true);
+    pomodoro.skip();
+    let schedule = pomodoro.schedule();
+    assert_eq!(schedule.len(), 2);
+    assert_eq!(schedule[0].label.as_deref(), Some("walk"));
+    assert_eq!(schedule[1].length, Duration::from_secs(50 * 60));
+}
+
+#[test]
+fn schedule_long_session() {
+    let target = Duration::from_secs(200 * 3600);
+    let pomodoro = Pomodoro::builder().until_duration(target).build().unwrap();
+    let schedule = pomodoro.schedule();
+    assert!(schedule.len() < 10_000);
+    assert_eq!(schedule.last().map(|slot| slot.phase), Some(Phase::Working));
+    let working = schedule.iter().filter(|slot| slot.phase == Phase::Working);
+    assert_eq!(working.count(), 480);
+    assert!(pomodoro.session_remaining().unwrap() > target);
+    assert!(pomodoro.status().session_ends_at.is_some());
+}
+
+#[test]
+fn run_without_runtime() {
+    let clock = |micros| Clock::new(Duration::from_micros(micros), Duration::from_micros(1));
+    let mut pomodoro = Pomodoro::new(clock(2), clock(3), clock(4), 2, true, Some(3));
+    pomodoro.run_blocking();
+    assert!(pomodoro.is_consumed());
+    assert_eq!(pomodoro.counter.working, 3);
+    assert_eq!(pomodoro.counter.short_break, 1);
+    assert_eq!(pomodoro.counter.long_break, 1);
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn run_with_any_sleeper() {
+    let clock = |secs| Clock::new(Duration::from_secs(secs), Duration::from_secs(1));
+    let mut pomodoro = Pomodoro::new(clock(60), clock(5), clock(15), 4, true, Some(2));
+    let started = Instant::now();
+    let wakeups = Cell::new(0);
+    let sleeper = |duration: Duration| {
+        wakeups.set(wakeups.get() + 1);
+        tokio::time::sleep(duration)
+    };
+    pomodoro.run_with(&sleeper).await;
+    assert!(pomodoro.is_consumed());
+    assert_eq!(started.elapsed(), Duration::from_secs(125));
+    assert!(wakeups.get() >= 125);
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn run_a_day_at_once() {
+    use crate::timer::MockClock;
+
+    let mut pomodoro = Pomodoro::builder()
+        .tick(Duration::from_secs(60))
+        .until(16)
+        .build()
+        .unwrap();
+    let clock = MockClock::new();
+    let started = Instant::now();
+    pomodoro.run_with(&clock).await;
+    assert!(pomodoro.is_consumed());
+    // Sixteen pomodoros, twelve short breaks and three long ones.
+    let day = Duration::from_secs((16 * 25 + 12 * 5 + 3 * 15) * 60);
+    assert_eq!(started.elapsed(), day);
+    assert_eq!(clock.elapsed(), day);
+    assert!(clock
+        .sleeps()
+        .iter()
+        .all(|sleep| *sleep == Duration::from_secs(60)));
+}
+
+#[cfg(feature = "tokio-timer")]
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn pause_mid_tick() {
+    use tokio::time::{advance, sleep};
+
+    let clock = |secs| Clock::new(Duration::from_secs(secs), Duration::from_secs(1));
+    let mut pomodoro = Pomodoro::new(clock(10), clock(1), clock(1), 4, true, Some(1));
+    let switch = pomodoro.switch();
+    let pausing = async {
+        sleep(Duration::from_millis(2500)).await;
+        switch.pause();
+    };
+    tokio::join!(pomodoro.run(), pausing);
+    assert_eq!(pomodoro.status().elapsed, Duration::from_millis(2500));
+    advance(Duration::from_secs(60)).await;
+    assert_eq!(pomodoro.status().elapsed, Duration::from_millis(2500));
+
+    let mut events = pomodoro.subscribe();
+    let resumed = Instant::now();
+    let pausing = async {
+        sleep(Duration::from_millis(700)).await;
+        switch.pause();
+    };
+    tokio::join!(pomodoro.run(), pausing);
+    assert_eq!(resumed.elapsed(), Duration::from_millis(700));
+    assert_eq!(pomodoro.status().elapsed, Duration::from_millis(3200));
+    let mut ticks = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        if let Event::Tick { elapsed, .. } = event {
+            ticks.push(elapsed);
+        }
+    }
+    // The tick cut short at 2.5s is finished half a second after resuming.
+    assert_eq!(ticks, vec![Duration::from_secs(3)]);
+}
+
+#[test]
+fn wait_for_resume_without_spinning() {
+    use std::sync::mpsc;
+
+    use crate::timer::MockClock;
+
+    let (done, finished) = mpsc::channel();
+    std::thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .start_paused(true)
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let clock = |secs| Clock::new(Duration::from_secs(secs), Duration::from_secs(1));
+            let mut pomodoro = Pomodoro::new(clock(60), clock(5), clock(15), 4, true, Some(1));
+            let switch = pomodoro.switch();
+            let sleeper = MockClock::new();
+            let idle = Duration::from_secs(3600);
+            let switching = async {
+                // A spinning engine would keep the paused clock from moving on.
+                tokio::time::sleep(idle).await;
+                assert!(sleeper.sleeps().is_empty());
+                switch.resume();
+                tokio::time::sleep(Duration::from_secs(10)).await;
+                switch.pause();
+                tokio::time::sleep(idle).await;
+                assert_eq!(switch.state(), State::Paused);
+                assert_eq!(sleeper.elapsed(), Duration::from_secs(10));
+                switch.resume();
+            };
+            tokio::join!(pomodoro.drive_with(&sleeper), switching);
+            assert!(pomodoro.is_consumed());
+            assert_eq!(pomodoro.counter.focused, Duration::from_secs(60));
+            assert_eq!(pomodoro.counter.paused, idle);
+        });
+        done.send(()).unwrap();
+    });
+    finished
+        .recv_timeout(Duration::from_secs(10))
+        .expect("the paused pomodoro kept the runtime busy");
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn sleep_to_deadline() {
+    use crate::timer::MockClock;
+
+    let mut pomodoro = Pomodoro::builder()
+        .until(2)
+        .power_saving(true)
+        .build()
+        .unwrap();
+    pomodoro.resume();
+    pomodoro.extend(Duration::from_secs(60));
+    let clock = MockClock::new();
+    pomodoro.run_with(&clock).await;
+    assert!(pomodoro.is_consumed());
+    let minutes = |m: u64| Duration::from_secs(m * 60);
+    assert_eq!(clock.sleeps(), vec![minutes(26), minutes(5), minutes(25)]);
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn end_phases_on_time_whatever_the_tick() {
+    use crate::timer::MockClock;
+
+    let clock = |millis| Clock::new(Duration::from_millis(millis), Duration::from_millis(300));
+    let mut pomodoro = Pomodoro::new(clock(1000), clock(300), clock(300), 4, true, Some(1));
+    pomodoro.resume();
+    let sleeper = MockClock::new();
+    pomodoro.run_with(&sleeper).await;
+    let millis = |m| Duration::from_millis(m);
+    assert_eq!(
+        sleeper.sleeps(),
+        vec![millis(300), millis(300), millis(300), millis(100)]
+    );
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn start_mid_session() {
+    use tokio::time::advance;
+
+    let minutes = |m: u64| Duration::from_secs(m * 60);
+    let snapshot = PomodoroSnapshot {
+        phase: Phase::ShortBreak,
+        elapsed: minutes(2),
+        remaining: minutes(3),
+        counter: Counter {
+            working: 3,
+            short_break: 2,
+            focused: minutes(75),
+            ..Counter::default()
+        },
+        paused: false,
+        position: 0,
+        task: Some("report".to_string()),
+        config: Settings {
+            until: Some(4),
+            ..Settings::default()
+        },
+    };
+    let mut pomodoro = Pomodoro::from_snapshot(&snapshot).unwrap();
+    let status = pomodoro.status();
+    assert!(status.paused);
+    assert_eq!(
+        (status.phase, status.elapsed, status.remaining),
+        (Phase::ShortBreak, minutes(2), minutes(3))
+    );
+    assert_eq!(status.pomodoros, 3);
+    assert_eq!(status.task.as_deref(), Some("report"));
+    assert_eq!(pomodoro.settings(), snapshot.config);
+
+    pomodoro.resume();
+    advance(minutes(3)).await;
+    pomodoro.advance();
+    assert_eq!(pomodoro.status().phase, Phase::Working);
+    advance(minutes(25)).await;
+    pomodoro.advance();
+    assert!(pomodoro.is_consumed());
+    assert_eq!(pomodoro.counter().focused, minutes(100));
+
+    let zero = PomodoroSnapshot {
+        config: Settings {
+            work: Duration::ZERO,
+            ..Settings::default()
+        },
+        ..snapshot
+    };
+    assert!(matches!(
+        Pomodoro::from_snapshot(&zero),
+        Err(PomoError::ZeroDuration("work"))
+    ));
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn go_to_phase() {
+    use tokio::time::advance;
+
+    let mut pomodoro = Pomodoro::builder().build().unwrap();
+    let mut events = pomodoro.subscribe();
+    pomodoro.resume();
+    advance(Duration::from_secs(60)).await;
+    pomodoro.goto(Phase::LongBreak).unwrap();
+    let status = pomodoro.status();
+    assert_eq!(status.phase, Phase::LongBreak);
+    assert_eq!(status.elapsed, Duration::ZERO);
+    assert_eq!(status.pomodoros, 0);
+    let received: Vec<_> = std::iter::from_fn(|| events.try_recv().ok()).collect();
+    assert_eq!(
+        &received[received.len() - 2..],
+        [
+            Event::PhaseAborted(Phase::Working),
+            Event::PhaseStarted(Phase::LongBreak)
+        ]
+    );
+    // A phase which has not begun is left without being aborted.
+    pomodoro.goto(Phase::Working).unwrap();
+    pomodoro.goto(Phase::Working).unwrap();
+    let received: Vec<_> = std::iter::from_fn(|| events.try_recv().ok()).collect();
+    assert_eq!(
+        received,
+        [
+            Event::PhaseStarted(Phase::Working),
+            Event::PhaseStarted(Phase::Working)
+        ]
+    );
+
+    let plan = vec![
+        Step::try_from(vec!["work".to_string(), "50m".to_string()]).unwrap(),
+        Step::try_from(vec!["break".to_string(), "10m".to_string()]).unwrap(),
+    ];
+    let mut planned = Pomodoro::builder().plan(plan).build().unwrap();
+    planned.goto(Phase::ShortBreak).unwrap();
+    assert_eq!(planned.remaining_in_phase(), Duration::from_secs(10 * 60));
+    assert!(matches!(
+        planned.goto(Phase::LongBreak),
+        Err(PomoError::NotInPlan("long_break"))
+    ));
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn restart_current_phase() {
+    use tokio::time::advance;
+
+    let mut pomodoro = Pomodoro::builder().build().unwrap();
+    pomodoro.resume();
+    advance(Duration::from_secs(25 * 60)).await;
+    pomodoro.advance();
+    advance(Duration::from_secs(60)).await;
+    pomodoro.extend(Duration::from_secs(120));
+    let mut events = pomodoro.subscribe();
+    pomodoro.restart_phase();
+    let status = pomodoro.status();
+    assert_eq!(status.phase, Phase::ShortBreak);
+    assert_eq!(status.elapsed, Duration::ZERO);
+    assert_eq!(status.remaining, Duration::from_secs(5 * 60));
+    assert_eq!(status.pomodoros, 1);
+    assert_eq!(
+        std::iter::from_fn(|| events.try_recv().ok()).collect::<Vec<_>>(),
+        [
+            Event::PhaseAborted(Phase::ShortBreak),
+            Event::PhaseStarted(Phase::ShortBreak)
+        ]
+    );
+    advance(Duration::from_secs(30)).await;
+    assert_eq!(pomodoro.status().elapsed, Duration::from_secs(30));
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn auto_start_breaks_only() {
+    use tokio::time::advance;
+
+    let mut pomodoro = Pomodoro::builder()
+        .continuous(false)
+        .auto_start_breaks(true)
+        .build()
+        .unwrap();
+    pomodoro.resume();
+    advance(Duration::from_secs(25 * 60)).await;
+    pomodoro.advance();
+    assert_eq!(pomodoro.current_status(), Phase::ShortBreak);
+    assert!(pomodoro.is_active());
+    advance(Duration::from_secs(5 * 60)).await;
+    pomodoro.advance();
+    assert_eq!(pomodoro.current_status(), Phase::Working);
+    assert!(!pomodoro.is_active());
+    assert_eq!(pomodoro.counter.working, 1);
+    assert_eq!(pomodoro.counter.short_break, 1);
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn auto_start_work_only() {
+    use tokio::time::advance;
+
+    let mut pomodoro = Pomodoro::builder()
+        .auto_start_breaks(false)
+        .build()
+        .unwrap();
+    pomodoro.resume();
+    advance(Duration::from_secs(25 * 60)).await;
+    pomodoro.advance();
+    assert_eq!(pomodoro.current_status(), Phase::Working);
+    assert!(!pomodoro.is_active());
+    pomodoro.ack().unwrap();
+    assert_eq!(pomodoro.current_status(), Phase::ShortBreak);
+    advance(Duration::from_secs(5 * 60)).await;
+    pomodoro.advance();
+    assert_eq!(pomodoro.current_status(), Phase::Working);
+    assert!(pomodoro.is_active());
+    assert_eq!(pomodoro.settings().auto_start_breaks, Some(false));
+    assert_eq!(pomodoro.settings().auto_start_work, None);
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn without_long_breaks() {
+    use tokio::time::advance;
+
+    let mut pomodoro = Pomodoro::builder().long_break_interval(0).build().unwrap();
+    pomodoro.resume();
+    for _ in 0..8 {
+        assert_eq!(pomodoro.current_status(), Phase::Working);
+        advance(Duration::from_secs(25 * 60)).await;
+        pomodoro.advance();
+        assert_eq!(pomodoro.current_status(), Phase::ShortBreak);
+        advance(Duration::from_secs(5 * 60)).await;
+        pomodoro.advance();
+    }
+    assert_eq!(pomodoro.counter.long_break, 0);
+    let phases: Vec<_> = pomodoro.schedule().iter().map(|slot| slot.phase).collect();
+    assert_eq!(phases, [Phase::Working, Phase::ShortBreak]);
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn without_short_breaks() {
+    use tokio::time::advance;
+
+    let mut pomodoro = Pomodoro::builder()
+        .short_breaks(false)
+        .long_break_interval(2)
+        .build()
+        .unwrap();
+    let phases: Vec<_> = pomodoro.schedule().iter().map(|slot| slot.phase).collect();
+    assert_eq!(phases, [Phase::Working, Phase::Working, Phase::LongBreak]);
+    pomodoro.resume();
+    advance(Duration::from_secs(25 * 60)).await;
+    pomodoro.advance();
+    assert_eq!(pomodoro.current_status(), Phase::Working);
+    advance(Duration::from_secs(25 * 60)).await;
+    pomodoro.advance();
+    assert_eq!(pomodoro.current_status(), Phase::LongBreak);
+    assert_eq!(pomodoro.counter.working, 2);
+    assert_eq!(pomodoro.counter.short_break, 0);
+
+    let pomodoro = Pomodoro::builder()
+        .short_breaks(false)
+        .long_break_interval(0)
+        .build()
+        .unwrap();
+    assert_eq!(pomodoro.schedule().len(), 1);
+}
+
+#[test]
+fn roll_counters_over_at_day_start() {
+    use chrono::TimeZone;
+
+    let at = |day: u32, hour: u32| {
+        Local
+            .with_ymd_and_hms(2024, 3, day, hour, 30, 0)
+            .earliest()
+            .unwrap()
+    };
+    let mut pomodoro = Pomodoro::builder()
+        .day_start(NaiveTime::from_hms_opt(4, 0, 0).unwrap())
+        .build()
+        .unwrap();
+    let mut events = pomodoro.subscribe();
+    pomodoro.counter.working = 3;
+    pomodoro.roll_over(at(4, 23));
+    pomodoro.roll_over(at(5, 2));
+    assert_eq!(pomodoro.counter.working, 3);
+    assert_eq!(
+        pomodoro.day(at(5, 2)),
+        NaiveDate::from_ymd_opt(2024, 3, 4).unwrap()
+    );
+    pomodoro.roll_over(at(5, 4));
+    assert_eq!(pomodoro.counter.working, 0);
+    assert_eq!(events.try_recv().unwrap(), Event::CountersReset);
+
+    let mut pomodoro = Pomodoro::builder().build().unwrap();
+    pomodoro.counter.working = 3;
+    pomodoro.roll_over(at(4, 23));
+    pomodoro.roll_over(at(5, 4));
+    assert_eq!(pomodoro.counter.working, 3);
+    pomodoro.reset_counters();
+    assert_eq!(pomodoro.counter, Counter::default());
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn pause_when_idle() {
+    use tokio::time::advance;
+
+    let mut pomodoro = Pomodoro::builder().build().unwrap();
+    pomodoro.resume();
+    advance(Duration::from_secs(10 * 60)).await;
+    let mut events = pomodoro.subscribe();
+    pomodoro.pause_idle(Duration::from_secs(4 * 60)).unwrap();
+    assert!(!pomodoro.is_active());
+    assert_eq!(events.try_recv().unwrap(), Event::Paused);
+    assert_eq!(
+        events.try_recv().unwrap(),
+        Event::Idle(Duration::from_secs(4 * 60))
+    );
+    let status = pomodoro.status();
+    assert_eq!(status.elapsed, Duration::from_secs(6 * 60));
+    assert_eq!(status.paused_for, Duration::from_secs(4 * 60));
+    assert_eq!(status.pauses, 1);
+
+    pomodoro.pause_idle(Duration::from_secs(60)).unwrap();
+    assert!(events.try_recv().is_err());
+    let mut strict = Pomodoro::builder().strict(true).build().unwrap();
+    strict.resume();
+    assert!(matches!(
+        strict.pause_idle(Duration::from_secs(60)),
+        Err(PomoError::Strict)
+    ));
+}
@@ -0,0 +1,187 @@
+//! The framing of the control protocol, whichever [`transport`](crate::transport) carries
+//! it: every message is JSON behind its length, as 4 big-endian bytes.
+//!
+//! A connection opens with a handshake: the client sends a [`Hello`] naming the
+//! [`VERSION`] it speaks, which the daemon accepts or rejects with a [`Welcome`] before any
+//! request is made.
+
+use std::convert::TryFrom;
+use std::io;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// The version of the protocol, raised whenever a message changes incompatibly.
+pub const VERSION: u32 = 2;
+
+/// The longest message read, so a stray connection cannot make the daemon allocate much.
+pub const MAX_LENGTH: u32 = 1 << 20;
+
+/// How long a client waits for the daemon to answer its [`Hello`]. A daemon older than
+/// the handshake never does, as it waits for a whole line.
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The first message of a client.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hello {
+    pub version: u32,
+}
+
+/// The daemon's answer to a [`Hello`], with the version it speaks.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Welcome {
+    Accepted(u32),
+    Rejected(u32),
+}
+
+/// Writes `message` as a frame.
+pub async fn write<W, T>(writer: &mut W, message: &T) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+    T: Serialize,
+{
+    let encoded = serde_json::to_vec(message)?;
+    let length = u32::try_from(encoded.len())
+        .ok()
+        .filter(|length| *length <= MAX_LENGTH)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "the message is too long"))?;
+    writer.write_all(&length.to_be_bytes()).await?;
+    writer.write_all(&encoded).await?;
+    writer.flush().await
+}
+
+/// Reads the message of the next frame, or `None` once the other end hung up. A frame
+/// which holds no such message fails with [`io::ErrorKind::InvalidData`], after which the
+/// next one can still be read. A frame longer than [`MAX_LENGTH`] fails with
+/// [`io::ErrorKind::InvalidInput`], and leaves nothing more to read.
+pub async fn read<R, T>(reader: &mut R) -> io::Result<Option<T>>
+where
+    R: AsyncRead + Unpin,
+    T: DeserializeOwned,
+{
+    let mut length = [0; 4];
+    match reader.read_exact(&mut length).await {
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        read => read?,
+    };
+    let length = u32::from_be_bytes(length);
+    if length > MAX_LENGTH {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("a message of {} bytes is too long", length),
+        ));
+    }
+    let mut encoded = vec![0; length as usize];
+    reader.read_exact(&mut encoded).await?;
+    serde_json::from_slice(&encoded)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Opens a connection as a client, failing when the daemon speaks another version.
+pub async fn greet<R, W>(reader: &mut R, writer: &mut W) -> io::Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    write(writer, &Hello { version: VERSION }).await?;
+    let welcome = tokio::time::timeout(HANDSHAKE_TIMEOUT, read(reader))
+        .await
+        .map_err(|_| incompatible("the daemon does not answer, it may be older than this pomo"))?;
+    match welcome {
+        Ok(Some(Welcome::Accepted(_))) => Ok(()),
+        Ok(Some(Welcome::Rejected(version))) => Err(incompatible(format!(
+            "the daemon speaks version {} of the protocol and this pomo version {}, restart it",
+            version, VERSION
+        ))),
+        Ok(None) | Err(_) => Err(incompatible("the daemon does not speak pomo's protocol")),
+    }
+}
+
+/// Answers the handshake of a client, telling whether it speaks this version. A client
+/// which is rejected has been told why, and the connection is then closed.
+pub async fn welcome<R, W>(reader: &mut R, writer: &mut W) -> io::Result<bool>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let hello: Hello = match read(reader).await {
+        Ok(Some(hello)) => hello,
+        Ok(None) => return Ok(false),
+        Err(e) if e.kind() == io::ErrorKind::InvalidData => Hello { version: 0 },
+        Err(e) => return Err(e),
+    };
+    let accepted = hello.version == VERSION;
+    let welcome = if accepted {
+        Welcome::Accepted(VERSION)
+    } else {
+        Welcome::Rejected(VERSION)
+    };
+    write(writer, &welcome).await?;
+    Ok(accepted)
+}
+
+fn incompatible(reason: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, reason.into())
+}
+
+#[tokio::test]
+async fn frame_messages() {
+    let mut written = Vec::new();
+    write(&mut written, &vec![1, 2]).await.unwrap();
+    write(&mut written, &"three").await.unwrap();
+    assert_eq!(written, b"\0\0\0\x05[1,2]\0\0\0\x07\"three\"");
+    written.extend_from_slice(b"\0\0\0\x05{oops\0\0\0\x014");
+    let mut reader = &written[..];
+    assert_eq!(read(&mut reader).await.unwrap(), Some(vec![1, 2]));
+    assert_eq!(
+        read::<_, String>(&mut reader).await.unwrap().as_deref(),
+        Some("three")
+    );
+    let malformed = read::<_, u32>(&mut reader).await.unwrap_err();
+    assert_eq!(malformed.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(read(&mut reader).await.unwrap(), Some(4));
+    assert_eq!(read::<_, u32>(&mut reader).await.unwrap(), None);
+    // A line of JSON, as a client older than the framing sends.
+    let mut reader = &b"{\"Status\":null}\n"[..];
+    let long = read::<_, u32>(&mut reader).await.unwrap_err();
+    assert_eq!(long.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[tokio::test]
+async fn shake_hands() {
+    let (client, server) = tokio::io::duplex(64);
+    let (mut client_reader, mut client_writer) = tokio::io::split(client);
+    let (mut server_reader, mut server_writer) = tokio::io::split(server);
+    let daemon = tokio::spawn(async move {
+        welcome(&mut server_reader, &mut server_writer)
+            .await
+            .unwrap()
+    });
+    greet(&mut client_reader, &mut client_writer).await.unwrap();
+    assert!(daemon.await.unwrap());
+
+    let (client, server) = tokio::io::duplex(64);
+    let (mut client_reader, mut client_writer) = tokio::io::split(client);
+    let (mut server_reader, mut server_writer) = tokio::io::split(server);
+    let daemon = tokio::spawn(async move {
+        welcome(&mut server_reader, &mut server_writer)
+            .await
+            .unwrap()
+    });
+    write(
+        &mut client_writer,
+        &Hello {
+            version: VERSION + 1,
+        },
+    )
+    .await
+    .unwrap();
+    assert!(!daemon.await.unwrap());
+    assert_eq!(
+        read(&mut client_reader).await.unwrap(),
+        Some(Welcome::Rejected(VERSION))
+    );
+}
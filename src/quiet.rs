@@ -0,0 +1,78 @@
+//! Times during which notifications and sounds are held back, while the timer and its
+//! events go on as usual.
+
+use chrono::{DateTime, Local};
+use serde::Deserialize;
+
+use crate::schedule::QuietHours;
+
+/// Quiet settings, read from the `[quiet]` table of the config file.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Quiet {
+    /// The stretch of every day without alerts, e.g. `"22:00-07:00"`.
+    pub hours: Option<QuietHours>,
+    /// Also keep quiet while the desktop's do-not-disturb mode is on, on Linux with the
+    /// `dnd` feature.
+    pub dnd: bool,
+}
+
+impl Quiet {
+    /// Whether `at` falls within the quiet hours.
+    pub fn is_quiet_at(&self, at: DateTime<Local>) -> bool {
+        self.hours.is_some_and(|hours| hours.contains(at.time()))
+    }
+
+    /// Whether alerts should be held back right now.
+    pub async fn is_quiet(&self) -> bool {
+        self.is_quiet_at(Local::now()) || self.dnd && do_not_disturb().await
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "dnd"))]
+#[zbus::proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+    /// Set by notification servers such as KDE Plasma's while they hold notifications back.
+    #[zbus(property)]
+    fn inhibited(&self) -> zbus::Result<bool>;
+}
+
+/// Whether the notification server on the session bus is in do-not-disturb mode. Servers
+/// which cannot tell are taken not to be.
+#[cfg(all(target_os = "linux", feature = "dnd"))]
+async fn do_not_disturb() -> bool {
+    let inhibited = async {
+        let connection = zbus::Connection::session().await?;
+        NotificationsProxy::new(&connection)
+            .await?
+            .inhibited()
+            .await
+    };
+    inhibited.await.unwrap_or(false)
+}
+
+#[cfg(not(all(target_os = "linux", feature = "dnd")))]
+async fn do_not_disturb() -> bool {
+    false
+}
+
+#[test]
+fn quiet_at_night() {
+    use chrono::TimeZone;
+
+    let at = |hour| {
+        Local
+            .with_ymd_and_hms(2024, 3, 4, hour, 0, 0)
+            .earliest()
+            .unwrap()
+    };
+    let quiet: Quiet = toml::from_str(r#"hours = "22:00-07:00""#).unwrap();
+    assert!(quiet.is_quiet_at(at(23)));
+    assert!(quiet.is_quiet_at(at(6)));
+    assert!(!quiet.is_quiet_at(at(12)));
+    assert!(!Quiet::default().is_quiet_at(at(23)));
+}
@@ -0,0 +1,182 @@
+//! Periodic checkpoints of a running session, so one cut short by a crash or a reboot can
+//! be picked up where it stopped.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+
+use crate::event::Event;
+use crate::pomodoro::PomodoroSnapshot;
+use crate::runtime::Client;
+
+/// How often a running session is written to disk.
+pub const INTERVAL: Duration = Duration::from_secs(5);
+
+/// A [`PomodoroSnapshot`] saved to carry on with the [`Pomodoro`](crate::Pomodoro) later.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    #[serde(flatten)]
+    pub snapshot: PomodoroSnapshot,
+    pub saved_at: DateTime<Local>,
+}
+
+impl Checkpoint {
+    /// The default file, `$XDG_DATA_HOME/pomo/session.json` on Linux.
+    pub fn path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("pomo").join("session.json"))
+    }
+
+    /// Reads the checkpoint at `path`, or `None` when no session was interrupted.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Option<Self>> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Writes the checkpoint to `path`, replacing the previous one in a single rename so a
+    /// crash halfway through leaves the old checkpoint intact.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let partial = path.with_extension("json.partial");
+        fs::write(&partial, serde_json::to_string(self)?)?;
+        fs::rename(&partial, path)
+    }
+
+    /// Forgets the checkpoint at `path`.
+    pub fn clear(path: impl AsRef<Path>) -> io::Result<()> {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Spawns a task writing a [`Checkpoint`] of the pomodoro behind `client` to `path` every
+/// [`INTERVAL`] and whenever it pauses or changes phase, leaving the task out unless
+/// `with_task`. The checkpoint is removed once the session completes.
+pub fn spawn(
+    path: PathBuf,
+    client: Client,
+    mut events: broadcast::Receiver<Event>,
+    with_task: bool,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut every = tokio::time::interval(INTERVAL);
+        loop {
+            tokio::select! {
+                _ = every.tick() => {}
+                event = events.recv() => match event {
+                    Ok(Event::Completed) => {
+                        if let Err(e) = Checkpoint::clear(&path) {
+                            tracing::warn!(path = %path.display(), error = %e, "cannot remove the session");
+                        }
+                        return;
+                    }
+                    Ok(Event::Tick { .. }) | Err(RecvError::Lagged(_)) => continue,
+                    Ok(_) => {}
+                    Err(RecvError::Closed) => return,
+                },
+            }
+            let mut checkpoint = match client.checkpoint().await {
+                Ok(checkpoint) => checkpoint,
+                Err(_) => return,
+            };
+            if !with_task {
+                checkpoint.snapshot.task = None;
+            }
+            if let Err(e) = checkpoint.save(&path) {
+                tracing::warn!(path = %path.display(), error = %e, "cannot save the session");
+            }
+        }
+    })
+}
+
+#[test]
+fn save_and_load() {
+    use chrono::TimeZone;
+
+    use crate::pomodoro::{Counter, Phase, Settings};
+
+    let dir = std::env::temp_dir().join(format!("pomo-recovery-{}", std::process::id()));
+    let path = dir.join("session.json");
+    assert_eq!(Checkpoint::load(&path).unwrap(), None);
+    let checkpoint = Checkpoint {
+        snapshot: PomodoroSnapshot {
+            phase: Phase::ShortBreak,
+            elapsed: Duration::from_secs(90),
+            remaining: Duration::from_secs(210),
+            counter: Counter {
+                working: 2,
+                short_break: 1,
+                focused: Duration::from_secs(50 * 60),
+                ..Counter::default()
+            },
+            paused: true,
+            position: 0,
+            task: Some("report".to_string()),
+            config: Settings::default(),
+        },
+        saved_at: Local.with_ymd_and_hms(2024, 1, 2, 9, 0, 0).unwrap(),
+    };
+    checkpoint.save(&path).unwrap();
+    assert_eq!(Checkpoint::load(&path).unwrap(), Some(checkpoint));
+    Checkpoint::clear(&path).unwrap();
+    Checkpoint::clear(&path).unwrap();
+    assert_eq!(Checkpoint::load(&path).unwrap(), None);
+    fs::remove_dir(&dir).unwrap();
+}
+
+#[test]
+fn load_checkpoints_without_config() {
+    use crate::pomodoro::Settings;
+
+    let saved = r#"{"phase":"working","elapsed":{"secs":90,"nanos":0},"counter":{"working":1,
+        "short_break":1,"long_break":0,"focused":{"secs":1500,"nanos":0},
+        "on_break":{"secs":300,"nanos":0},"paused":{"secs":0,"nanos":0},"pauses":0},
+        "paused":false,"saved_at":"2024-01-02T09:00:00+00:00"}"#;
+    let checkpoint: Checkpoint = serde_json::from_str(saved).unwrap();
+    assert_eq!(checkpoint.snapshot.elapsed, Duration::from_secs(90));
+    assert_eq!(checkpoint.snapshot.counter.working, 1);
+    assert_eq!(checkpoint.snapshot.config, Settings::default());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn checkpoint_running_session() {
+    use crate::pomodoro::{Phase, Pomodoro};
+
+    let path = std::env::temp_dir().join(format!("pomo-session-{}.json", std::process::id()));
+    let pomodoro = Pomodoro::builder()
+        .work(Duration::from_secs(60))
+        .until(1)
+        .build()
+        .unwrap()
+        .count_skipped(true);
+    let events = pomodoro.subscribe();
+    let (runtime, client, _events) = crate::start(pomodoro).await;
+    let saver = spawn(path.clone(), client.clone(), events, false);
+    client.set_task("report".to_string()).await.unwrap();
+    client.pause().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let saved = Checkpoint::load(&path).unwrap().unwrap();
+    assert_eq!(saved.snapshot.phase, Phase::Working);
+    assert!(saved.snapshot.paused);
+    assert_eq!(saved.snapshot.task, None);
+    client.skip().await.unwrap();
+    saver.await.unwrap();
+    assert_eq!(Checkpoint::load(&path).unwrap(), None);
+    runtime.shutdown().await.unwrap();
+}
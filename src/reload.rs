@@ -0,0 +1,85 @@
+//! Watching the config file, so a daemon takes on new phase lengths without restarting.
+
+use std::path::Path;
+use std::time::Duration;
+
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::sync::mpsc;
+
+/// How long to wait for the rest of a save, which editors often spread over several writes
+/// and renames.
+const SETTLE: Duration = Duration::from_millis(200);
+
+/// Changes to a config file, which need not exist yet.
+pub struct ConfigWatcher {
+    _watcher: RecommendedWatcher,
+    changes: mpsc::UnboundedReceiver<()>,
+}
+
+impl ConfigWatcher {
+    /// Starts watching `path`. The directory holding it is watched rather than the file
+    /// itself, so the file being replaced on save is noticed too, and it is created when
+    /// missing, so a config written after the daemon started is noticed as well.
+    pub fn new(path: &Path) -> notify::Result<Self> {
+        let name = path.file_name().map(|name| name.to_os_string());
+        let dir = path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."));
+        std::fs::create_dir_all(dir).map_err(notify::Error::io)?;
+        let (sender, changes) = mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let touched = match event {
+                Ok(event) => {
+                    !event.kind.is_access()
+                        && event.paths.iter().any(|p| p.file_name() == name.as_deref())
+                }
+                Err(_) => false,
+            };
+            if touched {
+                let _ = sender.send(());
+            }
+        })?;
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        Ok(Self {
+            _watcher: watcher,
+            changes,
+        })
+    }
+
+    /// Waits until the file has changed, taking the changes of one save as one.
+    pub async fn changed(&mut self) {
+        if self.changes.recv().await.is_none() {
+            return std::future::pending().await;
+        }
+        tokio::time::sleep(SETTLE).await;
+        while self.changes.try_recv().is_ok() {}
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn notice_changes() {
+    let dir = std::env::temp_dir().join(format!("pomo-reload-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("config.toml");
+    let mut watcher = ConfigWatcher::new(&path).unwrap();
+    std::fs::write(dir.join("other.toml"), "work = \"1m\"").unwrap();
+    std::fs::write(&path, "work = \"50m\"").unwrap();
+    tokio::time::timeout(Duration::from_secs(5), watcher.changed())
+        .await
+        .unwrap();
+    assert!(watcher.changes.try_recv().is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn watch_missing_directory() {
+    let dir = std::env::temp_dir().join(format!("pomo-reload-missing-{}", std::process::id()));
+    let path = dir.join("pomo").join("config.toml");
+    let mut watcher = ConfigWatcher::new(&path).unwrap();
+    std::fs::write(&path, "work = \"50m\"").unwrap();
+    tokio::time::timeout(Duration::from_secs(5), watcher.changed())
+        .await
+        .unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -1,77 +1,624 @@
-use tokio::sync::mpsc;
+use std::future::Future;
+use std::io;
+use std::time::Duration;
 
-use crate::pomodoro::Pomodoro;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+
+use crate::config::Config;
+use crate::error::PomoError;
+use crate::event::{Event, InterruptionKind, Until};
+use crate::pomodoro::{Durations, Phase, Pomodoro, PomodoroSnapshot, Status};
+use crate::recovery::Checkpoint;
 
 enum Signal {
     Abort,
-    Pause,
+    Pause(oneshot::Sender<Result<(), PomoError>>),
+    PauseIdle(Duration, oneshot::Sender<Result<(), PomoError>>),
     Resume,
+    Toggle(oneshot::Sender<Result<Status, PomoError>>),
+    Start,
+    Hold,
+    Skip,
+    Restart,
+    ResetCounters,
+    Goto(Phase, oneshot::Sender<Result<(), PomoError>>),
+    Ack(oneshot::Sender<Result<(), PomoError>>),
+    Extend(Duration),
+    Reconfigure(Durations, oneshot::Sender<Result<(), PomoError>>),
+    Reload(Durations, oneshot::Sender<Result<(), PomoError>>),
+    SetTask(String),
+    Void(String, oneshot::Sender<Result<(), PomoError>>),
+    Interrupt(
+        InterruptionKind,
+        String,
+        oneshot::Sender<Result<(), PomoError>>,
+    ),
+    Status(oneshot::Sender<Status>),
+    Snapshot(oneshot::Sender<PomodoroSnapshot>),
+    Checkpoint(oneshot::Sender<Checkpoint>),
+}
+
+impl Signal {
+    /// The name the signal is logged under.
+    fn name(&self) -> &'static str {
+        match self {
+            Signal::Abort => "abort",
+            Signal::Pause(_) => "pause",
+            Signal::PauseIdle(..) => "pause_idle",
+            Signal::Resume => "resume",
+            Signal::Toggle(_) => "toggle",
+            Signal::Start => "start",
+            Signal::Hold => "hold",
+            Signal::Skip => "skip",
+            Signal::Restart => "restart",
+            Signal::ResetCounters => "reset_counters",
+            Signal::Goto(..) => "goto",
+            Signal::Ack(_) => "ack",
+            Signal::Extend(_) => "extend",
+            Signal::Reconfigure(..) => "reconfigure",
+            Signal::Reload(..) => "reload",
+            Signal::SetTask(_) => "set_task",
+            Signal::Void(..) => "void",
+            Signal::Interrupt(..) => "interrupt",
+            Signal::Status(_) => "status",
+            Signal::Snapshot(_) => "snapshot",
+            Signal::Checkpoint(_) => "checkpoint",
+        }
+    }
+}
+
+/// Spawns the pomodoro onto the tokio runtime and returns right away with the [`Runtime`]
+/// owning the background task, a [`Client`] controlling it, and a stream of the [`Event`]s
+/// it emits.
+pub async fn start(pomodoro: Pomodoro) -> (Runtime, Client, broadcast::Receiver<Event>) {
+    spawn(pomodoro, true)
 }
 
-pub async fn start(mut pomodoro: Pomodoro) -> Client {
+/// Like [`start`], but leaves the pomodoro paused before its first phase until a [`Client`]
+/// resumes it.
+pub async fn start_idle(pomodoro: Pomodoro) -> (Runtime, Client, broadcast::Receiver<Event>) {
+    spawn(pomodoro, false)
+}
+
+fn spawn(mut pomodoro: Pomodoro, resume: bool) -> (Runtime, Client, broadcast::Receiver<Event>) {
     let (sender, mut receiver) = mpsc::channel::<Signal>(2);
-    let sender = sender.clone();
-    let shared = pomodoro.shared.clone();
-    tokio::spawn(async move {
-        loop {
-            pomodoro.run().await;
+    let events = pomodoro.events.downgrade();
+    let receiver_of_events = pomodoro.subscribe();
+    let token = CancellationToken::new();
+    let engine_token = token.clone();
+    let mut state = pomodoro.watch_state();
+    let engine = async move {
+        if resume {
+            pomodoro.resume();
         }
-    });
-    let t = tokio::spawn( async move {
+        let tick = sleep(pomodoro.next_wakeup());
+        tokio::pin!(tick);
+        let mut listening = true;
         loop {
-            if let Some(signal) = receiver.recv().await {
-                match signal {
-                    Signal::Pause => shared.lock().unwrap().pause(),
-                    Signal::Resume => shared.lock().unwrap().resume(),
-                    Signal::Abort => return,
+            tokio::select! {
+                _ = engine_token.cancelled() => break,
+                signal = receiver.recv(), if listening => match signal.inspect(|signal| {
+                    tracing::debug!(signal = signal.name(), "received signal")
+                }) {
+                    Some(Signal::Pause(reply)) => {
+                        let _ = reply.send(pomodoro.request_pause());
+                    }
+                    Some(Signal::PauseIdle(idle, reply)) => {
+                        let _ = reply.send(pomodoro.pause_idle(idle));
+                    }
+                    Some(Signal::Resume) => {
+                        if !pomodoro.is_active() {
+                            pomodoro.resume();
+                            tick.as_mut().reset(Instant::now() + pomodoro.next_wakeup());
+                        }
+                    }
+                    Some(Signal::Toggle(reply)) => {
+                        let toggled = if pomodoro.is_active() {
+                            pomodoro.request_pause()
+                        } else {
+                            pomodoro.resume();
+                            tick.as_mut().reset(Instant::now() + pomodoro.next_wakeup());
+                            Ok(())
+                        };
+                        let _ = reply.send(toggled.map(|_| pomodoro.status()));
+                    }
+                    Some(Signal::Start) => {
+                        if pomodoro.is_consumed() {
+                            pomodoro.reset();
+                        }
+                        if !pomodoro.is_active() {
+                            pomodoro.resume();
+                            tick.as_mut().reset(Instant::now() + pomodoro.next_wakeup());
+                        }
+                    }
+                    Some(Signal::Hold) => pomodoro.hold(),
+                    Some(Signal::Skip) => {
+                        pomodoro.skip();
+                        tick.as_mut().reset(Instant::now() + pomodoro.next_wakeup());
+                    }
+                    Some(Signal::Restart) => {
+                        pomodoro.restart_phase();
+                        tick.as_mut().reset(Instant::now() + pomodoro.next_wakeup());
+                    }
+                    Some(Signal::ResetCounters) => pomodoro.reset_counters(),
+                    Some(Signal::Goto(phase, reply)) => {
+                        let _ = reply.send(pomodoro.goto(phase));
+                        tick.as_mut().reset(Instant::now() + pomodoro.next_wakeup());
+                    }
+                    Some(Signal::Ack(reply)) => {
+                        let _ = reply.send(pomodoro.ack());
+                        tick.as_mut().reset(Instant::now() + pomodoro.next_wakeup());
+                    }
+                    Some(Signal::Extend(by)) => pomodoro.extend(by),
+                    Some(Signal::Reconfigure(durations, reply)) => {
+                        let _ = reply.send(pomodoro.reconfigure(durations));
+                        tick.as_mut().reset(Instant::now() + pomodoro.next_wakeup());
+                    }
+                    Some(Signal::Reload(durations, reply)) => {
+                        let _ = reply.send(pomodoro.reload(durations));
+                        tick.as_mut().reset(Instant::now() + pomodoro.next_wakeup());
+                    }
+                    Some(Signal::SetTask(task)) => pomodoro.set_task(task),
+                    Some(Signal::Interrupt(kind, note, reply)) => {
+                        let _ = reply.send(pomodoro.interrupt(kind, note));
+                    }
+                    Some(Signal::Void(reason, reply)) => {
+                        let _ = reply.send(pomodoro.void(reason));
+                        tick.as_mut().reset(Instant::now() + pomodoro.next_wakeup());
+                    }
+                    Some(Signal::Status(reply)) => {
+                        let _ = reply.send(pomodoro.status());
+                    }
+                    Some(Signal::Snapshot(reply)) => {
+                        let _ = reply.send(pomodoro.snapshot());
+                    }
+                    Some(Signal::Checkpoint(reply)) => {
+                        let _ = reply.send(pomodoro.checkpoint());
+                    }
+                    Some(Signal::Abort) => break,
+                    None => listening = false,
+                },
+                _ = state.changed() => {
+                    pomodoro.settle();
+                    tick.as_mut().reset(Instant::now() + pomodoro.next_wakeup());
+                }
+                _ = &mut tick, if pomodoro.is_running() => {
+                    pomodoro.advance();
+                    tick.as_mut().reset(Instant::now() + pomodoro.next_wakeup());
                 }
             }
         }
-    });
-    tokio::join!(t);
-    Client { sender }
+        pomodoro.reset();
+        tracing::debug!("engine stopped");
+    };
+    let engine = tokio::spawn(engine.instrument(tracing::info_span!("engine")));
+    let runtime = Runtime { token, engine };
+    (runtime, Client { sender, events }, receiver_of_events)
+}
+
+/// Listens for SIGINT and SIGTERM, returning a future which resolves once either arrives.
+/// Registering up front means a signal sent before the future is first polled is not lost.
+#[cfg(unix)]
+pub fn terminated() -> io::Result<impl Future<Output = ()>> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    let mut terminate = signal(SignalKind::terminate())?;
+    Ok(async move {
+        tokio::select! {
+            _ = interrupt.recv() => {}
+            _ = terminate.recv() => {}
+        }
+    })
+}
+
+/// Listens for Ctrl-C, returning a future which resolves once it is pressed.
+#[cfg(not(unix))]
+pub fn terminated() -> io::Result<impl Future<Output = ()>> {
+    Ok(async {
+        let _ = tokio::signal::ctrl_c().await;
+    })
 }
 
+/// Owns the background task driving a pomodoro.
+pub struct Runtime {
+    token: CancellationToken,
+    engine: JoinHandle<()>,
+}
+
+impl Runtime {
+    /// Waits until the pomodoro is aborted through a [`Client`].
+    pub async fn join(self) -> Result<(), PomoError> {
+        self.engine.await?;
+        Ok(())
+    }
+
+    /// Stops the pomodoro and waits for the background task to finish.
+    pub async fn shutdown(self) -> Result<(), PomoError> {
+        self.token.cancel();
+        self.join().await
+    }
+}
+
+/// A handle which sends control signals to a running pomodoro.
+#[derive(Clone)]
 pub struct Client {
     sender: mpsc::Sender<Signal>,
+    events: broadcast::WeakSender<Event>,
 }
 
 impl Client {
-    async fn send_signal(&self, signal: Signal) {
-        self.sender.send(signal).await;
+    /// Subscribes to the [`Event`]s emitted by the pomodoro. The stream closes once the
+    /// pomodoro has stopped.
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        match self.events.upgrade() {
+            Some(events) => events.subscribe(),
+            None => broadcast::channel(1).1,
+        }
+    }
+
+    async fn send_signal(&self, signal: Signal) -> Result<(), PomoError> {
+        self.sender
+            .send(signal)
+            .await
+            .map_err(|_| PomoError::Stopped)
+    }
+
+    /// Stops the pomodoro, resetting it to the beginning of the first working phase.
+    pub async fn abort(&self) -> Result<(), PomoError> {
+        self.send_signal(Signal::Abort).await
+    }
+
+    /// Fetches a snapshot of the pomodoro.
+    pub async fn status(&self) -> Result<Status, PomoError> {
+        let (reply, response) = oneshot::channel();
+        self.send_signal(Signal::Status(reply)).await?;
+        response.await.map_err(|_| PomoError::Stopped)
+    }
+
+    /// Waits for the pomodoro to reach `until`, or fails once it stops before.
+    pub async fn wait(&self, until: Until) -> Result<(), PomoError> {
+        let mut events = self.subscribe();
+        loop {
+            match events.recv().await {
+                Ok(event) if until.is_reached_by(&event) => return Ok(()),
+                Ok(_) | Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => return Err(PomoError::Stopped),
+            }
+        }
+    }
+
+    /// Fetches a snapshot of the engine, as a checkpoint would save it.
+    pub async fn snapshot(&self) -> Result<PomodoroSnapshot, PomoError> {
+        let (reply, response) = oneshot::channel();
+        self.send_signal(Signal::Snapshot(reply)).await?;
+        response.await.map_err(|_| PomoError::Stopped)
+    }
+
+    /// Fetches what it takes to carry on with the pomodoro after a restart.
+    pub async fn checkpoint(&self) -> Result<Checkpoint, PomoError> {
+        let (reply, response) = oneshot::channel();
+        self.send_signal(Signal::Checkpoint(reply)).await?;
+        response.await.map_err(|_| PomoError::Stopped)
+    }
+
+    /// Pauses the pomodoro, unless it is strict and in a working phase.
+    pub async fn pause(&self) -> Result<(), PomoError> {
+        let (reply, response) = oneshot::channel();
+        self.send_signal(Signal::Pause(reply)).await?;
+        response.await.map_err(|_| PomoError::Stopped)?
+    }
+
+    /// Pauses a running working phase the user has been away from for `idle`, moving that
+    /// time from the phase into the pause. Other phases go on.
+    pub async fn pause_idle(&self, idle: Duration) -> Result<(), PomoError> {
+        let (reply, response) = oneshot::channel();
+        self.send_signal(Signal::PauseIdle(idle, reply)).await?;
+        response.await.map_err(|_| PomoError::Stopped)?
+    }
+
+    /// Resumes a paused pomodoro.
+    pub async fn resume(&self) -> Result<(), PomoError> {
+        self.send_signal(Signal::Resume).await
+    }
+
+    /// Pauses the running pomodoro or resumes the paused one, and tells how it is left.
+    /// Pausing is refused as [`pause`](Self::pause) is.
+    pub async fn toggle(&self) -> Result<Status, PomoError> {
+        let (reply, response) = oneshot::channel();
+        self.send_signal(Signal::Toggle(reply)).await?;
+        response.await.map_err(|_| PomoError::Stopped)?
+    }
+
+    /// Resumes the pomodoro, starting a new session from the first phase when the last one
+    /// is over.
+    pub async fn start_session(&self) -> Result<(), PomoError> {
+        self.send_signal(Signal::Start).await
+    }
+
+    /// Pauses the pomodoro on behalf of a schedule rather than the user, which neither
+    /// strict mode refuses nor counts as a pause.
+    pub async fn hold(&self) -> Result<(), PomoError> {
+        self.send_signal(Signal::Hold).await
     }
 
-    pub async fn abort(&self) {
-        self.send_signal(Signal::Abort).await;
+    /// Ends the current phase early and moves on to the next one.
+    pub async fn skip(&self) -> Result<(), PomoError> {
+        self.send_signal(Signal::Skip).await
     }
 
-    pub async fn pause(&self) {
-        self.send_signal(Signal::Pause).await;
+    /// Starts the current phase over from zero without changing the totals, which history
+    /// records as aborted.
+    pub async fn restart_phase(&self) -> Result<(), PomoError> {
+        self.send_signal(Signal::Restart).await
     }
 
-    pub async fn resume(&self) {
-        self.send_signal(Signal::Resume).await;
+    /// Starts the counters over without touching the current phase or the history.
+    pub async fn reset_counters(&self) -> Result<(), PomoError> {
+        self.send_signal(Signal::ResetCounters).await
     }
+
+    /// Abandons the current phase and moves on to `phase` right away, which history records
+    /// as aborted.
+    pub async fn goto(&self, phase: Phase) -> Result<(), PomoError> {
+        let (reply, response) = oneshot::channel();
+        self.send_signal(Signal::Goto(phase, reply)).await?;
+        response.await.map_err(|_| PomoError::Stopped)?
+    }
+
+    /// Moves on from a phase which ended waiting to be acknowledged, as pomodoros built
+    /// with [`ack`](crate::PomodoroBuilder::ack) do.
+    pub async fn ack(&self) -> Result<(), PomoError> {
+        let (reply, response) = oneshot::channel();
+        self.send_signal(Signal::Ack(reply)).await?;
+        response.await.map_err(|_| PomoError::Stopped)?
+    }
+
+    /// Abandons the running working phase for `reason`, which history records as voided,
+    /// and starts it over.
+    pub async fn void(&self, reason: impl Into<String>) -> Result<(), PomoError> {
+        let (reply, response) = oneshot::channel();
+        self.send_signal(Signal::Void(reason.into(), reply)).await?;
+        response.await.map_err(|_| PomoError::Stopped)?
+    }
+
+    /// Tags the pomodoros from now on with `task`, which history records with each phase.
+    /// An empty task clears it.
+    pub async fn set_task(&self, task: impl Into<String>) -> Result<(), PomoError> {
+        self.send_signal(Signal::SetTask(task.into())).await
+    }
+
+    /// Logs an interruption of the running working phase without stopping it, which history
+    /// attaches to the phase.
+    pub async fn interrupt(
+        &self,
+        kind: InterruptionKind,
+        note: impl Into<String>,
+    ) -> Result<(), PomoError> {
+        let (reply, response) = oneshot::channel();
+        self.send_signal(Signal::Interrupt(kind, note.into(), reply))
+            .await?;
+        response.await.map_err(|_| PomoError::Stopped)?
+    }
+
+    /// Lengthens the current phase by `by`.
+    pub async fn extend(&self, by: Duration) -> Result<(), PomoError> {
+        self.send_signal(Signal::Extend(by)).await
+    }
+
+    /// Changes the phase lengths and the long break interval without restarting, starting
+    /// with the next phase.
+    pub async fn set_durations(&self, durations: Durations) -> Result<(), PomoError> {
+        let (reply, response) = oneshot::channel();
+        self.send_signal(Signal::Reconfigure(durations, reply))
+            .await?;
+        response.await.map_err(|_| PomoError::Stopped)?
+    }
+
+    /// Takes on the phase lengths and long break interval of a changed `config` like
+    /// [`set_durations`](Self::set_durations), emitting [`Event::ConfigReloaded`].
+    pub async fn reload(&self, config: &Config) -> Result<(), PomoError> {
+        let (reply, response) = oneshot::channel();
+        self.send_signal(Signal::Reload(config.durations(), reply))
+            .await?;
+        response.await.map_err(|_| PomoError::Stopped)?
+    }
+}
+
+#[cfg(test)]
+fn clocks(lifespan: Duration) -> Pomodoro {
+    use crate::pomodoro::Clock;
+
+    let working = Clock::new(lifespan, Duration::from_micros(1));
+    let short_break = Clock::new(lifespan, Duration::from_micros(1));
+    let long_break = Clock::new(lifespan, Duration::from_micros(1));
+    Pomodoro::new(working, short_break, long_break, 2, true, Some(1))
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn start_returns_immediately() {
+    let (runtime, _client, mut events) = start(clocks(Duration::from_micros(3))).await;
+    loop {
+        if events.recv().await.unwrap() == Event::Completed {
+            break;
+        }
+    }
+    runtime.shutdown().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn abort_stops_engine() {
+    use tokio::time::timeout;
+
+    let (runtime, client, mut events) = start(clocks(Duration::from_secs(60))).await;
+    assert_eq!(events.recv().await.unwrap(), Event::Resumed);
+    client.abort().await.unwrap();
+    timeout(Duration::from_secs(1), runtime.join())
+        .await
+        .unwrap()
+        .unwrap();
 }
 
-// #[tokio::test(flavor = "multi_thread", worker_threads = 3)]
-// async fn test_main_loop() {
-//     use std::time::Duration;
-//
-//     use tokio::time::sleep;
-//
-//     use crate::pomodoro::Clock;
-//
-//     let working = Clock::new(Duration::from_micros(1), Duration::from_micros(1));
-//     let short_break = Clock::new(Duration::from_micros(1), Duration::from_micros(1));
-//     let long_break = Clock::new(Duration::from_micros(1), Duration::from_micros(1));
-//
-//     let pomodoro = Pomodoro::new(working, short_break, long_break, 3, true, None);
-//     let client= start(pomodoro).await;
-//     sleep(Duration::from_micros(7)).await;
-//     client.pause().await;
-//     sleep(Duration::from_micros(7)).await;
-//     client.resume().await;
-//     sleep(Duration::from_micros(7)).await;
-//     client.pause().await;
-// }
\ No newline at end of file
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn query_status() {
+    use crate::pomodoro::Phase;
+
+    let (runtime, client, _events) = start(clocks(Duration::from_secs(60))).await;
+    let status = client.status().await.unwrap();
+    assert_eq!(status.phase, Phase::Working);
+    assert_eq!(status.pomodoros, 0);
+    assert!(status.elapsed + status.remaining == Duration::from_secs(60));
+    assert!(!status.paused);
+    client.pause().await.unwrap();
+    assert!(client.status().await.unwrap().paused);
+    client.abort().await.unwrap();
+    runtime.join().await.unwrap();
+    assert!(matches!(client.status().await, Err(PomoError::Stopped)));
+    assert!(matches!(client.skip().await, Err(PomoError::Stopped)));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn toggle_pause() {
+    let (runtime, client, _events) = start(clocks(Duration::from_secs(60))).await;
+    assert!(client.toggle().await.unwrap().paused);
+    assert!(client.status().await.unwrap().paused);
+    assert!(!client.toggle().await.unwrap().paused);
+    assert!(!client.status().await.unwrap().paused);
+    runtime.shutdown().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn skip_phase() {
+    use crate::pomodoro::Phase;
+
+    let (runtime, client, _events) = start(clocks(Duration::from_secs(60))).await;
+    client.skip().await.unwrap();
+    let status = client.status().await.unwrap();
+    assert_eq!(status.phase, Phase::ShortBreak);
+    assert_eq!(status.pomodoros, 0);
+    runtime.shutdown().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn extend_phase_and_set_task() {
+    let (runtime, client, _events) = start(clocks(Duration::from_secs(60))).await;
+    client.extend(Duration::from_secs(300)).await.unwrap();
+    let status = client.status().await.unwrap();
+    assert!(status.remaining > Duration::from_secs(300));
+    client.set_task("write report").await.unwrap();
+    let status = client.status().await.unwrap();
+    assert_eq!(status.task.as_deref(), Some("write report"));
+    client.set_task("").await.unwrap();
+    assert_eq!(client.status().await.unwrap().task, None);
+    runtime.shutdown().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn set_durations_for_next_phase() {
+    use crate::pomodoro::Phase;
+
+    let (runtime, client, _events) = start(clocks(Duration::from_secs(60))).await;
+    let durations = Durations {
+        short_break: Some(Duration::from_secs(600)),
+        ..Durations::default()
+    };
+    client.set_durations(durations).await.unwrap();
+    client.skip().await.unwrap();
+    let status = client.status().await.unwrap();
+    assert_eq!(status.phase, Phase::ShortBreak);
+    assert!(status.remaining > Duration::from_secs(60));
+    let zero = Durations {
+        long_break: Some(Duration::ZERO),
+        ..Durations::default()
+    };
+    assert!(matches!(
+        client.set_durations(zero).await,
+        Err(PomoError::ZeroDuration("long_break"))
+    ));
+    runtime.shutdown().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn reload_config() {
+    let (runtime, client, mut events) = start(clocks(Duration::from_secs(60))).await;
+    let config: Config = toml::from_str(r#"work = "50m""#).unwrap();
+    client.reload(&config).await.unwrap();
+    while events.recv().await.unwrap() != Event::ConfigReloaded {}
+    client.skip().await.unwrap();
+    client.skip().await.unwrap();
+    let status = client.status().await.unwrap();
+    assert!(status.remaining > Duration::from_secs(49 * 60));
+    runtime.shutdown().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn events_close_with_engine() {
+    let (runtime, client, mut events) = start(clocks(Duration::from_secs(60))).await;
+    runtime.shutdown().await.unwrap();
+    while events.recv().await.is_ok() {}
+    assert!(client.subscribe().recv().await.is_err());
+}
+
+#[cfg(unix)]
+#[tokio::test(flavor = "current_thread")]
+async fn resolve_on_sigterm() {
+    let terminated = terminated().unwrap();
+    std::process::Command::new("kill")
+        .args(["-TERM", &std::process::id().to_string()])
+        .status()
+        .unwrap();
+    tokio::time::timeout(Duration::from_secs(5), terminated)
+        .await
+        .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn void_and_interrupt_phase() {
+    use crate::pomodoro::Phase;
+
+    let (runtime, client, mut events) = start(clocks(Duration::from_secs(60))).await;
+    client.void("meeting").await.unwrap();
+    loop {
+        if let Event::PhaseVoided(phase, reason) = events.recv().await.unwrap() {
+            assert_eq!((phase, reason.as_str()), (Phase::Working, "meeting"));
+            break;
+        }
+    }
+    client
+        .interrupt(InterruptionKind::External, "door")
+        .await
+        .unwrap();
+    client.skip().await.unwrap();
+    assert!(matches!(
+        client.void("meeting").await,
+        Err(PomoError::NotWorking)
+    ));
+    assert!(matches!(
+        client.interrupt(InterruptionKind::Internal, "").await,
+        Err(PomoError::NotWorking)
+    ));
+    runtime.shutdown().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn start_sessions_on_demand() {
+    let (runtime, client, mut events) = start_idle(clocks(Duration::from_millis(20))).await;
+    assert!(client.status().await.unwrap().paused);
+    client.start_session().await.unwrap();
+    while events.recv().await.unwrap() != Event::Completed {}
+    assert_eq!(client.status().await.unwrap().pomodoros, 1);
+    client.start_session().await.unwrap();
+    let status = client.status().await.unwrap();
+    assert!(!status.paused);
+    assert_eq!(status.pomodoros, 0);
+    client.hold().await.unwrap();
+    let status = client.status().await.unwrap();
+    assert!(status.paused);
+    assert_eq!(status.pauses, 0);
+    runtime.shutdown().await.unwrap();
+}
@@ -0,0 +1,216 @@
+//! Times at which a daemon starts working on its own, and quiet hours during which no phase
+//! starts.
+
+use std::convert::TryFrom;
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, Days, Local, NaiveTime, Weekday};
+use serde::Deserialize;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::sleep;
+
+use crate::error::PomoError;
+use crate::event::Event;
+use crate::runtime::Client;
+
+/// How long to sleep at most before looking at the wall clock again, which keeps on
+/// running while the machine is suspended.
+const RECHECK: Duration = Duration::from_secs(60);
+
+/// When to start a session, written as `"mon-fri 09:00"`, `"sat,sun 10:30"` or `"13:00"`
+/// for every day.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Start {
+    pub days: Vec<Weekday>,
+    pub at: NaiveTime,
+}
+
+fn parse_time(s: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(s, "%H:%M").map_err(|e| format!("invalid time {:?}: {}", s, e))
+}
+
+fn parse_day(s: &str) -> Result<Weekday, String> {
+    s.parse().map_err(|_| format!("unknown day: {}", s))
+}
+
+/// Reads `mon-fri` or `sat,sun` into the days they name.
+fn parse_days(s: &str) -> Result<Vec<Weekday>, String> {
+    let mut days = Vec::new();
+    for part in s.split(',') {
+        match part.split_once('-') {
+            Some((first, last)) => {
+                let (mut day, last) = (parse_day(first)?, parse_day(last)?);
+                days.push(day);
+                while day != last {
+                    day = day.succ();
+                    days.push(day);
+                }
+            }
+            None => days.push(parse_day(part)?),
+        }
+    }
+    Ok(days)
+}
+
+impl TryFrom<String> for Start {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        let (days, at) = match s.split_once(' ') {
+            Some((days, at)) => (parse_days(days)?, at.trim()),
+            None => (Vec::new(), s.as_str()),
+        };
+        let days = if days.is_empty() {
+            (0..7).filter_map(|n| Weekday::try_from(n).ok()).collect()
+        } else {
+            days
+        };
+        Ok(Self {
+            days,
+            at: parse_time(at)?,
+        })
+    }
+}
+
+/// A stretch of every day during which no phase starts, written as `"22:00-07:00"`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct QuietHours {
+    pub from: NaiveTime,
+    pub to: NaiveTime,
+}
+
+impl QuietHours {
+    /// Whether `time` falls within the quiet hours, which may run past midnight.
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.from <= self.to {
+            self.from <= time && time < self.to
+        } else {
+            time >= self.from || time < self.to
+        }
+    }
+}
+
+impl TryFrom<String> for QuietHours {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        let (from, to) = s
+            .split_once('-')
+            .ok_or_else(|| format!("quiet hours are written as from-to, got {:?}", s))?;
+        Ok(Self {
+            from: parse_time(from.trim())?,
+            to: parse_time(to.trim())?,
+        })
+    }
+}
+
+/// The daemon's schedule, read from the `[schedule]` table of the config file.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Schedule {
+    pub start: Vec<Start>,
+    pub quiet_hours: Option<QuietHours>,
+}
+
+impl Schedule {
+    pub fn is_empty(&self) -> bool {
+        self.start.is_empty() && self.quiet_hours.is_none()
+    }
+
+    pub fn is_quiet(&self, at: DateTime<Local>) -> bool {
+        self.quiet_hours
+            .is_some_and(|quiet| quiet.contains(at.time()))
+    }
+
+    /// The first start after `after` which does not fall within the quiet hours.
+    pub fn next_start(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        (0..=7)
+            .filter_map(|offset| after.date_naive().checked_add_days(Days::new(offset)))
+            .flat_map(|date| {
+                self.start
+                    .iter()
+                    .filter(move |start| start.days.contains(&date.weekday()))
+                    .filter_map(move |start| {
+                        date.and_time(start.at).and_local_timezone(Local).earliest()
+                    })
+            })
+            .filter(|at| *at > after && !self.is_quiet(*at))
+            .min()
+    }
+}
+
+/// Starts a session of the pomodoro behind `client` at every time `schedule` lists, and
+/// holds it whenever a phase starts during the quiet hours.
+pub async fn follow(schedule: Schedule, client: Client) {
+    let mut events = client.subscribe();
+    let mut next = schedule.next_start(Local::now());
+    loop {
+        let wait = next.map(|at| (at - Local::now()).to_std().unwrap_or_default());
+        let result = tokio::select! {
+            _ = sleep(wait.unwrap_or(RECHECK).min(RECHECK)), if wait.is_some() => {
+                let now = Local::now();
+                if next.is_some_and(|at| at > now) {
+                    continue;
+                }
+                next = schedule.next_start(now);
+                client.start_session().await
+            }
+            event = events.recv() => match event {
+                Ok(Event::PhaseStarted(_)) if schedule.is_quiet(Local::now()) => {
+                    client.hold().await
+                }
+                Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            },
+        };
+        if let Err(PomoError::Stopped) = result {
+            break;
+        }
+    }
+}
+
+#[test]
+fn parse_schedule() {
+    let schedule: Schedule = toml::from_str(
+        r#"
+        start = ["mon-wed,fri 09:00", "13:30"]
+        quiet_hours = "22:00-07:00"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(
+        schedule.start[0],
+        Start {
+            days: vec![Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Fri],
+            at: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+        }
+    );
+    assert_eq!(schedule.start[1].days.len(), 7);
+    let quiet = schedule.quiet_hours.unwrap();
+    assert!(quiet.contains(NaiveTime::from_hms_opt(23, 0, 0).unwrap()));
+    assert!(quiet.contains(NaiveTime::from_hms_opt(6, 59, 0).unwrap()));
+    assert!(!quiet.contains(NaiveTime::from_hms_opt(7, 0, 0).unwrap()));
+    assert!(toml::from_str::<Schedule>(r#"start = ["someday 09:00"]"#).is_err());
+    assert!(toml::from_str::<Schedule>(r#"quiet_hours = "22:00""#).is_err());
+}
+
+#[test]
+fn find_next_start() {
+    use chrono::TimeZone;
+
+    let schedule = Schedule {
+        start: vec![
+            Start::try_from("mon-fri 09:00".to_string()).unwrap(),
+            Start::try_from("23:00".to_string()).unwrap(),
+        ],
+        quiet_hours: Some(QuietHours::try_from("22:00-07:00".to_string()).unwrap()),
+    };
+    // 2024-01-05 is a Friday.
+    let at = |d, h, m| Local.with_ymd_and_hms(2024, 1, d, h, m, 0).unwrap();
+    assert_eq!(schedule.next_start(at(5, 8, 0)), Some(at(5, 9, 0)));
+    assert_eq!(schedule.next_start(at(5, 9, 0)), Some(at(8, 9, 0)));
+    assert!(schedule.is_quiet(at(5, 23, 0)));
+    assert_eq!(Schedule::default().next_start(at(5, 8, 0)), None);
+}
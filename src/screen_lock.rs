@@ -0,0 +1,144 @@
+//! Pauses working phases while the screen is locked, following the lock and unlock signals
+//! systemd-logind sends for the session over D-Bus.
+
+use serde::Deserialize;
+use tokio::sync::mpsc;
+
+use crate::error::PomoError;
+use crate::pomodoro::Phase;
+use crate::runtime::Client;
+
+/// Screen lock settings, read from the `[screen_lock]` table of the config file.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScreenLock {
+    /// Pause a running working phase when the screen locks.
+    pub pause: bool,
+    /// Resume on unlock what the lock paused.
+    pub resume: bool,
+}
+
+impl Default for ScreenLock {
+    fn default() -> Self {
+        Self {
+            pause: true,
+            resume: true,
+        }
+    }
+}
+
+/// Pauses and resumes the pomodoro behind `client` as `locks` reports the screen being
+/// locked (`true`) and unlocked (`false`). A pomodoro the user paused themselves is left
+/// paused on unlock.
+pub async fn follow(settings: ScreenLock, client: Client, mut locks: mpsc::Receiver<bool>) {
+    let mut paused_by_lock = false;
+    while let Some(locked) = locks.recv().await {
+        let result = if locked {
+            if !settings.pause || paused_by_lock {
+                continue;
+            }
+            match client.status().await {
+                Ok(status) if status.phase == Phase::Working && !status.paused => {
+                    let paused = client.pause().await;
+                    paused_by_lock = paused.is_ok();
+                    paused
+                }
+                Ok(_) => continue,
+                Err(e) => Err(e),
+            }
+        } else {
+            if !std::mem::take(&mut paused_by_lock) || !settings.resume {
+                continue;
+            }
+            client.resume().await
+        };
+        if let Err(PomoError::Stopped) = result {
+            break;
+        }
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "logind"))]
+#[zbus::proxy(
+    interface = "org.freedesktop.login1.Session",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1/session/auto"
+)]
+trait Session {
+    #[zbus(signal)]
+    fn lock(&self) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn unlock(&self) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn locked_hint(&self) -> zbus::Result<bool>;
+}
+
+/// Listens to logind on the system bus for the lock state of the current session, sending
+/// every change into `locks`.
+#[cfg(all(target_os = "linux", feature = "logind"))]
+async fn listen(locks: mpsc::Sender<bool>) -> zbus::Result<()> {
+    use futures_util::StreamExt;
+
+    let connection = zbus::Connection::system().await?;
+    let session = SessionProxy::new(&connection).await?;
+    let mut lock = session.receive_lock().await?;
+    let mut unlock = session.receive_unlock().await?;
+    // Screen lockers which do not go through logind still set the hint.
+    let mut hint = session.receive_locked_hint_changed().await;
+    loop {
+        let locked = tokio::select! {
+            Some(_) = lock.next() => true,
+            Some(_) = unlock.next() => false,
+            Some(changed) = hint.next() => changed.get().await?,
+            else => return Ok(()),
+        };
+        if locks.send(locked).await.is_err() {
+            return Ok(());
+        }
+    }
+}
+
+/// Spawns a task pausing the pomodoro behind `client` while the screen is locked.
+#[cfg(all(target_os = "linux", feature = "logind"))]
+pub fn spawn(settings: ScreenLock, client: Client) -> tokio::task::JoinHandle<()> {
+    let (sender, receiver) = mpsc::channel(4);
+    tokio::spawn(async move {
+        tokio::spawn(async move {
+            if let Err(e) = listen(sender).await {
+                tracing::warn!(error = %e, "cannot follow the screen lock");
+            }
+        });
+        follow(settings, client, receiver).await
+    })
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn pause_while_locked() {
+    use std::time::Duration;
+
+    let pomodoro = crate::Pomodoro::builder().build().unwrap();
+    let (runtime, client, _events) = crate::start(pomodoro).await;
+    let (locks, receiver) = mpsc::channel(4);
+    let follower = tokio::spawn(follow(ScreenLock::default(), client.clone(), receiver));
+    let paused = || async { client.status().await.unwrap().paused };
+
+    locks.send(true).await.unwrap();
+    locks.send(true).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(paused().await);
+    locks.send(false).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!paused().await);
+
+    client.pause().await.unwrap();
+    locks.send(true).await.unwrap();
+    locks.send(false).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(paused().await);
+
+    drop(locks);
+    follower.await.unwrap();
+    runtime.shutdown().await.unwrap();
+}
@@ -0,0 +1,106 @@
+//! POSIX signals controlling the running process, so a window manager key binding can run
+//! `pkill -USR1 pomo` instead of the socket client. Configured under `[signals]`.
+
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::error::PomoError;
+use crate::runtime::Client;
+
+/// What a signal does to the pomodoro.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignalAction {
+    /// Leave the signal alone, so it terminates the process as usual.
+    Off,
+    TogglePause,
+    Pause,
+    Resume,
+    Skip,
+    /// Add the configured `extend_step` to the running phase.
+    Extend,
+    Void,
+}
+
+impl SignalAction {
+    /// Carries the action out on the pomodoro behind `client`.
+    pub async fn apply(self, client: &Client, extend_step: Duration) -> Result<(), PomoError> {
+        match self {
+            SignalAction::Off => Ok(()),
+            SignalAction::TogglePause => client.toggle().await.map(|_| ()),
+            SignalAction::Pause => client.pause().await,
+            SignalAction::Resume => client.resume().await,
+            SignalAction::Skip => client.skip().await,
+            SignalAction::Extend => client.extend(extend_step).await,
+            SignalAction::Void => client.void("").await,
+        }
+    }
+}
+
+/// The action of each signal, read from the `[signals]` table of the config file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Signals {
+    pub usr1: SignalAction,
+    pub usr2: SignalAction,
+}
+
+impl Default for Signals {
+    fn default() -> Self {
+        Self {
+            usr1: SignalAction::TogglePause,
+            usr2: SignalAction::Skip,
+        }
+    }
+}
+
+/// Spawns a task applying the configured action to the pomodoro behind `client` whenever
+/// the process receives SIGUSR1 or SIGUSR2. The handlers are installed before this returns.
+#[cfg(unix)]
+pub fn spawn(
+    signals: Signals,
+    client: Client,
+    extend_step: Duration,
+) -> std::io::Result<tokio::task::JoinHandle<()>> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let listen = |action, kind| match action {
+        SignalAction::Off => Ok(None),
+        _ => signal(kind).map(Some),
+    };
+    let mut usr1 = listen(signals.usr1, SignalKind::user_defined1())?;
+    let mut usr2 = listen(signals.usr2, SignalKind::user_defined2())?;
+    Ok(tokio::spawn(async move {
+        loop {
+            let action = tokio::select! {
+                Some(_) = async { usr1.as_mut()?.recv().await } => signals.usr1,
+                Some(_) = async { usr2.as_mut()?.recv().await } => signals.usr2,
+                else => break,
+            };
+            match action.apply(&client, extend_step).await {
+                Err(PomoError::Stopped) => break,
+                Err(e) => tracing::warn!(error = %e, "cannot act on the signal"),
+                Ok(()) => {}
+            }
+        }
+    }))
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn apply_signal_actions() {
+    let signals: Signals = toml::from_str(r#"usr2 = "extend""#).unwrap();
+    assert_eq!(signals.usr1, SignalAction::TogglePause);
+    assert!(toml::from_str::<Signals>(r#"usr1 = "explode""#).is_err());
+
+    let pomodoro = crate::Pomodoro::builder().build().unwrap();
+    let (runtime, client, _events) = crate::start(pomodoro).await;
+    let step = Duration::from_secs(60);
+    signals.usr1.apply(&client, step).await.unwrap();
+    assert!(client.status().await.unwrap().paused);
+    signals.usr2.apply(&client, step).await.unwrap();
+    assert!(client.status().await.unwrap().remaining > Duration::from_secs(25 * 60));
+    signals.usr1.apply(&client, step).await.unwrap();
+    assert!(!client.status().await.unwrap().paused);
+    runtime.shutdown().await.unwrap();
+}
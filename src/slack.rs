@@ -0,0 +1,299 @@
+//! Setting the Slack status while working, configured under `[slack]` in the config file.
+//!
+//! A working phase sets the status to the configured emoji and text, e.g. "Focusing until
+//! 14:25", and snoozes notifications until it ends. Breaks, pauses and the end of the
+//! session clear both again. The token needs the `users.profile:write` and `dnd:write`
+//! user scopes.
+
+use chrono::{DateTime, Local};
+use serde::Deserialize;
+
+use crate::event::Event;
+use crate::pomodoro::{Labels, Phase, Status};
+use crate::template::{self, Values};
+
+/// The Slack settings, read from the `[slack]` table of the config file.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Slack {
+    /// A user token, starting with `xoxp-`.
+    pub token: String,
+    #[serde(default = "tomato")]
+    pub emoji: String,
+    /// The status text, a [`template`](crate::template) in which `{ends_at}` is the end of
+    /// the phase. The `{{until}}` of older configs still stands for it, but is deprecated.
+    #[serde(default = "focusing")]
+    pub text: String,
+    /// Snooze notifications while working.
+    #[serde(default = "yes")]
+    pub dnd: bool,
+}
+
+fn tomato() -> String {
+    ":tomato:".to_string()
+}
+
+fn focusing() -> String {
+    "Focusing until {ends_at}".to_string()
+}
+
+fn yes() -> bool {
+    true
+}
+
+/// A change to make to the Slack status.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Update {
+    Focus {
+        text: String,
+        emoji: String,
+        until: DateTime<Local>,
+    },
+    Clear,
+}
+
+impl Slack {
+    /// Whether the text uses the deprecated `{{until}}`.
+    pub fn deprecated(&self) -> bool {
+        self.text.contains("{{until}}")
+    }
+
+    /// The change `event` calls for, with `status` taken right after it at `now`.
+    pub fn update(
+        &self,
+        event: &Event,
+        status: &Status,
+        labels: &Labels,
+        now: DateTime<Local>,
+    ) -> Option<Update> {
+        match event {
+            Event::PhaseStarted(Phase::Working) | Event::Resumed
+                if status.phase == Phase::Working && !status.paused =>
+            {
+                let until = now + chrono::Duration::from_std(status.remaining).ok()?;
+                let values = Values {
+                    ends_at: Some(until),
+                    ..Values::of(status, labels)
+                };
+                Some(Update::Focus {
+                    text: template::render(&self.text.replace("{{until}}", "{ends_at}"), &values),
+                    emoji: self.emoji.clone(),
+                    until,
+                })
+            }
+            Event::PhaseStarted(_) | Event::Paused | Event::Completed => Some(Update::Clear),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "slack")]
+mod api {
+    use std::time::Duration;
+
+    use chrono::Local;
+    use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
+    use serde_json::json;
+    use tokio::sync::broadcast::{self, error::RecvError};
+    use tokio::task::JoinHandle;
+
+    use super::{Slack, Update};
+    use crate::event::Event;
+    use crate::pomodoro::Labels;
+    use crate::runtime::Client;
+
+    const API: &str = "https://slack.com/api";
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Calls the Web API `method` with a `body` of the content `kind`, failing when Slack
+    /// answers anything but `"ok": true`.
+    async fn call(
+        http: &reqwest::Client,
+        token: &str,
+        method: &str,
+        kind: &str,
+        body: String,
+    ) -> Result<(), String> {
+        let response = http
+            .post(format!("{}/{}", API, method))
+            .timeout(TIMEOUT)
+            .header(AUTHORIZATION, format!("Bearer {}", token))
+            .header(CONTENT_TYPE, kind)
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        let text = response
+            .error_for_status()
+            .map_err(|e| e.to_string())?
+            .text()
+            .await
+            .map_err(|e| e.to_string())?;
+        let answer: serde_json::Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+        match answer["ok"].as_bool() {
+            Some(true) => Ok(()),
+            _ => Err(answer["error"]
+                .as_str()
+                .unwrap_or("no reason given")
+                .to_string()),
+        }
+    }
+
+    async fn apply(http: &reqwest::Client, slack: &Slack, update: &Update) -> Result<(), String> {
+        let (profile, snooze) = match update {
+            Update::Focus { text, emoji, until } => {
+                let minutes = (*until - Local::now()).num_seconds().max(0) as u64;
+                let profile = json!({
+                    "status_text": text,
+                    "status_emoji": emoji,
+                    "status_expiration": until.timestamp(),
+                });
+                (profile, Some(minutes.div_ceil(60).max(1)))
+            }
+            Update::Clear => {
+                let profile = json!({
+                    "status_text": "",
+                    "status_emoji": "",
+                    "status_expiration": 0,
+                });
+                (profile, None)
+            }
+        };
+        let body = json!({ "profile": profile }).to_string();
+        let json = "application/json; charset=utf-8";
+        call(http, &slack.token, "users.profile.set", json, body).await?;
+        if !slack.dnd {
+            return Ok(());
+        }
+        let form = "application/x-www-form-urlencoded";
+        match snooze {
+            Some(minutes) => {
+                let body = format!("num_minutes={}", minutes);
+                call(http, &slack.token, "dnd.setSnooze", form, body).await
+            }
+            None => {
+                let ended = call(http, &slack.token, "dnd.endSnooze", form, String::new());
+                match ended.await {
+                    // Nothing was snoozed, as when the phase ran out on its own.
+                    Err(e) if e == "snooze_not_active" => Ok(()),
+                    ended => ended,
+                }
+            }
+        }
+    }
+
+    /// Spawns a task keeping the Slack status in line with the pomodoro behind `client`,
+    /// and clearing it once the pomodoro stops.
+    pub fn spawn(
+        slack: Slack,
+        labels: Labels,
+        mut events: broadcast::Receiver<Event>,
+        client: Client,
+    ) -> JoinHandle<()> {
+        if slack.deprecated() {
+            tracing::warn!(
+                "the {{{{until}}}} of the Slack text is deprecated, write {{ends_at}} instead"
+            );
+        }
+        let http = reqwest::Client::new();
+        tokio::spawn(async move {
+            let mut focused = false;
+            loop {
+                let event = match events.recv().await {
+                    Ok(Event::Tick { .. }) | Err(RecvError::Lagged(_)) => continue,
+                    Ok(event) => event,
+                    Err(RecvError::Closed) => break,
+                };
+                let status = match client.status().await {
+                    Ok(status) => status,
+                    Err(_) => break,
+                };
+                let update = match slack.update(&event, &status, &labels, Local::now()) {
+                    Some(Update::Clear) if !focused => continue,
+                    Some(update) => update,
+                    None => continue,
+                };
+                focused = matches!(update, Update::Focus { .. });
+                if let Err(e) = apply(&http, &slack, &update).await {
+                    tracing::warn!(error = %e, "cannot set the Slack status");
+                }
+            }
+            if focused {
+                if let Err(e) = apply(&http, &slack, &Update::Clear).await {
+                    tracing::warn!(error = %e, "cannot clear the Slack status");
+                }
+            }
+        })
+    }
+}
+
+#[cfg(feature = "slack")]
+pub use api::spawn;
+
+#[test]
+fn follow_working_phases() {
+    use chrono::TimeZone;
+    use std::time::Duration;
+
+    let slack: Slack = toml::from_str(r#"token = "xoxp-1""#).unwrap();
+    let status = Status {
+        phase: Phase::Working,
+        elapsed: Duration::from_secs(0),
+        remaining: Duration::from_secs(25 * 60),
+        pomodoros: 0,
+        short_breaks: 0,
+        long_breaks: 0,
+        paused: false,
+        focused: Duration::from_secs(0),
+        on_break: Duration::from_secs(0),
+        paused_for: Duration::from_secs(0),
+        pauses: 0,
+        label: None,
+        task: None,
+        goal: None,
+        awaiting_ack: false,
+        overtime: Duration::from_secs(0),
+        ends_at: None,
+        session_ends_at: None,
+    };
+    let labels = Labels::default();
+    let now = Local.with_ymd_and_hms(2024, 1, 2, 14, 0, 0).unwrap();
+    assert_eq!(
+        slack.update(&Event::PhaseStarted(Phase::Working), &status, &labels, now),
+        Some(Update::Focus {
+            text: "Focusing until 14:25".to_string(),
+            emoji: ":tomato:".to_string(),
+            until: now + chrono::Duration::minutes(25),
+        })
+    );
+    let on_break = Status {
+        phase: Phase::ShortBreak,
+        ..status.clone()
+    };
+    assert_eq!(
+        slack.update(
+            &Event::PhaseStarted(Phase::ShortBreak),
+            &on_break,
+            &labels,
+            now
+        ),
+        Some(Update::Clear)
+    );
+    assert_eq!(slack.update(&Event::Resumed, &on_break, &labels, now), None);
+    let paused = Status {
+        paused: true,
+        ..status.clone()
+    };
+    assert_eq!(
+        slack.update(&Event::Paused, &paused, &labels, now),
+        Some(Update::Clear)
+    );
+    let older = Slack {
+        text: "{phase} until {{until}}".to_string(),
+        ..slack
+    };
+    assert!(older.deprecated());
+    let focus = older.update(&Event::Resumed, &status, &labels, now);
+    assert!(matches!(focus, Some(Update::Focus { text, .. }) if text == "working until 14:25"));
+    assert!(toml::from_str::<Slack>("dnd = false").is_err());
+}
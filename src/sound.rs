@@ -0,0 +1,134 @@
+//! Audible alerts played when a phase ends.
+
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use serde::Deserialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+
+use crate::event::Event;
+use crate::quiet::Quiet;
+
+/// Sound settings, read from the `[sound]` table of the config file.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Sound {
+    /// The audio file to play. The built-in chime is played when unset.
+    pub file: Option<PathBuf>,
+    /// Playback volume in percent.
+    pub volume: u8,
+    pub mute: bool,
+}
+
+impl Default for Sound {
+    fn default() -> Self {
+        Self {
+            file: None,
+            volume: 100,
+            mute: false,
+        }
+    }
+}
+
+/// Something able to play a [`Sound`].
+pub trait Player: Send + Sync + 'static {
+    fn play(&self, sound: &Sound) -> Result<(), Box<dyn Error + Send + Sync>>;
+}
+
+/// Plays sounds on the default audio output device.
+#[cfg(feature = "sound")]
+pub struct Speaker;
+
+#[cfg(feature = "sound")]
+impl Player for Speaker {
+    fn play(&self, sound: &Sound) -> Result<(), Box<dyn Error + Send + Sync>> {
+        use rodio::source::{SineWave, Source};
+        use std::time::Duration;
+
+        let mut output = rodio::DeviceSinkBuilder::open_default_sink()?;
+        output.log_on_drop(false);
+        let player = rodio::Player::connect_new(output.mixer());
+        player.set_volume(f32::from(sound.volume) / 100.0);
+        match &sound.file {
+            Some(path) => player.append(rodio::Decoder::new(std::io::BufReader::new(
+                std::fs::File::open(path)?,
+            ))?),
+            None => {
+                for frequency in [880.0, 660.0] {
+                    let tone = SineWave::new(frequency)
+                        .take_duration(Duration::from_millis(250))
+                        .amplify(0.5);
+                    player.append(tone);
+                }
+            }
+        }
+        player.sleep_until_end();
+        Ok(())
+    }
+}
+
+/// Spawns a task which plays the sound whenever a phase ends, unless it is muted or
+/// `quiet`.
+pub fn spawn<P: Player>(
+    player: P,
+    sound: Sound,
+    quiet: Quiet,
+    mut events: broadcast::Receiver<Event>,
+) -> JoinHandle<()> {
+    let player = Arc::new(player);
+    let sound = Arc::new(sound);
+    tokio::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(Event::PhaseEnded(_)) if !sound.mute => {
+                    if quiet.is_quiet().await {
+                        continue;
+                    }
+                    let player = player.clone();
+                    let sound = sound.clone();
+                    let played = tokio::task::spawn_blocking(move || player.play(&sound)).await;
+                    if let Ok(Err(e)) = played {
+                        tracing::warn!(error = %e, "failed to play a sound");
+                    }
+                }
+                Ok(_) | Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+struct Counter(std::sync::atomic::AtomicUsize);
+
+#[cfg(test)]
+impl Player for Arc<Counter> {
+    fn play(&self, _: &Sound) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn play_on_phase_end() {
+    use crate::pomodoro::Phase;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    for (mute, expected) in [(false, 2), (true, 0)] {
+        let counter = Arc::new(Counter(AtomicUsize::new(0)));
+        let sound = Sound {
+            mute,
+            ..Sound::default()
+        };
+        let (sender, receiver) = broadcast::channel(8);
+        let handle = spawn(counter.clone(), sound, Quiet::default(), receiver);
+        sender.send(Event::PhaseStarted(Phase::Working)).unwrap();
+        sender.send(Event::PhaseEnded(Phase::Working)).unwrap();
+        sender.send(Event::PhaseEnded(Phase::ShortBreak)).unwrap();
+        drop(sender);
+        handle.await.unwrap();
+        assert_eq!(counter.0.load(Ordering::SeqCst), expected);
+    }
+}
@@ -0,0 +1,215 @@
+//! Spoken announcements of the phase changes, configured under `[speech]` in the config
+//! file.
+//!
+//! The announcements are piped to a text-to-speech command: `say` on macOS, `espeak` or
+//! the `espeak` of espeak-ng elsewhere, and the speech synthesizer of .NET through
+//! PowerShell on Windows. What is said is written with the placeholders of
+//! [`template`](crate::template).
+
+use std::error::Error;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+
+use serde::Deserialize;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+use crate::event::Event;
+use crate::pomodoro::{Labels, Phase};
+use crate::quiet::Quiet;
+use crate::runtime::Client;
+use crate::template::{self, Values};
+
+/// The speech settings, read from the `[speech]` table of the config file. An empty text
+/// says nothing.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Speech {
+    /// The command reading the text to say on its standard input, instead of the platform's
+    /// own, e.g. `["espeak-ng", "-v", "en-gb"]`.
+    pub command: Vec<String>,
+    pub work_start: String,
+    pub work_end: String,
+    pub break_start: String,
+    pub break_end: String,
+    pub goal_reached: String,
+}
+
+impl Default for Speech {
+    fn default() -> Self {
+        Self {
+            command: Vec::new(),
+            work_start: "Back to work, {count} pomodoros done".to_string(),
+            work_end: String::new(),
+            break_start: "Break time".to_string(),
+            break_end: String::new(),
+            goal_reached: "Daily goal reached".to_string(),
+        }
+    }
+}
+
+impl Speech {
+    fn for_event(&self, event: &Event) -> Option<&str> {
+        let text = match event {
+            Event::PhaseStarted(Phase::Working) => &self.work_start,
+            Event::PhaseStarted(_) => &self.break_start,
+            Event::PhaseEnded(Phase::Working) => &self.work_end,
+            Event::PhaseEnded(_) => &self.break_end,
+            Event::GoalReached(_) => &self.goal_reached,
+            _ => return None,
+        };
+        Some(text.as_str()).filter(|text| !text.is_empty())
+    }
+}
+
+/// Something able to say a text out loud.
+pub trait Voice: Send + Sync + 'static {
+    fn say(&self, text: &str) -> Result<(), Box<dyn Error + Send + Sync>>;
+}
+
+/// A command saying what it reads on its standard input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpeechCommand {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl SpeechCommand {
+    /// The command `settings` name, or else the platform's own one.
+    pub fn new(settings: &Speech) -> Self {
+        if let Some((program, args)) = settings.command.split_first() {
+            return Self {
+                program: program.clone(),
+                args: args.to_vec(),
+            };
+        }
+        let (program, args): (&str, &[&str]) = if cfg!(target_os = "macos") {
+            ("say", &[])
+        } else if cfg!(windows) {
+            (
+                "powershell",
+                &[
+                    "-NoProfile",
+                    "-Command",
+                    "Add-Type -AssemblyName System.Speech; \
+                     (New-Object System.Speech.Synthesis.SpeechSynthesizer)\
+                     .Speak([Console]::In.ReadToEnd())",
+                ],
+            )
+        } else {
+            ("espeak", &[])
+        };
+        Self {
+            program: program.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        }
+    }
+}
+
+impl Voice for SpeechCommand {
+    fn say(&self, text: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(format!("{} failed with {}", self.program, status).into());
+        }
+        Ok(())
+    }
+}
+
+/// Spawns a task which has `voice` announce the phase changes of the pomodoro behind
+/// `client`, unless it is `quiet`.
+pub fn spawn<V: Voice>(
+    voice: V,
+    speech: Speech,
+    labels: Labels,
+    quiet: Quiet,
+    client: Client,
+) -> JoinHandle<()> {
+    let voice = Arc::new(voice);
+    let mut events = client.subscribe();
+    tokio::spawn(async move {
+        loop {
+            let event = match events.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            };
+            let text = match speech.for_event(&event) {
+                Some(text) => text,
+                None => continue,
+            };
+            if quiet.is_quiet().await {
+                continue;
+            }
+            let status = match client.status().await {
+                Ok(status) => status,
+                Err(_) => break,
+            };
+            let text = template::render(text, &Values::at(&event, Some(&status), &labels));
+            let voice = voice.clone();
+            let said = tokio::task::spawn_blocking(move || voice.say(&text)).await;
+            if let Ok(Err(e)) = said {
+                tracing::warn!(error = %e, "failed to announce a phase");
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+struct Transcript(std::sync::Mutex<Vec<String>>);
+
+#[cfg(test)]
+impl Voice for Arc<Transcript> {
+    fn say(&self, text: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.0.lock().unwrap().push(text.to_string());
+        Ok(())
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn announce_phases() {
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    let pomodoro = crate::Pomodoro::builder()
+        .work(Duration::from_millis(50))
+        .short_break(Duration::from_millis(50))
+        .continuous(true)
+        .build()
+        .unwrap();
+    let (runtime, client, _events) = crate::start_idle(pomodoro).await;
+    let transcript = Arc::new(Transcript(Mutex::new(Vec::new())));
+    let speech = Speech {
+        work_end: "{phase} is over".to_string(),
+        ..Speech::default()
+    };
+    let announcer = spawn(
+        transcript.clone(),
+        speech,
+        Labels::default(),
+        Quiet::default(),
+        client.clone(),
+    );
+    client.start_session().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(130)).await;
+    runtime.shutdown().await.unwrap();
+    announcer.await.unwrap();
+    let said = transcript.0.lock().unwrap().clone();
+    assert_eq!(
+        said[..3],
+        [
+            "Back to work, 0 pomodoros done",
+            "working is over",
+            "Break time"
+        ]
+    );
+}
@@ -0,0 +1,449 @@
+//! Summaries computed from the [`history`](crate::history).
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate, Timelike};
+
+use crate::event::InterruptionKind;
+use crate::history::{Outcome, Record};
+use crate::pomodoro::Phase;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Stats {
+    /// Pomodoros completed today.
+    pub today: usize,
+    /// Pomodoros completed since Monday.
+    pub this_week: usize,
+    /// Time spent in completed working phases.
+    pub focused: Duration,
+    /// The most consecutive days on which at least one pomodoro was completed.
+    pub longest_streak: usize,
+    /// The average length of a completed working phase.
+    pub average_session: Duration,
+    /// How long completed working phases went on before a break was started.
+    pub overtime: Duration,
+    /// Interruptions logged during any working phase.
+    pub internal_interruptions: usize,
+    pub external_interruptions: usize,
+}
+
+impl Stats {
+    pub fn from_records(records: &[Record], now: DateTime<Local>) -> Self {
+        let pomodoros: Vec<&Record> = records.iter().filter(|r| is_pomodoro(r)).collect();
+        let today = now.date_naive();
+        let monday = today - Days::new(u64::from(today.weekday().num_days_from_monday()));
+        let focused: Duration = pomodoros.iter().map(|r| length(r)).sum();
+        let average_session = if pomodoros.is_empty() {
+            Duration::from_secs(0)
+        } else {
+            focused / pomodoros.len() as u32
+        };
+        let interruptions = |kind| {
+            records
+                .iter()
+                .flat_map(|r| &r.interruptions)
+                .filter(|i| i.kind == kind)
+                .count()
+        };
+        Self {
+            today: pomodoros.iter().filter(|r| day(r) == today).count(),
+            this_week: pomodoros
+                .iter()
+                .filter(|r| day(r) >= monday && day(r) <= today)
+                .count(),
+            focused,
+            longest_streak: longest_streak(pomodoros.iter().map(|r| day(r)).collect()),
+            average_session,
+            overtime: pomodoros.iter().map(|r| r.overtime).sum(),
+            internal_interruptions: interruptions(InterruptionKind::Internal),
+            external_interruptions: interruptions(InterruptionKind::External),
+        }
+    }
+}
+
+/// What the completed pomodoros spent on one task add up to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TaskStats {
+    /// `None` for the pomodoros which were not tagged with a task.
+    pub task: Option<String>,
+    pub pomodoros: usize,
+    pub focused: Duration,
+}
+
+impl TaskStats {
+    /// Groups the completed pomodoros by task, the one focused on the longest first.
+    pub fn from_records(records: &[Record]) -> Vec<Self> {
+        let mut tasks: Vec<Self> = Vec::new();
+        for record in records.iter().filter(|r| is_pomodoro(r)) {
+            let index = match tasks.iter().position(|t| t.task == record.task) {
+                Some(index) => index,
+                None => {
+                    tasks.push(Self {
+                        task: record.task.clone(),
+                        pomodoros: 0,
+                        focused: Duration::from_secs(0),
+                    });
+                    tasks.len() - 1
+                }
+            };
+            tasks[index].pomodoros += 1;
+            tasks[index].focused += length(record);
+        }
+        tasks.sort_by(|a, b| b.focused.cmp(&a.focused).then_with(|| a.task.cmp(&b.task)));
+        tasks
+    }
+}
+
+/// The stretch of days a [`Report`] covers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Period {
+    /// Today.
+    Day,
+    /// Monday through today.
+    Week,
+}
+
+impl FromStr for Period {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "day" => Ok(Period::Day),
+            "week" => Ok(Period::Week),
+            _ => Err(format!("unknown period: {}", s)),
+        }
+    }
+}
+
+/// The completed pomodoros of one day.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DayStats {
+    pub day: NaiveDate,
+    pub pomodoros: usize,
+    pub focused: Duration,
+}
+
+/// A summary of a day or a week, for reading rather than for scripts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Report {
+    pub period: Period,
+    /// Every day of the period, oldest first, including those without pomodoros.
+    pub days: Vec<DayStats>,
+    /// The tasks of the period, the one focused on the longest first.
+    pub tasks: Vec<TaskStats>,
+    pub internal_interruptions: usize,
+    pub external_interruptions: usize,
+}
+
+impl Report {
+    pub fn from_records(records: &[Record], period: Period, now: DateTime<Local>) -> Self {
+        let today = now.date_naive();
+        let first = match period {
+            Period::Day => today,
+            Period::Week => today - Days::new(u64::from(today.weekday().num_days_from_monday())),
+        };
+        let records: Vec<Record> = records
+            .iter()
+            .filter(|r| day(r) >= first && day(r) <= today)
+            .cloned()
+            .collect();
+        let days = first
+            .iter_days()
+            .take_while(|d| *d <= today)
+            .map(|d| {
+                let pomodoros: Vec<&Record> = records
+                    .iter()
+                    .filter(|r| is_pomodoro(r) && day(r) == d)
+                    .collect();
+                DayStats {
+                    day: d,
+                    pomodoros: pomodoros.len(),
+                    focused: pomodoros.iter().map(|r| length(r)).sum(),
+                }
+            })
+            .collect();
+        let interruptions = |kind| {
+            records
+                .iter()
+                .flat_map(|r| &r.interruptions)
+                .filter(|i| i.kind == kind)
+                .count()
+        };
+        Self {
+            period,
+            days,
+            tasks: TaskStats::from_records(&records),
+            internal_interruptions: interruptions(InterruptionKind::Internal),
+            external_interruptions: interruptions(InterruptionKind::External),
+        }
+    }
+
+    pub fn pomodoros(&self) -> usize {
+        self.days.iter().map(|d| d.pomodoros).sum()
+    }
+
+    pub fn focused(&self) -> Duration {
+        self.days.iter().map(|d| d.focused).sum()
+    }
+}
+
+/// Completed pomodoros on each of the last `days` days through today, oldest first.
+pub fn daily_counts(records: &[Record], days: usize, now: DateTime<Local>) -> Vec<usize> {
+    let today = now.date_naive();
+    let first = today - Days::new(days.saturating_sub(1) as u64);
+    let mut counts = vec![0; days];
+    for record in records.iter().filter(|r| is_pomodoro(r)) {
+        let offset = (day(record) - first).num_days();
+        if offset < 0 {
+            continue;
+        }
+        if let Some(count) = counts.get_mut(offset as usize) {
+            *count += 1;
+        }
+    }
+    counts
+}
+
+/// Completed pomodoros of the last `days` days through today by the weekday, Monday first,
+/// and the hour they were started in.
+pub fn heatmap(records: &[Record], days: usize, now: DateTime<Local>) -> [[usize; 24]; 7] {
+    let first = now.date_naive() - Days::new(days.saturating_sub(1) as u64);
+    let mut grid = [[0; 24]; 7];
+    for record in records.iter().filter(|r| is_pomodoro(r) && day(r) >= first) {
+        let started_at = record.started_at;
+        let weekday = started_at.weekday().num_days_from_monday() as usize;
+        grid[weekday][started_at.hour() as usize] += 1;
+    }
+    grid
+}
+
+fn is_pomodoro(record: &Record) -> bool {
+    record.phase == Phase::Working && record.outcome == Outcome::Completed
+}
+
+fn day(record: &Record) -> NaiveDate {
+    record.ended_at.date_naive()
+}
+
+fn length(record: &Record) -> Duration {
+    (record.ended_at - record.started_at)
+        .to_std()
+        .unwrap_or_default()
+}
+
+fn longest_streak(mut days: Vec<NaiveDate>) -> usize {
+    days.sort();
+    days.dedup();
+    let mut longest = 0;
+    let mut current = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for day in days {
+        current = match previous {
+            Some(p) if p.succ_opt() == Some(day) => current + 1,
+            _ => 1,
+        };
+        longest = longest.max(current);
+        previous = Some(day);
+    }
+    longest
+}
+
+#[test]
+fn summarize_records() {
+    use crate::history::Interruption;
+    use chrono::TimeZone;
+
+    let record = |day, hour, minutes, phase, outcome| {
+        let started_at = Local.with_ymd_and_hms(2024, 1, day, hour, 0, 0).unwrap();
+        Record {
+            phase,
+            outcome,
+            started_at,
+            ended_at: started_at + chrono::Duration::minutes(minutes),
+            paused: Duration::from_secs(0),
+            pauses: 0,
+            reason: None,
+            task: None,
+            interruptions: Vec::new(),
+            overtime: Duration::from_secs(0),
+        }
+    };
+    let mut records = vec![
+        record(1, 9, 25, Phase::Working, Outcome::Completed),
+        record(3, 9, 25, Phase::Working, Outcome::Completed),
+        record(4, 9, 20, Phase::Working, Outcome::Completed),
+        record(4, 10, 5, Phase::ShortBreak, Outcome::Completed),
+        record(5, 9, 30, Phase::Working, Outcome::Completed),
+        record(5, 10, 3, Phase::Working, Outcome::Skipped),
+        record(10, 9, 25, Phase::Working, Outcome::Completed),
+        record(10, 10, 25, Phase::Working, Outcome::Completed),
+    ];
+    let at = records[0].started_at;
+    let interruption = |kind| Interruption {
+        kind,
+        note: String::new(),
+        at,
+    };
+    records[0].interruptions = vec![
+        interruption(InterruptionKind::Internal),
+        interruption(InterruptionKind::External),
+    ];
+    records[5].interruptions = vec![interruption(InterruptionKind::Internal)];
+    records[2].overtime = Duration::from_secs(4 * 60);
+    // 2024-01-10 is a Wednesday.
+    let now = Local.with_ymd_and_hms(2024, 1, 10, 18, 0, 0).unwrap();
+    assert_eq!(
+        Stats::from_records(&records, now),
+        Stats {
+            today: 2,
+            this_week: 2,
+            focused: Duration::from_secs(150 * 60),
+            longest_streak: 3,
+            average_session: Duration::from_secs(25 * 60),
+            overtime: Duration::from_secs(4 * 60),
+            internal_interruptions: 2,
+            external_interruptions: 1,
+        }
+    );
+}
+
+#[test]
+fn summarize_nothing() {
+    let stats = Stats::from_records(&[], Local::now());
+    assert_eq!(stats.today, 0);
+    assert_eq!(stats.longest_streak, 0);
+    assert_eq!(stats.average_session, Duration::from_secs(0));
+}
+
+#[test]
+fn group_by_task() {
+    use chrono::TimeZone;
+
+    let record = |minutes, outcome, task: Option<&str>| {
+        let started_at = Local.with_ymd_and_hms(2024, 1, 2, 9, 0, 0).unwrap();
+        Record {
+            phase: Phase::Working,
+            outcome,
+            started_at,
+            ended_at: started_at + chrono::Duration::minutes(minutes),
+            paused: Duration::from_secs(0),
+            pauses: 0,
+            reason: None,
+            task: task.map(str::to_string),
+            interruptions: Vec::new(),
+            overtime: Duration::from_secs(0),
+        }
+    };
+    let records = vec![
+        record(25, Outcome::Completed, Some("report")),
+        record(25, Outcome::Completed, None),
+        record(25, Outcome::Completed, Some("report")),
+        record(10, Outcome::Voided, Some("mail")),
+        record(30, Outcome::Completed, Some("mail")),
+    ];
+    let minutes = |m: u64| Duration::from_secs(m * 60);
+    assert_eq!(
+        TaskStats::from_records(&records),
+        vec![
+            TaskStats {
+                task: Some("report".to_string()),
+                pomodoros: 2,
+                focused: minutes(50),
+            },
+            TaskStats {
+                task: Some("mail".to_string()),
+                pomodoros: 1,
+                focused: minutes(30),
+            },
+            TaskStats {
+                task: None,
+                pomodoros: 1,
+                focused: minutes(25),
+            },
+        ]
+    );
+}
+
+#[test]
+fn report_week() {
+    use crate::history::Interruption;
+    use chrono::TimeZone;
+
+    let record = |day, minutes, task: Option<&str>| {
+        let started_at = Local.with_ymd_and_hms(2024, 1, day, 9, 0, 0).unwrap();
+        Record {
+            phase: Phase::Working,
+            outcome: Outcome::Completed,
+            started_at,
+            ended_at: started_at + chrono::Duration::minutes(minutes),
+            paused: Duration::from_secs(0),
+            pauses: 0,
+            reason: None,
+            task: task.map(str::to_string),
+            interruptions: Vec::new(),
+            overtime: Duration::from_secs(0),
+        }
+    };
+    let mut records = vec![
+        record(5, 25, Some("report")),
+        record(8, 25, Some("report")),
+        record(10, 25, None),
+        record(10, 30, Some("mail")),
+    ];
+    records[1].interruptions = vec![Interruption {
+        kind: InterruptionKind::External,
+        note: "call".to_string(),
+        at: records[1].started_at,
+    }];
+    // 2024-01-10 is a Wednesday.
+    let now = Local.with_ymd_and_hms(2024, 1, 10, 18, 0, 0).unwrap();
+    let report = Report::from_records(&records, Period::Week, now);
+    let days: Vec<(u32, usize)> = report
+        .days
+        .iter()
+        .map(|d| (d.day.day(), d.pomodoros))
+        .collect();
+    assert_eq!(days, vec![(8, 1), (9, 0), (10, 2)]);
+    assert_eq!(report.pomodoros(), 3);
+    assert_eq!(report.focused(), Duration::from_secs(80 * 60));
+    assert_eq!(report.tasks[0].task.as_deref(), Some("mail"));
+    assert_eq!(
+        (report.internal_interruptions, report.external_interruptions),
+        (0, 1)
+    );
+    let report = Report::from_records(&records, Period::Day, now);
+    assert_eq!(report.days.len(), 1);
+    assert_eq!(report.pomodoros(), 2);
+    assert_eq!("week".parse(), Ok(Period::Week));
+    assert!("month".parse::<Period>().is_err());
+}
+
+#[test]
+fn count_by_day_and_hour() {
+    use chrono::TimeZone;
+
+    let record = |day, hour| {
+        let started_at = Local.with_ymd_and_hms(2024, 1, day, hour, 0, 0).unwrap();
+        Record {
+            phase: Phase::Working,
+            outcome: Outcome::Completed,
+            started_at,
+            ended_at: started_at + chrono::Duration::minutes(25),
+            paused: Duration::from_secs(0),
+            pauses: 0,
+            reason: None,
+            task: None,
+            interruptions: Vec::new(),
+            overtime: Duration::from_secs(0),
+        }
+    };
+    let records = vec![record(1, 9), record(8, 9), record(8, 14), record(10, 9)];
+    // 2024-01-10 is a Wednesday.
+    let now = Local.with_ymd_and_hms(2024, 1, 10, 18, 0, 0).unwrap();
+    assert_eq!(daily_counts(&records, 4, now), vec![0, 2, 0, 1]);
+    assert!(daily_counts(&records, 0, now).is_empty());
+    let grid = heatmap(&records, 7, now);
+    assert_eq!((grid[0][9], grid[0][14], grid[2][9]), (1, 1, 1));
+    assert_eq!(heatmap(&records, 14, now)[0][9], 2);
+}
@@ -0,0 +1,259 @@
+//! Files the daemon keeps the status of its pomodoro in, so that shell prompts, conky,
+//! xmobar and scripts can tell where it is by reading a file rather than asking the socket.
+//!
+//! The [`StatusFile`] at the default path, which `pomo prompt` reads, is rewritten whenever
+//! the pomodoro changes phase, pauses or resumes, rather than on every tick: its readers
+//! work the countdown out from when the phase ends. The file of an [`Export`] is rewritten
+//! on every second of the countdown too, so that its readers need not.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+use crate::event::Event;
+use crate::pomodoro::{Labels, Status};
+use crate::runtime::Client;
+use crate::template::{self, Values};
+
+/// The [`Status`] of the daemon's pomodoro as last written, with the name shown for its
+/// phase so readers need not load the config.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusFile {
+    #[serde(flatten)]
+    pub status: Status,
+    /// The name of the running phase or plan step.
+    pub name: String,
+    pub written_at: DateTime<Local>,
+}
+
+impl StatusFile {
+    pub fn new(status: Status, labels: &Labels) -> Self {
+        Self {
+            name: status.label(labels).to_string(),
+            status,
+            written_at: Local::now(),
+        }
+    }
+
+    /// The default file, `$XDG_RUNTIME_DIR/pomo/status.json` on Linux, or else in the cache
+    /// directory.
+    pub fn path() -> Option<PathBuf> {
+        dirs::runtime_dir()
+            .or_else(dirs::cache_dir)
+            .map(|dir| dir.join("pomo").join("status.json"))
+    }
+
+    /// Reads the file at `path`, or `None` when the daemon is not running.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Option<Self>> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Writes the file to `path`.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        replace(path.as_ref(), &serde_json::to_string(self)?)
+    }
+
+    /// Removes the file at `path`, once the daemon stops.
+    pub fn clear(path: impl AsRef<Path>) -> io::Result<()> {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// What is left of the phase at `now`, counting down from when it was written unless
+    /// the pomodoro is paused or waiting.
+    pub fn remaining_at(&self, now: DateTime<Local>) -> Duration {
+        let status = &self.status;
+        if status.paused || status.awaiting_ack {
+            return status.remaining;
+        }
+        match status.ends_at {
+            Some(ends_at) => (ends_at - now).to_std().unwrap_or_default(),
+            None => Duration::from_secs(0),
+        }
+    }
+}
+
+/// Writes `contents` to `path` in a single rename, so that readers never see half of it.
+fn replace(path: &Path, contents: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    fs::write(&partial, contents)?;
+    fs::rename(&partial, path)
+}
+
+/// How the file of an [`Export`] is written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+    /// A [`StatusFile`].
+    #[default]
+    Json,
+    /// A single line from the template.
+    Text,
+}
+
+/// A status file for other programs, read from the `[status_file]` table of the config
+/// file.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Export {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub format: Format,
+    /// The line of the `text` format, with the placeholders of
+    /// [`template`](crate::template).
+    #[serde(default = "phase_and_remaining")]
+    pub template: String,
+}
+
+fn phase_and_remaining() -> String {
+    "{phase} {remaining}".to_string()
+}
+
+impl Export {
+    fn contents(&self, status: Status, labels: &Labels) -> io::Result<String> {
+        match self.format {
+            Format::Json => Ok(serde_json::to_string(&StatusFile::new(status, labels))?),
+            Format::Text => {
+                Ok(template::render(&self.template, &Values::of(&status, labels)) + "\n")
+            }
+        }
+    }
+}
+
+/// Keeps `path` up to date with the pomodoro behind `client` through `contents`, on every
+/// second of the countdown when `ticks` say so, and removes it once the pomodoro stops.
+async fn keep(
+    path: &Path,
+    ticks: bool,
+    client: Client,
+    contents: impl Fn(Status) -> io::Result<String>,
+) {
+    let mut events = client.subscribe();
+    let mut shown = None;
+    'writing: loop {
+        let written = match client.status().await {
+            Ok(status) => contents(status).and_then(|contents| replace(path, &contents)),
+            Err(_) => break,
+        };
+        if let Err(e) = written {
+            tracing::warn!(path = %path.display(), error = %e, "cannot write the status");
+        }
+        loop {
+            match events.recv().await {
+                Ok(Event::Tick { remaining, .. }) => {
+                    let secs = remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0);
+                    if ticks && shown.replace(secs) != Some(secs) {
+                        break;
+                    }
+                }
+                Ok(_) | Err(RecvError::Lagged(_)) => break,
+                Err(RecvError::Closed) => break 'writing,
+            }
+        }
+    }
+    if let Err(e) = StatusFile::clear(path) {
+        tracing::warn!(path = %path.display(), error = %e, "cannot remove the status");
+    }
+}
+
+/// Spawns a task keeping the [`StatusFile`] at `path` up to date with the pomodoro behind
+/// `client`, leaving the task out unless `with_task`, and removing it once the pomodoro
+/// stops.
+pub fn spawn(path: PathBuf, labels: Labels, client: Client, with_task: bool) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        keep(&path, false, client, |mut status| {
+            if !with_task {
+                status.task = None;
+            }
+            Ok(serde_json::to_string(&StatusFile::new(status, &labels))?)
+        })
+        .await
+    })
+}
+
+/// Spawns a task keeping the file of `export` up to date with the pomodoro behind `client`,
+/// and removing it once the pomodoro stops.
+pub fn export(export: Export, labels: Labels, client: Client) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        keep(&export.path, true, client, |status| {
+            export.contents(status, &labels)
+        })
+        .await
+    })
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn follow_the_daemon() {
+    let dir = std::env::temp_dir().join(format!("pomo-status-{}", std::process::id()));
+    let path = dir.join("status.json");
+    let pomodoro = crate::Pomodoro::builder().build().unwrap();
+    let (runtime, client, _events) = crate::start(pomodoro).await;
+    let writer = spawn(path.clone(), Labels::default(), client.clone(), true);
+    let settle = || tokio::time::sleep(Duration::from_millis(50));
+
+    settle().await;
+    let file = StatusFile::load(&path).unwrap().unwrap();
+    assert_eq!(file.name, "working");
+    assert!(!file.status.paused);
+    let later = file.written_at + chrono::Duration::minutes(5);
+    let remaining = file.remaining_at(later);
+    assert!(remaining <= Duration::from_secs(20 * 60));
+    assert!(remaining > Duration::from_secs(20 * 60 - 5));
+
+    client.pause().await.unwrap();
+    settle().await;
+    let file = StatusFile::load(&path).unwrap().unwrap();
+    assert!(file.status.paused);
+    assert_eq!(file.remaining_at(later), file.status.remaining);
+
+    runtime.shutdown().await.unwrap();
+    writer.await.unwrap();
+    assert_eq!(StatusFile::load(&path).unwrap(), None);
+    fs::remove_dir(&dir).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn export_every_second() {
+    let dir = std::env::temp_dir().join(format!("pomo-export-{}", std::process::id()));
+    let path = dir.join("pomo.txt");
+    let pomodoro = crate::Pomodoro::builder()
+        .work(Duration::from_secs(3))
+        .tick(Duration::from_millis(100))
+        .build()
+        .unwrap();
+    let (runtime, client, _events) = crate::start(pomodoro).await;
+    let settings: Export = toml::from_str(&format!(
+        "path = {:?}\nformat = \"text\"\ntemplate = \"{{remaining}} left\"",
+        path
+    ))
+    .unwrap();
+    let exporter = export(settings, Labels::default(), client.clone());
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(fs::read_to_string(&path).unwrap(), "00:03 left\n");
+    tokio::time::sleep(Duration::from_millis(1000)).await;
+    assert_eq!(fs::read_to_string(&path).unwrap(), "00:02 left\n");
+    runtime.shutdown().await.unwrap();
+    exporter.await.unwrap();
+    assert!(!path.exists());
+    fs::remove_dir(&dir).unwrap();
+}
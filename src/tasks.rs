@@ -0,0 +1,191 @@
+//! Tasks read from a todo.txt file or a markdown checklist, which get a `pomo:N` tag
+//! counting the pomodoros completed on them.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+
+use crate::event::Event;
+use crate::pomodoro::Phase;
+
+const TAG: &str = "pomo:";
+
+/// An open task of a task file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Task {
+    /// The task as written, without its checkbox and its `pomo:N` tag.
+    pub title: String,
+    /// The pomodoros completed on it so far.
+    pub pomodoros: u32,
+}
+
+/// Whether `path` holds a markdown checklist rather than a todo.txt list.
+fn is_markdown(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("md") | Some("markdown")
+    )
+}
+
+/// The text of an open task on `line`, or `None` when the line is not one.
+fn open_task(line: &str, markdown: bool) -> Option<&str> {
+    let line = line.trim();
+    let text = if markdown {
+        ["- [ ] ", "* [ ] "]
+            .iter()
+            .find_map(|checkbox| line.strip_prefix(checkbox))?
+    } else if line.starts_with("x ") {
+        return None;
+    } else {
+        line
+    };
+    Some(text.trim()).filter(|text| !text.is_empty())
+}
+
+fn parse(text: &str) -> Task {
+    let mut pomodoros = 0;
+    let words: Vec<&str> = text
+        .split_whitespace()
+        .filter(|word| match word.strip_prefix(TAG).map(str::parse) {
+            Some(Ok(count)) => {
+                pomodoros = count;
+                false
+            }
+            _ => true,
+        })
+        .collect();
+    Task {
+        title: words.join(" "),
+        pomodoros,
+    }
+}
+
+/// Rewrites the `pomo:N` tag of `line` to count one more pomodoro.
+fn tally(line: &str) -> String {
+    let pomodoros = parse(line).pomodoros;
+    let tag = format!("{}{}", TAG, pomodoros);
+    match line.find(&tag) {
+        Some(at) => format!(
+            "{}{}{}{}",
+            &line[..at],
+            TAG,
+            pomodoros + 1,
+            &line[at + tag.len()..]
+        ),
+        _ => format!("{} {}1", line.trim_end(), TAG),
+    }
+}
+
+/// Reads the open tasks of the file at `path`, a markdown checklist when it ends with
+/// `.md` and a todo.txt list otherwise.
+pub fn read(path: impl AsRef<Path>) -> io::Result<Vec<Task>> {
+    let path = path.as_ref();
+    let markdown = is_markdown(path);
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .filter_map(|line| open_task(line, markdown))
+        .map(parse)
+        .collect())
+}
+
+/// Counts one more pomodoro on the open task titled `title`, returning whether the file
+/// has such a task.
+pub fn record_pomodoro(path: impl AsRef<Path>, title: &str) -> io::Result<bool> {
+    let path = path.as_ref();
+    let markdown = is_markdown(path);
+    let content = fs::read_to_string(path)?;
+    let mut found = false;
+    let mut lines: Vec<String> = content
+        .lines()
+        .map(|line| match open_task(line, markdown) {
+            Some(text) if !found && parse(text).title == title => {
+                found = true;
+                tally(line)
+            }
+            _ => line.to_string(),
+        })
+        .collect();
+    if !found {
+        return Ok(false);
+    }
+    if content.ends_with('\n') {
+        lines.push(String::new());
+    }
+    fs::write(path, lines.join("\n"))?;
+    Ok(true)
+}
+
+/// Spawns a task counting every completed working phase on the task the pomodoro is
+/// tagged with, when the file at `path` lists it.
+pub fn spawn(path: PathBuf, mut events: broadcast::Receiver<Event>) -> JoinHandle<()> {
+    tokio::task::spawn_blocking(move || {
+        let mut task = String::new();
+        loop {
+            match events.blocking_recv() {
+                Ok(Event::TaskSet(set)) => task = set,
+                Ok(Event::PhaseEnded(Phase::Working)) if !task.is_empty() => {
+                    if let Err(e) = record_pomodoro(&path, &task) {
+                        tracing::warn!(path = %path.display(), error = %e, "cannot update the task list");
+                    }
+                }
+                Ok(_) | Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+#[test]
+fn parse_tasks() {
+    assert_eq!(open_task("x 2024-01-02 done", false), None);
+    assert_eq!(open_task("(A) call mom", false), Some("(A) call mom"));
+    assert_eq!(open_task("  ", false), None);
+    assert_eq!(open_task("- [ ] write report", true), Some("write report"));
+    assert_eq!(open_task("- [x] write report", true), None);
+    assert_eq!(open_task("# Today", true), None);
+    assert_eq!(
+        parse("write report pomo:3 +work"),
+        Task {
+            title: "write report +work".to_string(),
+            pomodoros: 3,
+        }
+    );
+    assert_eq!(tally("write report"), "write report pomo:1");
+    assert_eq!(tally("write pomo:3 +work"), "write pomo:4 +work");
+}
+
+#[test]
+fn count_pomodoros_in_file() {
+    let path = std::env::temp_dir().join(format!("pomo-tasks-{}.md", std::process::id()));
+    fs::write(
+        &path,
+        "# Today\n- [x] review\n- [ ] write report pomo:2\n- [ ] read\n",
+    )
+    .unwrap();
+    assert!(record_pomodoro(&path, "write report").unwrap());
+    assert!(record_pomodoro(&path, "read").unwrap());
+    assert!(!record_pomodoro(&path, "review").unwrap());
+    let tasks = read(&path).unwrap();
+    let content = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(
+        content,
+        "# Today\n- [x] review\n- [ ] write report pomo:3\n- [ ] read pomo:1\n"
+    );
+    assert_eq!(
+        tasks,
+        vec![
+            Task {
+                title: "write report".to_string(),
+                pomodoros: 3,
+            },
+            Task {
+                title: "read".to_string(),
+                pomodoros: 1,
+            },
+        ]
+    );
+}
@@ -0,0 +1,186 @@
+//! Working on a Taskwarrior task, picked with `--taskwarrior <id>`: its description becomes
+//! the task of the session, it is started with `task start` while a working phase runs and
+//! stopped otherwise, and every completed pomodoro adds an annotation counting them.
+
+use crate::event::Event;
+use crate::pomodoro::Phase;
+
+/// Follows the events of a pomodoro to tell which `task` commands to run on one task.
+pub struct Taskwarrior {
+    id: String,
+    /// Whether the running phase is a working one.
+    working: bool,
+    /// Whether the task was started with `task start`.
+    active: bool,
+    completed: u32,
+}
+
+impl Taskwarrior {
+    /// Follows the task `id` from a pomodoro about to run `phase`.
+    pub fn new(id: impl Into<String>, phase: Phase) -> Self {
+        Self {
+            id: id.into(),
+            working: phase == Phase::Working,
+            active: false,
+            completed: 0,
+        }
+    }
+
+    /// The arguments of `task` to run for `event`, in order.
+    pub fn commands(&mut self, event: &Event) -> Vec<Vec<String>> {
+        let mut commands = Vec::new();
+        match event {
+            Event::PhaseStarted(phase) => {
+                self.working = *phase == Phase::Working;
+                if self.working {
+                    commands.extend(self.start());
+                }
+            }
+            Event::Resumed if self.working => commands.extend(self.start()),
+            Event::Paused | Event::Completed => commands.extend(self.stop()),
+            Event::PhaseEnded(Phase::Working) => {
+                commands.extend(self.stop());
+                self.completed += 1;
+                let note = format!("Completed pomodoro #{}", self.completed);
+                commands.push(self.command(&["annotate", &note]));
+            }
+            Event::PhaseSkipped(Phase::Working)
+            | Event::PhaseAborted(Phase::Working)
+            | Event::PhaseVoided(Phase::Working, _) => commands.extend(self.stop()),
+            _ => {}
+        }
+        commands
+    }
+
+    /// The command to run once the pomodoro is gone, so the task does not stay started.
+    pub fn stop(&mut self) -> Option<Vec<String>> {
+        if !self.active {
+            return None;
+        }
+        self.active = false;
+        Some(self.command(&["stop"]))
+    }
+
+    fn start(&mut self) -> Option<Vec<String>> {
+        if self.active {
+            return None;
+        }
+        self.active = true;
+        Some(self.command(&["start"]))
+    }
+
+    fn command(&self, args: &[&str]) -> Vec<String> {
+        let mut command = vec!["rc.confirmation=off".to_string(), self.id.clone()];
+        command.extend(args.iter().map(|arg| arg.to_string()));
+        command
+    }
+}
+
+#[cfg(feature = "taskwarrior")]
+mod run {
+    use std::io;
+    use std::process::Stdio;
+
+    use tokio::process::Command;
+    use tokio::sync::broadcast::{self, error::RecvError};
+    use tokio::task::JoinHandle;
+
+    use super::Taskwarrior;
+    use crate::event::Event;
+
+    /// Runs `task` with `args`, failing when it exits unsuccessfully.
+    async fn task(args: &[String]) -> io::Result<String> {
+        let output = Command::new("task")
+            .args(args)
+            .stdin(Stdio::null())
+            .output()
+            .await?;
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            return Err(io::Error::other(error.trim().to_string()));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// The description of the task `id`.
+    pub async fn description(id: &str) -> io::Result<String> {
+        let description = task(&["_get".to_string(), format!("{}.description", id)]).await?;
+        if description.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("there is no task {}", id),
+            ));
+        }
+        Ok(description)
+    }
+
+    /// Spawns a task running the `task` commands `taskwarrior` asks for as the events come,
+    /// and stopping the task once the pomodoro stops.
+    pub fn spawn(
+        mut taskwarrior: Taskwarrior,
+        mut events: broadcast::Receiver<Event>,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                let event = match events.recv().await {
+                    Ok(Event::Tick { .. }) | Err(RecvError::Lagged(_)) => continue,
+                    Ok(event) => event,
+                    Err(RecvError::Closed) => break,
+                };
+                for command in taskwarrior.commands(&event) {
+                    if let Err(e) = task(&command).await {
+                        tracing::warn!(command = %command[2..].join(" "), error = %e, "task failed");
+                    }
+                }
+            }
+            if let Some(command) = taskwarrior.stop() {
+                if let Err(e) = task(&command).await {
+                    tracing::warn!(command = "stop", error = %e, "task failed");
+                }
+            }
+        })
+    }
+}
+
+#[cfg(feature = "taskwarrior")]
+pub use run::{description, spawn};
+
+#[test]
+fn follow_working_phases() {
+    let command = |args: &[&str]| -> Vec<String> {
+        ["rc.confirmation=off", "12"]
+            .iter()
+            .chain(args)
+            .map(|arg| arg.to_string())
+            .collect()
+    };
+    let mut taskwarrior = Taskwarrior::new("12", Phase::Working);
+    assert!(taskwarrior.commands(&Event::Paused).is_empty());
+    assert_eq!(
+        taskwarrior.commands(&Event::Resumed),
+        vec![command(&["start"])]
+    );
+    assert!(taskwarrior
+        .commands(&Event::PhaseStarted(Phase::Working))
+        .is_empty());
+    assert_eq!(
+        taskwarrior.commands(&Event::PhaseEnded(Phase::Working)),
+        vec![
+            command(&["stop"]),
+            command(&["annotate", "Completed pomodoro #1"])
+        ]
+    );
+    assert!(taskwarrior
+        .commands(&Event::PhaseStarted(Phase::ShortBreak))
+        .is_empty());
+    assert!(taskwarrior.commands(&Event::Resumed).is_empty());
+    assert_eq!(
+        taskwarrior.commands(&Event::PhaseStarted(Phase::Working)),
+        vec![command(&["start"])]
+    );
+    assert_eq!(
+        taskwarrior.commands(&Event::PhaseSkipped(Phase::Working)),
+        vec![command(&["stop"])]
+    );
+    assert_eq!(taskwarrior.stop(), None);
+}
@@ -0,0 +1,174 @@
+//! Texts written in the config file or on the command line with `{placeholders}` filled in
+//! from the state of the pomodoro, for notifications, hooks, announcements, status bars,
+//! webhooks and the Slack status.
+//!
+//! - `{phase}`: the name of the phase or plan step.
+//! - `{remaining}`: the time left in the phase, e.g. `24:59`.
+//! - `{count}`: the working phases completed so far.
+//! - `{task}`: what the pomodoros are spent on.
+//! - `{ends_at}`: when the phase will be over, e.g. `14:25`.
+//!
+//! Other text in braces is left as it is, and the placeholders which cannot be told, such
+//! as the task when there is none, are left empty.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+
+use crate::event::Event;
+use crate::pomodoro::{Labels, Status};
+
+/// What the placeholders stand for.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Values {
+    pub phase: String,
+    pub remaining: Duration,
+    pub count: u8,
+    pub task: Option<String>,
+    pub ends_at: Option<DateTime<Local>>,
+}
+
+impl Values {
+    pub fn of(status: &Status, labels: &Labels) -> Self {
+        Self {
+            phase: status.label(labels).to_string(),
+            remaining: status.remaining,
+            count: status.pomodoros,
+            task: status.task.clone(),
+            ends_at: status.ends_at,
+        }
+    }
+
+    /// The values as of `event`, from `status` taken right after it when there is one.
+    /// Ending phases have nothing left and no end, as the next phase may have started by
+    /// the time the status is taken.
+    pub fn at(event: &Event, status: Option<&Status>, labels: &Labels) -> Self {
+        let mut values = status.map(|s| Self::of(s, labels)).unwrap_or_default();
+        let phase = match event.phase() {
+            Some(phase) => phase,
+            None => return values,
+        };
+        if status.is_none_or(|status| status.phase != phase) {
+            values.phase = phase.label(labels).to_string();
+        }
+        if let Event::PhaseEnded(_)
+        | Event::PhaseSkipped(_)
+        | Event::PhaseAborted(_)
+        | Event::PhaseVoided(..)
+        | Event::AwaitingAck(_) = event
+        {
+            values.remaining = Duration::from_secs(0);
+            values.ends_at = None;
+        }
+        values
+    }
+
+    fn get(&self, name: &str) -> Option<String> {
+        let value = match name {
+            "phase" => self.phase.clone(),
+            "remaining" => {
+                let secs = self.remaining.as_secs() + u64::from(self.remaining.subsec_nanos() > 0);
+                format!("{:02}:{:02}", secs / 60, secs % 60)
+            }
+            "count" => self.count.to_string(),
+            "task" => self.task.clone().unwrap_or_default(),
+            "ends_at" => self
+                .ends_at
+                .map(|at| at.format("%H:%M").to_string())
+                .unwrap_or_default(),
+            _ => return None,
+        };
+        Some(value)
+    }
+}
+
+/// `template` with its placeholders replaced by `values`.
+pub fn render(template: &str, values: &Values) -> String {
+    render_with(template, values, str::to_string)
+}
+
+/// Whether `template` has placeholders to fill in.
+pub fn has_placeholders(template: &str) -> bool {
+    render(template, &Values::default()) != template
+}
+
+/// `template` with its placeholders replaced by `values`, each passed through `escape`.
+pub fn render_with(template: &str, values: &Values, escape: impl Fn(&str) -> String) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        rendered.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let value = after
+            .find('}')
+            .and_then(|close| Some((close, values.get(&after[..close])?)));
+        match value {
+            Some((close, value)) => {
+                rendered.push_str(&escape(&value));
+                rest = &after[close + 1..];
+            }
+            None => {
+                rendered.push('{');
+                rest = after;
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+#[test]
+fn render_placeholders() {
+    let values = Values {
+        phase: "working".to_string(),
+        remaining: Duration::from_millis(4 * 60_000 + 1),
+        count: 4,
+        task: None,
+        ends_at: None,
+    };
+    assert_eq!(
+        render("Back to {phase}, {count} pomodoros done", &values),
+        "Back to working, 4 pomodoros done"
+    );
+    assert_eq!(
+        render("{remaining} left until {ends_at} on {task}", &values),
+        "04:01 left until  on "
+    );
+    assert_eq!(
+        render("{{count}} {unknown} {task", &values),
+        "{4} {unknown} {task"
+    );
+    assert!(has_placeholders("say {phase}"));
+    assert!(!has_placeholders("say {unknown} {phase"));
+    let values = Values {
+        task: Some("it's {count}".to_string()),
+        ..values
+    };
+    assert_eq!(
+        render_with("[{task}]", &values, |value| value.replace('\'', "\\'")),
+        "[it\\'s {count}]"
+    );
+}
+
+#[test]
+fn values_at_events() {
+    use crate::pomodoro::Phase;
+
+    let mut status = crate::Pomodoro::builder().build().unwrap().status();
+    status.phase = Phase::ShortBreak;
+    status.label = Some("stretch".to_string());
+    status.remaining = Duration::from_secs(300);
+    let labels = Labels::default();
+    let started = Values::at(
+        &Event::PhaseStarted(Phase::ShortBreak),
+        Some(&status),
+        &labels,
+    );
+    assert_eq!(started.phase, "stretch");
+    assert_eq!(started.remaining, Duration::from_secs(300));
+    let ended = Values::at(&Event::PhaseEnded(Phase::Working), Some(&status), &labels);
+    assert_eq!(ended.phase, "working");
+    assert_eq!(ended.remaining, Duration::from_secs(0));
+    let alone = Values::at(&Event::PhaseStarted(Phase::LongBreak), None, &labels);
+    assert_eq!(alone.phase, "long break");
+}
@@ -0,0 +1,99 @@
+//! The waiting between two ticks of a [`Pomodoro`](crate::Pomodoro), which is all the state
+//! machine needs from an async runtime.
+//!
+//! [`Pomodoro::drive_with`](crate::Pomodoro::drive_with) takes any [`Sleeper`], so async-std,
+//! smol or any other runtime can drive the pomodoro, and
+//! [`Pomodoro::run_blocking`](crate::Pomodoro::run_blocking) needs no runtime at all.
+//!
+//! The engine measures time with [`tokio::time::Instant`] throughout, so with the
+//! `test-util` feature tests can pause tokio's clock and run hours of pomodoros at once,
+//! either letting it advance by itself or stepping it with a [`MockClock`].
+
+use std::future::Future;
+#[cfg(any(test, feature = "test-util"))]
+use std::pin::Pin;
+#[cfg(any(test, feature = "test-util"))]
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+/// Waits until the next tick of a pomodoro on the timer of some async runtime.
+///
+/// Any function returning a future from a [`Duration`] is a sleeper, such as
+/// `async_std::task::sleep`.
+pub trait Sleeper {
+    type Sleep: Future<Output = ()>;
+
+    /// A future which resolves once `duration` has passed.
+    fn sleep(&self, duration: Duration) -> Self::Sleep;
+}
+
+impl<F, S> Sleeper for F
+where
+    F: Fn(Duration) -> S,
+    S: Future<Output = ()>,
+{
+    type Sleep = S;
+
+    fn sleep(&self, duration: Duration) -> S {
+        self(duration)
+    }
+}
+
+/// Sleeps on the tokio timer, which is what [`Pomodoro::run`](crate::Pomodoro::run) does.
+#[cfg(feature = "tokio-timer")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TokioSleeper;
+
+#[cfg(feature = "tokio-timer")]
+impl Sleeper for TokioSleeper {
+    type Sleep = tokio::time::Sleep;
+
+    fn sleep(&self, duration: Duration) -> Self::Sleep {
+        tokio::time::sleep(duration)
+    }
+}
+
+/// A [`Sleeper`] for tests which, instead of waiting, moves tokio's paused clock forward by
+/// each sleep at once, and remembers the sleeps. Time must be paused, with
+/// [`tokio::time::pause`] or `#[tokio::test(start_paused = true)]`.
+#[cfg(any(test, feature = "test-util"))]
+#[derive(Clone, Debug, Default)]
+pub struct MockClock {
+    sleeps: Arc<Mutex<Vec<Duration>>>,
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl MockClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The sleeps asked for so far, in order.
+    pub fn sleeps(&self) -> Vec<Duration> {
+        self.sleeps
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// How far the clock has been moved forward in total.
+    pub fn elapsed(&self) -> Duration {
+        self.sleeps().iter().sum()
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl Sleeper for MockClock {
+    type Sleep = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+    fn sleep(&self, duration: Duration) -> Self::Sleep {
+        let sleeps = self.sleeps.clone();
+        Box::pin(async move {
+            sleeps
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(duration);
+            tokio::time::advance(duration).await;
+        })
+    }
+}
@@ -0,0 +1,330 @@
+//! Logging every completed pomodoro as a time entry on Toggl Track or Clockify, configured
+//! under `[time_tracking]` in the config file:
+//!
+//! ```toml
+//! [time_tracking]
+//! service = "toggl"
+//! api_key = "…"
+//! workspace = "1234567"
+//! project = "7654321"
+//!
+//! [time_tracking.projects]
+//! "write report" = "8765432"
+//! ```
+//!
+//! The entry is described by the task of the pomodoro, which also picks its project from
+//! `projects`, falling back to `project`.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Local, SecondsFormat};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::event::Event;
+use crate::pomodoro::Phase;
+
+/// The time tracker to send the entries to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Service {
+    Toggl,
+    Clockify,
+}
+
+/// The time tracking settings, read from the `[time_tracking]` table of the config file.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TimeTracking {
+    pub service: Service,
+    /// The API token of Toggl Track's profile page, or the API key of Clockify's.
+    pub api_key: String,
+    /// The id of the workspace to log the entries to.
+    pub workspace: String,
+    /// The ids of the projects to log the pomodoros of each task to.
+    #[serde(default)]
+    pub projects: BTreeMap<String, String>,
+    /// The id of the project for the pomodoros whose task is not in `projects`.
+    pub project: Option<String>,
+    /// How many more times to try an entry which failed for a reason that may go away.
+    #[serde(default = "three")]
+    pub retries: u32,
+}
+
+fn three() -> u32 {
+    3
+}
+
+/// A completed pomodoro to log.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub description: String,
+    pub project: Option<String>,
+    pub start: DateTime<Local>,
+    pub stop: DateTime<Local>,
+}
+
+impl TimeTracking {
+    /// The entry of a pomodoro spent on `task` from `start` to `stop`.
+    pub fn entry(
+        &self,
+        task: Option<&str>,
+        start: DateTime<Local>,
+        stop: DateTime<Local>,
+    ) -> Entry {
+        let project = task.and_then(|task| self.projects.get(task));
+        Entry {
+            description: task.unwrap_or("Pomodoro").to_string(),
+            project: project.or(self.project.as_ref()).cloned(),
+            start,
+            stop,
+        }
+    }
+
+    /// The URL to POST `entry` to and the body to POST.
+    pub fn request(&self, entry: &Entry) -> (String, Value) {
+        let at = |time: &DateTime<Local>| time.to_utc().to_rfc3339_opts(SecondsFormat::Secs, true);
+        match self.service {
+            Service::Toggl => {
+                let url = format!(
+                    "https://api.track.toggl.com/api/v9/workspaces/{}/time_entries",
+                    self.workspace
+                );
+                let mut body = json!({
+                    "created_with": "pomo",
+                    "description": entry.description,
+                    "start": at(&entry.start),
+                    "stop": at(&entry.stop),
+                    "duration": (entry.stop - entry.start).num_seconds(),
+                    "workspace_id": id(&self.workspace),
+                });
+                if let Some(project) = &entry.project {
+                    body["project_id"] = id(project);
+                }
+                (url, body)
+            }
+            Service::Clockify => {
+                let url = format!(
+                    "https://api.clockify.me/api/v1/workspaces/{}/time-entries",
+                    self.workspace
+                );
+                let mut body = json!({
+                    "description": entry.description,
+                    "start": at(&entry.start),
+                    "end": at(&entry.stop),
+                });
+                if let Some(project) = &entry.project {
+                    body["projectId"] = json!(project);
+                }
+                (url, body)
+            }
+        }
+    }
+}
+
+/// Toggl's ids are numbers, which the config file may hold as strings.
+fn id(id: &str) -> Value {
+    id.parse::<u64>()
+        .map(Value::from)
+        .unwrap_or_else(|_| json!(id))
+}
+
+/// Follows the events of a pomodoro to tell when its working phases were completed.
+#[derive(Default)]
+pub struct Tracker {
+    started_at: Option<DateTime<Local>>,
+    task: Option<String>,
+}
+
+impl Tracker {
+    /// The task and the start and end of the pomodoro `event` completes at `now`.
+    pub fn observe(
+        &mut self,
+        event: &Event,
+        now: DateTime<Local>,
+    ) -> Option<(Option<String>, DateTime<Local>, DateTime<Local>)> {
+        match event {
+            Event::PhaseStarted(Phase::Working) => self.started_at = Some(now),
+            Event::PhaseStarted(_)
+            | Event::PhaseSkipped(_)
+            | Event::PhaseAborted(_)
+            | Event::PhaseVoided(..) => self.started_at = None,
+            Event::TaskSet(task) => self.task = Some(task.clone()).filter(|t| !t.is_empty()),
+            Event::PhaseEnded(Phase::Working) => {
+                let started_at = self.started_at.take()?;
+                return Some((self.task.clone(), started_at, now));
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+#[cfg(feature = "timetrack")]
+mod sync {
+    use std::time::Duration;
+
+    use chrono::Local;
+    use reqwest::header::CONTENT_TYPE;
+    use tokio::sync::broadcast::{self, error::RecvError};
+    use tokio::sync::mpsc;
+    use tokio::task::JoinHandle;
+
+    use super::{Entry, Service, TimeTracking, Tracker};
+    use crate::event::Event;
+
+    /// How long to wait before the first retry, doubling with every other one.
+    const BACKOFF: Duration = Duration::from_secs(2);
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Sends `entry`, retrying after `backoff` when the service cannot be reached, is
+    /// overloaded or fails.
+    async fn send(
+        http: &reqwest::Client,
+        settings: &TimeTracking,
+        entry: &Entry,
+        backoff: Duration,
+    ) -> Result<(), String> {
+        let (url, body) = settings.request(entry);
+        let mut attempt = 0;
+        loop {
+            let request = http
+                .post(&url)
+                .timeout(TIMEOUT)
+                .header(CONTENT_TYPE, "application/json")
+                .body(body.to_string());
+            let request = match settings.service {
+                Service::Toggl => request.basic_auth(&settings.api_key, Some("api_token")),
+                Service::Clockify => request.header("X-Api-Key", &settings.api_key),
+            };
+            let failure = match request.send().await {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => {
+                    let status = response.status();
+                    if !status.is_server_error() && status.as_u16() != 429 {
+                        return Err(status.to_string());
+                    }
+                    status.to_string()
+                }
+                Err(e) => e.to_string(),
+            };
+            if attempt >= settings.retries {
+                return Err(failure);
+            }
+            tokio::time::sleep(backoff * 2u32.saturating_pow(attempt)).await;
+            attempt += 1;
+        }
+    }
+
+    /// Spawns a task logging every completed pomodoro, one entry after the other so a slow
+    /// service delays the entries rather than the pomodoro. It ends once the pomodoro stops
+    /// and the entries queued by then were sent.
+    pub fn spawn(settings: TimeTracking, mut events: broadcast::Receiver<Event>) -> JoinHandle<()> {
+        let (queue, mut entries) = mpsc::unbounded_channel::<Entry>();
+        let http = reqwest::Client::new();
+        let sender = settings.clone();
+        let sync = tokio::spawn(async move {
+            while let Some(entry) = entries.recv().await {
+                if let Err(e) = send(&http, &sender, &entry, BACKOFF).await {
+                    tracing::warn!(error = %e, "cannot log the pomodoro as a time entry");
+                }
+            }
+        });
+        tokio::spawn(async move {
+            let mut tracker = Tracker::default();
+            loop {
+                let event = match events.recv().await {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                };
+                if let Some((task, start, stop)) = tracker.observe(&event, Local::now()) {
+                    let _ = queue.send(settings.entry(task.as_deref(), start, stop));
+                }
+            }
+            drop(queue);
+            let _ = sync.await;
+        })
+    }
+}
+
+#[cfg(feature = "timetrack")]
+pub use sync::spawn;
+
+#[test]
+fn build_time_entries() {
+    use chrono::TimeZone;
+
+    let toggl: TimeTracking = toml::from_str(
+        r#"
+        service = "toggl"
+        api_key = "secret"
+        workspace = "123"
+        project = "7"
+
+        [projects]
+        "write report" = "42"
+        "#,
+    )
+    .unwrap();
+    let start = Local.with_ymd_and_hms(2024, 1, 2, 9, 0, 0).unwrap();
+    let stop = start + chrono::Duration::minutes(25);
+    let entry = toggl.entry(Some("write report"), start, stop);
+    assert_eq!(entry.project.as_deref(), Some("42"));
+    assert_eq!(toggl.entry(None, start, stop).project.as_deref(), Some("7"));
+    let (url, body) = toggl.request(&entry);
+    assert_eq!(
+        url,
+        "https://api.track.toggl.com/api/v9/workspaces/123/time_entries"
+    );
+    assert_eq!(body["description"], "write report");
+    assert_eq!(
+        (&body["workspace_id"], &body["project_id"]),
+        (&json!(123), &json!(42))
+    );
+    assert_eq!(body["duration"], 25 * 60);
+    let at = start.to_utc().to_rfc3339_opts(SecondsFormat::Secs, true);
+    assert_eq!(body["start"], at);
+
+    let clockify = TimeTracking {
+        service: Service::Clockify,
+        workspace: "abc".to_string(),
+        project: None,
+        ..toggl
+    };
+    let entry = clockify.entry(Some("mail"), start, stop);
+    let (url, body) = clockify.request(&entry);
+    assert_eq!(
+        url,
+        "https://api.clockify.me/api/v1/workspaces/abc/time-entries"
+    );
+    assert_eq!(body["description"], "mail");
+    assert!(body.get("projectId").is_none());
+    assert!(toml::from_str::<TimeTracking>("service = \"harvest\"").is_err());
+}
+
+#[test]
+fn track_completed_pomodoros() {
+    use chrono::TimeZone;
+
+    let at = |minute| Local.with_ymd_and_hms(2024, 1, 2, 9, minute, 0).unwrap();
+    let mut tracker = Tracker::default();
+    assert_eq!(
+        tracker.observe(&Event::TaskSet("mail".to_string()), at(0)),
+        None
+    );
+    assert_eq!(
+        tracker.observe(&Event::PhaseStarted(Phase::Working), at(0)),
+        None
+    );
+    assert_eq!(
+        tracker.observe(&Event::PhaseEnded(Phase::Working), at(25)),
+        Some((Some("mail".to_string()), at(0), at(25)))
+    );
+    tracker.observe(&Event::PhaseStarted(Phase::Working), at(30));
+    tracker.observe(&Event::PhaseSkipped(Phase::Working), at(31));
+    assert_eq!(
+        tracker.observe(&Event::PhaseEnded(Phase::Working), at(55)),
+        None
+    );
+}
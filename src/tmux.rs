@@ -0,0 +1,121 @@
+//! Keeps the `#(pomo tmux)` of tmux status lines up to date.
+//!
+//! tmux runs the commands of its status line again every `status-interval`, 15 seconds by
+//! default. Rather than having it lowered, the daemon asks every tmux client to redraw its
+//! status line whenever the countdown moves to another second or the phase changes. It
+//! does so when the `tmux` config key asks it to or when it was started inside tmux, and
+//! asks less and less often while no tmux server runs.
+
+use std::io;
+use std::process::Stdio;
+use std::time::Duration;
+
+use tokio::process::Command;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+use crate::event::Event;
+use crate::runtime::Client;
+
+/// How long to wait before asking again once no tmux server runs, doubling every time up
+/// to [`MAX_BACKOFF`].
+const BACKOFF: Duration = Duration::from_secs(30);
+const MAX_BACKOFF: Duration = Duration::from_secs(15 * 60);
+
+/// Whether `tmux` failed with `stderr` for want of a server to talk to.
+fn no_server(stderr: &str) -> bool {
+    let stderr = stderr.to_lowercase();
+    stderr.contains("no server running") || stderr.starts_with("error connecting to")
+}
+
+/// The names `tmux list-clients -F '#{client_name}'` prints, one on each line.
+fn client_names(output: &str) -> Vec<&str> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// Has the status line of every attached client redrawn, telling whether a tmux server
+/// runs.
+async fn refresh() -> io::Result<bool> {
+    let output = Command::new("tmux")
+        .args(["list-clients", "-F", "#{client_name}"])
+        .stdin(Stdio::null())
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Ok(!no_server(&String::from_utf8_lossy(&output.stderr)));
+    }
+    for name in client_names(&String::from_utf8_lossy(&output.stdout)) {
+        Command::new("tmux")
+            .args(["refresh-client", "-S", "-t", name])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .await?;
+    }
+    Ok(true)
+}
+
+fn whole_seconds(remaining: Duration) -> u64 {
+    remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0)
+}
+
+/// Spawns a task refreshing the tmux status lines as the pomodoro behind `client` goes,
+/// until it stops or tmux turns out not to be installed.
+pub fn spawn(client: Client) -> JoinHandle<()> {
+    let mut events = client.subscribe();
+    tokio::spawn(async move {
+        let mut shown = None;
+        let mut backoff = BACKOFF;
+        let mut quiet_until = None;
+        loop {
+            match events.recv().await {
+                Ok(Event::Tick { remaining, .. }) => {
+                    if shown.replace(whole_seconds(remaining)) == Some(whole_seconds(remaining)) {
+                        continue;
+                    }
+                }
+                Ok(_) | Err(RecvError::Lagged(_)) => shown = None,
+                Err(RecvError::Closed) => break,
+            }
+            if quiet_until.is_some_and(|until| Instant::now() < until) {
+                continue;
+            }
+            match refresh().await {
+                Err(e) if e.kind() == io::ErrorKind::NotFound => break,
+                Err(e) => tracing::debug!(error = %e, "cannot refresh tmux"),
+                Ok(true) => {
+                    backoff = BACKOFF;
+                    quiet_until = None;
+                }
+                Ok(false) => {
+                    tracing::debug!(backoff = ?backoff, "no tmux server runs");
+                    quiet_until = Some(Instant::now() + backoff);
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                }
+            }
+        }
+    })
+}
+
+#[test]
+fn list_clients() {
+    assert_eq!(
+        client_names("/dev/pts/3\n/dev/pts/7\n\n"),
+        ["/dev/pts/3", "/dev/pts/7"]
+    );
+    assert!(client_names("").is_empty());
+}
+
+#[test]
+fn tell_missing_servers() {
+    assert!(no_server("no server running on /tmp/tmux-1000/default\n"));
+    assert!(no_server(
+        "Error connecting to /tmp/tmux-1000/default (No such file or directory)\n"
+    ));
+    assert!(!no_server("can't find session: work\n"));
+}
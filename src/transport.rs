@@ -0,0 +1,136 @@
+//! The connections the daemon and its clients talk over: a Unix domain socket, or a named
+//! pipe on Windows. Either way a path names where the daemon listens, and the
+//! [`protocol`](crate::protocol) is spoken on top.
+
+#[cfg(unix)]
+mod unix {
+    use std::io;
+    use std::path::{Path, PathBuf};
+
+    use tokio::net::{UnixListener, UnixStream};
+
+    /// The connection a daemon accepts.
+    pub type ServerStream = UnixStream;
+    /// The connection a client opens.
+    pub type ClientStream = UnixStream;
+
+    /// The default socket, `$XDG_RUNTIME_DIR/pomo.sock` falling back to the temporary
+    /// directory.
+    pub fn default_path() -> PathBuf {
+        dirs::runtime_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("pomo.sock")
+    }
+
+    /// Where a daemon accepts connections.
+    pub struct Listener(UnixListener);
+
+    impl Listener {
+        /// Binds the socket at `path`, replacing a stale socket file left by a dead daemon.
+        pub async fn bind(path: &Path) -> io::Result<Self> {
+            if path.exists() {
+                if UnixStream::connect(path).await.is_ok() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AddrInUse,
+                        format!("a daemon is already listening on {}", path.display()),
+                    ));
+                }
+                std::fs::remove_file(path)?;
+            }
+            UnixListener::bind(path).map(Self)
+        }
+
+        pub async fn accept(&mut self) -> io::Result<ServerStream> {
+            self.0.accept().await.map(|(stream, _)| stream)
+        }
+    }
+
+    pub async fn connect(path: &Path) -> io::Result<ClientStream> {
+        UnixStream::connect(path).await
+    }
+
+    /// Removes what is left at `path` once the daemon stopped listening.
+    pub fn clean_up(path: &Path) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+#[cfg(unix)]
+pub use unix::*;
+
+#[cfg(windows)]
+mod windows {
+    use std::io;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    use tokio::net::windows::named_pipe::{
+        ClientOptions, NamedPipeClient, NamedPipeServer, ServerOptions,
+    };
+
+    /// Returned while every instance of the pipe is busy with another client.
+    const ERROR_PIPE_BUSY: i32 = 231;
+    /// Returned when another process already created the first instance of the pipe.
+    const ERROR_ACCESS_DENIED: i32 = 5;
+
+    /// The connection a daemon accepts.
+    pub type ServerStream = NamedPipeServer;
+    /// The connection a client opens.
+    pub type ClientStream = NamedPipeClient;
+
+    /// The default pipe, `\\.\pipe\pomo-<user>`, which every session of the user shares.
+    pub fn default_path() -> PathBuf {
+        let user = std::env::var("USERNAME").unwrap_or_default();
+        PathBuf::from(format!(r"\\.\pipe\pomo-{}", user))
+    }
+
+    /// Where a daemon accepts connections. A pipe instance serves a single client, so
+    /// another one is created for the next client as each connects.
+    pub struct Listener {
+        path: PathBuf,
+        next: NamedPipeServer,
+    }
+
+    impl Listener {
+        /// Creates the pipe at `path`, which goes away with the daemon.
+        pub async fn bind(path: &Path) -> io::Result<Self> {
+            let next = ServerOptions::new()
+                .first_pipe_instance(true)
+                .create(path)
+                .map_err(|e| match e.raw_os_error() {
+                    Some(ERROR_ACCESS_DENIED) => io::Error::new(
+                        io::ErrorKind::AddrInUse,
+                        format!("a daemon is already listening on {}", path.display()),
+                    ),
+                    _ => e,
+                })?;
+            Ok(Self {
+                path: path.to_path_buf(),
+                next,
+            })
+        }
+
+        pub async fn accept(&mut self) -> io::Result<ServerStream> {
+            self.next.connect().await?;
+            let next = ServerOptions::new().create(&self.path)?;
+            Ok(std::mem::replace(&mut self.next, next))
+        }
+    }
+
+    /// Opens the pipe at `path`, waiting for an instance to free up when all are busy.
+    pub async fn connect(path: &Path) -> io::Result<ClientStream> {
+        loop {
+            match ClientOptions::new().open(path) {
+                Err(e) if e.raw_os_error() == Some(ERROR_PIPE_BUSY) => {}
+                opened => return opened,
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+
+    /// Nothing is left behind, as the pipe goes away with its last instance.
+    pub fn clean_up(_path: &Path) {}
+}
+
+#[cfg(windows)]
+pub use windows::*;
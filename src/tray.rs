@@ -0,0 +1,429 @@
+//! An icon in the system tray showing the remaining time of the pomodoro, with a menu to
+//! pause, resume or skip it and to quit.
+//!
+//! Rather than running a GUI toolkit's event loop next to the tokio runtime, the icon is a
+//! `org.kde.StatusNotifierItem` on the D-Bus session bus, which KDE Plasma, the GNOME
+//! AppIndicator extension, waybar and most other tray hosts on Linux show. Its menu is a
+//! `com.canonical.dbusmenu` object, and both are served from the same tokio tasks as the
+//! rest of the daemon.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Str, Structure, Value};
+use zbus::{fdo, interface};
+
+use crate::event::Event;
+use crate::pomodoro::{Labels, Status};
+use crate::runtime::Client;
+
+/// The path of the tray icon object.
+pub const ITEM_PATH: &str = "/StatusNotifierItem";
+/// The path of its menu.
+pub const MENU_PATH: &str = "/MenuBar";
+
+/// An entry of the tray menu, whose id in the menu is its position from 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Entry {
+    /// Pauses the running phase, or resumes the paused one.
+    Toggle,
+    Skip,
+    /// Stops the pomodoro, which ends the session running it.
+    Quit,
+}
+
+impl Entry {
+    const ALL: [Entry; 3] = [Entry::Toggle, Entry::Skip, Entry::Quit];
+
+    fn id(self) -> i32 {
+        match self {
+            Entry::Toggle => 1,
+            Entry::Skip => 2,
+            Entry::Quit => 3,
+        }
+    }
+
+    fn from_id(id: i32) -> Option<Self> {
+        Self::ALL.iter().copied().find(|entry| entry.id() == id)
+    }
+
+    fn label(self, paused: bool) -> &'static str {
+        match self {
+            Entry::Toggle if paused => "Resume",
+            Entry::Toggle => "Pause",
+            Entry::Skip => "Skip",
+            Entry::Quit => "Quit",
+        }
+    }
+
+    /// Does what the entry says to the pomodoro behind `client`.
+    async fn activate(self, client: &Client) -> fdo::Result<()> {
+        let done = match self {
+            Entry::Toggle => client.toggle().await.map(|_| ()),
+            Entry::Skip => client.skip().await,
+            Entry::Quit => client.abort().await,
+        };
+        done.map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+}
+
+fn countdown(remaining: Duration) -> String {
+    let secs = remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0);
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+}
+
+/// The title of the icon, which hosts such as waybar show next to it.
+fn title(status: &Status, labels: &Labels) -> String {
+    let label = status.phase.label(labels);
+    if status.awaiting_ack {
+        format!("{} is over", label)
+    } else if status.paused {
+        format!("{} {} (paused)", label, countdown(status.remaining))
+    } else {
+        format!("{} {}", label, countdown(status.remaining))
+    }
+}
+
+fn icon(status: &Status) -> &'static str {
+    if status.paused || status.awaiting_ack {
+        "media-playback-pause"
+    } else {
+        "appointment-soon"
+    }
+}
+
+struct Item {
+    client: Client,
+    labels: Labels,
+}
+
+impl Item {
+    async fn title_now(&self) -> String {
+        match self.client.status().await {
+            Ok(status) => title(&status, &self.labels),
+            Err(_) => "pomo".to_string(),
+        }
+    }
+}
+
+#[interface(name = "org.kde.StatusNotifierItem")]
+impl Item {
+    #[zbus(property)]
+    fn category(&self) -> &str {
+        "ApplicationStatus"
+    }
+
+    #[zbus(property)]
+    fn id(&self) -> &str {
+        "pomo"
+    }
+
+    #[zbus(property)]
+    async fn title(&self) -> String {
+        self.title_now().await
+    }
+
+    #[zbus(property)]
+    fn status(&self) -> &str {
+        "Active"
+    }
+
+    #[zbus(property)]
+    async fn icon_name(&self) -> String {
+        match self.client.status().await {
+            Ok(status) => icon(&status).to_string(),
+            Err(_) => "appointment-soon".to_string(),
+        }
+    }
+
+    /// The icon name, icon pixmaps, title and description of the tooltip.
+    #[zbus(property)]
+    async fn tool_tip(&self) -> (String, Vec<(i32, i32, Vec<u8>)>, String, String) {
+        (
+            "".to_string(),
+            Vec::new(),
+            "pomo".to_string(),
+            self.title_now().await,
+        )
+    }
+
+    #[zbus(property)]
+    fn item_is_menu(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn menu(&self) -> OwnedObjectPath {
+        ObjectPath::from_static_str_unchecked(MENU_PATH).into()
+    }
+
+    /// Pauses or resumes, for hosts which activate the icon on a click anyway.
+    async fn activate(&self, _x: i32, _y: i32) -> fdo::Result<()> {
+        Entry::Toggle.activate(&self.client).await
+    }
+
+    #[zbus(signal)]
+    async fn new_title(emitter: &SignalEmitter<'_>) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn new_icon(emitter: &SignalEmitter<'_>) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn new_tool_tip(emitter: &SignalEmitter<'_>) -> zbus::Result<()>;
+}
+
+/// The `(ia{sv}av)` layout of a menu item: its id, its properties and its children.
+type Layout = (i32, HashMap<String, OwnedValue>, Vec<OwnedValue>);
+
+struct Menu {
+    client: Client,
+    revision: Arc<AtomicU32>,
+}
+
+impl Menu {
+    async fn entries(&self) -> Vec<(i32, HashMap<String, OwnedValue>)> {
+        let paused = match self.client.status().await {
+            Ok(status) => status.paused,
+            Err(_) => false,
+        };
+        Entry::ALL
+            .iter()
+            .map(|entry| {
+                let mut properties = HashMap::new();
+                properties.insert(
+                    "label".to_string(),
+                    OwnedValue::from(Str::from_static(entry.label(paused))),
+                );
+                (entry.id(), properties)
+            })
+            .collect()
+    }
+}
+
+fn failed(e: zbus::zvariant::Error) -> fdo::Error {
+    fdo::Error::Failed(e.to_string())
+}
+
+#[interface(name = "com.canonical.dbusmenu")]
+impl Menu {
+    /// The whole menu, whichever part is asked for, as it is a single level deep.
+    async fn get_layout(
+        &self,
+        _parent_id: i32,
+        _recursion_depth: i32,
+        _property_names: Vec<String>,
+    ) -> fdo::Result<(u32, Layout)> {
+        let mut children = Vec::new();
+        for (id, properties) in self.entries().await {
+            let child = Structure::from((id, properties, Vec::<OwnedValue>::new()));
+            children.push(OwnedValue::try_from(Value::from(child)).map_err(failed)?);
+        }
+        let mut root = HashMap::new();
+        root.insert(
+            "children-display".to_string(),
+            OwnedValue::from(Str::from_static("submenu")),
+        );
+        Ok((self.revision.load(Ordering::SeqCst), (0, root, children)))
+    }
+
+    async fn get_group_properties(
+        &self,
+        ids: Vec<i32>,
+        _property_names: Vec<String>,
+    ) -> Vec<(i32, HashMap<String, OwnedValue>)> {
+        let mut entries = self.entries().await;
+        entries.retain(|(id, _)| ids.is_empty() || ids.contains(id));
+        entries
+    }
+
+    async fn event(
+        &self,
+        id: i32,
+        event_id: &str,
+        _data: Value<'_>,
+        _timestamp: u32,
+    ) -> fdo::Result<()> {
+        match Entry::from_id(id) {
+            Some(entry) if event_id == "clicked" => entry.activate(&self.client).await,
+            _ => Ok(()),
+        }
+    }
+
+    async fn event_group(&self, events: Vec<(i32, String, OwnedValue, u32)>) -> Vec<i32> {
+        let mut unknown = Vec::new();
+        for (id, event_id, _, _) in events {
+            match Entry::from_id(id) {
+                Some(entry) if event_id == "clicked" => {
+                    let _ = entry.activate(&self.client).await;
+                }
+                Some(_) => {}
+                None => unknown.push(id),
+            }
+        }
+        unknown
+    }
+
+    fn about_to_show(&self, _id: i32) -> bool {
+        false
+    }
+
+    fn about_to_show_group(&self, _ids: Vec<i32>) -> (Vec<i32>, Vec<i32>) {
+        (Vec::new(), Vec::new())
+    }
+
+    #[zbus(property)]
+    fn version(&self) -> u32 {
+        3
+    }
+
+    #[zbus(property)]
+    fn text_direction(&self) -> &str {
+        "ltr"
+    }
+
+    #[zbus(property)]
+    fn status(&self) -> &str {
+        "normal"
+    }
+
+    #[zbus(signal)]
+    async fn layout_updated(
+        emitter: &SignalEmitter<'_>,
+        revision: u32,
+        parent: i32,
+    ) -> zbus::Result<()>;
+}
+
+#[zbus::proxy(
+    interface = "org.kde.StatusNotifierWatcher",
+    default_service = "org.kde.StatusNotifierWatcher",
+    default_path = "/StatusNotifierWatcher"
+)]
+trait StatusNotifierWatcher {
+    fn register_status_notifier_item(&self, service: &str) -> zbus::Result<()>;
+}
+
+/// Shows the icon of the pomodoro behind `client` in the tray and keeps it up to date
+/// until the pomodoro stops.
+pub async fn serve(client: Client, labels: Labels) -> zbus::Result<()> {
+    let mut events = client.subscribe();
+    let revision = Arc::new(AtomicU32::new(1));
+    let name = format!("org.kde.StatusNotifierItem-{}-1", std::process::id());
+    let item = Item {
+        client: client.clone(),
+        labels,
+    };
+    let menu = Menu {
+        client,
+        revision: revision.clone(),
+    };
+    let connection = zbus::connection::Builder::session()?
+        .name(name.as_str())?
+        .serve_at(ITEM_PATH, item)?
+        .serve_at(MENU_PATH, menu)?
+        .build()
+        .await?;
+    StatusNotifierWatcherProxy::new(&connection)
+        .await?
+        .register_status_notifier_item(&name)
+        .await?;
+    let item = SignalEmitter::new(&connection, ITEM_PATH)?;
+    let menu = SignalEmitter::new(&connection, MENU_PATH)?;
+    // The whole seconds last shown, so that fast tick rates do not flood the bus.
+    let mut shown = None;
+    loop {
+        match events.recv().await {
+            Ok(Event::Tick { remaining, .. }) => {
+                let secs = remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0);
+                if shown.replace(secs) != Some(secs) {
+                    Item::new_title(&item).await?;
+                    Item::new_tool_tip(&item).await?;
+                }
+            }
+            Ok(Event::PhaseStarted(_))
+            | Ok(Event::Paused)
+            | Ok(Event::Resumed)
+            | Ok(Event::AwaitingAck(_))
+            | Err(RecvError::Lagged(_)) => {
+                shown = None;
+                Item::new_title(&item).await?;
+                Item::new_tool_tip(&item).await?;
+                Item::new_icon(&item).await?;
+                let revision = revision.fetch_add(1, Ordering::SeqCst) + 1;
+                Menu::layout_updated(&menu, revision, 0).await?;
+            }
+            Ok(_) => {}
+            Err(RecvError::Closed) => return Ok(()),
+        }
+    }
+}
+
+/// Spawns a task showing the pomodoro behind `client` in the system tray.
+pub fn spawn(client: Client, labels: Labels) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        if let Err(e) = serve(client, labels).await {
+            tracing::warn!(error = %e, "cannot show the tray icon");
+        }
+    })
+}
+
+#[test]
+fn title_the_icon() {
+    let labels = Labels::default();
+    let mut status = crate::Pomodoro::builder().build().unwrap().status();
+    status.paused = false;
+    assert_eq!(title(&status, &labels), "working 25:00");
+    status.remaining = Duration::from_millis(59_001);
+    status.paused = true;
+    assert_eq!(title(&status, &labels), "working 01:00 (paused)");
+    status.phase = crate::Phase::ShortBreak;
+    status.awaiting_ack = true;
+    assert_eq!(title(&status, &labels), "short break is over");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn click_menu_entries() {
+    use std::convert::TryInto;
+
+    let pomodoro = crate::Pomodoro::builder().build().unwrap();
+    let (runtime, client, _events) = crate::start(pomodoro).await;
+    let menu = Menu {
+        client: client.clone(),
+        revision: Arc::new(AtomicU32::new(1)),
+    };
+    let labels = |layout: (u32, Layout)| -> Vec<String> {
+        let (_, (_, _, children)) = layout;
+        children
+            .into_iter()
+            .map(|child| {
+                let child: Structure<'_> = Value::from(child).try_into().unwrap();
+                let (_, properties, _): (i32, HashMap<String, OwnedValue>, Vec<OwnedValue>) =
+                    child.try_into().unwrap();
+                String::try_from(properties["label"].try_clone().unwrap()).unwrap()
+            })
+            .collect()
+    };
+    let layout = menu.get_layout(0, -1, Vec::new()).await.unwrap();
+    assert_eq!(labels(layout), ["Pause", "Skip", "Quit"]);
+
+    let click = |entry: Entry| menu.event(entry.id(), "clicked", Value::from(0), 0);
+    click(Entry::Toggle).await.unwrap();
+    assert!(client.status().await.unwrap().paused);
+    let layout = menu.get_layout(0, -1, Vec::new()).await.unwrap();
+    assert_eq!(labels(layout), ["Resume", "Skip", "Quit"]);
+    click(Entry::Toggle).await.unwrap();
+    assert!(!client.status().await.unwrap().paused);
+    click(Entry::Skip).await.unwrap();
+    assert_eq!(
+        client.status().await.unwrap().phase,
+        crate::Phase::ShortBreak
+    );
+    click(Entry::Quit).await.unwrap();
+    runtime.join().await.unwrap();
+}
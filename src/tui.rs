@@ -0,0 +1,211 @@
+use std::future::Future;
+use std::io;
+use std::time::Duration;
+
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style};
+use ratatui::widgets::{Block, Gauge, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use pomo::{Client, Event, Labels, Phase, PomoError, Status};
+
+use crate::keys::{read_actions, Action};
+use crate::output::clock_face;
+
+struct View<'a> {
+    status: Option<Status>,
+    completed: bool,
+    labels: &'a Labels,
+}
+
+impl View<'_> {
+    fn ratio(&self) -> f64 {
+        match &self.status {
+            Some(status) => {
+                let total = status.elapsed + status.remaining;
+                if total.as_secs_f64() == 0.0 {
+                    1.0
+                } else {
+                    status.elapsed.as_secs_f64() / total.as_secs_f64()
+                }
+            }
+            None => 0.0,
+        }
+    }
+}
+
+fn render(frame: &mut Frame, view: &View) {
+    let block = Block::bordered().title(" pomo ");
+    let area = block.inner(frame.area());
+    frame.render_widget(block, frame.area());
+    let [phase_area, gauge_area, counter_area, _, help_area] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Length(1),
+        Constraint::Length(1),
+        Constraint::Min(0),
+        Constraint::Length(1),
+    ])
+    .spacing(1)
+    .areas(area);
+
+    let status = match &view.status {
+        Some(status) => status,
+        None => {
+            frame.render_widget(Paragraph::new("The pomodoro has stopped"), phase_area);
+            return;
+        }
+    };
+    let headline = if view.completed {
+        "Session complete".to_string()
+    } else if status.paused {
+        format!("{} (paused)", status.label(view.labels))
+    } else {
+        status.label(view.labels).to_string()
+    };
+    frame.render_widget(Paragraph::new(headline), phase_area);
+    let color = match status.phase {
+        Phase::Working => Color::Red,
+        Phase::ShortBreak | Phase::LongBreak => Color::Green,
+    };
+    let gauge = Gauge::default()
+        .gauge_style(Style::default().fg(color))
+        .ratio(view.ratio().clamp(0.0, 1.0))
+        .label(clock_face(status.remaining));
+    frame.render_widget(gauge, gauge_area);
+    frame.render_widget(
+        Paragraph::new(format!(
+            "pomodoros: {}  short breaks: {}  long breaks: {}",
+            status.pomodoros, status.short_breaks, status.long_breaks
+        )),
+        counter_area,
+    );
+    frame.render_widget(
+        Paragraph::new(
+            "space pause/resume · s skip · e extend · v void · i/x interrupted · q quit",
+        )
+        .style(Style::default().fg(Color::DarkGray)),
+        help_area,
+    );
+}
+
+/// Shows the running pomodoro until the user quits or `terminated` resolves. Extending the
+/// phase adds `extend_step` to it.
+pub async fn run(
+    client: &Client,
+    events: &mut broadcast::Receiver<Event>,
+    labels: &Labels,
+    extend_step: Duration,
+    terminated: impl Future<Output = ()>,
+) -> io::Result<()> {
+    let mut terminal = ratatui::try_init()?;
+    let result = drive(
+        &mut terminal,
+        client,
+        events,
+        labels,
+        extend_step,
+        terminated,
+    )
+    .await;
+    ratatui::restore();
+    result
+}
+
+async fn drive(
+    terminal: &mut DefaultTerminal,
+    client: &Client,
+    events: &mut broadcast::Receiver<Event>,
+    labels: &Labels,
+    extend_step: Duration,
+    terminated: impl Future<Output = ()>,
+) -> io::Result<()> {
+    tokio::pin!(terminated);
+    let mut actions = read_actions();
+    let mut view = View {
+        status: client.status().await.ok(),
+        completed: false,
+        labels,
+    };
+    loop {
+        terminal.draw(|frame| render(frame, &view))?;
+        tokio::select! {
+            _ = &mut terminated => return Ok(()),
+            action = actions.recv() => {
+                let sent = match action {
+                    Some(Action::TogglePause) => client.toggle().await.map(|_| ()),
+                    Some(Action::Skip) => client.skip().await,
+                    Some(Action::Extend) => client.extend(extend_step).await,
+                    Some(Action::Void) => client.void("").await,
+                    Some(Action::Interrupt(kind)) => client.interrupt(kind, "").await,
+                    Some(Action::Quit) | None => return Ok(()),
+                };
+                if let Err(PomoError::Stopped) = sent {
+                    return Ok(());
+                }
+            }
+            event = events.recv() => match event {
+                Ok(Event::Tick { phase, elapsed, remaining }) => {
+                    if let Some(status) = view.status.as_mut() {
+                        status.phase = phase;
+                        status.elapsed = elapsed;
+                        status.remaining = remaining;
+                    }
+                }
+                Ok(event) => {
+                    view.completed |= event == Event::Completed;
+                    view.status = client.status().await.ok();
+                }
+                Err(RecvError::Lagged(_)) => view.status = client.status().await.ok(),
+                Err(RecvError::Closed) => return Ok(()),
+            },
+        }
+    }
+}
+
+#[test]
+fn render_view() {
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    let view = View {
+        status: Some(Status {
+            phase: Phase::Working,
+            elapsed: Duration::from_secs(5 * 60),
+            remaining: Duration::from_secs(20 * 60),
+            pomodoros: 2,
+            short_breaks: 1,
+            long_breaks: 0,
+            paused: true,
+            focused: Duration::from_secs(0),
+            on_break: Duration::from_secs(0),
+            paused_for: Duration::from_secs(0),
+            pauses: 0,
+            label: None,
+            task: None,
+            goal: None,
+            awaiting_ack: false,
+            overtime: Duration::from_secs(0),
+            ends_at: None,
+            session_ends_at: None,
+        }),
+        completed: false,
+        labels: &Labels {
+            working: "Focus".to_string(),
+            ..Labels::default()
+        },
+    };
+    assert_eq!(view.ratio(), 0.2);
+    let mut terminal = Terminal::new(TestBackend::new(60, 12)).unwrap();
+    terminal.draw(|frame| render(frame, &view)).unwrap();
+    let screen: String = terminal
+        .backend()
+        .buffer()
+        .content()
+        .iter()
+        .map(|cell| cell.symbol())
+        .collect();
+    assert!(screen.contains("Focus (paused)"));
+    assert!(screen.contains("20:00"));
+    assert!(screen.contains("pomodoros: 2"));
+}
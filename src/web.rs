@@ -0,0 +1,399 @@
+//! A small web server for dashboards. `/` serves a single-page dashboard and `/events` is a
+//! WebSocket pushing every event of the running pomodoro, the per-second ticks included,
+//! as the JSON lines of the [`feed`](crate::feed).
+//!
+//! Each state change is followed by a `status` line with a snapshot of the pomodoro, and the
+//! WebSocket takes `pause`, `resume` and `skip` as text messages. It is only opened with
+//! the token the dashboard is served with, which changes with every daemon, and browsers
+//! may only open it from the dashboard itself, so that other web pages cannot control the
+//! pomodoro.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use chrono::Local;
+use futures_util::{SinkExt, StreamExt};
+use serde::Serialize;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::error::RecvError;
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use tokio_tungstenite::tungstenite::protocol::Role;
+use tokio_tungstenite::tungstenite::{self, Message};
+use tokio_tungstenite::WebSocketStream;
+
+use crate::error::PomoError;
+use crate::event::Event;
+use crate::feed;
+use crate::history::Store;
+use crate::pomodoro::{Goal, Phase, Status};
+use crate::runtime::Client;
+use crate::stats::Stats;
+
+/// The path the WebSocket is served on.
+pub const EVENTS: &str = "/events";
+
+const DASHBOARD: &str = include_str!("dashboard.html");
+
+/// The request head is read line by line up to this many lines.
+const MAX_HEADERS: usize = 64;
+
+/// Binds `addr`, e.g. `127.0.0.1:7878`.
+pub async fn bind(addr: SocketAddr) -> io::Result<TcpListener> {
+    TcpListener::bind(addr).await
+}
+
+/// Serves the dashboard and the events of the pomodoro behind `client` on every connection
+/// accepted by `listener`. The dashboard counts today's pomodoros from the `history`
+/// database when one is given.
+pub async fn serve(
+    listener: TcpListener,
+    client: Client,
+    history: Option<Store>,
+) -> io::Result<()> {
+    let token: Arc<str> = token().into();
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let client = client.clone();
+        let history = history.clone();
+        let token = token.clone();
+        tokio::spawn(async move {
+            let _ = handle(stream, client, history, &token).await;
+        });
+    }
+}
+
+/// A token nobody else can tell, from the keys the standard library seeds its hash maps
+/// with at random.
+fn token() -> String {
+    let half = || RandomState::new().build_hasher().finish();
+    format!("{:016x}{:016x}", half(), half())
+}
+
+/// The parts of an HTTP request the server looks at.
+struct Head {
+    path: String,
+    websocket_key: Option<String>,
+    origin: Option<String>,
+    host: Option<String>,
+}
+
+impl Head {
+    /// The value of `name` in the query of the path.
+    fn query(&self, name: &str) -> Option<&str> {
+        let (_, query) = self.path.split_once('?')?;
+        query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))
+    }
+}
+
+async fn read_head(stream: &mut BufReader<TcpStream>) -> io::Result<Option<Head>> {
+    let mut line = String::new();
+    stream.read_line(&mut line).await?;
+    let path = match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+        ["GET", path, _] => path.to_string(),
+        _ => return Ok(None),
+    };
+    let mut websocket_key = None;
+    let mut origin = None;
+    let mut host = None;
+    for _ in 0..MAX_HEADERS {
+        line.clear();
+        if stream.read_line(&mut line).await? == 0 || line.trim().is_empty() {
+            return Ok(Some(Head {
+                path,
+                websocket_key,
+                origin,
+                host,
+            }));
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            let name = name.trim();
+            if name.eq_ignore_ascii_case("sec-websocket-key") {
+                websocket_key = Some(value.trim().to_string());
+            } else if name.eq_ignore_ascii_case("origin") {
+                origin = Some(value.trim().to_string());
+            } else if name.eq_ignore_ascii_case("host") {
+                host = Some(value.trim().to_string());
+            }
+        }
+    }
+    Ok(None)
+}
+
+async fn respond(stream: &mut BufReader<TcpStream>, status: &str, body: &str) -> io::Result<()> {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        if body.starts_with('<') {
+            "text/html; charset=utf-8"
+        } else {
+            "text/plain; charset=utf-8"
+        },
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Whether `host` names the address the server is `served` at, as a host renamed to it
+/// through DNS does not.
+fn known_host(host: &str, served: SocketAddr) -> bool {
+    host == served.to_string()
+        || (served.ip().is_loopback() && host == format!("localhost:{}", served.port()))
+}
+
+/// Whether a request sent from `origin` comes from the pages served at `served`. Browsers
+/// send the origin of the page opening a WebSocket, which other clients leave out.
+fn same_origin(origin: Option<&str>, served: SocketAddr) -> bool {
+    match origin {
+        Some(origin) => origin
+            .strip_prefix("http://")
+            .is_some_and(|host| known_host(host, served)),
+        None => true,
+    }
+}
+
+async fn handle(
+    stream: TcpStream,
+    client: Client,
+    history: Option<Store>,
+    token: &str,
+) -> tungstenite::Result<()> {
+    let mut stream = BufReader::new(stream);
+    let head = match read_head(&mut stream).await? {
+        Some(head) => head,
+        None => return Ok(respond(&mut stream, "400 Bad Request", "bad request\n").await?),
+    };
+    let served = stream.get_ref().local_addr()?;
+    let path = head.path.split('?').next().unwrap_or_default();
+    match (path, &head.websocket_key) {
+        ("/", _) | ("/index.html", _) => {
+            if !head
+                .host
+                .as_deref()
+                .is_some_and(|host| known_host(host, served))
+            {
+                let body = "the dashboard is only served at its address\n";
+                return Ok(respond(&mut stream, "403 Forbidden", body).await?);
+            }
+            let dashboard = DASHBOARD.replace("{{token}}", token);
+            Ok(respond(&mut stream, "200 OK", &dashboard).await?)
+        }
+        (EVENTS, Some(_))
+            if !same_origin(head.origin.as_deref(), served)
+                || head.query("token") != Some(token) =>
+        {
+            let body = "the events are only served to the dashboard\n";
+            Ok(respond(&mut stream, "403 Forbidden", body).await?)
+        }
+        (EVENTS, Some(key)) => {
+            let accepted = format!(
+                "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\
+                 Connection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+                derive_accept_key(key.as_bytes())
+            );
+            stream.write_all(accepted.as_bytes()).await?;
+            let socket = WebSocketStream::from_raw_socket(stream, Role::Server, None).await;
+            stream_events(socket, client, history).await
+        }
+        (EVENTS, None) => {
+            let body = "this is a WebSocket endpoint\n";
+            Ok(respond(&mut stream, "426 Upgrade Required", body).await?)
+        }
+        _ => Ok(respond(&mut stream, "404 Not Found", "not found\n").await?),
+    }
+}
+
+/// The `status` line sent to the dashboard.
+#[derive(Serialize)]
+struct StatusLine<'a> {
+    event: &'static str,
+    phase: Phase,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    label: Option<&'a str>,
+    remaining_secs: u64,
+    paused: bool,
+    pomodoros: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    task: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    goal: Option<Goal>,
+    /// Pomodoros completed today according to the history.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    today: Option<usize>,
+}
+
+fn status_line(status: &Status, today: Option<usize>) -> String {
+    let line = StatusLine {
+        event: "status",
+        phase: status.phase,
+        label: status.label.as_deref(),
+        remaining_secs: status.remaining.as_secs() + u64::from(status.remaining.subsec_nanos() > 0),
+        paused: status.paused,
+        pomodoros: status.pomodoros,
+        task: status.task.as_deref(),
+        goal: status.goal,
+        today,
+    };
+    serde_json::to_string(&line).unwrap_or_default()
+}
+
+fn error_line(e: &PomoError) -> String {
+    serde_json::json!({ "event": "error", "message": e.to_string() }).to_string()
+}
+
+async fn completed_today(history: Option<Store>) -> Option<usize> {
+    let records = tokio::task::spawn_blocking(move || history?.open().ok()?.records().ok())
+        .await
+        .ok()??;
+    Some(Stats::from_records(&records, Local::now()).today)
+}
+
+async fn stream_events(
+    socket: WebSocketStream<BufReader<TcpStream>>,
+    client: Client,
+    history: Option<Store>,
+) -> tungstenite::Result<()> {
+    let (mut sink, mut incoming) = socket.split();
+    let mut events = client.subscribe();
+    let mut today = completed_today(history).await;
+    let mut changed = true;
+    loop {
+        if std::mem::take(&mut changed) {
+            match client.status().await {
+                Ok(status) => {
+                    sink.send(Message::text(status_line(&status, today)))
+                        .await?
+                }
+                Err(_) => break,
+            }
+        }
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => {
+                    let line = feed::encode(&event, Local::now());
+                    sink.send(Message::text(line)).await?;
+                    if let (Event::PhaseEnded(Phase::Working), Some(today)) = (&event, &mut today) {
+                        *today += 1;
+                    }
+                    changed = !matches!(event, Event::Tick { .. });
+                }
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            },
+            message = incoming.next() => match message {
+                Some(Ok(Message::Text(command))) => {
+                    let done = match command.as_str().trim() {
+                        "pause" => client.pause().await,
+                        "resume" => client.resume().await,
+                        "skip" => client.skip().await,
+                        _ => continue,
+                    };
+                    if let Err(e) = done {
+                        sink.send(Message::text(error_line(&e))).await?;
+                    }
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return Ok(()),
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    sink.close().await
+}
+
+#[test]
+fn check_origins() {
+    let served: SocketAddr = ([127, 0, 0, 1], 7878).into();
+    assert!(same_origin(None, served));
+    assert!(same_origin(Some("http://127.0.0.1:7878"), served));
+    assert!(same_origin(Some("http://localhost:7878"), served));
+    assert!(!same_origin(Some("http://localhost:8080"), served));
+    assert!(!same_origin(Some("https://evil.example"), served));
+    assert!(!same_origin(Some("null"), served));
+    let served: SocketAddr = ([192, 168, 1, 2], 7878).into();
+    assert!(!same_origin(Some("http://localhost:7878"), served));
+}
+
+/// The next line other than a tick read from `socket`.
+#[cfg(test)]
+async fn next_line(socket: &mut WebSocketStream<TcpStream>) -> serde_json::Value {
+    loop {
+        let message = socket.next().await.unwrap().unwrap();
+        let line: serde_json::Value = serde_json::from_str(message.to_text().unwrap()).unwrap();
+        if line["event"] != "tick" {
+            return line;
+        }
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn serve_dashboard_and_events() {
+    use crate::pomodoro::Pomodoro;
+    use tokio::io::AsyncReadExt;
+    use tokio_tungstenite::client_async;
+
+    let pomodoro = Pomodoro::builder().build().unwrap();
+    let (runtime, client, _events) = crate::start(pomodoro).await;
+    let listener = bind(([127, 0, 0, 1], 0).into()).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(serve(listener, client.clone(), None));
+
+    let request = |path: String, headers: String| async move {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!("GET {} HTTP/1.1\r\nHost: {}\r\n{}\r\n", path, addr, headers);
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    };
+    let get = |path: &str| request(path.to_string(), String::new());
+    let dashboard = get("/").await;
+    assert!(dashboard.starts_with("HTTP/1.1 200 OK"));
+    assert!(get("/elsewhere").await.starts_with("HTTP/1.1 404"));
+    assert!(get(EVENTS).await.starts_with("HTTP/1.1 426"));
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nHost: evil.example\r\n\r\n")
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 403"));
+
+    let start = dashboard.find("token=").unwrap() + "token=".len();
+    let token = &dashboard[start..start + 32];
+    let upgrade = |origin: &str| {
+        format!(
+            "Origin: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Version: 13\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n",
+            origin
+        )
+    };
+    let events = format!("{}?token={}", EVENTS, token);
+    let foreign = request(events.clone(), upgrade("http://evil.example")).await;
+    assert!(foreign.starts_with("HTTP/1.1 403"));
+    let own = format!("http://{}", addr);
+    let tokenless = request(EVENTS.to_string(), upgrade(&own)).await;
+    assert!(tokenless.starts_with("HTTP/1.1 403"));
+
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let url = format!("ws://{}{}", addr, events);
+    let (mut socket, _) = client_async(url, stream).await.unwrap();
+    let line = next_line(&mut socket).await;
+    assert_eq!(line["event"], "status");
+    assert_eq!(line["paused"], false);
+    socket.send(Message::text("pause")).await.unwrap();
+    assert_eq!(next_line(&mut socket).await["event"], "paused");
+    let line = next_line(&mut socket).await;
+    assert_eq!(line["event"], "status");
+    assert_eq!(line["paused"], true);
+
+    server.abort();
+    runtime.shutdown().await.unwrap();
+}
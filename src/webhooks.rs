@@ -0,0 +1,327 @@
+//! HTTP webhooks, configured as `[[webhooks]]` tables in the config file, which POST to a
+//! URL on the events they list.
+//!
+//! Without a `body` a webhook receives the JSON line of the [`feed`](crate::feed). A `body`
+//! is a [`template`](crate::template) in which `{event}` is replaced with the name of the
+//! event too, so it can take the shape Slack, Discord or ntfy expect. The values are
+//! escaped for JSON when the content type is JSON. The `{{name}}` placeholders of older
+//! configs still work, but are deprecated.
+//!
+//! ```toml
+//! [[webhooks]]
+//! url = "https://ntfy.sh/my-pomodoros"
+//! events = ["phase_started"]
+//! body = "{phase} until {ends_at}"
+//! content_type = "text/plain"
+//! ```
+
+use std::collections::BTreeMap;
+
+use chrono::Local;
+use serde::{Deserialize, Deserializer};
+
+use crate::event::{self, Event};
+use crate::feed;
+use crate::pomodoro::{Labels, Status};
+use crate::template::{self, Values};
+
+/// The placeholders older configs wrote as `{{name}}`.
+const PLACEHOLDERS: [&str; 6] = ["event", "phase", "remaining", "ends_at", "count", "task"];
+
+/// Where and what to POST, and on which events.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Webhook {
+    pub url: String,
+    /// The names of the events to POST on, e.g. `phase_started`, or every event but the
+    /// ticks when empty.
+    #[serde(default, deserialize_with = "event_names")]
+    pub events: Vec<String>,
+    pub body: Option<String>,
+    #[serde(default = "json")]
+    pub content_type: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// How many more times to try a delivery which failed for a reason that may go away.
+    #[serde(default = "three")]
+    pub retries: u32,
+}
+
+fn json() -> String {
+    "application/json".to_string()
+}
+
+fn three() -> u32 {
+    3
+}
+
+fn event_names<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    let names = Vec::<String>::deserialize(deserializer)?;
+    match names
+        .iter()
+        .find(|name| !event::NAMES.contains(&name.as_str()))
+    {
+        Some(name) => Err(serde::de::Error::custom(format!("unknown event: {}", name))),
+        None => Ok(names),
+    }
+}
+
+impl Webhook {
+    /// Whether the webhook is called on `event`.
+    pub fn wants(&self, event: &Event) -> bool {
+        if self.events.is_empty() {
+            return !matches!(event, Event::Tick { .. });
+        }
+        self.events.iter().any(|name| name == event.name())
+    }
+
+    /// Whether the body uses the deprecated `{{name}}` placeholders.
+    pub fn deprecated(&self) -> bool {
+        self.body.as_deref().is_some_and(|body| {
+            PLACEHOLDERS
+                .iter()
+                .any(|name| body.contains(&format!("{{{{{}}}}}", name)))
+        })
+    }
+
+    /// The body to POST for `event`, with `status` taken right after it.
+    pub fn render(&self, event: &Event, status: Option<&Status>, labels: &Labels) -> String {
+        let body = match &self.body {
+            Some(body) => body,
+            None => return feed::encode(event, Local::now()),
+        };
+        let body = PLACEHOLDERS
+            .iter()
+            .fold(body.clone(), |body, name| {
+                body.replace(&format!("{{{{{}}}}}", name), &format!("{{{}}}", name))
+            })
+            .replace("{event}", event.name());
+        let values = Values::at(event, status, labels);
+        if self.content_type.contains("json") {
+            template::render_with(&body, &values, json_escape)
+        } else {
+            template::render(&body, &values)
+        }
+    }
+}
+
+/// `value` escaped to go between the quotes of a JSON string.
+fn json_escape(value: &str) -> String {
+    let quoted = serde_json::to_string(value).unwrap_or_default();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+#[cfg(feature = "webhooks")]
+mod delivery {
+    use std::time::Duration;
+
+    use reqwest::header::CONTENT_TYPE;
+    use tokio::sync::broadcast::{self, error::RecvError};
+    use tokio::task::JoinHandle;
+
+    use super::Webhook;
+    use crate::event::Event;
+    use crate::pomodoro::Labels;
+    use crate::runtime::Client;
+
+    /// How long to wait before the first retry, doubling with every other one.
+    const BACKOFF: Duration = Duration::from_secs(1);
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// POSTs `body` to `webhook`, retrying after `backoff` when the server cannot be reached,
+    /// is overloaded or fails.
+    pub(super) async fn deliver(
+        http: &reqwest::Client,
+        webhook: &Webhook,
+        body: String,
+        backoff: Duration,
+    ) -> Result<(), String> {
+        let mut attempt = 0;
+        loop {
+            let mut request = http
+                .post(&webhook.url)
+                .timeout(TIMEOUT)
+                .header(CONTENT_TYPE, &webhook.content_type)
+                .body(body.clone());
+            for (name, value) in &webhook.headers {
+                request = request.header(name, value);
+            }
+            let failure = match request.send().await {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => {
+                    let status = response.status();
+                    if !status.is_server_error() && status.as_u16() != 429 {
+                        return Err(status.to_string());
+                    }
+                    status.to_string()
+                }
+                Err(e) => e.to_string(),
+            };
+            if attempt >= webhook.retries {
+                return Err(failure);
+            }
+            tokio::time::sleep(backoff * 2u32.saturating_pow(attempt)).await;
+            attempt += 1;
+        }
+    }
+
+    /// Spawns a task calling every webhook which wants an event, without waiting for the
+    /// deliveries to finish.
+    pub fn spawn(
+        webhooks: Vec<Webhook>,
+        labels: Labels,
+        mut events: broadcast::Receiver<Event>,
+        client: Client,
+    ) -> JoinHandle<()> {
+        for webhook in webhooks.iter().filter(|webhook| webhook.deprecated()) {
+            tracing::warn!(
+                url = %webhook.url,
+                "the {{{{name}}}} placeholders of webhooks are deprecated, write {{name}} instead"
+            );
+        }
+        let http = reqwest::Client::new();
+        tokio::spawn(async move {
+            loop {
+                let event = match events.recv().await {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                };
+                let wanted: Vec<_> = webhooks.iter().filter(|w| w.wants(&event)).collect();
+                if wanted.is_empty() {
+                    continue;
+                }
+                let status = client.status().await.ok();
+                for webhook in wanted {
+                    let body = webhook.render(&event, status.as_ref(), &labels);
+                    let (http, webhook) = (http.clone(), webhook.clone());
+                    tokio::spawn(async move {
+                        if let Err(e) = deliver(&http, &webhook, body, BACKOFF).await {
+                            tracing::warn!(url = %webhook.url, error = %e, "webhook failed");
+                        }
+                    });
+                }
+            }
+        })
+    }
+}
+
+#[cfg(feature = "webhooks")]
+pub use delivery::spawn;
+
+#[test]
+fn render_webhooks() {
+    use chrono::TimeZone;
+    use std::time::Duration;
+
+    use crate::pomodoro::Phase;
+
+    let webhook: Webhook = toml::from_str(
+        r#"
+        url = "https://hooks.slack.com/services/T0/B0/X"
+        events = ["phase_started", "completed"]
+        body = '{"text": "{phase} on {task}, {remaining} left until {ends_at}"}'
+        "#,
+    )
+    .unwrap();
+    assert!(!webhook.deprecated());
+    assert!(webhook.wants(&Event::PhaseStarted(Phase::Working)));
+    assert!(!webhook.wants(&Event::Paused));
+    let labels = Labels::default();
+    let status = Status {
+        phase: Phase::Working,
+        elapsed: Duration::from_millis(10),
+        remaining: Duration::from_millis(1_499_990),
+        pomodoros: 0,
+        short_breaks: 0,
+        long_breaks: 0,
+        paused: false,
+        focused: Duration::from_secs(0),
+        on_break: Duration::from_secs(0),
+        paused_for: Duration::from_secs(0),
+        pauses: 0,
+        label: None,
+        task: Some("the \"big\" report".to_string()),
+        goal: None,
+        awaiting_ack: false,
+        overtime: Duration::from_secs(0),
+        ends_at: Some(Local.with_ymd_and_hms(2024, 1, 2, 14, 25, 0).unwrap()),
+        session_ends_at: None,
+    };
+    assert_eq!(
+        webhook.render(&Event::PhaseStarted(Phase::Working), Some(&status), &labels),
+        r#"{"text": "working on the \"big\" report, 25:00 left until 14:25"}"#
+    );
+
+    let ntfy: Webhook = toml::from_str(
+        r#"
+        url = "https://ntfy.sh/pomo"
+        body = "{{event}}: {{task}} {{remaining}} {count}"
+        content_type = "text/plain"
+        "#,
+    )
+    .unwrap();
+    assert!(ntfy.deprecated());
+    assert_eq!(
+        ntfy.render(&Event::PhaseEnded(Phase::Working), Some(&status), &labels),
+        r#"phase_ended: the "big" report 00:00 0"#
+    );
+    assert!(!ntfy.wants(&Event::Tick {
+        phase: Phase::Working,
+        elapsed: Duration::from_secs(1),
+        remaining: Duration::from_secs(1),
+    }));
+    let feed = Webhook { body: None, ..ntfy };
+    let line: serde_json::Value =
+        serde_json::from_str(&feed.render(&Event::Paused, None, &labels)).unwrap();
+    assert_eq!(line["event"], "paused");
+    let unknown = "url = \"https://ntfy.sh/pomo\"\nevents = [\"phase_begun\"]";
+    assert!(toml::from_str::<Webhook>(unknown).is_err());
+}
+
+#[cfg(feature = "webhooks")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn retry_failed_deliveries() {
+    use std::time::Duration;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    // Answers the first request with an error and the second one with success.
+    let server = tokio::spawn(async move {
+        let mut bodies = Vec::new();
+        for status in ["503 Service Unavailable", "204 No Content"] {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0; 1024];
+            while !String::from_utf8_lossy(&request).ends_with("}") {
+                let read = stream.read(&mut buffer).await.unwrap();
+                request.extend_from_slice(&buffer[..read]);
+            }
+            let request = String::from_utf8(request).unwrap();
+            bodies.push(request.split("\r\n\r\n").nth(1).unwrap().to_string());
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                status
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+        }
+        bodies
+    });
+    let webhook = Webhook {
+        url,
+        events: Vec::new(),
+        body: None,
+        content_type: json(),
+        headers: BTreeMap::new(),
+        retries: 1,
+    };
+    let http = reqwest::Client::new();
+    let body = webhook.render(&Event::Completed, None, &Labels::default());
+    delivery::deliver(&http, &webhook, body.clone(), Duration::from_millis(10))
+        .await
+        .unwrap();
+    assert_eq!(server.await.unwrap(), vec![body.clone(), body]);
+}